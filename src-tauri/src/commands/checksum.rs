@@ -0,0 +1,83 @@
+use crate::commands::error::CommandError;
+use crate::db::postgres::PostgresState;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+use tauri::State;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryChecksum {
+    pub digest: String,
+    pub row_count: usize,
+}
+
+/// Executes a query and returns a SHA-256 digest of its rows, for checking that
+/// two queries return identical data. With order_insensitive, row order is ignored.
+#[tauri::command]
+pub async fn query_checksum(
+    sql: String,
+    order_insensitive: Option<bool>,
+    postgres: State<'_, PostgresState>,
+) -> Result<QueryChecksum, CommandError> {
+    let result = postgres.execute_query(&sql).await?;
+
+    checksum_rows(&result.rows, order_insensitive.unwrap_or(false))
+}
+
+/// Hashes rows in their canonical form: each row is serialized as a JSON array in
+/// column order (object keys sorted) and terminated by a newline
+pub fn checksum_rows(
+    rows: &[Vec<JsonValue>],
+    order_insensitive: bool,
+) -> Result<QueryChecksum, CommandError> {
+    let mut encoded = rows
+        .iter()
+        .map(serde_json::to_vec)
+        .collect::<Result<Vec<Vec<u8>>, _>>()?;
+
+    if order_insensitive {
+        encoded.sort_unstable();
+    }
+
+    let mut hasher = Sha256::new();
+    for row in &encoded {
+        hasher.update(row);
+        hasher.update(b"\n");
+    }
+
+    Ok(QueryChecksum {
+        digest: hex::encode(hasher.finalize()),
+        row_count: rows.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_checksum_rows() {
+        let rows = vec![vec![json!(1), json!("a")], vec![json!(2), json!(null)]];
+        let reversed: Vec<Vec<JsonValue>> = rows.iter().rev().cloned().collect();
+
+        let ordered = checksum_rows(&rows, false).unwrap();
+        assert_eq!(ordered.row_count, 2);
+        assert_eq!(ordered.digest.len(), 64);
+        assert_ne!(
+            ordered.digest,
+            checksum_rows(&reversed, false).unwrap().digest
+        );
+
+        assert_eq!(
+            checksum_rows(&rows, true).unwrap().digest,
+            checksum_rows(&reversed, true).unwrap().digest
+        );
+
+        // Empty input hashes to the SHA-256 of nothing
+        assert_eq!(
+            checksum_rows(&[], false).unwrap().digest,
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
@@ -0,0 +1,912 @@
+use crate::commands::error::CommandError;
+use crate::db::metadata;
+use crate::db::postgres::{
+    quote_ident, quote_literal, ColumnDefinition, ConstraintInfo, ForeignKeyInfo, IndexInfo,
+    PartitionInfo, PostgresState, SequenceDefinition, TableFilter, TypeDefinition, ViewDefinition,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tauri::State;
+
+/// Index access methods accepted by create_index
+const INDEX_METHODS: [&str; 6] = ["btree", "hash", "gist", "spgist", "gin", "brin"];
+
+/// Postgres truncates identifiers longer than this many bytes
+const MAX_IDENTIFIER_LEN: usize = 63;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DdlResult {
+    pub ddl: String,
+    pub success: bool,
+}
+
+/// What build_schema_sql needs to recreate one table
+struct TableParts {
+    columns: Vec<ColumnDefinition>,
+    constraints: Vec<ConstraintInfo>,
+    indexes: Vec<IndexInfo>,
+    partition: Option<PartitionInfo>,
+}
+
+/// Everything build_schema_sql puts into the script of a schema
+struct SchemaObjects {
+    types: Vec<TypeDefinition>,
+    sequences: Vec<SequenceDefinition>,
+    functions: Vec<String>,
+    tables: Vec<(String, TableParts)>,
+    views: Vec<(ViewDefinition, Vec<IndexInfo>)>,
+    foreign_keys: Vec<ForeignKeyInfo>,
+    view_dependencies: Vec<(String, String)>,
+}
+
+/// Creates an index on a table and returns the DDL that was run
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn create_index(
+    schema: String,
+    table: String,
+    columns: Vec<String>,
+    unique: bool,
+    method: Option<String>,
+    concurrently: bool,
+    name: Option<String>,
+    postgres: State<'_, PostgresState>,
+) -> Result<DdlResult, CommandError> {
+    let ddl = build_create_index_sql(
+        &schema,
+        &table,
+        &columns,
+        unique,
+        method.as_deref(),
+        concurrently,
+        name.as_deref(),
+    )?;
+
+    postgres.execute_ddl(&ddl).await?;
+
+    Ok(DdlResult { ddl, success: true })
+}
+
+/// Drops an index and returns the DDL that was run
+#[tauri::command]
+pub async fn drop_index(
+    schema: String,
+    index_name: String,
+    concurrently: bool,
+    postgres: State<'_, PostgresState>,
+) -> Result<DdlResult, CommandError> {
+    let ddl = build_drop_index_sql(&schema, &index_name, concurrently);
+
+    postgres.execute_ddl(&ddl).await?;
+
+    Ok(DdlResult { ddl, success: true })
+}
+
+/// Sets or, with None or an empty comment, removes the comment of a table and returns the
+/// DDL that was run
+#[tauri::command]
+pub async fn set_table_comment(
+    schema: String,
+    table: String,
+    comment: Option<String>,
+    postgres: State<'_, PostgresState>,
+) -> Result<DdlResult, CommandError> {
+    let target = format!("TABLE {}.{}", quote_ident(&schema), quote_ident(&table));
+    let ddl = build_comment_sql(&target, comment.as_deref());
+
+    postgres.execute_ddl(&ddl).await?;
+
+    Ok(DdlResult { ddl, success: true })
+}
+
+/// Sets or, with None or an empty comment, removes the comment of a column and returns
+/// the DDL that was run
+#[tauri::command]
+pub async fn set_column_comment(
+    schema: String,
+    table: String,
+    column: String,
+    comment: Option<String>,
+    postgres: State<'_, PostgresState>,
+) -> Result<DdlResult, CommandError> {
+    let target = format!(
+        "COLUMN {}.{}.{}",
+        quote_ident(&schema),
+        quote_ident(&table),
+        quote_ident(&column)
+    );
+    let ddl = build_comment_sql(&target, comment.as_deref());
+
+    postgres.execute_ddl(&ddl).await?;
+
+    Ok(DdlResult { ddl, success: true })
+}
+
+/// Gets the definition of a view or materialized view; for a materialized view also
+/// whether it is populated and when it was last refreshed from the app
+#[tauri::command]
+pub async fn fetch_view_definition(
+    schema: String,
+    name: String,
+    postgres: State<'_, PostgresState>,
+) -> Result<ViewDefinition, CommandError> {
+    let mut view = postgres
+        .fetch_view_definition(&schema, &name)
+        .await?
+        .ok_or_else(|| CommandError::not_found(format!("View {}.{} not found", schema, name)))?;
+
+    if view.is_populated.is_some() {
+        if let Some(connection_id) = postgres.get_connection_id().await {
+            view.last_refreshed =
+                metadata::get_matview_refresh(&connection_id, &view.database, &schema, &name)?;
+        }
+    }
+
+    Ok(view)
+}
+
+/// Refreshes a materialized view and returns the DDL that was run. CONCURRENTLY keeps the
+/// view readable meanwhile but needs a unique index and an already populated view
+#[tauri::command]
+pub async fn refresh_materialized_view(
+    schema: String,
+    name: String,
+    concurrently: bool,
+    postgres: State<'_, PostgresState>,
+) -> Result<DdlResult, CommandError> {
+    let ddl = build_refresh_materialized_view_sql(&schema, &name, concurrently);
+
+    postgres.execute_ddl(&ddl).await?;
+
+    let view = postgres.fetch_view_definition(&schema, &name).await?;
+    if let (Some(view), Some(connection_id)) = (view, postgres.get_connection_id().await) {
+        metadata::record_matview_refresh(&connection_id, &view.database, &schema, &name)?;
+    }
+
+    Ok(DdlResult { ddl, success: true })
+}
+
+/// Reconstructs the CREATE TABLE statement of a table: columns, defaults, keys, checks and
+/// foreign keys, followed by the CREATE INDEX statements of its other indexes when
+/// `include_indexes` is set
+#[tauri::command]
+pub async fn generate_table_ddl(
+    schema: String,
+    table: String,
+    include_indexes: bool,
+    postgres: State<'_, PostgresState>,
+) -> Result<String, CommandError> {
+    let columns = postgres.fetch_column_definitions(&schema, &table).await?;
+    if columns.is_empty() {
+        return Err(CommandError::not_found(format!(
+            "Table {}.{} not found",
+            schema, table
+        )));
+    }
+
+    let constraints = postgres.fetch_constraints(&schema, &table).await?;
+    let indexes = if include_indexes {
+        postgres.fetch_indexes(&schema, &table).await?
+    } else {
+        Vec::new()
+    };
+
+    Ok(build_create_table_sql(
+        &schema,
+        &table,
+        &columns,
+        &constraints,
+        &indexes,
+        None,
+    ))
+}
+
+/// Assembles a script that recreates a schema: its types, sequences, tables with their
+/// indexes, functions, and views, each after the objects it depends on. Foreign keys that
+/// would point at a table not created yet, as in a cycle, are added once every table
+/// exists. Partitions are created PARTITION OF their parent, which gives them its keys
+/// and indexes
+#[tauri::command]
+pub async fn export_schema_ddl(
+    schema: String,
+    postgres: State<'_, PostgresState>,
+) -> Result<String, CommandError> {
+    schema_ddl(&schema, &postgres).await
+}
+
+async fn schema_ddl(schema: &str, postgres: &PostgresState) -> Result<String, CommandError> {
+    let mut partitions = postgres.fetch_partitions(schema).await?;
+    let relations = postgres
+        .fetch_tables(&TableFilter {
+            schema: Some(schema.to_string()),
+            ..Default::default()
+        })
+        .await?;
+
+    let mut tables = Vec::new();
+    let mut views = Vec::new();
+    for relation in relations {
+        match relation.table_type.as_str() {
+            "BASE TABLE" => {
+                let partition = partitions
+                    .iter()
+                    .position(|partition| partition.table == relation.name)
+                    .map(|index| partitions.swap_remove(index));
+                let parts = TableParts {
+                    columns: postgres
+                        .fetch_column_definitions(schema, &relation.name)
+                        .await?,
+                    constraints: postgres.fetch_constraints(schema, &relation.name).await?,
+                    indexes: postgres.fetch_indexes(schema, &relation.name).await?,
+                    partition,
+                };
+                tables.push((relation.name, parts));
+            }
+            "VIEW" | "MATERIALIZED VIEW" => {
+                let Some(view) = postgres
+                    .fetch_view_definition(schema, &relation.name)
+                    .await?
+                else {
+                    continue;
+                };
+                let indexes = if view.is_populated.is_some() {
+                    postgres.fetch_indexes(schema, &relation.name).await?
+                } else {
+                    Vec::new()
+                };
+                views.push((view, indexes));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(build_schema_sql(
+        schema,
+        &SchemaObjects {
+            types: postgres.fetch_type_definitions(schema).await?,
+            sequences: postgres.fetch_sequences(schema).await?,
+            functions: postgres.fetch_function_definitions(schema).await?,
+            tables,
+            views,
+            foreign_keys: postgres.fetch_foreign_keys(schema, None).await?,
+            view_dependencies: postgres.fetch_view_dependencies(schema).await?,
+        },
+    ))
+}
+
+fn build_schema_sql(schema: &str, objects: &SchemaObjects) -> String {
+    let mut sql = format!("CREATE SCHEMA IF NOT EXISTS {};\n", quote_ident(schema));
+    // Function bodies may call functions that are only created further down
+    sql.push_str("SET check_function_bodies = false;\n");
+
+    let type_names: Vec<String> = objects.types.iter().map(|t| t.name.clone()).collect();
+    let type_edges: Vec<(String, String)> = objects
+        .types
+        .iter()
+        .flat_map(|t| {
+            t.depends_on
+                .iter()
+                .map(|dependency| (t.name.clone(), dependency.clone()))
+        })
+        .collect();
+    for name in dependency_order(&type_names, &type_edges) {
+        if let Some(definition) = objects.types.iter().find(|t| t.name == name) {
+            sql.push('\n');
+            sql.push_str(&build_create_type_sql(schema, definition));
+        }
+    }
+
+    for sequence in &objects.sequences {
+        sql.push('\n');
+        sql.push_str(&build_create_sequence_sql(schema, sequence));
+    }
+
+    // Only foreign keys within the schema can be ordered; the rest must already exist
+    let references: HashMap<(&str, &str), &str> = objects
+        .foreign_keys
+        .iter()
+        .filter(|fk| fk.target_schema == schema)
+        .map(|fk| {
+            (
+                (fk.source_table.as_str(), fk.name.as_str()),
+                fk.target_table.as_str(),
+            )
+        })
+        .collect();
+    let table_names: Vec<String> = objects
+        .tables
+        .iter()
+        .map(|(name, _)| name.clone())
+        .collect();
+    // A partition can only be created once its parent exists
+    let parents = objects.tables.iter().filter_map(|(name, parts)| {
+        let partition = parts.partition.as_ref()?;
+        if partition.parent_schema.as_deref() != Some(schema) {
+            return None;
+        }
+        Some((name.clone(), partition.parent_table.clone()?))
+    });
+    let table_edges: Vec<(String, String)> = references
+        .iter()
+        .map(|((source, _), target)| (source.to_string(), target.to_string()))
+        .chain(parents)
+        .collect();
+    let parts: HashMap<&str, &TableParts> = objects
+        .tables
+        .iter()
+        .map(|(name, parts)| (name.as_str(), parts))
+        .collect();
+
+    let mut created: HashSet<String> = HashSet::new();
+    let mut deferred = Vec::new();
+    for name in dependency_order(&table_names, &table_edges) {
+        let Some(table) = parts.get(name.as_str()) else {
+            continue;
+        };
+        let partition = table.partition.as_ref();
+
+        sql.push('\n');
+        if let Some(partition_of) =
+            partition.and_then(|partition| build_create_partition_sql(schema, &name, partition))
+        {
+            sql.push_str(&partition_of);
+            created.insert(name);
+            continue;
+        }
+
+        let (inline, later): (Vec<ConstraintInfo>, Vec<ConstraintInfo>) =
+            table.constraints.iter().cloned().partition(|constraint| {
+                match references.get(&(name.as_str(), constraint.name.as_str())) {
+                    Some(&target) => {
+                        target == name || created.contains(target) || !parts.contains_key(target)
+                    }
+                    None => true,
+                }
+            });
+        sql.push_str(&build_create_table_sql(
+            schema,
+            &name,
+            &table.columns,
+            &inline,
+            &table.indexes,
+            partition.and_then(|partition| partition.partition_key.as_deref()),
+        ));
+        deferred.extend(later.into_iter().map(|constraint| {
+            format!(
+                "ALTER TABLE {}.{} ADD CONSTRAINT {} {};\n",
+                quote_ident(schema),
+                quote_ident(&name),
+                quote_ident(&constraint.name),
+                constraint.definition
+            )
+        }));
+        created.insert(name);
+    }
+    if !deferred.is_empty() {
+        sql.push('\n');
+        sql.push_str(&deferred.concat());
+    }
+
+    for function in &objects.functions {
+        sql.push_str(&format!("\n{};\n", function.trim_end()));
+    }
+
+    let view_names: Vec<String> = objects
+        .views
+        .iter()
+        .map(|(view, _)| view.name.clone())
+        .collect();
+    for name in dependency_order(&view_names, &objects.view_dependencies) {
+        let Some((view, indexes)) = objects.views.iter().find(|(view, _)| view.name == name) else {
+            continue;
+        };
+        sql.push('\n');
+        sql.push_str(&build_create_view_sql(view));
+        for index in indexes {
+            sql.push_str(&format!("{};\n", index.definition));
+        }
+    }
+
+    sql
+}
+
+/// Orders `names` so each comes after the names it depends on, given `edges` of
+/// (dependent, dependency). Edges to names not in the list are ignored, and names caught
+/// in a cycle come last in their original order
+fn dependency_order(names: &[String], edges: &[(String, String)]) -> Vec<String> {
+    let mut ordered = Vec::with_capacity(names.len());
+    let mut placed: HashSet<&str> = HashSet::new();
+
+    loop {
+        let mut progress = false;
+        for name in names {
+            if placed.contains(name.as_str()) {
+                continue;
+            }
+            let ready =
+                edges
+                    .iter()
+                    .filter(|(dependent, _)| dependent == name)
+                    .all(|(_, dependency)| {
+                        dependency == name
+                            || placed.contains(dependency.as_str())
+                            || !names.contains(dependency)
+                    });
+            if ready {
+                placed.insert(name);
+                ordered.push(name.clone());
+                progress = true;
+            }
+        }
+        if !progress {
+            break;
+        }
+    }
+
+    ordered.extend(
+        names
+            .iter()
+            .filter(|name| !placed.contains(name.as_str()))
+            .cloned(),
+    );
+    ordered
+}
+
+/// Builds a CREATE SEQUENCE statement. The sequence starts over at its start value; its
+/// current value is data, not schema
+pub fn build_create_sequence_sql(schema: &str, sequence: &SequenceDefinition) -> String {
+    format!(
+        "CREATE SEQUENCE {}.{} AS {} INCREMENT BY {} MINVALUE {} MAXVALUE {} START WITH {} CACHE {}{};\n",
+        quote_ident(schema),
+        quote_ident(&sequence.name),
+        sequence.data_type,
+        sequence.increment_by,
+        sequence.min_value,
+        sequence.max_value,
+        sequence.start_value,
+        sequence.cache_size,
+        if sequence.cycle { " CYCLE" } else { "" }
+    )
+}
+
+/// Builds the CREATE TYPE statement of an enum or composite type, or the CREATE DOMAIN
+/// statement of a domain
+pub fn build_create_type_sql(schema: &str, definition: &TypeDefinition) -> String {
+    let (object, keyword) = match definition.kind.as_str() {
+        "domain" => ("DOMAIN", ""),
+        "enum" => ("TYPE", "ENUM "),
+        _ => ("TYPE", ""),
+    };
+    format!(
+        "CREATE {} {}.{} AS {}{};\n",
+        object,
+        quote_ident(schema),
+        quote_ident(&definition.name),
+        keyword,
+        definition.definition
+    )
+}
+
+/// Builds the CREATE TABLE ... PARTITION OF statement of a partition, or None if the
+/// table isn't one. A partition that is partitioned itself keeps its PARTITION BY
+pub fn build_create_partition_sql(
+    schema: &str,
+    table: &str,
+    partition: &PartitionInfo,
+) -> Option<String> {
+    let (Some(parent_schema), Some(parent_table), Some(bound)) = (
+        &partition.parent_schema,
+        &partition.parent_table,
+        &partition.bound,
+    ) else {
+        return None;
+    };
+    let partition_by = partition
+        .partition_key
+        .as_ref()
+        .map(|key| format!(" PARTITION BY {}", key))
+        .unwrap_or_default();
+    Some(format!(
+        "CREATE TABLE {}.{} PARTITION OF {}.{} {}{};\n",
+        quote_ident(schema),
+        quote_ident(table),
+        quote_ident(parent_schema),
+        quote_ident(parent_table),
+        bound,
+        partition_by
+    ))
+}
+
+/// Builds the CREATE [MATERIALIZED] VIEW statement of a view. A materialized view that
+/// isn't populated is created WITH NO DATA
+pub fn build_create_view_sql(view: &ViewDefinition) -> String {
+    let query = view.definition.trim().trim_end_matches(';');
+    match view.is_populated {
+        Some(populated) => format!(
+            "CREATE MATERIALIZED VIEW {}.{} AS\n{}\nWITH {}DATA;\n",
+            quote_ident(&view.schema),
+            quote_ident(&view.name),
+            query,
+            if populated { "" } else { "NO " }
+        ),
+        None => format!(
+            "CREATE VIEW {}.{} AS\n{};\n",
+            quote_ident(&view.schema),
+            quote_ident(&view.name),
+            query
+        ),
+    }
+}
+
+/// Builds a CREATE TABLE statement, then one CREATE INDEX statement per index that doesn't
+/// back a constraint. Serial columns are declared as serial again so that the statement
+/// doesn't depend on a sequence that only exists in the source database. A partitioned
+/// table gets `partition_key` as its PARTITION BY
+pub fn build_create_table_sql(
+    schema: &str,
+    table: &str,
+    columns: &[ColumnDefinition],
+    constraints: &[ConstraintInfo],
+    indexes: &[IndexInfo],
+    partition_key: Option<&str>,
+) -> String {
+    let mut lines: Vec<String> = columns.iter().map(column_sql).collect();
+
+    // Keys first so foreign keys read last, the way they are usually written
+    let order = ["primary_key", "unique", "check", "exclusion", "foreign_key"];
+    for constraint_type in order {
+        lines.extend(
+            constraints
+                .iter()
+                .filter(|c| c.constraint_type == constraint_type)
+                .map(|c| format!("CONSTRAINT {} {}", quote_ident(&c.name), c.definition)),
+        );
+    }
+
+    let mut sql = format!(
+        "CREATE TABLE {}.{} (\n    {}\n){};\n",
+        quote_ident(schema),
+        quote_ident(table),
+        lines.join(",\n    "),
+        partition_key
+            .map(|key| format!(" PARTITION BY {}", key))
+            .unwrap_or_default()
+    );
+
+    // Primary key and unique constraints create their index under the constraint's name
+    for index in indexes {
+        if !constraints.iter().any(|c| c.name == index.name) {
+            sql.push_str(&format!("{};\n", index.definition));
+        }
+    }
+
+    sql
+}
+
+fn column_sql(column: &ColumnDefinition) -> String {
+    let serial_type = match column.data_type.as_str() {
+        "smallint" => Some("smallserial"),
+        "integer" => Some("serial"),
+        "bigint" => Some("bigserial"),
+        _ => None,
+    };
+
+    let mut sql = quote_ident(&column.name);
+    match serial_type.filter(|_| column.is_serial) {
+        Some(serial_type) => {
+            sql.push(' ');
+            sql.push_str(serial_type);
+        }
+        None => {
+            sql.push(' ');
+            sql.push_str(&column.data_type);
+            if let Some(identity) = &column.identity {
+                sql.push_str(&format!(" GENERATED {} AS IDENTITY", identity));
+            } else if let Some(generated) = &column.generated {
+                sql.push_str(&format!(" GENERATED ALWAYS AS ({}) STORED", generated));
+            } else if let Some(default) = &column.default {
+                sql.push_str(&format!(" DEFAULT {}", default));
+            }
+        }
+    }
+    if column.not_null {
+        sql.push_str(" NOT NULL");
+    }
+
+    sql
+}
+
+/// Builds a CREATE [UNIQUE] INDEX [CONCURRENTLY] statement
+pub fn build_create_index_sql(
+    schema: &str,
+    table: &str,
+    columns: &[String],
+    unique: bool,
+    method: Option<&str>,
+    concurrently: bool,
+    name: Option<&str>,
+) -> Result<String, CommandError> {
+    if columns.is_empty() {
+        return Err(CommandError::invalid_input(
+            "An index needs at least one column",
+        ));
+    }
+
+    let method = method.unwrap_or("btree").to_lowercase();
+    if !INDEX_METHODS.contains(&method.as_str()) {
+        return Err(CommandError::invalid_input(format!(
+            "Unsupported index method: {}",
+            method
+        )));
+    }
+
+    let name = match name {
+        Some(name) if !name.trim().is_empty() => name.to_string(),
+        _ => default_index_name(table, columns),
+    };
+
+    let column_list: Vec<String> = columns.iter().map(|c| quote_ident(c)).collect();
+
+    Ok(format!(
+        "CREATE {}INDEX {}{} ON {}.{} USING {} ({})",
+        if unique { "UNIQUE " } else { "" },
+        if concurrently { "CONCURRENTLY " } else { "" },
+        quote_ident(&name),
+        quote_ident(schema),
+        quote_ident(table),
+        method,
+        column_list.join(", ")
+    ))
+}
+
+/// Builds a DROP INDEX [CONCURRENTLY] statement
+pub fn build_drop_index_sql(schema: &str, index_name: &str, concurrently: bool) -> String {
+    format!(
+        "DROP INDEX {}{}.{}",
+        if concurrently { "CONCURRENTLY " } else { "" },
+        quote_ident(schema),
+        quote_ident(index_name)
+    )
+}
+
+/// Builds a COMMENT ON statement for `target` (e.g. `TABLE "s"."t"`). An empty comment
+/// is the same as none: Postgres drops the comment
+pub fn build_comment_sql(target: &str, comment: Option<&str>) -> String {
+    match comment.filter(|c| !c.is_empty()) {
+        Some(comment) => format!("COMMENT ON {} IS {}", target, quote_literal(comment)),
+        None => format!("COMMENT ON {} IS NULL", target),
+    }
+}
+
+/// Builds a REFRESH MATERIALIZED VIEW [CONCURRENTLY] statement
+pub fn build_refresh_materialized_view_sql(schema: &str, name: &str, concurrently: bool) -> String {
+    format!(
+        "REFRESH MATERIALIZED VIEW {}{}.{}",
+        if concurrently { "CONCURRENTLY " } else { "" },
+        quote_ident(schema),
+        quote_ident(name)
+    )
+}
+
+/// Names an index `<table>_<col>_..._idx`, truncated to the identifier length limit
+fn default_index_name(table: &str, columns: &[String]) -> String {
+    let mut name = format!("{}_{}_idx", table, columns.join("_"));
+    if name.len() > MAX_IDENTIFIER_LEN {
+        let mut end = MAX_IDENTIFIER_LEN - "_idx".len();
+        while !name.is_char_boundary(end) {
+            end -= 1;
+        }
+        name = format!("{}_idx", &name[..end]);
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_index_sql() {
+        let columns = vec!["user_id".to_string(), "created at".to_string()];
+        let sql =
+            build_create_index_sql("public", "events", &columns, true, None, true, None).unwrap();
+        assert_eq!(
+            sql,
+            r#"CREATE UNIQUE INDEX CONCURRENTLY "events_user_id_created at_idx" ON "public"."events" USING btree ("user_id", "created at")"#
+        );
+
+        assert!(build_create_index_sql(
+            "public",
+            "events",
+            &columns,
+            false,
+            Some("bogus"),
+            false,
+            None
+        )
+        .is_err());
+        assert!(build_create_index_sql("public", "events", &[], false, None, false, None).is_err());
+
+        let long_name = default_index_name(&"t".repeat(80), &columns);
+        assert_eq!(long_name.len(), MAX_IDENTIFIER_LEN);
+
+        assert_eq!(
+            build_drop_index_sql("public", "odd\"name", false),
+            r#"DROP INDEX "public"."odd""name""#
+        );
+    }
+
+    #[test]
+    fn test_build_comment_sql() {
+        assert_eq!(
+            build_comment_sql(r#"TABLE "public"."t""#, Some("Bob's \\d table")),
+            r#"COMMENT ON TABLE "public"."t" IS E'Bob''s \\d table'"#
+        );
+        assert_eq!(
+            build_comment_sql(r#"COLUMN "public"."t"."c""#, Some("")),
+            r#"COMMENT ON COLUMN "public"."t"."c" IS NULL"#
+        );
+    }
+
+    #[test]
+    fn test_build_create_table_sql() {
+        let column = |name: &str, data_type: &str, not_null: bool| ColumnDefinition {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            not_null,
+            ..Default::default()
+        };
+        let constraint = |name: &str, constraint_type: &str, definition: &str| ConstraintInfo {
+            name: name.to_string(),
+            constraint_type: constraint_type.to_string(),
+            definition: definition.to_string(),
+            ..Default::default()
+        };
+        let index = |name: &str, definition: &str| IndexInfo {
+            name: name.to_string(),
+            method: "btree".to_string(),
+            definition: definition.to_string(),
+            ..Default::default()
+        };
+
+        let columns = vec![
+            ColumnDefinition {
+                is_serial: true,
+                default: Some("nextval('orders_id_seq'::regclass)".to_string()),
+                ..column("id", "integer", true)
+            },
+            ColumnDefinition {
+                default: Some("'new'::character varying".to_string()),
+                ..column("status", "character varying(255)", true)
+            },
+            column("price", "numeric(10,2)", false),
+            ColumnDefinition {
+                generated: Some("price * 2::numeric".to_string()),
+                ..column("double", "numeric", false)
+            },
+            ColumnDefinition {
+                identity: Some("BY DEFAULT".to_string()),
+                ..column("seq", "bigint", true)
+            },
+        ];
+        let constraints = vec![
+            constraint(
+                "orders_user_fkey",
+                "foreign_key",
+                "FOREIGN KEY (user_id) REFERENCES users(id)",
+            ),
+            constraint("orders_price_check", "check", "CHECK (price > 0::numeric)"),
+            constraint("orders_pkey", "primary_key", "PRIMARY KEY (id)"),
+        ];
+        let indexes = vec![
+            index(
+                "orders_pkey",
+                "CREATE UNIQUE INDEX orders_pkey ON public.orders USING btree (id)",
+            ),
+            index(
+                "orders_status_idx",
+                "CREATE INDEX orders_status_idx ON public.orders USING btree (status)",
+            ),
+        ];
+
+        assert_eq!(
+            build_create_table_sql("public", "orders", &columns, &constraints, &indexes, None),
+            r#"CREATE TABLE "public"."orders" (
+    "id" serial NOT NULL,
+    "status" character varying(255) DEFAULT 'new'::character varying NOT NULL,
+    "price" numeric(10,2),
+    "double" numeric GENERATED ALWAYS AS (price * 2::numeric) STORED,
+    "seq" bigint GENERATED BY DEFAULT AS IDENTITY NOT NULL,
+    CONSTRAINT "orders_pkey" PRIMARY KEY (id),
+    CONSTRAINT "orders_price_check" CHECK (price > 0::numeric),
+    CONSTRAINT "orders_user_fkey" FOREIGN KEY (user_id) REFERENCES users(id)
+);
+CREATE INDEX orders_status_idx ON public.orders USING btree (status);
+"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_schema_ddl() {
+        let Some(pg) = crate::db::postgres::tests::test_database().await else {
+            return;
+        };
+        let postgres = PostgresState::new(pg);
+        let setup = r#"
+            DROP SCHEMA IF EXISTS dt_export CASCADE;
+            CREATE SCHEMA dt_export;
+            CREATE TYPE dt_export.mood AS ENUM ('sad', 'happy');
+            CREATE DOMAIN dt_export.score AS int DEFAULT 0 NOT NULL CHECK (VALUE >= 0);
+            CREATE TYPE dt_export.rating AS (mood dt_export.mood, score dt_export.score);
+            CREATE TABLE dt_export.events (
+                id int,
+                at date NOT NULL,
+                rating dt_export.rating,
+                PRIMARY KEY (id, at)
+            ) PARTITION BY RANGE (at);
+            CREATE TABLE dt_export.events_2024 PARTITION OF dt_export.events
+                FOR VALUES FROM ('2024-01-01') TO ('2025-01-01');
+            CREATE FUNCTION dt_export.event_count() RETURNS bigint
+                LANGUAGE sql AS 'SELECT count(*) FROM dt_export.events';
+            CREATE VIEW dt_export.summary AS SELECT dt_export.event_count() AS events;
+        "#;
+        postgres.execute_script(setup).await.unwrap();
+
+        let ddl = schema_ddl("dt_export", &postgres).await.unwrap();
+        let position = |needle: &str| {
+            ddl.find(needle)
+                .unwrap_or_else(|| panic!("{} missing from\n{}", needle, ddl))
+        };
+        assert!(position("CREATE TYPE \"dt_export\".\"mood\" AS ENUM ('sad', 'happy')") > 0);
+        assert!(
+            position("CREATE DOMAIN \"dt_export\".\"score\"") > position("AS ENUM")
+                && position("CREATE TYPE \"dt_export\".\"rating\"") > position("CREATE DOMAIN")
+        );
+        assert!(
+            position(") PARTITION BY RANGE (at);")
+                > position("CREATE TYPE \"dt_export\".\"rating\"")
+        );
+        assert!(
+            position("PARTITION OF \"dt_export\".\"events\" FOR VALUES FROM ('2024-01-01') TO ('2025-01-01');")
+                > position("PARTITION BY RANGE")
+        );
+        assert!(position("FUNCTION dt_export.event_count()") > position("PARTITION OF"));
+        assert!(position("CREATE VIEW") > position("FUNCTION dt_export.event_count()"));
+
+        // The script recreates the schema as it was
+        postgres
+            .execute_script("DROP SCHEMA dt_export CASCADE")
+            .await
+            .unwrap();
+        let replayed = postgres.execute_script(&ddl).await.unwrap();
+        assert!(replayed.committed, "{:?}", replayed.statements);
+        assert_eq!(schema_ddl("dt_export", &postgres).await.unwrap(), ddl);
+
+        postgres
+            .execute_script("DROP SCHEMA dt_export CASCADE")
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_dependency_order() {
+        let names: Vec<String> = ["orders", "users", "items", "a", "b"]
+            .iter()
+            .map(|n| n.to_string())
+            .collect();
+        let edge = |from: &str, to: &str| (from.to_string(), to.to_string());
+        let edges = vec![
+            edge("orders", "users"),
+            edge("items", "orders"),
+            edge("items", "products"),
+            edge("users", "users"),
+            edge("a", "b"),
+            edge("b", "a"),
+        ];
+
+        assert_eq!(
+            dependency_order(&names, &edges),
+            vec!["users", "orders", "items", "a", "b"]
+        );
+    }
+}
@@ -0,0 +1,384 @@
+use crate::crypto::CryptoError;
+use crate::db::manager::DatabaseError;
+use crate::db::metadata::MetadataError;
+use crate::db::mysql::MySqlError;
+use crate::db::postgres::{PostgresError, ServerError};
+use crate::db::sqlite::SqliteError;
+use serde::{Deserialize, Serialize};
+use sqlx::mysql::MySqlDatabaseError;
+use std::fmt;
+
+/// What went wrong, coarsely enough for the UI to react to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// The server can't be reached, or the connection dropped
+    Connection,
+    /// The server rejected the login
+    Auth,
+    /// The statement doesn't parse, or names things in a way the server rejects
+    Syntax,
+    /// The user lacks a privilege, or the connection is read-only
+    Permission,
+    /// A table, column, database or saved item that doesn't exist
+    NotFound,
+    /// The connect or statement timeout ran out
+    Timeout,
+    /// The user stopped the operation
+    Cancelled,
+    /// Arguments or data the command can't work with
+    InvalidInput,
+    /// Any other error the database raised
+    Database,
+    Internal,
+}
+
+/// The error every command returns
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandError {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// Boxed, as every command's Result is as large as its error
+    #[serde(flatten)]
+    pub details: Box<ErrorDetails>,
+}
+
+/// What a server error reports beside its message; all None for other errors
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ErrorDetails {
+    /// SQLSTATE
+    pub code: Option<String>,
+    pub detail: Option<String>,
+    pub hint: Option<String>,
+    /// Constraint a statement violated
+    pub constraint: Option<String>,
+    /// 1-based character offset of the error in the SQL the command was given
+    pub position: Option<usize>,
+}
+
+impl CommandError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            details: Box::default(),
+        }
+    }
+
+    /// For failures outside the database, like a file that can't be written
+    pub fn internal(error: impl fmt::Display) -> Self {
+        Self::new(ErrorKind::Internal, error.to_string())
+    }
+
+    /// For arguments or data the command can't work with
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::InvalidInput, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::NotFound, message)
+    }
+
+    /// Prefixes the message with what was being worked on, keeping the kind
+    pub fn context(mut self, context: impl fmt::Display) -> Self {
+        self.message = format!("{}: {}", context, self.message);
+        self
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<ServerError> for CommandError {
+    fn from(error: ServerError) -> Self {
+        Self {
+            kind: sqlstate_kind(&error.code),
+            message: error.message,
+            details: Box::new(ErrorDetails {
+                code: Some(error.code),
+                detail: error.detail,
+                hint: error.hint,
+                constraint: error.constraint,
+                position: error.position,
+            }),
+        }
+    }
+}
+
+impl From<PostgresError> for CommandError {
+    fn from(error: PostgresError) -> Self {
+        let kind = match error {
+            PostgresError::Server(server_error) => return (*server_error).into(),
+            PostgresError::Sqlx(ref e) => match ServerError::from_sqlx(e) {
+                Some(server_error) => return server_error.into(),
+                None => ErrorKind::Database,
+            },
+            PostgresError::ConnectionFailed(_)
+            | PostgresError::ConnectionLost(_)
+            | PostgresError::NoActiveConnection => ErrorKind::Connection,
+            PostgresError::ConnectionTimedOut(_) | PostgresError::QueryTimeout => {
+                ErrorKind::Timeout
+            }
+            PostgresError::ReadOnly => ErrorKind::Permission,
+            PostgresError::Cancelled => ErrorKind::Cancelled,
+            PostgresError::InvalidInput(_) => ErrorKind::InvalidInput,
+            PostgresError::ExtensionNotInstalled(_) => ErrorKind::NotFound,
+            PostgresError::QueryFailed(_) | PostgresError::SerializationFailure(_) => {
+                ErrorKind::Database
+            }
+            PostgresError::EncodingFailed(_) => ErrorKind::Internal,
+        };
+        Self::new(kind, error.to_string())
+    }
+}
+
+impl From<MySqlError> for CommandError {
+    fn from(error: MySqlError) -> Self {
+        let kind = match &error {
+            MySqlError::ConnectionFailed(_) | MySqlError::NoActiveConnection => {
+                ErrorKind::Connection
+            }
+            MySqlError::QueryFailed(e) => {
+                let server_error = e
+                    .as_database_error()
+                    .and_then(|e| e.try_downcast_ref::<MySqlDatabaseError>());
+                if let Some(server_error) = server_error {
+                    let mut command_error =
+                        Self::new(mysql_error_kind(server_error.number()), error.to_string());
+                    command_error.details.code = server_error.code().map(str::to_string);
+                    return command_error;
+                }
+                sqlx_kind(e)
+            }
+            MySqlError::InvalidInput(_) => ErrorKind::InvalidInput,
+        };
+        Self::new(kind, error.to_string())
+    }
+}
+
+impl From<SqliteError> for CommandError {
+    fn from(error: SqliteError) -> Self {
+        let kind = match &error {
+            SqliteError::ConnectionFailed(_) | SqliteError::NoActiveConnection => {
+                ErrorKind::Connection
+            }
+            SqliteError::QueryFailed(e) => match e.as_database_error() {
+                Some(server_error) => {
+                    let code = server_error.code().map(|code| code.into_owned());
+                    let kind = sqlite_error_kind(
+                        code.as_deref().and_then(|code| code.parse().ok()),
+                        server_error.message(),
+                    );
+                    let mut command_error = Self::new(kind, error.to_string());
+                    command_error.details.code = code;
+                    return command_error;
+                }
+                None => sqlx_kind(e),
+            },
+            SqliteError::InvalidInput(_) => ErrorKind::InvalidInput,
+        };
+        Self::new(kind, error.to_string())
+    }
+}
+
+impl From<DatabaseError> for CommandError {
+    fn from(error: DatabaseError) -> Self {
+        match error {
+            DatabaseError::Postgres(e) => e.into(),
+            DatabaseError::MySql(e) => e.into(),
+            DatabaseError::Sqlite(e) => e.into(),
+            DatabaseError::UnknownDriver(_) => {
+                Self::new(ErrorKind::InvalidInput, error.to_string())
+            }
+        }
+    }
+}
+
+impl From<MetadataError> for CommandError {
+    fn from(error: MetadataError) -> Self {
+        let kind = match error {
+            MetadataError::ConnectionNotFound
+            | MetadataError::SavedQueryNotFound
+            | MetadataError::ExplainPlanNotFound
+            | MetadataError::EditorTabNotFound => ErrorKind::NotFound,
+            _ => ErrorKind::Internal,
+        };
+        Self::new(kind, error.to_string())
+    }
+}
+
+impl From<CryptoError> for CommandError {
+    fn from(error: CryptoError) -> Self {
+        Self::internal(error)
+    }
+}
+
+impl From<std::io::Error> for CommandError {
+    fn from(error: std::io::Error) -> Self {
+        Self::new(io_error_kind(&error), error.to_string())
+    }
+}
+
+fn io_error_kind(error: &std::io::Error) -> ErrorKind {
+    match error.kind() {
+        std::io::ErrorKind::NotFound => ErrorKind::NotFound,
+        std::io::ErrorKind::PermissionDenied => ErrorKind::Permission,
+        _ => ErrorKind::Internal,
+    }
+}
+
+impl From<serde_json::Error> for CommandError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::internal(error)
+    }
+}
+
+impl From<csv::Error> for CommandError {
+    fn from(error: csv::Error) -> Self {
+        match error.kind() {
+            csv::ErrorKind::Io(e) => Self::new(io_error_kind(e), error.to_string()),
+            _ => Self::internal(error),
+        }
+    }
+}
+
+/// Sorts an error sqlx raised without the server's involvement
+fn sqlx_kind(error: &sqlx::Error) -> ErrorKind {
+    match error {
+        sqlx::Error::Io(_)
+        | sqlx::Error::Tls(_)
+        | sqlx::Error::Protocol(_)
+        | sqlx::Error::PoolClosed
+        | sqlx::Error::WorkerCrashed => ErrorKind::Connection,
+        sqlx::Error::PoolTimedOut => ErrorKind::Timeout,
+        sqlx::Error::ColumnNotFound(_) => ErrorKind::NotFound,
+        _ => ErrorKind::Database,
+    }
+}
+
+/// Sorts a MySQL error by its error number. Its SQLSTATE says too little: 42000 covers
+/// both syntax errors and missing privileges
+fn mysql_error_kind(number: u16) -> ErrorKind {
+    match number {
+        // ER_PARSE_ERROR, ER_SYNTAX_ERROR
+        1064 | 1149 => ErrorKind::Syntax,
+        // ER_ACCESS_DENIED_ERROR
+        1045 => ErrorKind::Auth,
+        // ER_DBACCESS_DENIED_ERROR, ER_TABLEACCESS_DENIED_ERROR,
+        // ER_COLUMNACCESS_DENIED_ERROR, ER_SPECIFIC_ACCESS_DENIED_ERROR,
+        // ER_OPTION_PREVENTS_STATEMENT, ER_CANT_EXECUTE_IN_READ_ONLY_TRANSACTION
+        1044 | 1142 | 1143 | 1227 | 1290 | 1792 => ErrorKind::Permission,
+        // ER_BAD_DB_ERROR, ER_BAD_FIELD_ERROR, ER_NO_SUCH_TABLE, ER_SP_DOES_NOT_EXIST
+        1049 | 1054 | 1146 | 1305 => ErrorKind::NotFound,
+        // ER_LOCK_WAIT_TIMEOUT, ER_QUERY_TIMEOUT
+        1205 | 3024 => ErrorKind::Timeout,
+        // ER_QUERY_INTERRUPTED
+        1317 => ErrorKind::Cancelled,
+        // ER_DUP_ENTRY, ER_BAD_NULL_ERROR, ER_ROW_IS_REFERENCED_2, ER_NO_REFERENCED_ROW_2,
+        // ER_TRUNCATED_WRONG_VALUE_FOR_FIELD, ER_CHECK_CONSTRAINT_VIOLATED
+        1062 | 1048 | 1451 | 1452 | 1366 | 3819 => ErrorKind::InvalidInput,
+        _ => ErrorKind::Database,
+    }
+}
+
+/// Sorts a SQLite error by its primary result code (the low byte of the extended one).
+/// SQLITE_ERROR covers syntax errors and missing objects alike, so those go by message
+fn sqlite_error_kind(code: Option<u32>, message: &str) -> ErrorKind {
+    match code.map(|code| code & 0xff) {
+        // SQLITE_ERROR
+        Some(1) if message.starts_with("no such") => ErrorKind::NotFound,
+        Some(1) if message.contains("syntax error") => ErrorKind::Syntax,
+        // SQLITE_PERM, SQLITE_READONLY, SQLITE_AUTH
+        Some(3) | Some(8) | Some(23) => ErrorKind::Permission,
+        // SQLITE_BUSY, SQLITE_LOCKED
+        Some(5) | Some(6) => ErrorKind::Timeout,
+        // SQLITE_INTERRUPT
+        Some(9) => ErrorKind::Cancelled,
+        // SQLITE_CANTOPEN
+        Some(14) => ErrorKind::Connection,
+        // SQLITE_CONSTRAINT, SQLITE_MISMATCH
+        Some(19) | Some(20) => ErrorKind::InvalidInput,
+        _ => ErrorKind::Database,
+    }
+}
+
+/// Sorts a Postgres error by its SQLSTATE, going by class (the first two characters) where
+/// the class says enough
+fn sqlstate_kind(code: &str) -> ErrorKind {
+    match code {
+        // insufficient_privilege, read_only_sql_transaction
+        "42501" | "25006" => ErrorKind::Permission,
+        // undefined_table, undefined_column, undefined_function, undefined_object,
+        // invalid_schema_name, invalid_catalog_name
+        "42P01" | "42703" | "42883" | "42704" | "3F000" | "3D000" => ErrorKind::NotFound,
+        // query_canceled, which statement_timeout raises too
+        "57014" => ErrorKind::Timeout,
+        _ => match code.get(..2) {
+            Some("28") => ErrorKind::Auth,
+            Some("08") | Some("57") => ErrorKind::Connection,
+            Some("42") => ErrorKind::Syntax,
+            Some("22") | Some("23") => ErrorKind::InvalidInput,
+            _ => ErrorKind::Database,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sqlstate_kind() {
+        assert_eq!(sqlstate_kind("42601"), ErrorKind::Syntax);
+        assert_eq!(sqlstate_kind("42P01"), ErrorKind::NotFound);
+        assert_eq!(sqlstate_kind("42501"), ErrorKind::Permission);
+        assert_eq!(sqlstate_kind("28P01"), ErrorKind::Auth);
+        assert_eq!(sqlstate_kind("08006"), ErrorKind::Connection);
+        assert_eq!(sqlstate_kind("23505"), ErrorKind::InvalidInput);
+        assert_eq!(sqlstate_kind("40P01"), ErrorKind::Database);
+    }
+
+    #[test]
+    fn test_mysql_error_kind() {
+        assert_eq!(mysql_error_kind(1064), ErrorKind::Syntax);
+        assert_eq!(mysql_error_kind(1146), ErrorKind::NotFound);
+        assert_eq!(mysql_error_kind(1142), ErrorKind::Permission);
+        assert_eq!(mysql_error_kind(1062), ErrorKind::InvalidInput);
+        assert_eq!(mysql_error_kind(1213), ErrorKind::Database);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_error_kind() {
+        // Every connection to :memory: opens a database of its own
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let kind = |sql: &'static str| {
+            let pool = pool.clone();
+            async move {
+                let error = sqlx::query(sql).execute(&pool).await.unwrap_err();
+                CommandError::from(SqliteError::QueryFailed(error)).kind
+            }
+        };
+
+        assert_eq!(kind("SELEC 1").await, ErrorKind::Syntax);
+        assert_eq!(kind("SELECT * FROM missing").await, ErrorKind::NotFound);
+        sqlx::query("CREATE TABLE t (id INTEGER PRIMARY KEY)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO t VALUES (1)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            kind("INSERT INTO t VALUES (1)").await,
+            ErrorKind::InvalidInput
+        );
+    }
+}
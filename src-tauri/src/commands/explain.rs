@@ -1,26 +1,144 @@
-use crate::db::postgres::PostgresState;
+use crate::commands::error::CommandError;
+use crate::db::metadata::{self, SavedExplainPlan};
+use crate::db::postgres::{position_after, PostgresError, PostgresState};
+use crate::db::script::{self, QueryKind};
+use crate::db::search;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
+use std::collections::HashMap;
 use tauri::State;
 
+/// Sequential scans over tables with at least this many estimated rows are risky
+const LARGE_TABLE_ROWS: f64 = 100_000.0;
+const HUGE_TABLE_ROWS: f64 = 1_000_000.0;
+/// Planner cost thresholds for flagging a query as expensive
+const HIGH_PLAN_COST: f64 = 100_000.0;
+const VERY_HIGH_PLAN_COST: f64 = 1_000_000.0;
+/// A node whose actual row count is off from the estimate by this factor, either way,
+/// is flagged as a bad estimate
+const BAD_ESTIMATE_FACTOR: f64 = 10.0;
+/// How many of the nodes with the most exclusive time explain_query reports as slow
+const SLOW_NODE_COUNT: usize = 3;
+/// Put in front of the SQL to explain it without running it
+const EXPLAIN_PREFIX: &str = "EXPLAIN (FORMAT JSON, VERBOSE) ";
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ExplainResult {
     pub plan: JsonValue,
+    pub nodes: Vec<PlanNode>,
+    pub warnings: Vec<PlanWarning>,
     pub planning_time: Option<f64>,
     pub execution_time: Option<f64>,
     pub total_cost: Option<f64>,
 }
 
-/// Runs EXPLAIN ANALYZE on a query and returns the execution plan
+/// A single node of an EXPLAIN plan, flattened out of the nested `Plans` arrays
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanNode {
+    pub id: usize,
+    pub parent: Option<usize>,
+    pub node_type: String,
+    pub schema: Option<String>,
+    pub relation_name: Option<String>,
+    pub startup_cost: Option<f64>,
+    pub total_cost: Option<f64>,
+    pub plan_rows: Option<f64>,
+    pub actual_rows: Option<f64>,
+    pub actual_time: Option<f64>,
+    pub loops: Option<f64>,
+    /// Time spent in this node itself, over all loops, excluding its children
+    pub self_time: Option<f64>,
+    /// Actual rows over estimated rows (both floored at 1); only set for analyzed plans
+    pub estimate_ratio: Option<f64>,
+    pub bad_estimate: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanWarningKind {
+    SlowNode,
+    SeqScanOnLargeTable,
+    BadEstimate,
+}
+
+/// Something in an analyzed plan worth tuning, pointing at the node by its PlanNode id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanWarning {
+    pub kind: PlanWarningKind,
+    pub node_id: usize,
+    pub node_type: String,
+    pub relation_name: Option<String>,
+    pub message: String,
+}
+
+/// How one node changed between two plans. Nodes are matched by node type and relation
+/// name, in order of appearance when a plan has several alike
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanNodeDiff {
+    pub node_type: String,
+    pub relation_name: Option<String>,
+    /// The node in each plan; None when it only appears in the other one
+    pub before: Option<PlanNode>,
+    pub after: Option<PlanNode>,
+    pub total_cost_delta: Option<f64>,
+    pub self_time_delta: Option<f64>,
+}
+
+/// Differences between two plans; every delta is `after - before`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanDiff {
+    pub planning_time_delta: Option<f64>,
+    pub execution_time_delta: Option<f64>,
+    pub total_cost_delta: Option<f64>,
+    pub nodes: Vec<PlanNodeDiff>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskLevel {
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskFinding {
+    pub level: RiskLevel,
+    pub node_type: String,
+    pub relation_name: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryRiskReport {
+    pub level: RiskLevel,
+    pub total_cost: Option<f64>,
+    pub findings: Vec<RiskFinding>,
+}
+
+/// The estimated plan of one statement of a script
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatementPlan {
+    /// 0-based position of the statement in the script
+    pub statement_index: usize,
+    /// The start of the statement, on one line
+    pub sql_snippet: String,
+    pub kind: QueryKind,
+    pub total_cost: Option<f64>,
+    /// None for statements EXPLAIN doesn't take, such as DDL, and when EXPLAIN failed
+    pub plan: Option<JsonValue>,
+    /// Why EXPLAIN failed, e.g. the statement uses a table an earlier one creates
+    pub error: Option<String>,
+}
+
+/// Runs EXPLAIN ANALYZE on a query and returns the execution plan, flattened into nodes
+/// and with warnings about slow nodes, large sequential scans and bad row estimates
 #[tauri::command]
 pub async fn explain_query(
     sql: String,
     postgres: State<'_, PostgresState>,
-) -> Result<ExplainResult, String> {
-    let plan = postgres
-        .explain_query(&sql)
-        .await
-        .map_err(|e| e.to_string())?;
+) -> Result<ExplainResult, CommandError> {
+    let plan = postgres.explain_query(&sql).await?;
 
     // Extract timing information from the plan
     let planning_time = plan
@@ -39,7 +157,12 @@ pub async fn explain_query(
         .and_then(|p| p.get("Total Cost"))
         .and_then(|v| v.as_f64());
 
+    let table_rows = seq_scan_table_rows(&plan, &postgres).await?;
+    let nodes = flatten_plan(&plan);
+
     Ok(ExplainResult {
+        warnings: find_plan_warnings(&nodes, &table_rows),
+        nodes,
         plan,
         planning_time,
         execution_time,
@@ -52,11 +175,11 @@ pub async fn explain_query(
 pub async fn explain_query_no_analyze(
     sql: String,
     postgres: State<'_, PostgresState>,
-) -> Result<JsonValue, String> {
+) -> Result<JsonValue, CommandError> {
     let pool = postgres
-        .execute_query(&format!("EXPLAIN (FORMAT JSON, VERBOSE) {}", sql))
+        .execute_query(&format!("{}{}", EXPLAIN_PREFIX, sql))
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| e.map_position(|p| position_after(EXPLAIN_PREFIX, p)))?;
 
     // The result is returned as a single row with a JSON column
     if let Some(row) = pool.rows.first() {
@@ -65,6 +188,782 @@ pub async fn explain_query_no_analyze(
         }
     }
 
-    Err("Failed to parse EXPLAIN output".to_string())
+    Err(CommandError::internal("Failed to parse EXPLAIN output"))
+}
+
+/// Renders the query plan as a Graphviz DOT graph
+#[tauri::command]
+pub async fn explain_query_dot(
+    sql: String,
+    analyze: bool,
+    postgres: State<'_, PostgresState>,
+) -> Result<String, CommandError> {
+    let plan = fetch_plan(&sql, analyze, &postgres).await?;
+    Ok(plan_to_dot(&flatten_plan(&plan)))
+}
+
+/// Estimates how expensive a query is from its plan (without running it)
+#[tauri::command]
+pub async fn query_risk_check(
+    sql: String,
+    postgres: State<'_, PostgresState>,
+) -> Result<QueryRiskReport, CommandError> {
+    let plan = fetch_plan(&sql, false, &postgres).await?;
+    let table_rows = seq_scan_table_rows(&plan, &postgres).await?;
+
+    Ok(assess_plan_risk(&plan, &table_rows))
+}
+
+/// Runs EXPLAIN (without ANALYZE) on each SELECT, INSERT, UPDATE, DELETE and MERGE of a
+/// script, so the expensive steps show before it is run. Nothing is executed, so a
+/// statement that relies on an earlier one having run, e.g. on a table it creates, gets
+/// the planner's error instead of a plan. Other statements are listed without a plan
+#[tauri::command]
+pub async fn explain_script(
+    sql: String,
+    postgres: State<'_, PostgresState>,
+) -> Result<Vec<StatementPlan>, CommandError> {
+    explain_statements(&sql, &postgres).await
+}
+
+async fn explain_statements(
+    sql: &str,
+    postgres: &PostgresState,
+) -> Result<Vec<StatementPlan>, CommandError> {
+    if postgres.get_connection_id().await.is_none() {
+        return Err(PostgresError::NoActiveConnection.into());
+    }
+
+    let mut plans = Vec::new();
+    for (statement_index, statement) in script::split_statements(sql).iter().enumerate() {
+        let kind = script::classify(statement).kind;
+        let explainable = matches!(
+            kind,
+            QueryKind::Select
+                | QueryKind::Insert
+                | QueryKind::Update
+                | QueryKind::Delete
+                | QueryKind::Merge
+        );
+
+        let (plan, error) = if explainable {
+            match fetch_plan(statement, false, postgres).await {
+                Ok(plan) => (Some(plan), None),
+                Err(e) => (None, Some(e.message)),
+            }
+        } else {
+            (None, None)
+        };
+
+        plans.push(StatementPlan {
+            statement_index,
+            sql_snippet: search::snippet(statement, ""),
+            kind,
+            total_cost: plan
+                .as_ref()
+                .and_then(|plan| plan.get(0)?.get("Plan")?.get("Total Cost")?.as_f64()),
+            plan,
+            error,
+        });
+    }
+
+    Ok(plans)
+}
+
+/// Saves a plan (as returned by explain_query) under the active connection for comparison
+#[tauri::command]
+pub async fn save_explain_plan(
+    sql: String,
+    plan: JsonValue,
+    label: Option<String>,
+    postgres: State<'_, PostgresState>,
+) -> Result<SavedExplainPlan, CommandError> {
+    let connection_id = postgres.get_connection_id().await;
+    metadata::save_explain_plan(connection_id.as_deref(), &sql, &plan, label.as_deref())
+        .map_err(CommandError::from)
+}
+
+/// Lists saved plans, newest first, optionally only those of one connection
+#[tauri::command]
+pub fn list_explain_plans(
+    connection_id: Option<String>,
+) -> Result<Vec<SavedExplainPlan>, CommandError> {
+    metadata::list_explain_plans(connection_id.as_deref()).map_err(CommandError::from)
+}
+
+/// Deletes a saved plan
+#[tauri::command]
+pub fn delete_explain_plan(id: String) -> Result<(), CommandError> {
+    metadata::delete_explain_plan(&id).map_err(CommandError::from)
+}
+
+/// Compares two saved plans node by node, e.g. before and after adding an index
+#[tauri::command]
+pub fn diff_explain_plans(id_a: String, id_b: String) -> Result<PlanDiff, CommandError> {
+    let before = metadata::get_explain_plan_by_id(&id_a)?;
+    let after = metadata::get_explain_plan_by_id(&id_b)?;
+    Ok(diff_plans(&before.plan, &after.plan))
+}
+
+/// Looks up the planner's size estimate for every relation the plan scans sequentially,
+/// keyed by "schema.table"
+async fn seq_scan_table_rows(
+    plan: &JsonValue,
+    postgres: &PostgresState,
+) -> Result<HashMap<String, f64>, CommandError> {
+    let mut table_rows = HashMap::new();
+    for node in plan_nodes(plan) {
+        if node.get("Node Type").and_then(|v| v.as_str()) != Some("Seq Scan") {
+            continue;
+        }
+        if let (Some(schema), Some(relation)) = (
+            node.get("Schema").and_then(|v| v.as_str()),
+            node.get("Relation Name").and_then(|v| v.as_str()),
+        ) {
+            let key = format!("{}.{}", schema, relation);
+            if table_rows.contains_key(&key) {
+                continue;
+            }
+            if let Some(rows) = postgres.estimate_table_rows(schema, relation).await? {
+                table_rows.insert(key, rows);
+            }
+        }
+    }
+
+    Ok(table_rows)
+}
+
+/// Runs EXPLAIN with or without ANALYZE and returns the raw JSON plan
+async fn fetch_plan(
+    sql: &str,
+    analyze: bool,
+    postgres: &PostgresState,
+) -> Result<JsonValue, CommandError> {
+    if analyze {
+        return postgres
+            .explain_query(sql)
+            .await
+            .map_err(CommandError::from);
+    }
+
+    let result = postgres
+        .execute_query(&format!("{}{}", EXPLAIN_PREFIX, sql))
+        .await
+        .map_err(|e| e.map_position(|p| position_after(EXPLAIN_PREFIX, p)))?;
+
+    result
+        .rows
+        .first()
+        .and_then(|row| row.first())
+        .cloned()
+        .ok_or_else(|| CommandError::internal("Failed to parse EXPLAIN output"))
+}
+
+/// Flattens an EXPLAIN (FORMAT JSON) plan into a list of nodes in depth-first order
+pub fn flatten_plan(plan: &JsonValue) -> Vec<PlanNode> {
+    let mut nodes = Vec::new();
+
+    if let Some(root) = plan.get(0).and_then(|p| p.get("Plan")) {
+        flatten_node(root, None, &mut nodes);
+    }
+
+    // Actual Total Time is per loop; a node's own time is its total minus its children's.
+    // Parallel workers can make the children add up to more, hence the floor at zero
+    let totals: Vec<Option<f64>> = nodes
+        .iter()
+        .map(|n| n.actual_time.map(|t| t * n.loops.unwrap_or(1.0)))
+        .collect();
+    for id in 0..nodes.len() {
+        nodes[id].self_time = totals[id].map(|total| {
+            let children: f64 = nodes
+                .iter()
+                .filter(|n| n.parent == Some(id))
+                .filter_map(|n| totals[n.id])
+                .sum();
+            (total - children).max(0.0)
+        });
+    }
+
+    nodes
+}
+
+fn flatten_node(node: &JsonValue, parent: Option<usize>, nodes: &mut Vec<PlanNode>) {
+    let id = nodes.len();
+    let number = |key: &str| node.get(key).and_then(|v| v.as_f64());
+    let plan_rows = number("Plan Rows");
+    let actual_rows = number("Actual Rows");
+    let estimate_ratio = plan_rows
+        .zip(actual_rows)
+        .map(|(planned, actual)| actual.max(1.0) / planned.max(1.0));
+
+    nodes.push(PlanNode {
+        id,
+        parent,
+        node_type: node
+            .get("Node Type")
+            .and_then(|v| v.as_str())
+            .unwrap_or("Unknown")
+            .to_string(),
+        schema: node
+            .get("Schema")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
+        relation_name: node
+            .get("Relation Name")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
+        startup_cost: number("Startup Cost"),
+        total_cost: number("Total Cost"),
+        plan_rows,
+        actual_rows,
+        actual_time: number("Actual Total Time"),
+        loops: number("Actual Loops"),
+        self_time: None,
+        estimate_ratio,
+        bad_estimate: estimate_ratio.is_some_and(|ratio| {
+            ratio >= BAD_ESTIMATE_FACTOR || ratio <= 1.0 / BAD_ESTIMATE_FACTOR
+        }),
+    });
+
+    if let Some(children) = node.get("Plans").and_then(|v| v.as_array()) {
+        for child in children {
+            flatten_node(child, Some(id), nodes);
+        }
+    }
+}
+
+/// Reports the SLOW_NODE_COUNT nodes with the most exclusive time, then sequential scans on
+/// large tables and nodes whose row estimate was off by BAD_ESTIMATE_FACTOR or more.
+/// `table_rows` maps "schema.table" to the planner's row estimate (`pg_class.reltuples`).
+pub fn find_plan_warnings(
+    nodes: &[PlanNode],
+    table_rows: &HashMap<String, f64>,
+) -> Vec<PlanWarning> {
+    let warning = |kind, node: &PlanNode, message| PlanWarning {
+        kind,
+        node_id: node.id,
+        node_type: node.node_type.clone(),
+        relation_name: node.relation_name.clone(),
+        message,
+    };
+
+    let mut slowest: Vec<(&PlanNode, f64)> = nodes
+        .iter()
+        .filter_map(|n| n.self_time.filter(|t| *t > 0.0).map(|t| (n, t)))
+        .collect();
+    slowest.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let mut warnings: Vec<PlanWarning> = slowest
+        .into_iter()
+        .take(SLOW_NODE_COUNT)
+        .map(|(node, time)| {
+            warning(
+                PlanWarningKind::SlowNode,
+                node,
+                format!("{} spent {:.3} ms in itself", node.node_type, time),
+            )
+        })
+        .collect();
+
+    for node in nodes {
+        if node.node_type == "Seq Scan" {
+            let relation = node.relation_name.as_deref().unwrap_or("");
+            let key = format!("{}.{}", node.schema.as_deref().unwrap_or(""), relation);
+            if let Some(&rows) = table_rows.get(&key).filter(|r| **r >= LARGE_TABLE_ROWS) {
+                warnings.push(warning(
+                    PlanWarningKind::SeqScanOnLargeTable,
+                    node,
+                    format!("Sequential scan on {} (~{:.0} rows)", relation, rows),
+                ));
+            }
+        }
+
+        if let (true, Some(planned), Some(actual)) =
+            (node.bad_estimate, node.plan_rows, node.actual_rows)
+        {
+            warnings.push(warning(
+                PlanWarningKind::BadEstimate,
+                node,
+                format!(
+                    "{} estimated {:.0} rows but returned {:.0}",
+                    node.node_type, planned, actual
+                ),
+            ));
+        }
+    }
+
+    warnings
+}
+
+/// Compares two EXPLAIN (FORMAT JSON) plans. Nodes of `before` come first in plan order,
+/// followed by the nodes that only `after` has
+pub fn diff_plans(before: &JsonValue, after: &JsonValue) -> PlanDiff {
+    let delta = |before: Option<f64>, after: Option<f64>| Some(after? - before?);
+    let summary = |plan: &JsonValue, key: &str| plan.get(0)?.get(key)?.as_f64();
+    let total_cost = |plan: &JsonValue| plan.get(0)?.get("Plan")?.get("Total Cost")?.as_f64();
+
+    // The n-th node with a given type and relation in one plan matches the n-th in the other
+    let keyed = |nodes: Vec<PlanNode>| {
+        let mut seen: HashMap<(String, Option<String>), usize> = HashMap::new();
+        nodes
+            .into_iter()
+            .map(|node| {
+                let key = (node.node_type.clone(), node.relation_name.clone());
+                let occurrence = seen.entry(key.clone()).or_default();
+                *occurrence += 1;
+                ((key.0, key.1, *occurrence), node)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut after_nodes: HashMap<_, _> = keyed(flatten_plan(after)).into_iter().collect();
+    let mut nodes: Vec<PlanNodeDiff> = keyed(flatten_plan(before))
+        .into_iter()
+        .map(|(key, node)| (after_nodes.remove(&key), node))
+        .map(|(after, before)| PlanNodeDiff {
+            node_type: before.node_type.clone(),
+            relation_name: before.relation_name.clone(),
+            total_cost_delta: delta(before.total_cost, after.as_ref().and_then(|n| n.total_cost)),
+            self_time_delta: delta(before.self_time, after.as_ref().and_then(|n| n.self_time)),
+            before: Some(before),
+            after,
+        })
+        .collect();
+
+    let mut added: Vec<PlanNode> = after_nodes.into_values().collect();
+    added.sort_by_key(|node| node.id);
+    nodes.extend(added.into_iter().map(|after| PlanNodeDiff {
+        node_type: after.node_type.clone(),
+        relation_name: after.relation_name.clone(),
+        before: None,
+        after: Some(after),
+        total_cost_delta: None,
+        self_time_delta: None,
+    }));
+
+    PlanDiff {
+        planning_time_delta: delta(
+            summary(before, "Planning Time"),
+            summary(after, "Planning Time"),
+        ),
+        execution_time_delta: delta(
+            summary(before, "Execution Time"),
+            summary(after, "Execution Time"),
+        ),
+        total_cost_delta: delta(total_cost(before), total_cost(after)),
+        nodes,
+    }
+}
+
+/// Collects every node of an EXPLAIN (FORMAT JSON) plan in depth-first order
+fn plan_nodes(plan: &JsonValue) -> Vec<&JsonValue> {
+    let mut nodes = Vec::new();
+    let mut stack: Vec<&JsonValue> = plan
+        .get(0)
+        .and_then(|p| p.get("Plan"))
+        .into_iter()
+        .collect();
+
+    while let Some(node) = stack.pop() {
+        nodes.push(node);
+        if let Some(children) = node.get("Plans").and_then(|v| v.as_array()) {
+            stack.extend(children.iter().rev());
+        }
+    }
+
+    nodes
+}
+
+/// Flags sequential scans on large tables, expensive plans and cartesian joins.
+/// `table_rows` maps "schema.table" to the planner's row estimate (`pg_class.reltuples`).
+pub fn assess_plan_risk(plan: &JsonValue, table_rows: &HashMap<String, f64>) -> QueryRiskReport {
+    let mut findings = Vec::new();
+
+    for node in plan_nodes(plan) {
+        let node_type = node
+            .get("Node Type")
+            .and_then(|v| v.as_str())
+            .unwrap_or("Unknown");
+        let relation_name = node
+            .get("Relation Name")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+
+        if node_type == "Seq Scan" {
+            let schema = node.get("Schema").and_then(|v| v.as_str()).unwrap_or("");
+            let relation = relation_name.as_deref().unwrap_or("");
+            if let Some(&rows) = table_rows.get(&format!("{}.{}", schema, relation)) {
+                if rows >= LARGE_TABLE_ROWS {
+                    findings.push(RiskFinding {
+                        level: if rows >= HUGE_TABLE_ROWS {
+                            RiskLevel::High
+                        } else {
+                            RiskLevel::Medium
+                        },
+                        node_type: node_type.to_string(),
+                        relation_name: relation_name.clone(),
+                        message: format!("Sequential scan on {} (~{:.0} rows)", relation, rows),
+                    });
+                }
+            }
+        }
+
+        if node_type == "Nested Loop" && is_cartesian_join(node) {
+            findings.push(RiskFinding {
+                level: RiskLevel::High,
+                node_type: node_type.to_string(),
+                relation_name: None,
+                message: "Join without a join condition (cartesian product)".to_string(),
+            });
+        }
+    }
+
+    let total_cost = plan
+        .get(0)
+        .and_then(|p| p.get("Plan"))
+        .and_then(|p| p.get("Total Cost"))
+        .and_then(|v| v.as_f64());
+
+    if let Some(cost) = total_cost {
+        if cost >= HIGH_PLAN_COST {
+            findings.push(RiskFinding {
+                level: if cost >= VERY_HIGH_PLAN_COST {
+                    RiskLevel::High
+                } else {
+                    RiskLevel::Medium
+                },
+                node_type: "Plan".to_string(),
+                relation_name: None,
+                message: format!("High estimated cost ({:.0})", cost),
+            });
+        }
+    }
+
+    QueryRiskReport {
+        level: findings
+            .iter()
+            .map(|f| f.level)
+            .max()
+            .unwrap_or(RiskLevel::Low),
+        total_cost,
+        findings,
+    }
 }
 
+/// A nested loop is a cartesian product when nothing restricts which inner rows match
+fn is_cartesian_join(node: &JsonValue) -> bool {
+    if node.get("Join Filter").is_some() {
+        return false;
+    }
+
+    let inner = match node
+        .get("Plans")
+        .and_then(|v| v.as_array())
+        .and_then(|children| children.get(1))
+    {
+        Some(inner) => inner,
+        None => return false,
+    };
+
+    // A parameterized inner side (e.g. an index lookup per outer row) carries its own condition
+    let conditions = [
+        "Index Cond",
+        "Recheck Cond",
+        "Filter",
+        "Hash Cond",
+        "Merge Cond",
+    ];
+    !plan_nodes_from(inner)
+        .iter()
+        .any(|n| conditions.iter().any(|key| n.get(*key).is_some()))
+}
+
+fn plan_nodes_from(node: &JsonValue) -> Vec<&JsonValue> {
+    let mut nodes = vec![node];
+    if let Some(children) = node.get("Plans").and_then(|v| v.as_array()) {
+        for child in children {
+            nodes.extend(plan_nodes_from(child));
+        }
+    }
+    nodes
+}
+
+/// Serializes flattened plan nodes into a Graphviz DOT digraph
+pub fn plan_to_dot(nodes: &[PlanNode]) -> String {
+    let mut dot = String::from("digraph plan {\n    node [shape=box, fontname=\"monospace\"];\n");
+
+    for node in nodes {
+        let mut label = node.node_type.clone();
+        if let Some(relation) = &node.relation_name {
+            label.push_str(&format!(" on {}", relation));
+        }
+        if let Some(cost) = node.total_cost {
+            label.push_str(&format!("\ncost={:.2}", cost));
+        }
+        if let Some(time) = node.actual_time {
+            label.push_str(&format!("\ntime={:.3} ms", time));
+        }
+
+        dot.push_str(&format!(
+            "    n{} [label=\"{}\"];\n",
+            node.id,
+            escape_dot_label(&label)
+        ));
+    }
+
+    for node in nodes {
+        if let Some(parent) = node.parent {
+            dot.push_str(&format!("    n{} -> n{};\n", parent, node.id));
+        }
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+/// Escapes a string for use inside a double-quoted DOT label
+fn escape_dot_label(label: &str) -> String {
+    let mut escaped = String::with_capacity(label.len());
+    for c in label.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample_plan() -> JsonValue {
+        json!([{
+            "Plan": {
+                "Node Type": "Hash Join",
+                "Total Cost": 42.5,
+                "Plans": [
+                    { "Node Type": "Seq Scan", "Relation Name": "orders", "Total Cost": 20.0 },
+                    {
+                        "Node Type": "Hash",
+                        "Plans": [
+                            { "Node Type": "Seq Scan", "Relation Name": "users\"x", "Total Cost": 10.0 }
+                        ]
+                    }
+                ]
+            }
+        }])
+    }
+
+    #[test]
+    fn test_flatten_plan() {
+        let nodes = flatten_plan(&sample_plan());
+        assert_eq!(nodes.len(), 4);
+        assert_eq!(nodes[0].parent, None);
+        assert_eq!(nodes[1].parent, Some(0));
+        assert_eq!(nodes[1].relation_name.as_deref(), Some("orders"));
+        assert_eq!(nodes[3].parent, Some(2));
+        assert_eq!(nodes[0].estimate_ratio, None);
+        assert!(!nodes[0].bad_estimate);
+
+        let analyzed = flatten_plan(&json!([{
+            "Plan": {
+                "Node Type": "Nested Loop",
+                "Plan Rows": 5,
+                "Actual Rows": 4,
+                "Plans": [
+                    { "Node Type": "Seq Scan", "Plan Rows": 2, "Actual Rows": 500 },
+                    { "Node Type": "Index Scan", "Plan Rows": 1000, "Actual Rows": 0 }
+                ]
+            }
+        }]));
+        assert_eq!(analyzed[0].estimate_ratio, Some(0.8));
+        assert!(!analyzed[0].bad_estimate);
+        assert_eq!(analyzed[1].estimate_ratio, Some(250.0));
+        assert!(analyzed[1].bad_estimate);
+        assert_eq!(analyzed[2].estimate_ratio, Some(0.001));
+        assert!(analyzed[2].bad_estimate);
+    }
+
+    #[test]
+    fn test_find_plan_warnings() {
+        let nodes = flatten_plan(&json!([{
+            "Plan": {
+                "Node Type": "Hash Join",
+                "Plan Rows": 100,
+                "Actual Rows": 90,
+                "Actual Total Time": 50.0,
+                "Actual Loops": 1,
+                "Plans": [
+                    {
+                        "Node Type": "Seq Scan",
+                        "Schema": "public",
+                        "Relation Name": "events",
+                        "Plan Rows": 100,
+                        "Actual Rows": 5000,
+                        "Actual Total Time": 30.0,
+                        "Actual Loops": 1
+                    },
+                    {
+                        "Node Type": "Index Scan",
+                        "Schema": "public",
+                        "Relation Name": "users",
+                        "Plan Rows": 1,
+                        "Actual Rows": 1,
+                        "Actual Total Time": 0.5,
+                        "Actual Loops": 10
+                    }
+                ]
+            }
+        }]));
+        assert_eq!(nodes[0].self_time, Some(15.0));
+        assert_eq!(nodes[1].self_time, Some(30.0));
+        assert_eq!(nodes[2].self_time, Some(5.0));
+
+        let mut table_rows = HashMap::new();
+        table_rows.insert("public.events".to_string(), 2_000_000.0);
+
+        let warnings = find_plan_warnings(&nodes, &table_rows);
+        let kinds: Vec<(PlanWarningKind, usize)> =
+            warnings.iter().map(|w| (w.kind, w.node_id)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (PlanWarningKind::SlowNode, 1),
+                (PlanWarningKind::SlowNode, 0),
+                (PlanWarningKind::SlowNode, 2),
+                (PlanWarningKind::SeqScanOnLargeTable, 1),
+                (PlanWarningKind::BadEstimate, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_plans() {
+        let before = json!([{
+            "Execution Time": 120.0,
+            "Plan": {
+                "Node Type": "Hash Join",
+                "Total Cost": 500.0,
+                "Actual Total Time": 110.0,
+                "Actual Loops": 1,
+                "Plans": [
+                    {
+                        "Node Type": "Seq Scan",
+                        "Relation Name": "orders",
+                        "Total Cost": 400.0,
+                        "Actual Total Time": 100.0,
+                        "Actual Loops": 1
+                    }
+                ]
+            }
+        }]);
+        let after = json!([{
+            "Execution Time": 12.0,
+            "Plan": {
+                "Node Type": "Hash Join",
+                "Total Cost": 80.0,
+                "Actual Total Time": 11.0,
+                "Actual Loops": 1,
+                "Plans": [
+                    {
+                        "Node Type": "Index Scan",
+                        "Relation Name": "orders",
+                        "Total Cost": 8.0,
+                        "Actual Total Time": 1.0,
+                        "Actual Loops": 1
+                    }
+                ]
+            }
+        }]);
+
+        let diff = diff_plans(&before, &after);
+        assert_eq!(diff.execution_time_delta, Some(-108.0));
+        assert_eq!(diff.total_cost_delta, Some(-420.0));
+        assert_eq!(diff.planning_time_delta, None);
+        assert_eq!(diff.nodes.len(), 3);
+
+        assert_eq!(diff.nodes[0].node_type, "Hash Join");
+        assert_eq!(diff.nodes[0].total_cost_delta, Some(-420.0));
+        assert_eq!(diff.nodes[0].self_time_delta, Some(0.0));
+        assert_eq!(diff.nodes[1].node_type, "Seq Scan");
+        assert!(diff.nodes[1].after.is_none());
+        assert_eq!(diff.nodes[2].node_type, "Index Scan");
+        assert!(diff.nodes[2].before.is_none());
+    }
+
+    #[test]
+    fn test_assess_plan_risk() {
+        let plan = json!([{
+            "Plan": {
+                "Node Type": "Nested Loop",
+                "Total Cost": 250000.0,
+                "Plans": [
+                    { "Node Type": "Seq Scan", "Schema": "public", "Relation Name": "events" },
+                    {
+                        "Node Type": "Materialize",
+                        "Plans": [
+                            { "Node Type": "Seq Scan", "Schema": "public", "Relation Name": "users" }
+                        ]
+                    }
+                ]
+            }
+        }]);
+
+        let mut table_rows = HashMap::new();
+        table_rows.insert("public.events".to_string(), 5_000_000.0);
+        table_rows.insert("public.users".to_string(), 200.0);
+
+        let report = assess_plan_risk(&plan, &table_rows);
+        assert_eq!(report.level, RiskLevel::High);
+        assert_eq!(report.findings.len(), 3);
+        assert!(report
+            .findings
+            .iter()
+            .any(|f| f.message.contains("cartesian")));
+
+        let safe = assess_plan_risk(
+            &json!([{ "Plan": { "Node Type": "Index Scan", "Total Cost": 8.0 } }]),
+            &table_rows,
+        );
+        assert_eq!(safe.level, RiskLevel::Low);
+        assert!(safe.findings.is_empty());
+    }
+
+    #[test]
+    fn test_plan_to_dot() {
+        let dot = plan_to_dot(&flatten_plan(&sample_plan()));
+        assert!(dot.starts_with("digraph plan {"));
+        assert!(dot.contains("n0 -> n1;"));
+        assert!(dot.contains("n2 -> n3;"));
+        assert!(dot.contains(r#"Seq Scan on users\"x\ncost=10.00"#));
+    }
+
+    #[tokio::test]
+    async fn test_explain_statements() {
+        let disconnected = PostgresState::default();
+        assert!(explain_statements("SELECT 1", &disconnected).await.is_err());
+
+        let Some(pg) = crate::db::postgres::tests::test_database().await else {
+            return;
+        };
+        let postgres = PostgresState::new(pg);
+        let plans = explain_statements(
+            "SELECT 1; CREATE TABLE dt_explained (a int); SELECT * FROM dt_explained",
+            &postgres,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(plans.len(), 3);
+        assert_eq!(plans[0].kind, QueryKind::Select);
+        assert!(plans[0].plan.is_some() && plans[0].total_cost.is_some());
+        // Listed without a plan, and not run
+        assert!(plans[1].plan.is_none() && plans[1].error.is_none());
+        assert_eq!(plans[2].statement_index, 2);
+        assert!(plans[2].error.as_ref().unwrap().contains("dt_explained"));
+    }
+}
@@ -0,0 +1,489 @@
+use crate::commands::error::CommandError;
+use crate::db::postgres::{quote_ident, ColumnMeta, PostgresState};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use tauri::{AppHandle, Emitter, State};
+
+/// An `export-progress` event is emitted every this many rows
+const PROGRESS_INTERVAL_ROWS: u64 = 10_000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    /// Newline-delimited JSON, one row object per line
+    Jsonl,
+}
+
+/// Output formats of export_query_json
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JsonExportFormat {
+    Json,
+    Jsonl,
+}
+
+impl From<JsonExportFormat> for ExportFormat {
+    fn from(format: JsonExportFormat) -> Self {
+        match format {
+            JsonExportFormat::Json => ExportFormat::Json,
+            JsonExportFormat::Jsonl => ExportFormat::Jsonl,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportProgress {
+    pub export_id: String,
+    pub rows_written: u64,
+    pub percent: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportSummary {
+    pub rows_written: u64,
+    pub file_size: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LiteralLang {
+    Rust,
+    Python,
+    JavaScript,
+}
+
+impl LiteralLang {
+    fn indent(self) -> &'static str {
+        match self {
+            LiteralLang::JavaScript => "  ",
+            LiteralLang::Rust | LiteralLang::Python => "    ",
+        }
+    }
+}
+
+/// Renders a cell or row as a literal in the given language (rust, python, javascript, json)
+#[tauri::command]
+pub fn format_as_literal(value: JsonValue, lang: String) -> Result<String, CommandError> {
+    let lang = match lang.to_lowercase().as_str() {
+        "json" => return serde_json::to_string_pretty(&value).map_err(CommandError::from),
+        "rust" | "rs" => LiteralLang::Rust,
+        "python" | "py" => LiteralLang::Python,
+        "javascript" | "js" | "typescript" | "ts" => LiteralLang::JavaScript,
+        other => {
+            return Err(CommandError::invalid_input(format!(
+                "Unsupported literal language: {}",
+                other
+            )))
+        }
+    };
+
+    let body = render_literal(&value, lang, 0);
+    Ok(match lang {
+        LiteralLang::Rust => format!("serde_json::json!({})", body),
+        _ => body,
+    })
+}
+
+/// Streams a whole table to a CSV or JSON file, emitting `export-progress` events as rows
+/// are written. The export can be aborted with cancel_export; the partial file is removed.
+#[tauri::command]
+pub async fn export_table_streaming(
+    export_id: String,
+    schema: String,
+    table: String,
+    path: String,
+    format: ExportFormat,
+    app: AppHandle,
+    postgres: State<'_, PostgresState>,
+) -> Result<ExportSummary, CommandError> {
+    let writer = ExportWriter::create(Path::new(&path), format, false)?;
+
+    // reltuples is only an estimate, so it can only drive an approximate percentage
+    let estimated_rows = postgres
+        .estimate_table_rows(&schema, &table)
+        .await
+        .ok()
+        .flatten()
+        .filter(|rows| *rows > 0.0);
+
+    let sql = format!(
+        "SELECT * FROM {}.{}",
+        quote_ident(&schema),
+        quote_ident(&table)
+    );
+    run_export(
+        &export_id,
+        &sql,
+        &path,
+        writer,
+        estimated_rows,
+        &app,
+        &postgres,
+    )
+    .await
+}
+
+/// Streams a query's result to a file of row objects keyed by column name: a JSON array
+/// (optionally indented) or newline-delimited JSON. Progress and cancellation work as for
+/// export_table_streaming.
+#[tauri::command]
+pub async fn export_query_json(
+    export_id: String,
+    sql: String,
+    path: String,
+    format: JsonExportFormat,
+    pretty: Option<bool>,
+    app: AppHandle,
+    postgres: State<'_, PostgresState>,
+) -> Result<ExportSummary, CommandError> {
+    let writer = ExportWriter::create(Path::new(&path), format.into(), pretty.unwrap_or(false))?;
+    run_export(&export_id, &sql, &path, writer, None, &app, &postgres).await
+}
+
+/// Streams `sql` into `writer`, emitting `export-progress` events. The partial file is
+/// removed if the export fails or is cancelled
+async fn run_export(
+    export_id: &str,
+    sql: &str,
+    path: &str,
+    mut writer: ExportWriter<BufWriter<File>>,
+    estimated_rows: Option<f64>,
+    app: &AppHandle,
+    postgres: &PostgresState,
+) -> Result<ExportSummary, CommandError> {
+    let path = Path::new(path);
+    let progress = |rows_written: u64| ExportProgress {
+        export_id: export_id.to_string(),
+        rows_written,
+        percent: estimated_rows.map(|total| (rows_written as f64 / total * 100.0).min(100.0)),
+    };
+
+    let cancelled = postgres.begin_export(export_id).await;
+    let mut rows_written = 0u64;
+
+    let result = postgres
+        .stream_query(sql, &cancelled, |columns, row| {
+            writer.write_row(columns, row).map_err(|e| e.to_string())?;
+            rows_written += 1;
+            if rows_written.is_multiple_of(PROGRESS_INTERVAL_ROWS) {
+                app.emit("export-progress", progress(rows_written)).ok();
+            }
+            Ok(())
+        })
+        .await
+        .map_err(CommandError::from);
+    postgres.end_export(export_id).await;
+
+    if let Err(e) = result.and_then(|_| writer.finish()) {
+        std::fs::remove_file(path).ok();
+        return Err(e);
+    }
+
+    app.emit(
+        "export-progress",
+        ExportProgress {
+            percent: Some(100.0),
+            ..progress(rows_written)
+        },
+    )
+    .ok();
+
+    let file_size = std::fs::metadata(path)?.len();
+    Ok(ExportSummary {
+        rows_written,
+        file_size,
+    })
+}
+
+/// Cancels a running table export
+#[tauri::command]
+pub async fn cancel_export(
+    export_id: String,
+    postgres: State<'_, PostgresState>,
+) -> Result<bool, CommandError> {
+    Ok(postgres.cancel_export(&export_id).await)
+}
+
+/// Writes rows to an export file one at a time
+enum ExportWriter<W: Write> {
+    Csv {
+        writer: Box<csv::Writer<W>>,
+        wrote_header: bool,
+    },
+    /// A JSON array of row objects
+    Json { out: W, rows: u64, pretty: bool },
+    /// One row object per line
+    Jsonl { out: W },
+}
+
+impl ExportWriter<BufWriter<File>> {
+    fn create(path: &Path, format: ExportFormat, pretty: bool) -> Result<Self, CommandError> {
+        let file = File::create(path)?;
+        Ok(ExportWriter::new(BufWriter::new(file), format, pretty))
+    }
+}
+
+impl<W: Write> ExportWriter<W> {
+    /// `pretty` indents the objects of a JSON array; other formats ignore it
+    fn new(out: W, format: ExportFormat, pretty: bool) -> Self {
+        match format {
+            ExportFormat::Csv => ExportWriter::Csv {
+                writer: Box::new(csv::Writer::from_writer(out)),
+                wrote_header: false,
+            },
+            ExportFormat::Json => ExportWriter::Json {
+                out,
+                rows: 0,
+                pretty,
+            },
+            ExportFormat::Jsonl => ExportWriter::Jsonl { out },
+        }
+    }
+
+    fn write_row(&mut self, columns: &[ColumnMeta], row: &[JsonValue]) -> Result<(), CommandError> {
+        match self {
+            ExportWriter::Csv {
+                writer,
+                wrote_header,
+            } => {
+                if !*wrote_header {
+                    writer.write_record(columns.iter().map(|c| c.name.as_str()))?;
+                    *wrote_header = true;
+                }
+                writer
+                    .write_record(row.iter().map(csv_field))
+                    .map_err(CommandError::from)
+            }
+            ExportWriter::Json { out, rows, pretty } => {
+                out.write_all(if *rows == 0 { b"[\n" } else { b",\n" })?;
+                let object = row_object(columns, row);
+                if *pretty {
+                    let text = serde_json::to_string_pretty(&object)?;
+                    write!(out, "  {}", text.replace('\n', "\n  "))?;
+                } else {
+                    serde_json::to_writer(&mut *out, &object)?;
+                }
+                *rows += 1;
+                Ok(())
+            }
+            ExportWriter::Jsonl { out } => {
+                serde_json::to_writer(&mut *out, &row_object(columns, row))?;
+                out.write_all(b"\n").map_err(CommandError::from)
+            }
+        }
+    }
+
+    fn finish(self) -> Result<W, CommandError> {
+        match self {
+            ExportWriter::Csv { writer, .. } => writer.into_inner().map_err(CommandError::internal),
+            ExportWriter::Json { mut out, rows, .. } => {
+                out.write_all(if rows == 0 { b"[]\n" } else { b"\n]\n" })
+                    .and_then(|_| out.flush())?;
+                Ok(out)
+            }
+            ExportWriter::Jsonl { mut out } => {
+                out.flush()?;
+                Ok(out)
+            }
+        }
+    }
+}
+
+/// Zips a row with its column names into a JSON object
+fn row_object(columns: &[ColumnMeta], row: &[JsonValue]) -> serde_json::Map<String, JsonValue> {
+    columns
+        .iter()
+        .map(|c| c.name.clone())
+        .zip(row.iter().cloned())
+        .collect()
+}
+
+/// NULL is written as an empty field; nested JSON is written as its JSON text
+fn csv_field(value: &JsonValue) -> String {
+    match value {
+        JsonValue::Null => String::new(),
+        JsonValue::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn render_literal(value: &JsonValue, lang: LiteralLang, depth: usize) -> String {
+    match value {
+        JsonValue::Null => match lang {
+            LiteralLang::Python => "None".to_string(),
+            _ => "null".to_string(),
+        },
+        JsonValue::Bool(b) => match (lang, b) {
+            (LiteralLang::Python, true) => "True".to_string(),
+            (LiteralLang::Python, false) => "False".to_string(),
+            _ => b.to_string(),
+        },
+        JsonValue::Number(n) => n.to_string(),
+        JsonValue::String(s) => quote_string(s, lang),
+        JsonValue::Array(items) => {
+            let items: Vec<String> = items
+                .iter()
+                .map(|item| render_literal(item, lang, depth + 1))
+                .collect();
+            wrap_items("[", "]", items, lang, depth)
+        }
+        JsonValue::Object(map) => {
+            let entries: Vec<String> = map
+                .iter()
+                .map(|(key, item)| {
+                    let key = match lang {
+                        LiteralLang::JavaScript if is_js_identifier(key) => key.clone(),
+                        _ => quote_string(key, lang),
+                    };
+                    format!("{}: {}", key, render_literal(item, lang, depth + 1))
+                })
+                .collect();
+            wrap_items("{", "}", entries, lang, depth)
+        }
+    }
+}
+
+fn wrap_items(
+    open: &str,
+    close: &str,
+    items: Vec<String>,
+    lang: LiteralLang,
+    depth: usize,
+) -> String {
+    if items.is_empty() {
+        return format!("{}{}", open, close);
+    }
+
+    let inner = lang.indent().repeat(depth + 1);
+    let outer = lang.indent().repeat(depth);
+    let body: Vec<String> = items
+        .into_iter()
+        .map(|item| format!("{}{},", inner, item))
+        .collect();
+
+    format!("{}\n{}\n{}{}", open, body.join("\n"), outer, close)
+}
+
+/// Quotes a string using the escaping rules of the target language
+fn quote_string(s: &str, lang: LiteralLang) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => match lang {
+                LiteralLang::Rust => quoted.push_str(&format!("\\u{{{:x}}}", c as u32)),
+                LiteralLang::Python if (c as u32) < 0x100 => {
+                    quoted.push_str(&format!("\\x{:02x}", c as u32))
+                }
+                _ => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            },
+            // Line/paragraph separators terminate string literals in older JS engines
+            '\u{2028}' | '\u{2029}' if lang == LiteralLang::JavaScript => {
+                quoted.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn is_js_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '$' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_export_writer() {
+        let columns = vec![
+            ColumnMeta {
+                name: "id".to_string(),
+                data_type: "INT4".to_string(),
+                ..Default::default()
+            },
+            ColumnMeta {
+                name: "note".to_string(),
+                data_type: "TEXT".to_string(),
+                ..Default::default()
+            },
+        ];
+        let rows = [
+            vec![json!(1), json!("a, \"b\"")],
+            vec![json!(2), JsonValue::Null],
+        ];
+
+        let mut csv = ExportWriter::new(Vec::new(), ExportFormat::Csv, false);
+        let mut json = ExportWriter::new(Vec::new(), ExportFormat::Json, false);
+        let mut pretty = ExportWriter::new(Vec::new(), ExportFormat::Json, true);
+        let mut jsonl = ExportWriter::new(Vec::new(), ExportFormat::Jsonl, false);
+        for row in &rows {
+            csv.write_row(&columns, row).unwrap();
+            json.write_row(&columns, row).unwrap();
+            pretty.write_row(&columns, row).unwrap();
+            jsonl.write_row(&columns, row).unwrap();
+        }
+
+        let csv = String::from_utf8(csv.finish().unwrap()).unwrap();
+        assert_eq!(csv, "id,note\n1,\"a, \"\"b\"\"\"\n2,\n");
+
+        let expected = json!([{ "id": 1, "note": "a, \"b\"" }, { "id": 2, "note": null }]);
+        let json: JsonValue = serde_json::from_slice(&json.finish().unwrap()).unwrap();
+        assert_eq!(json, expected);
+
+        let pretty = String::from_utf8(pretty.finish().unwrap()).unwrap();
+        assert!(pretty.starts_with("[\n  {\n    \"id\": 1,\n"));
+        assert_eq!(
+            serde_json::from_str::<JsonValue>(&pretty).unwrap(),
+            expected
+        );
+
+        let jsonl = String::from_utf8(jsonl.finish().unwrap()).unwrap();
+        let lines: Vec<JsonValue> = jsonl
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(JsonValue::Array(lines), expected);
+
+        let empty = ExportWriter::new(Vec::new(), ExportFormat::Json, false)
+            .finish()
+            .unwrap();
+        assert_eq!(empty, b"[]\n");
+    }
+
+    #[test]
+    fn test_format_as_literal() {
+        let row = json!({ "id": 1, "name": "O\"Brien\n", "active": true, "note": null });
+
+        let python = format_as_literal(row.clone(), "python".to_string()).unwrap();
+        assert!(python.contains(r#""name": "O\"Brien\n","#));
+        assert!(python.contains(r#""active": True,"#));
+        assert!(python.contains(r#""note": None,"#));
+
+        let js = format_as_literal(row.clone(), "js".to_string()).unwrap();
+        assert!(js.contains("  active: true,"));
+        assert!(js.contains("  note: null,"));
+
+        let rust = format_as_literal(json!("tab\there\u{1}"), "rust".to_string()).unwrap();
+        assert_eq!(rust, r#"serde_json::json!("tab\there\u{1}")"#);
+
+        assert!(format_as_literal(row, "cobol".to_string()).is_err());
+    }
+}
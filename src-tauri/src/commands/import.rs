@@ -0,0 +1,260 @@
+use crate::commands::error::CommandError;
+use crate::db::postgres::{quote_ident, quote_literal, PostgresError, PostgresState};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tauri::State;
+
+/// Row errors kept in the summary of a row-by-row import; later ones are only counted
+const MAX_REPORTED_ERRORS: usize = 1_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CsvImportOptions {
+    pub delimiter: char,
+    /// Whether the first line holds column names; it is skipped, not matched to columns
+    pub header: bool,
+    /// Fields equal to this are loaded as NULL. Without it only empty unquoted fields
+    /// are NULL under COPY, and every empty field is NULL row by row
+    pub null_string: Option<String>,
+    /// Table columns the file's columns load into, in file order. Empty means every
+    /// column of the table, in table order
+    pub columns: Vec<String>,
+    /// Load with COPY, all rows or none. Otherwise each row is inserted on its own and
+    /// the lines that fail are reported and skipped
+    pub atomic: bool,
+}
+
+impl Default for CsvImportOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            header: true,
+            null_string: None,
+            columns: Vec::new(),
+            atomic: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsvRowError {
+    /// Line of the file the row starts on, counting from 1
+    pub line: u64,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CsvImportSummary {
+    pub rows_imported: u64,
+    pub rows_failed: u64,
+    /// The first MAX_REPORTED_ERRORS failures; always empty for an atomic import
+    pub errors: Vec<CsvRowError>,
+}
+
+/// Loads a CSV file into a table. The default atomic import streams the file through
+/// `COPY ... FROM STDIN` and fails as a whole on the first bad line; with `atomic` off
+/// rows are inserted one at a time and bad lines come back as row errors
+#[tauri::command]
+pub async fn import_csv(
+    path: String,
+    schema: String,
+    table: String,
+    options: Option<CsvImportOptions>,
+    postgres: State<'_, PostgresState>,
+) -> Result<CsvImportSummary, CommandError> {
+    let options = options.unwrap_or_default();
+    if !options.delimiter.is_ascii() {
+        return Err(CommandError::invalid_input(
+            "The delimiter must be a single ASCII character",
+        ));
+    }
+
+    if options.atomic {
+        let file = tokio::fs::File::open(&path)
+            .await
+            .map_err(|e| CommandError::from(e).context(format!("Failed to open {}", path)))?;
+        let rows_imported = postgres
+            .copy_in(&build_copy_sql(&schema, &table, &options), file)
+            .await?;
+        return Ok(CsvImportSummary {
+            rows_imported,
+            ..Default::default()
+        });
+    }
+
+    // A failed insert would abort the open transaction and every row after it
+    if postgres.transaction_status().await.active {
+        return Err(CommandError::invalid_input(
+            "Commit or roll back the open transaction before a row-by-row import",
+        ));
+    }
+    insert_rows(&postgres, &path, &schema, &table, &options).await
+}
+
+/// Inserts the file's rows one statement at a time, collecting the lines that fail.
+/// Errors that would fail every row, like a lost connection, end the import
+async fn insert_rows(
+    postgres: &PostgresState,
+    path: &str,
+    schema: &str,
+    table: &str,
+    options: &CsvImportOptions,
+) -> Result<CsvImportSummary, CommandError> {
+    let columns = if options.columns.is_empty() {
+        postgres
+            .fetch_columns(schema, table)
+            .await?
+            .into_iter()
+            .map(|column| column.name)
+            .collect()
+    } else {
+        options.columns.clone()
+    };
+    let column_types = postgres.fetch_column_types(schema, table).await?;
+    let types = columns
+        .iter()
+        .map(|column| {
+            column_types
+                .get(column)
+                .map(String::as_str)
+                .ok_or_else(|| CommandError::not_found(format!("Unknown column \"{}\"", column)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let sql = build_insert_sql(schema, table, &columns, &types);
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(options.delimiter as u8)
+        .has_headers(options.header)
+        .flexible(true)
+        .from_path(path)
+        .map_err(|e| CommandError::from(e).context(format!("Failed to open {}", path)))?;
+
+    let mut summary = CsvImportSummary::default();
+    for record in reader.records() {
+        let result = match record {
+            Ok(record) => {
+                let line = record.position().map_or(0, |p| p.line());
+                let outcome = if record.len() != columns.len() {
+                    Err(format!(
+                        "Expected {} fields, found {}",
+                        columns.len(),
+                        record.len()
+                    ))
+                } else {
+                    let params = record_params(&record, options.null_string.as_deref());
+                    match postgres.execute_with_params(&sql, &params).await {
+                        Ok(_) => Ok(()),
+                        Err(PostgresError::Server(server_error)) => Err(server_error.message),
+                        Err(PostgresError::QueryFailed(message)) => Err(message),
+                        Err(e) => return Err(e.into()),
+                    }
+                };
+                (line, outcome)
+            }
+            Err(e) if e.is_io_error() => return Err(e.into()),
+            Err(e) => (e.position().map_or(0, |p| p.line()), Err(e.to_string())),
+        };
+
+        match result {
+            (_, Ok(())) => summary.rows_imported += 1,
+            (line, Err(message)) => {
+                summary.rows_failed += 1;
+                if summary.errors.len() < MAX_REPORTED_ERRORS {
+                    summary.errors.push(CsvRowError { line, message });
+                }
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Turns a record's fields into text parameters, mapping NULL markers to null
+fn record_params(record: &csv::StringRecord, null_string: Option<&str>) -> Vec<JsonValue> {
+    record
+        .iter()
+        .map(|field| {
+            let is_null = match null_string {
+                Some(null_string) => field == null_string,
+                None => field.is_empty(),
+            };
+            if is_null {
+                JsonValue::Null
+            } else {
+                JsonValue::String(field.to_string())
+            }
+        })
+        .collect()
+}
+
+fn build_copy_sql(schema: &str, table: &str, options: &CsvImportOptions) -> String {
+    let mut settings = vec![
+        "FORMAT csv".to_string(),
+        format!("HEADER {}", options.header),
+        format!(
+            "DELIMITER {}",
+            quote_literal(&options.delimiter.to_string())
+        ),
+    ];
+    if let Some(null_string) = &options.null_string {
+        settings.push(format!("NULL {}", quote_literal(null_string)));
+    }
+
+    format!(
+        "COPY {}.{}{} FROM STDIN WITH ({})",
+        quote_ident(schema),
+        quote_ident(table),
+        column_list(&options.columns),
+        settings.join(", ")
+    )
+}
+
+/// Builds an INSERT binding one text parameter per column, cast to the column's type
+fn build_insert_sql(schema: &str, table: &str, columns: &[String], types: &[&str]) -> String {
+    let placeholders = types
+        .iter()
+        .enumerate()
+        .map(|(i, data_type)| format!("${}::{}", i + 1, data_type))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(
+        "INSERT INTO {}.{}{} VALUES ({})",
+        quote_ident(schema),
+        quote_ident(table),
+        column_list(columns),
+        placeholders
+    )
+}
+
+/// Renders ` ("a", "b")`, or nothing for no columns
+fn column_list(columns: &[String]) -> String {
+    if columns.is_empty() {
+        return String::new();
+    }
+    let quoted: Vec<String> = columns.iter().map(|c| quote_ident(c)).collect();
+    format!(" ({})", quoted.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_copy_sql() {
+        let options = CsvImportOptions {
+            delimiter: ';',
+            null_string: Some("\\N".to_string()),
+            columns: vec!["id".to_string(), "full name".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            build_copy_sql("public", "people", &options),
+            r#"COPY "public"."people" ("id", "full name") FROM STDIN WITH (FORMAT csv, HEADER true, DELIMITER ';', NULL E'\\N')"#
+        );
+        assert_eq!(
+            build_copy_sql("public", "people", &CsvImportOptions::default()),
+            r#"COPY "public"."people" FROM STDIN WITH (FORMAT csv, HEADER true, DELIMITER ',')"#
+        );
+    }
+}
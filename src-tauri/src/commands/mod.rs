@@ -1,4 +1,13 @@
+pub mod checksum;
 pub mod connections;
+pub mod ddl;
+pub mod error;
 pub mod explain;
+pub mod export;
+pub mod import;
+pub mod monitoring;
+pub mod profile;
 pub mod queries;
+pub mod rows;
+pub mod transactions;
 
@@ -0,0 +1,153 @@
+use crate::commands::connections::emit_connection_lost;
+use crate::commands::error::CommandError;
+use crate::db::postgres::{
+    ConnectionStats, LockInfo, PostgresState, ReplicationStatus, SessionInfo, TableSize, TopQuery,
+    TopQueryOrder,
+};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+/// Upper bound on the statements returned by fetch_top_queries
+const MAX_TOP_QUERIES: u32 = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PgNotificationEvent {
+    pub channel: String,
+    pub payload: String,
+    /// Backend PID of the session that sent the NOTIFY
+    pub process_id: u32,
+}
+
+/// Fetches replication status for either a primary or a replica server
+#[tauri::command]
+pub async fn fetch_replication_status(
+    postgres: State<'_, PostgresState>,
+) -> Result<ReplicationStatus, CommandError> {
+    postgres
+        .fetch_replication_status()
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Pool metrics and server info for the connection status panel
+#[tauri::command]
+pub async fn connection_stats(
+    postgres: State<'_, PostgresState>,
+) -> Result<ConnectionStats, CommandError> {
+    postgres
+        .connection_stats()
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Lists server sessions from pg_stat_activity, each with the PIDs blocking it
+#[tauri::command]
+pub async fn list_sessions(
+    postgres: State<'_, PostgresState>,
+) -> Result<Vec<SessionInfo>, CommandError> {
+    postgres.list_sessions().await.map_err(CommandError::from)
+}
+
+/// Lists held and awaited locks in the current database, to show who blocks whom
+#[tauri::command]
+pub async fn list_locks(postgres: State<'_, PostgresState>) -> Result<Vec<LockInfo>, CommandError> {
+    postgres.list_locks().await.map_err(CommandError::from)
+}
+
+/// Lists the heaviest statements recorded by pg_stat_statements, by total time unless
+/// `order_by` says otherwise. Errors if the extension isn't installed
+#[tauri::command]
+pub async fn fetch_top_queries(
+    order_by: Option<TopQueryOrder>,
+    limit: u32,
+    postgres: State<'_, PostgresState>,
+) -> Result<Vec<TopQuery>, CommandError> {
+    postgres
+        .fetch_top_queries(
+            order_by.unwrap_or_default(),
+            limit.clamp(1, MAX_TOP_QUERIES) as i64,
+        )
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Lists table sizes, largest first, for `schema` or all non-system schemas. The bloat
+/// estimate scans every table, so it is only made when `with_bloat` is set
+#[tauri::command]
+pub async fn fetch_table_sizes(
+    schema: Option<String>,
+    with_bloat: Option<bool>,
+    postgres: State<'_, PostgresState>,
+) -> Result<Vec<TableSize>, CommandError> {
+    postgres
+        .fetch_table_sizes(schema.as_deref(), with_bloat.unwrap_or(false))
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Terminates a server session. Returns false if it had already gone
+#[tauri::command]
+pub async fn terminate_session(
+    pid: i32,
+    postgres: State<'_, PostgresState>,
+) -> Result<bool, CommandError> {
+    postgres
+        .terminate_session(pid)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Starts listening on a NOTIFY channel, emitting a `pg-notification` event for each
+/// notification until unlisten_channel or disconnect. The listener has a connection of
+/// its own; if that drops it reconnects, and `connection-lost` is emitted only if it can't
+#[tauri::command]
+pub async fn listen_channel(
+    channel: String,
+    app: AppHandle,
+    postgres: State<'_, PostgresState>,
+) -> Result<(), CommandError> {
+    let mut listener = postgres.listen(&channel).await?;
+    let connection_id = postgres.get_connection_id().await;
+
+    let handle = tokio::spawn(async move {
+        loop {
+            match listener.recv().await {
+                Ok(notification) => {
+                    app.emit(
+                        "pg-notification",
+                        PgNotificationEvent {
+                            channel: notification.channel().to_string(),
+                            payload: notification.payload().to_string(),
+                            process_id: notification.process_id(),
+                        },
+                    )
+                    .ok();
+                }
+                Err(e) => {
+                    emit_connection_lost(&app, connection_id, &e.to_string(), false);
+                    break;
+                }
+            }
+        }
+    });
+
+    postgres.register_listen_task(&channel, handle).await;
+    Ok(())
+}
+
+/// Stops listening on a channel. Returns false if it wasn't being listened on
+#[tauri::command]
+pub async fn unlisten_channel(
+    channel: String,
+    postgres: State<'_, PostgresState>,
+) -> Result<bool, CommandError> {
+    Ok(postgres.stop_listen_task(&channel).await)
+}
+
+/// Lists the channels being listened on
+#[tauri::command]
+pub async fn list_listen_channels(
+    postgres: State<'_, PostgresState>,
+) -> Result<Vec<String>, CommandError> {
+    Ok(postgres.listened_channels().await)
+}
@@ -0,0 +1,154 @@
+use crate::commands::error::CommandError;
+use crate::db::postgres::PostgresState;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use tauri::State;
+
+/// Upper bound on the number of rows sampled for profiling
+const MAX_SAMPLE_SIZE: u32 = 10_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonFieldStats {
+    pub path: String,
+    pub types: BTreeMap<String, usize>,
+    pub occurrences: usize,
+    pub frequency: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonSchemaReport {
+    pub sampled_rows: usize,
+    pub root_types: BTreeMap<String, usize>,
+    pub fields: Vec<JsonFieldStats>,
+}
+
+/// Samples a json/jsonb column and reports the keys and value types it contains
+#[tauri::command]
+pub async fn infer_jsonb_schema(
+    schema: String,
+    table: String,
+    column: String,
+    sample_size: u32,
+    include_nested: Option<bool>,
+    postgres: State<'_, PostgresState>,
+) -> Result<JsonSchemaReport, CommandError> {
+    let limit = sample_size.clamp(1, MAX_SAMPLE_SIZE);
+
+    let values = postgres
+        .sample_json_column(&schema, &table, &column, limit as i64)
+        .await?;
+
+    Ok(infer_json_schema(&values, include_nested.unwrap_or(false)))
+}
+
+/// Aggregates observed keys, value types and presence frequency across JSON values
+pub fn infer_json_schema(values: &[JsonValue], include_nested: bool) -> JsonSchemaReport {
+    let mut root_types = BTreeMap::new();
+    let mut fields: BTreeMap<String, (BTreeMap<String, usize>, usize)> = BTreeMap::new();
+
+    for value in values {
+        *root_types
+            .entry(json_type_name(value).to_string())
+            .or_insert(0) += 1;
+        if let JsonValue::Object(map) = value {
+            for (key, child) in map {
+                collect_field(key, child, include_nested, &mut fields);
+            }
+        }
+    }
+
+    let sampled_rows = values.len();
+    let fields = fields
+        .into_iter()
+        .map(|(path, (types, occurrences))| JsonFieldStats {
+            path,
+            types,
+            occurrences,
+            frequency: if sampled_rows == 0 {
+                0.0
+            } else {
+                occurrences as f64 / sampled_rows as f64
+            },
+        })
+        .collect();
+
+    JsonSchemaReport {
+        sampled_rows,
+        root_types,
+        fields,
+    }
+}
+
+fn collect_field(
+    path: &str,
+    value: &JsonValue,
+    include_nested: bool,
+    fields: &mut BTreeMap<String, (BTreeMap<String, usize>, usize)>,
+) {
+    let entry = fields.entry(path.to_string()).or_default();
+    *entry
+        .0
+        .entry(json_type_name(value).to_string())
+        .or_insert(0) += 1;
+    entry.1 += 1;
+
+    if !include_nested {
+        return;
+    }
+
+    match value {
+        JsonValue::Object(map) => {
+            for (key, child) in map {
+                collect_field(&format!("{}.{}", path, key), child, include_nested, fields);
+            }
+        }
+        JsonValue::Array(items) => {
+            for item in items {
+                collect_field(&format!("{}[]", path), item, include_nested, fields);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn json_type_name(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Bool(_) => "boolean",
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_infer_json_schema() {
+        let values = vec![
+            json!({ "status": "active", "meta": { "score": 1 } }),
+            json!({ "status": null, "tags": ["a", "b"] }),
+            json!([1, 2]),
+        ];
+
+        let report = infer_json_schema(&values, false);
+        assert_eq!(report.sampled_rows, 3);
+        assert_eq!(report.root_types.get("object"), Some(&2));
+        assert_eq!(report.fields.len(), 3);
+
+        let status = report.fields.iter().find(|f| f.path == "status").unwrap();
+        assert_eq!(status.occurrences, 2);
+        assert_eq!(status.types.get("string"), Some(&1));
+        assert_eq!(status.types.get("null"), Some(&1));
+
+        let nested = infer_json_schema(&values, true);
+        assert!(nested.fields.iter().any(|f| f.path == "meta.score"));
+        let tags = nested.fields.iter().find(|f| f.path == "tags[]").unwrap();
+        assert_eq!(tags.occurrences, 2);
+    }
+}
@@ -1,7 +1,47 @@
-use crate::db::metadata;
-use crate::db::postgres::{ColumnInfo, PaginatedResult, PostgresState, QueryResult, TableInfo};
+use crate::commands::connections::emit_connection_lost;
+use crate::commands::error::CommandError;
+use crate::db::browse::{ColumnFilter, DistinctValue, SortColumn};
+use crate::db::cache::{CacheStatus, QueryCacheState};
+use crate::db::catalog::SchemaCatalog;
+use crate::db::manager::{DatabaseError, DatabaseState};
+use crate::db::metadata::{self, EditorTab, QueryHistoryEntry};
+use crate::db::postgres::{
+    ColumnInfo, ColumnMeta, ConstraintInfo, CursorBatch, ForeignKeyInfo, IndexInfo,
+    PaginatedResult, PostgresError, PostgresState, QueryResult, QueryValidation, RowKey,
+    ScriptResult, TableFilter, TableInfo, TablePreview,
+};
+use crate::db::record::{self, BinaryEncoding};
+use crate::db::script::{self, FormatOptions, QueryClassification};
+use crate::db::search;
+use crate::db::template::{self, QueryParameter};
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tauri::ipc::Response;
+use tauri::{AppHandle, Emitter, State};
+
+/// Rows per record batch when encoding results as Arrow IPC
+const DEFAULT_ARROW_BATCH_SIZE: usize = 10_000;
+
+/// Rows per `query-chunk` event when streaming a query result
+const DEFAULT_STREAM_BATCH_SIZE: usize = 1_000;
+
+/// History entries returned by list_query_history when no limit is given
+const DEFAULT_HISTORY_LIMIT: i64 = 100;
+
+/// Rows returned by execute_query when the caller doesn't set max_rows
+const DEFAULT_MAX_ROWS: u64 = 1_000;
+
+/// Upper bound on the values returned by fetch_distinct_values
+const MAX_DISTINCT_VALUES: u32 = 1_000;
+
+/// Rows returned by preview_table when no limit is given, and the most it returns
+const DEFAULT_PREVIEW_ROWS: u32 = 100;
+const MAX_PREVIEW_ROWS: u32 = 1_000;
+
+/// Lower bound for polling intervals so a dashboard can't hammer the server
+const MIN_POLL_INTERVAL_SECS: u64 = 5;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SavedQueryInfo {
@@ -10,6 +50,18 @@ pub struct SavedQueryInfo {
     pub name: String,
     pub sql: String,
     pub created_at: String,
+    pub tags: Vec<String>,
+    pub parameters: Vec<QueryParameter>,
+}
+
+/// A table's documentation and columns, for the schema browser
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TableDetails {
+    pub schema: String,
+    pub name: String,
+    /// Set with COMMENT ON TABLE
+    pub table_comment: Option<String>,
+    pub columns: Vec<ColumnInfo>,
 }
 
 impl From<metadata::SavedQuery> for SavedQueryInfo {
@@ -20,26 +72,415 @@ impl From<metadata::SavedQuery> for SavedQueryInfo {
             name: q.name,
             sql: q.sql,
             created_at: q.created_at,
+            tags: q.tags,
+            parameters: q.parameters,
         }
     }
 }
 
-/// Executes a SQL query against the active connection
+/// Executes a SQL query against the active connection, optionally aborting it once it
+/// runs longer than `timeout_ms`. A single SELECT returns at most `max_rows` rows (1000
+/// unless given, 0 for no cap) and sets `truncated` when there were more; queries with
+/// their own LIMIT and other statements run as written. Every execution is recorded in
+/// the query history. Losing the connection also emits a `connection-lost` event.
+///
+/// With `use_cache`, a read-only query outside a transaction is answered from the query
+/// cache when the same SQL ran on this connection in the last few minutes, and
+/// `cache_status` says whether it was. Cached results are dropped once anything this
+/// connection runs may have changed the data, or it switches database or search_path;
+/// writes made elsewhere only show once the cached result expires.
+///
+/// With `distinguish_nulls`, NULL cells come back as `{"__null__": true}` instead of
+/// JSON null. A json or jsonb value that is itself null stays null
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn execute_query(
+    sql: String,
+    query_id: Option<String>,
+    timeout_ms: Option<u64>,
+    max_rows: Option<u64>,
+    use_cache: Option<bool>,
+    distinguish_nulls: Option<bool>,
+    app: AppHandle,
+    database: State<'_, DatabaseState>,
+    cache: State<'_, QueryCacheState>,
+) -> Result<QueryResult, CommandError> {
+    let manager = database.current().await;
+    let max_rows = Some(max_rows.unwrap_or(DEFAULT_MAX_ROWS)).filter(|&max_rows| max_rows > 0);
+    let distinguish_nulls = distinguish_nulls == Some(true);
+    let connection_id = manager.get_connection_id().await;
+
+    // A transaction can see rows nobody else can, so its results are never shared
+    let cache_id = match connection_id.as_deref() {
+        Some(id)
+            if use_cache == Some(true)
+                && script::is_read_only_query(&sql)
+                && !manager.in_transaction().await =>
+        {
+            Some(id)
+        }
+        _ => None,
+    };
+    // Read before running, so a result is stale if the data changed while it was read
+    let data_version = manager.data_version();
+    if let Some(mut hit) =
+        cache_id.and_then(|id| cache.get(id, &sql, max_rows, distinguish_nulls, data_version))
+    {
+        hit.cache_status = Some(CacheStatus::Hit);
+        return Ok(hit);
+    }
+
+    let started = Instant::now();
+    let mut result = manager
+        .execute_query(
+            &sql,
+            query_id.as_deref(),
+            timeout_ms,
+            max_rows,
+            distinguish_nulls,
+        )
+        .await;
+    // Failed queries have no timing of their own
+    let duration_ms = match &result {
+        Ok(result) => result.duration_ms,
+        Err(_) => started.elapsed().as_millis() as u64,
+    };
+
+    if let Err(DatabaseError::Postgres(PostgresError::ConnectionLost(error))) = &result {
+        emit_connection_lost(&app, connection_id.clone(), error, false);
+    }
+    if let (Ok(result), Some(id)) = (&mut result, cache_id) {
+        result.cache_status = Some(CacheStatus::Miss);
+        cache.insert(
+            id,
+            &sql,
+            max_rows,
+            distinguish_nulls,
+            data_version,
+            result.clone(),
+        );
+    }
+    let result = result.map_err(CommandError::from);
+
+    // History is best-effort; failing to record it must not fail the query
+    metadata::record_query_history(
+        connection_id.as_deref(),
+        &sql,
+        duration_ms,
+        result.as_ref().ok().map(|r| r.row_count as u64),
+        result.as_ref().err().map(|e| e.message.as_str()),
+    )
+    .ok();
+
+    result
+}
+
+/// Executes one or more statements against the active connection and returns a result
+/// per result set, e.g. for a batch of SELECTs or a procedure returning several. Each
+/// result set returns at most `max_rows` rows (1000 unless given, 0 for no cap) and sets
+/// `truncated` when there were more. Unlike execute_query, nothing is served from the
+/// query cache. The batch is recorded in the query history as one entry.
+/// `distinguish_nulls` works as in execute_query
+#[tauri::command]
+pub async fn execute_query_multi(
+    sql: String,
+    query_id: Option<String>,
+    timeout_ms: Option<u64>,
+    max_rows: Option<u64>,
+    distinguish_nulls: Option<bool>,
+    app: AppHandle,
+    database: State<'_, DatabaseState>,
+) -> Result<Vec<QueryResult>, CommandError> {
+    let manager = database.current().await;
+    let max_rows = Some(max_rows.unwrap_or(DEFAULT_MAX_ROWS)).filter(|&max_rows| max_rows > 0);
+    let connection_id = manager.get_connection_id().await;
+
+    let started = Instant::now();
+    let results = manager
+        .execute_query_multi(
+            &sql,
+            query_id.as_deref(),
+            timeout_ms,
+            max_rows,
+            distinguish_nulls == Some(true),
+        )
+        .await;
+    // Each result set carries the duration of the whole batch
+    let duration_ms = match results.as_ref().map(|results| results.first()) {
+        Ok(Some(result)) => result.duration_ms,
+        _ => started.elapsed().as_millis() as u64,
+    };
+
+    if let Err(DatabaseError::Postgres(PostgresError::ConnectionLost(error))) = &results {
+        emit_connection_lost(&app, connection_id.clone(), error, false);
+    }
+    let results = results.map_err(CommandError::from);
+
+    // History is best-effort; failing to record it must not fail the query
+    metadata::record_query_history(
+        connection_id.as_deref(),
+        &sql,
+        duration_ms,
+        results
+            .as_ref()
+            .ok()
+            .map(|results| results.iter().map(|r| r.row_count as u64).sum()),
+        results.as_ref().err().map(|e| e.message.as_str()),
+    )
+    .ok();
+
+    results
+}
+
+/// Executes a query with `params` bound to its `$1`, `$2`, ... placeholders, so values are
+/// never spliced into the SQL. Each value is bound by its JSON type: integers as bigint,
+/// other numbers as double precision, booleans as boolean, strings as text, arrays and
+/// objects as jsonb, and null as a NULL of whatever type its placeholder needs. Cast a
+/// placeholder (`$1::uuid`) where the server can't convert that type on its own. Runs
+/// inside the open transaction when there is one and is recorded in the query history
+#[tauri::command]
+pub async fn execute_query_params(
+    sql: String,
+    params: Vec<JsonValue>,
+    postgres: State<'_, PostgresState>,
+) -> Result<QueryResult, CommandError> {
+    let started = Instant::now();
+    let result = postgres
+        .query_with_params(&sql, &params)
+        .await
+        .map_err(CommandError::from);
+    let duration_ms = match &result {
+        Ok(result) => result.duration_ms,
+        Err(_) => started.elapsed().as_millis() as u64,
+    };
+
+    let connection_id = postgres.get_connection_id().await;
+    metadata::record_query_history(
+        connection_id.as_deref(),
+        &sql,
+        duration_ms,
+        result.as_ref().ok().map(|r| r.row_count as u64),
+        result.as_ref().err().map(|e| e.message.as_str()),
+    )
+    .ok();
+
+    result
+}
+
+/// Runs a statement and returns just the first value of its first row, e.g. for
+/// `SELECT current_database()`. Errors if there is no row or no column. Not recorded in
+/// the query history
+#[tauri::command]
+pub async fn fetch_scalar(
+    sql: String,
+    postgres: State<'_, PostgresState>,
+) -> Result<JsonValue, CommandError> {
+    postgres
+        .fetch_scalar(&sql)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Empties the query cache for one connection, or for all of them. Returns how many
+/// cached results were dropped
+#[tauri::command]
+pub fn clear_query_cache(
+    connection_id: Option<String>,
+    cache: State<'_, QueryCacheState>,
+) -> Result<usize, CommandError> {
+    Ok(cache.clear(connection_id.as_deref()))
+}
+
+/// Classifies SQL by its most dangerous statement, so the UI can ask for confirmation
+/// before running anything destructive. Works on the text alone; no connection needed
+#[tauri::command]
+pub fn classify_query(sql: String) -> Result<QueryClassification, CommandError> {
+    Ok(script::classify(&sql))
+}
+
+/// Reformats SQL with one clause per line, uppercase keywords and two-space indents unless
+/// `options` says otherwise. Only whitespace and keyword case change
+#[tauri::command]
+pub fn format_sql(sql: String, options: Option<FormatOptions>) -> Result<String, CommandError> {
+    Ok(script::format_sql(&sql, options.unwrap_or_default()))
+}
+
+/// Checks that a statement parses and that the objects it references exist, without
+/// running it. Returns the server's error, or the inferred parameter and result types
+#[tauri::command]
+pub async fn validate_query(
+    sql: String,
+    postgres: State<'_, PostgresState>,
+) -> Result<QueryValidation, CommandError> {
+    postgres
+        .validate_query(&sql)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Runs a semicolon-separated script in one transaction, rolling everything back if any
+/// statement fails, and reports the outcome of each statement
+#[tauri::command]
+pub async fn execute_script(
+    sql: String,
+    postgres: State<'_, PostgresState>,
+) -> Result<ScriptResult, CommandError> {
+    postgres
+        .execute_script(&sql)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Cancels a query started with execute_query under the given query id
+#[tauri::command]
+pub async fn cancel_query(
+    query_id: String,
+    postgres: State<'_, PostgresState>,
+) -> Result<bool, CommandError> {
+    postgres
+        .cancel_query(&query_id)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Cancels every query running under a query id. Returns how many were cancelled
+#[tauri::command]
+pub async fn cancel_all_queries(postgres: State<'_, PostgresState>) -> Result<usize, CommandError> {
+    postgres
+        .cancel_all_queries()
+        .await
+        .map_err(CommandError::from)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryChunkEvent {
+    pub query_id: String,
+    pub columns: Vec<ColumnMeta>,
+    pub rows: Vec<Vec<JsonValue>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryCompleteEvent {
+    pub query_id: String,
+    pub row_count: usize,
+    pub error: Option<CommandError>,
+}
+
+/// Executes a query and streams the rows as `query-chunk` events of `batch_size` rows,
+/// followed by a single `query-complete` event, so large results never sit in memory
+/// all at once. Can be aborted with cancel_query under the same query id
+#[tauri::command]
+pub async fn execute_query_stream(
+    sql: String,
+    query_id: String,
+    batch_size: Option<usize>,
+    app: AppHandle,
+    postgres: State<'_, PostgresState>,
+) -> Result<usize, CommandError> {
+    let batch_size = batch_size.unwrap_or(DEFAULT_STREAM_BATCH_SIZE).max(1);
+
+    let result = postgres
+        .execute_query_stream(&sql, Some(&query_id), batch_size, |columns, rows| {
+            let event = QueryChunkEvent {
+                query_id: query_id.clone(),
+                columns: columns.to_vec(),
+                rows,
+            };
+            app.emit("query-chunk", event).map_err(|e| e.to_string())
+        })
+        .await
+        .map_err(CommandError::from);
+
+    let (row_count, error) = match &result {
+        Ok(row_count) => (*row_count, None),
+        Err(e) => (0, Some(e.clone())),
+    };
+    app.emit(
+        "query-complete",
+        QueryCompleteEvent {
+            query_id,
+            row_count,
+            error,
+        },
+    )
+    .ok();
+
+    result
+}
+
+/// Executes a query and returns the result as Arrow IPC stream bytes,
+/// a cheaper alternative to JSON for very large result sets
+#[tauri::command]
+pub async fn execute_query_arrow(
+    sql: String,
+    batch_size: Option<usize>,
+    postgres: State<'_, PostgresState>,
+) -> Result<Response, CommandError> {
+    let batch_size = batch_size.unwrap_or(DEFAULT_ARROW_BATCH_SIZE).max(1);
+
+    postgres
+        .execute_query_arrow(&sql, batch_size)
+        .await
+        .map(Response::new)
+        .map_err(CommandError::from)
+}
+
+/// Opens a server-side cursor over a query so it can be read in bounded batches
+#[tauri::command]
+pub async fn declare_cursor(
     sql: String,
     postgres: State<'_, PostgresState>,
-) -> Result<QueryResult, String> {
+) -> Result<(), CommandError> {
     postgres
-        .execute_query(&sql)
+        .declare_cursor(&sql)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
-/// Fetches all tables from the active connection
+/// Fetches the next batch of rows from the open cursor
 #[tauri::command]
-pub async fn fetch_tables(postgres: State<'_, PostgresState>) -> Result<Vec<TableInfo>, String> {
-    postgres.fetch_tables().await.map_err(|e| e.to_string())
+pub async fn fetch_cursor(
+    batch_size: u32,
+    postgres: State<'_, PostgresState>,
+) -> Result<CursorBatch, CommandError> {
+    postgres
+        .fetch_cursor(batch_size.max(1))
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Closes the open cursor and releases its connection
+#[tauri::command]
+pub async fn close_cursor(postgres: State<'_, PostgresState>) -> Result<(), CommandError> {
+    postgres.close_cursor().await.map_err(CommandError::from)
+}
+
+/// Fetches tables from the active connection, all of them unless `filter` narrows the
+/// schema or name or pages through the list
+#[tauri::command]
+pub async fn fetch_tables(
+    filter: Option<TableFilter>,
+    database: State<'_, DatabaseState>,
+) -> Result<Vec<TableInfo>, CommandError> {
+    database
+        .current()
+        .await
+        .fetch_tables(&filter.unwrap_or_default())
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Gets every schema, relation, column and function of the active Postgres connection for
+/// editor autocompletion. Served from a per-connection cache unless `refresh` is set
+#[tauri::command]
+pub async fn fetch_schema_catalog(
+    refresh: Option<bool>,
+    postgres: State<'_, PostgresState>,
+) -> Result<SchemaCatalog, CommandError> {
+    postgres
+        .fetch_schema_catalog(refresh.unwrap_or(false))
+        .await
+        .map_err(CommandError::from)
 }
 
 /// Fetches columns for a specific table
@@ -47,68 +488,529 @@ pub async fn fetch_tables(postgres: State<'_, PostgresState>) -> Result<Vec<Tabl
 pub async fn fetch_columns(
     schema: String,
     table: String,
+    database: State<'_, DatabaseState>,
+) -> Result<Vec<ColumnInfo>, CommandError> {
+    database
+        .current()
+        .await
+        .fetch_columns(&schema, &table)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Fetches a table's comment along with its columns and their comments
+#[tauri::command]
+pub async fn fetch_table_details(
+    schema: String,
+    table: String,
+    postgres: State<'_, PostgresState>,
+) -> Result<TableDetails, CommandError> {
+    let table_comment = postgres
+        .fetch_table_comment(&schema, &table)
+        .await?
+        .ok_or_else(|| CommandError::not_found(format!("Table {}.{} not found", schema, table)))?;
+    let columns = postgres.fetch_columns(&schema, &table).await?;
+
+    Ok(TableDetails {
+        schema,
+        name: table,
+        table_comment,
+        columns,
+    })
+}
+
+/// Lists the values of an enum type, as reported by fetch_columns in udt_schema and
+/// udt_name. Without a schema the type is looked up on the search_path
+#[tauri::command]
+pub async fn fetch_enum_values(
+    type_name: String,
+    schema: Option<String>,
     postgres: State<'_, PostgresState>,
-) -> Result<Vec<ColumnInfo>, String> {
+) -> Result<Vec<String>, CommandError> {
     postgres
-        .fetch_columns(&schema, &table)
+        .fetch_enum_values(schema.as_deref(), &type_name)
+        .await?
+        .ok_or_else(|| CommandError::not_found(format!("Enum type {} not found", type_name)))
+}
+
+/// Detects which columns identify a row (primary key, or a unique index standing in for one)
+#[tauri::command]
+pub async fn detect_row_key(
+    schema: String,
+    table: String,
+    postgres: State<'_, PostgresState>,
+) -> Result<RowKey, CommandError> {
+    postgres
+        .detect_row_key(&schema, &table)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Fetches the indexes of a table
+#[tauri::command]
+pub async fn fetch_indexes(
+    schema: String,
+    table: String,
+    postgres: State<'_, PostgresState>,
+) -> Result<Vec<IndexInfo>, CommandError> {
+    postgres
+        .fetch_indexes(&schema, &table)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Fetches the constraints of a table (keys, foreign keys, checks, exclusions)
+#[tauri::command]
+pub async fn fetch_constraints(
+    schema: String,
+    table: String,
+    postgres: State<'_, PostgresState>,
+) -> Result<Vec<ConstraintInfo>, CommandError> {
+    postgres
+        .fetch_constraints(&schema, &table)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
-/// Fetches paginated data from a table
+/// Fetches the foreign keys of a schema, or only those touching one table, for
+/// relationship diagrams
 #[tauri::command]
+pub async fn fetch_foreign_keys(
+    schema: String,
+    table: Option<String>,
+    postgres: State<'_, PostgresState>,
+) -> Result<Vec<ForeignKeyInfo>, CommandError> {
+    postgres
+        .fetch_foreign_keys(&schema, table.as_deref())
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Fetches paginated data from a table. Pass the previous page's `next_after` as `after`
+/// to page by key rather than by offset; set `count` to false to skip the COUNT(*).
+/// `order_by` and `filters` must name columns of the table; `filters` apply to the count
+/// too, and sorting on Postgres falls back to offset paging. A filter with a `json_path`
+/// reaches into a json or jsonb column, on Postgres only. `distinguish_nulls` works as
+/// in execute_query
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn fetch_table_data(
     schema: String,
     table: String,
     page: i32,
     page_size: i32,
-    postgres: State<'_, PostgresState>,
-) -> Result<PaginatedResult, String> {
-    postgres
-        .fetch_table_data(&schema, &table, page, page_size)
+    after: Option<Vec<JsonValue>>,
+    count: Option<bool>,
+    order_by: Option<Vec<SortColumn>>,
+    filters: Option<Vec<ColumnFilter>>,
+    distinguish_nulls: Option<bool>,
+    database: State<'_, DatabaseState>,
+) -> Result<PaginatedResult, CommandError> {
+    Ok(database
+        .current()
+        .await
+        .fetch_table_data(
+            &schema,
+            &table,
+            page,
+            page_size,
+            after.as_deref(),
+            count.unwrap_or(true),
+            order_by.as_deref().unwrap_or_default(),
+            filters.as_deref().unwrap_or_default(),
+            distinguish_nulls == Some(true),
+        )
+        .await?)
+}
+
+/// Fetches up to `limit` rows of a table for a quick look, skipping the COUNT(*) of
+/// fetch_table_data. Large Postgres tables are sampled, so the rows are representative
+/// and come back as fast as on a small table
+#[tauri::command]
+pub async fn preview_table(
+    schema: String,
+    table: String,
+    limit: Option<u32>,
+    database: State<'_, DatabaseState>,
+) -> Result<TablePreview, CommandError> {
+    let limit = limit
+        .unwrap_or(DEFAULT_PREVIEW_ROWS)
+        .clamp(1, MAX_PREVIEW_ROWS);
+    database
+        .current()
         .await
-        .map_err(|e| e.to_string())
+        .preview_table(&schema, &table, limit as i64)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Fetches the most frequent values of a column, for building filter dropdowns. `limit`
+/// is capped so a high-cardinality column can't flood the UI
+#[tauri::command]
+pub async fn fetch_distinct_values(
+    schema: String,
+    table: String,
+    column: String,
+    limit: u32,
+    database: State<'_, DatabaseState>,
+) -> Result<Vec<DistinctValue>, CommandError> {
+    database
+        .current()
+        .await
+        .fetch_distinct_values(
+            &schema,
+            &table,
+            &column,
+            limit.clamp(1, MAX_DISTINCT_VALUES) as i64,
+        )
+        .await
+        .map_err(CommandError::from)
 }
 
 // ============ Saved Queries ============
 
-/// Saves a query for later use
+/// Saves a query for later use. Tags are trimmed and deduplicated ignoring case.
+/// Every `:name` placeholder in the SQL gets a parameter definition: the one given in
+/// `parameters`, or plain text without a default
 #[tauri::command]
 pub fn save_query(
     connection_id: Option<String>,
     name: String,
     sql: String,
-) -> Result<SavedQueryInfo, String> {
-    metadata::create_saved_query(connection_id.as_deref(), &name, &sql)
-        .map(SavedQueryInfo::from)
-        .map_err(|e| e.to_string())
+    tags: Option<Vec<String>>,
+    parameters: Option<Vec<QueryParameter>>,
+) -> Result<SavedQueryInfo, CommandError> {
+    let parameters = template::detect_parameters(&sql, &parameters.unwrap_or_default());
+    metadata::create_saved_query(
+        connection_id.as_deref(),
+        &name,
+        &sql,
+        &tags.unwrap_or_default(),
+        &parameters,
+    )
+    .map(SavedQueryInfo::from)
+    .map_err(CommandError::from)
 }
 
 /// Lists all saved queries
 #[tauri::command]
-pub fn list_saved_queries() -> Result<Vec<SavedQueryInfo>, String> {
+pub fn list_saved_queries() -> Result<Vec<SavedQueryInfo>, CommandError> {
     metadata::list_saved_queries()
         .map(|queries| queries.into_iter().map(SavedQueryInfo::from).collect())
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
+}
+
+/// Lists the saved queries carrying a tag, compared ignoring case
+#[tauri::command]
+pub fn list_saved_queries_by_tag(tag: String) -> Result<Vec<SavedQueryInfo>, CommandError> {
+    metadata::list_saved_queries_by_tag(&tag)
+        .map(|queries| queries.into_iter().map(SavedQueryInfo::from).collect())
+        .map_err(CommandError::from)
+}
+
+/// Lists every tag used by a saved query, for filtering
+#[tauri::command]
+pub fn list_saved_query_tags() -> Result<Vec<String>, CommandError> {
+    metadata::list_saved_query_tags().map_err(CommandError::from)
+}
+
+/// Replaces the tags of a saved query
+#[tauri::command]
+pub fn set_saved_query_tags(id: String, tags: Vec<String>) -> Result<SavedQueryInfo, CommandError> {
+    metadata::set_saved_query_tags(&id, &tags)
+        .map(SavedQueryInfo::from)
+        .map_err(CommandError::from)
+}
+
+/// Replaces the parameter definitions of a saved query. Definitions for names that aren't
+/// placeholders in its SQL are dropped, and missing ones are added as plain text
+#[tauri::command]
+pub fn set_saved_query_parameters(
+    id: String,
+    parameters: Vec<QueryParameter>,
+) -> Result<SavedQueryInfo, CommandError> {
+    let saved_query = metadata::get_saved_query_by_id(&id)?;
+    let parameters = template::detect_parameters(&saved_query.sql, &parameters);
+    metadata::set_saved_query_parameters(&id, &parameters)
+        .map(SavedQueryInfo::from)
+        .map_err(CommandError::from)
+}
+
+/// Runs a saved query with `params` filled into its `:name` placeholders. The values are
+/// bound as query parameters, never spliced into the SQL; a parameter without a value
+/// falls back to its default. Recorded in the query history under the saved SQL
+#[tauri::command]
+pub async fn run_saved_query(
+    id: String,
+    params: HashMap<String, JsonValue>,
+    postgres: State<'_, PostgresState>,
+) -> Result<QueryResult, CommandError> {
+    let saved_query = metadata::get_saved_query_by_id(&id)?;
+    let (sql, binds) = template::bind_template(&saved_query.sql, &saved_query.parameters, &params)?;
+
+    let started = Instant::now();
+    let result = postgres
+        .query_with_params(&sql, &binds)
+        .await
+        .map_err(CommandError::from);
+    let duration_ms = match &result {
+        Ok(result) => result.duration_ms,
+        Err(_) => started.elapsed().as_millis() as u64,
+    };
+
+    let connection_id = postgres.get_connection_id().await;
+    metadata::record_query_history(
+        connection_id.as_deref(),
+        &saved_query.sql,
+        duration_ms,
+        result.as_ref().ok().map(|r| r.row_count as u64),
+        result.as_ref().err().map(|e| e.message.as_str()),
+    )
+    .ok();
+
+    result
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedQuerySearchResult {
+    pub query: SavedQueryInfo,
+    /// The SQL around the first match
+    pub snippet: String,
+    pub score: u32,
+}
+
+/// Finds saved queries whose name or SQL contains `term`, best matches first: name matches
+/// rank above SQL matches, then by how often the term occurs
+#[tauri::command]
+pub fn search_saved_queries(term: String) -> Result<Vec<SavedQuerySearchResult>, CommandError> {
+    let mut results: Vec<SavedQuerySearchResult> = metadata::search_saved_queries(&term)?
+        .into_iter()
+        .map(|query| SavedQuerySearchResult {
+            score: search::match_score(&term, Some(&query.name), &query.sql),
+            snippet: search::snippet(&query.sql, &term),
+            query: query.into(),
+        })
+        .collect();
+
+    // Stable, so equally good matches stay newest first
+    results.sort_by_key(|r| std::cmp::Reverse(r.score));
+    Ok(results)
 }
 
 /// Deletes a saved query
 #[tauri::command]
-pub fn delete_saved_query(id: String) -> Result<(), String> {
-    metadata::delete_saved_query(&id).map_err(|e| e.to_string())
+pub fn delete_saved_query(id: String) -> Result<(), CommandError> {
+    metadata::delete_saved_query(&id).map_err(CommandError::from)
+}
+
+// ============ Query History ============
+
+/// Lists executed queries, newest first, optionally only those of one connection
+#[tauri::command]
+pub fn list_query_history(
+    connection_id: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<Vec<QueryHistoryEntry>, CommandError> {
+    metadata::list_query_history(
+        connection_id.as_deref(),
+        limit.unwrap_or(DEFAULT_HISTORY_LIMIT).max(1),
+        offset.unwrap_or(0).max(0),
+    )
+    .map_err(CommandError::from)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QueryHistorySearchResult {
+    pub entry: QueryHistoryEntry,
+    /// The SQL around the first match
+    pub snippet: String,
+    pub score: u32,
+}
+
+/// Finds history entries whose SQL contains `term`, optionally only those of one
+/// connection. Ranked like search_saved_queries; returns at most `limit` entries
+#[tauri::command]
+pub fn search_query_history(
+    term: String,
+    connection_id: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<QueryHistorySearchResult>, CommandError> {
+    let mut results: Vec<QueryHistorySearchResult> =
+        metadata::search_query_history(&term, connection_id.as_deref())?
+            .into_iter()
+            .map(|entry| QueryHistorySearchResult {
+                score: search::match_score(&term, None, &entry.sql),
+                snippet: search::snippet(&entry.sql, &term),
+                entry,
+            })
+            .collect();
+
+    results.sort_by_key(|r| std::cmp::Reverse(r.score));
+    results.truncate(limit.unwrap_or(DEFAULT_HISTORY_LIMIT as usize).max(1));
+    Ok(results)
+}
+
+/// Clears the query history, optionally only that of one connection
+#[tauri::command]
+pub fn clear_query_history(connection_id: Option<String>) -> Result<usize, CommandError> {
+    metadata::clear_query_history(connection_id.as_deref()).map_err(CommandError::from)
+}
+
+// ============ Polling ============
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PollResultEvent {
+    pub saved_query_id: String,
+    pub result: Option<QueryResult>,
+    pub error: Option<String>,
+}
+
+/// Starts re-running a saved query on an interval, emitting `poll-result` events. Its
+/// `:name` placeholders are bound to the parameters' defaults, so every parameter needs one
+#[tauri::command]
+pub async fn start_polling(
+    saved_query_id: String,
+    interval_secs: u64,
+    app: AppHandle,
+    postgres: State<'_, PostgresState>,
+) -> Result<u64, CommandError> {
+    let saved_query = metadata::get_saved_query_by_id(&saved_query_id)?;
+    let (sql, binds) =
+        template::bind_template(&saved_query.sql, &saved_query.parameters, &HashMap::new())
+            .map_err(|e| CommandError::from(e).context("Polling uses the parameter defaults"))?;
+
+    if postgres.get_connection_id().await.is_none() {
+        return Err(PostgresError::NoActiveConnection.into());
+    }
+
+    let interval_secs = interval_secs.max(MIN_POLL_INTERVAL_SECS);
+    let manager = postgres.inner().clone();
+    let key = saved_query_id.clone();
+
+    let handle = tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval_secs));
+        loop {
+            ticker.tick().await;
+
+            let event = match manager.execute_query_on_pool(&sql, &binds, false).await {
+                Ok(result) => {
+                    manager.cache_poll_result(&key, result.clone()).await;
+                    PollResultEvent {
+                        saved_query_id: key.clone(),
+                        result: Some(result),
+                        error: None,
+                    }
+                }
+                Err(e) => PollResultEvent {
+                    saved_query_id: key.clone(),
+                    result: None,
+                    error: Some(e.to_string()),
+                },
+            };
+
+            app.emit("poll-result", event).ok();
+        }
+    });
+
+    postgres.register_poll_task(&saved_query_id, handle).await;
+
+    Ok(interval_secs)
+}
+
+/// Stops polling a saved query
+#[tauri::command]
+pub async fn stop_polling(
+    saved_query_id: String,
+    postgres: State<'_, PostgresState>,
+) -> Result<bool, CommandError> {
+    Ok(postgres.stop_poll_task(&saved_query_id).await)
+}
+
+/// Gets the latest cached result of a polled saved query
+#[tauri::command]
+pub async fn get_poll_result(
+    saved_query_id: String,
+    postgres: State<'_, PostgresState>,
+) -> Result<Option<QueryResult>, CommandError> {
+    Ok(postgres.get_poll_result(&saved_query_id).await)
 }
 
 // ============ App State for Editor ============
 
-/// Saves the current editor content to persist across sessions
+/// Sets how BYTEA values are rendered in results and remembers the choice
+#[tauri::command]
+pub fn set_binary_encoding(encoding: BinaryEncoding) -> Result<(), CommandError> {
+    record::set_binary_encoding(encoding);
+    metadata::set_app_state(record::BINARY_ENCODING_KEY, encoding.as_str())
+        .map_err(CommandError::from)
+}
+
+/// Gets how BYTEA values are rendered in results
+#[tauri::command]
+pub fn get_binary_encoding() -> Result<BinaryEncoding, CommandError> {
+    Ok(record::binary_encoding())
+}
+
+/// Saves the current editor content to persist across sessions. Kept for the single-editor
+/// UI: the content lives in the first tab without a connection
+#[tauri::command]
+pub fn save_editor_content(content: String) -> Result<(), CommandError> {
+    let tabs = metadata::list_editor_tabs(None)?;
+    match tabs.first() {
+        Some(tab) => metadata::update_editor_tab(&tab.id, None, Some(&content), None),
+        None => metadata::create_editor_tab(None, metadata::DEFAULT_EDITOR_TAB_TITLE, &content),
+    }
+    .map(|_| ())
+    .map_err(CommandError::from)
+}
+
+/// Gets the last saved editor content (see save_editor_content)
+#[tauri::command]
+pub fn get_editor_content() -> Result<Option<String>, CommandError> {
+    metadata::list_editor_tabs(None)
+        .map(|tabs| tabs.into_iter().next().map(|tab| tab.content))
+        .map_err(CommandError::from)
+}
+
+/// Opens a new editor tab, after the existing ones, for a connection or for no connection
+#[tauri::command]
+pub fn create_editor_tab(
+    connection_id: Option<String>,
+    title: Option<String>,
+    content: Option<String>,
+) -> Result<EditorTab, CommandError> {
+    metadata::create_editor_tab(
+        connection_id.as_deref(),
+        title
+            .as_deref()
+            .unwrap_or(metadata::DEFAULT_EDITOR_TAB_TITLE),
+        content.as_deref().unwrap_or(""),
+    )
+    .map_err(CommandError::from)
+}
+
+/// Lists the editor tabs of a connection (or those without one) in tab order
+#[tauri::command]
+pub fn list_editor_tabs(connection_id: Option<String>) -> Result<Vec<EditorTab>, CommandError> {
+    metadata::list_editor_tabs(connection_id.as_deref()).map_err(CommandError::from)
+}
+
+/// Renames, edits or moves an editor tab; fields left out are unchanged
 #[tauri::command]
-pub fn save_editor_content(content: String) -> Result<(), String> {
-    metadata::set_app_state("editor_content", &content).map_err(|e| e.to_string())
+pub fn update_editor_tab(
+    id: String,
+    title: Option<String>,
+    content: Option<String>,
+    position: Option<i64>,
+) -> Result<EditorTab, CommandError> {
+    metadata::update_editor_tab(&id, title.as_deref(), content.as_deref(), position)
+        .map_err(CommandError::from)
 }
 
-/// Gets the last saved editor content
+/// Closes an editor tab
 #[tauri::command]
-pub fn get_editor_content() -> Result<Option<String>, String> {
-    metadata::get_app_state("editor_content").map_err(|e| e.to_string())
+pub fn delete_editor_tab(id: String) -> Result<(), CommandError> {
+    metadata::delete_editor_tab(&id).map_err(CommandError::from)
 }
 
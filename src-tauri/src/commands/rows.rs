@@ -0,0 +1,305 @@
+use crate::commands::error::CommandError;
+use crate::db::postgres::{quote_ident, PostgresState, QueryResult, RowKeyStrategy};
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, HashMap};
+use tauri::State;
+
+/// Postgres accepts at most this many bind parameters per statement
+const MAX_BIND_PARAMS: usize = 65_535;
+
+/// A column bound to a statement parameter, cast to the column's type
+#[derive(Debug)]
+struct BoundColumn {
+    name: String,
+    data_type: String,
+    value: JsonValue,
+}
+
+/// Updates the row whose primary key matches `key` (column -> value), setting each
+/// column in `changes` to its new value. Returns the number of rows updated
+#[tauri::command]
+pub async fn update_row(
+    schema: String,
+    table: String,
+    key: HashMap<String, JsonValue>,
+    changes: BTreeMap<String, JsonValue>,
+    postgres: State<'_, PostgresState>,
+) -> Result<u64, CommandError> {
+    if changes.is_empty() {
+        return Err(CommandError::invalid_input("No columns to update"));
+    }
+
+    let column_types = postgres.fetch_column_types(&schema, &table).await?;
+    let key_columns = primary_key_columns(&postgres, &schema, &table).await?;
+
+    let key = bind_columns(&column_types, key_values(&key_columns, key)?)?;
+    let changes = bind_columns(&column_types, changes.into_iter().collect())?;
+
+    let sql = build_update_sql(&schema, &table, &changes, &key);
+    let params: Vec<JsonValue> = changes.into_iter().chain(key).map(|c| c.value).collect();
+
+    postgres
+        .execute_with_params(&sql, &params)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Deletes every row whose primary key matches one of `keys`, all or none of them.
+/// Returns the number of rows deleted
+#[tauri::command]
+pub async fn delete_rows(
+    schema: String,
+    table: String,
+    keys: Vec<HashMap<String, JsonValue>>,
+    postgres: State<'_, PostgresState>,
+) -> Result<u64, CommandError> {
+    if keys.is_empty() {
+        return Ok(0);
+    }
+
+    let column_types = postgres.fetch_column_types(&schema, &table).await?;
+    let key_columns = primary_key_columns(&postgres, &schema, &table).await?;
+
+    // Validate every row before deleting any of them
+    let rows = keys
+        .into_iter()
+        .enumerate()
+        .map(|(i, key)| {
+            key_values(&key_columns, key)
+                .and_then(|values| bind_columns(&column_types, values))
+                .map_err(|e| e.context(format!("Row {}", i + 1)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let statements: Vec<(String, Vec<JsonValue>)> = rows
+        .chunks(MAX_BIND_PARAMS / key_columns.len())
+        .map(|chunk| {
+            let sql = build_delete_sql(&schema, &table, chunk);
+            let params = chunk.iter().flatten().map(|c| c.value.clone()).collect();
+            (sql, params)
+        })
+        .collect();
+
+    postgres
+        .execute_batch_with_params(&statements)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Inserts a row from a column -> value map and returns it as stored, defaults included.
+/// Columns left out get their default (or NULL)
+#[tauri::command]
+pub async fn insert_row(
+    schema: String,
+    table: String,
+    values: BTreeMap<String, JsonValue>,
+    postgres: State<'_, PostgresState>,
+) -> Result<QueryResult, CommandError> {
+    let column_types = postgres.fetch_column_types(&schema, &table).await?;
+    let values = bind_columns(&column_types, values.into_iter().collect())?;
+
+    let sql = build_insert_sql(&schema, &table, &values);
+    let params: Vec<JsonValue> = values.into_iter().map(|c| c.value).collect();
+
+    postgres
+        .query_with_params(&sql, &params)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Gets the primary key columns of a table, refusing tables that don't have one
+async fn primary_key_columns(
+    postgres: &PostgresState,
+    schema: &str,
+    table: &str,
+) -> Result<Vec<String>, CommandError> {
+    let row_key = postgres.detect_row_key(schema, table).await?;
+
+    if row_key.strategy != RowKeyStrategy::PrimaryKey {
+        return Err(CommandError::invalid_input(format!(
+            "{}.{} has no primary key, so its rows cannot be edited",
+            schema, table
+        )));
+    }
+    Ok(row_key.columns)
+}
+
+/// Orders a primary key map by `key_columns`, rejecting missing or extra columns
+fn key_values(
+    key_columns: &[String],
+    mut key: HashMap<String, JsonValue>,
+) -> Result<Vec<(String, JsonValue)>, CommandError> {
+    let values = key_columns
+        .iter()
+        .map(|column| {
+            key.remove(column)
+                .map(|value| (column.clone(), value))
+                .ok_or_else(|| {
+                    CommandError::invalid_input(format!(
+                        "Missing value for primary key column \"{}\"",
+                        column
+                    ))
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if let Some(column) = key.keys().next() {
+        return Err(CommandError::invalid_input(format!(
+            "\"{}\" is not a primary key column",
+            column
+        )));
+    }
+    Ok(values)
+}
+
+/// Attaches each column's type, rejecting columns the table doesn't have
+fn bind_columns(
+    column_types: &HashMap<String, String>,
+    values: Vec<(String, JsonValue)>,
+) -> Result<Vec<BoundColumn>, CommandError> {
+    values
+        .into_iter()
+        .map(|(name, value)| {
+            let data_type = column_types
+                .get(&name)
+                .ok_or_else(|| CommandError::not_found(format!("Unknown column \"{}\"", name)))?
+                .clone();
+            Ok(BoundColumn {
+                name,
+                data_type,
+                value,
+            })
+        })
+        .collect()
+}
+
+/// Renders `"column" = $n::type` for each column, numbering placeholders from `first`
+fn assignments(columns: &[BoundColumn], first: usize) -> Vec<String> {
+    columns
+        .iter()
+        .enumerate()
+        .map(|(i, c)| format!("{} = ${}::{}", quote_ident(&c.name), first + i, c.data_type))
+        .collect()
+}
+
+/// Builds an UPDATE whose placeholders are the changed values followed by the key values
+fn build_update_sql(
+    schema: &str,
+    table: &str,
+    changes: &[BoundColumn],
+    key: &[BoundColumn],
+) -> String {
+    format!(
+        "UPDATE {}.{} SET {} WHERE {}",
+        quote_ident(schema),
+        quote_ident(table),
+        assignments(changes, 1).join(", "),
+        assignments(key, changes.len() + 1).join(" AND ")
+    )
+}
+
+/// Builds an INSERT ... RETURNING * with one placeholder per value
+fn build_insert_sql(schema: &str, table: &str, values: &[BoundColumn]) -> String {
+    let table_ref = format!("{}.{}", quote_ident(schema), quote_ident(table));
+    if values.is_empty() {
+        return format!("INSERT INTO {} DEFAULT VALUES RETURNING *", table_ref);
+    }
+
+    let columns: Vec<String> = values.iter().map(|c| quote_ident(&c.name)).collect();
+    let placeholders: Vec<String> = values
+        .iter()
+        .enumerate()
+        .map(|(i, c)| format!("${}::{}", i + 1, c.data_type))
+        .collect();
+
+    format!(
+        "INSERT INTO {} ({}) VALUES ({}) RETURNING *",
+        table_ref,
+        columns.join(", "),
+        placeholders.join(", ")
+    )
+}
+
+/// Builds `DELETE ... WHERE (key columns) IN ((...), ...)` for a non-empty set of rows
+/// whose key columns come in the same order
+fn build_delete_sql(schema: &str, table: &str, rows: &[Vec<BoundColumn>]) -> String {
+    let columns: Vec<String> = rows[0].iter().map(|c| quote_ident(&c.name)).collect();
+
+    let mut placeholder = 0;
+    let tuples: Vec<String> = rows
+        .iter()
+        .map(|row| {
+            let values: Vec<String> = row
+                .iter()
+                .map(|c| {
+                    placeholder += 1;
+                    format!("${}::{}", placeholder, c.data_type)
+                })
+                .collect();
+            format!("({})", values.join(", "))
+        })
+        .collect();
+
+    format!(
+        "DELETE FROM {}.{} WHERE ({}) IN ({})",
+        quote_ident(schema),
+        quote_ident(table),
+        columns.join(", "),
+        tuples.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_build_update_sql() {
+        let column_types: HashMap<String, String> = [
+            ("id", "integer"),
+            ("tenant", "uuid"),
+            ("name", "character varying"),
+        ]
+        .into_iter()
+        .map(|(name, data_type)| (name.to_string(), data_type.to_string()))
+        .collect();
+        let key_columns = vec!["tenant".to_string(), "id".to_string()];
+
+        let key = HashMap::from([
+            ("id".to_string(), json!(7)),
+            ("tenant".to_string(), json!("a0ee")),
+        ]);
+        let key = bind_columns(&column_types, key_values(&key_columns, key).unwrap()).unwrap();
+        let changes = bind_columns(&column_types, vec![("name".to_string(), json!("x"))]).unwrap();
+
+        assert_eq!(
+            build_update_sql("public", "users", &changes, &key),
+            r#"UPDATE "public"."users" SET "name" = $1::character varying WHERE "tenant" = $2::uuid AND "id" = $3::integer"#
+        );
+
+        let missing = HashMap::from([("id".to_string(), json!(7))]);
+        assert!(key_values(&key_columns, missing).is_err());
+        assert!(bind_columns(&column_types, vec![("nope".to_string(), json!(1))]).is_err());
+
+        let second = HashMap::from([
+            ("id".to_string(), json!(8)),
+            ("tenant".to_string(), json!("a0ee")),
+        ]);
+        let second =
+            bind_columns(&column_types, key_values(&key_columns, second).unwrap()).unwrap();
+        assert_eq!(
+            build_delete_sql("public", "users", &[key, second]),
+            r#"DELETE FROM "public"."users" WHERE ("tenant", "id") IN (($1::uuid, $2::integer), ($3::uuid, $4::integer))"#
+        );
+
+        assert_eq!(
+            build_insert_sql("public", "users", &changes),
+            r#"INSERT INTO "public"."users" ("name") VALUES ($1::character varying) RETURNING *"#
+        );
+        assert_eq!(
+            build_insert_sql("public", "users", &[]),
+            r#"INSERT INTO "public"."users" DEFAULT VALUES RETURNING *"#
+        );
+    }
+}
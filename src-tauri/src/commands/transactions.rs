@@ -0,0 +1,74 @@
+use crate::commands::error::CommandError;
+use crate::db::postgres::{IsolationLevel, PostgresState, TransactionStatus};
+use tauri::State;
+
+/// Begins an explicit transaction; subsequent queries run inside it until commit/rollback
+#[tauri::command]
+pub async fn begin_transaction(
+    isolation_level: Option<IsolationLevel>,
+    postgres: State<'_, PostgresState>,
+) -> Result<(), CommandError> {
+    postgres
+        .begin_transaction(isolation_level)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Commits the open transaction
+#[tauri::command]
+pub async fn commit_transaction(postgres: State<'_, PostgresState>) -> Result<(), CommandError> {
+    postgres
+        .commit_transaction()
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Rolls back the open transaction
+#[tauri::command]
+pub async fn rollback_transaction(postgres: State<'_, PostgresState>) -> Result<(), CommandError> {
+    postgres
+        .rollback_transaction()
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Sets a savepoint in the open transaction
+#[tauri::command]
+pub async fn savepoint(
+    name: String,
+    postgres: State<'_, PostgresState>,
+) -> Result<(), CommandError> {
+    postgres.savepoint(&name).await.map_err(CommandError::from)
+}
+
+/// Rolls the open transaction back to a savepoint, keeping the transaction itself open
+#[tauri::command]
+pub async fn rollback_to_savepoint(
+    name: String,
+    postgres: State<'_, PostgresState>,
+) -> Result<(), CommandError> {
+    postgres
+        .rollback_to_savepoint(&name)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Gets the state of the current transaction
+#[tauri::command]
+pub async fn get_transaction_status(
+    postgres: State<'_, PostgresState>,
+) -> Result<TransactionStatus, CommandError> {
+    Ok(postgres.transaction_status().await)
+}
+
+/// Sets the default isolation level for the active connection (None restores the server default)
+#[tauri::command]
+pub async fn set_default_isolation_level(
+    isolation_level: Option<IsolationLevel>,
+    postgres: State<'_, PostgresState>,
+) -> Result<(), CommandError> {
+    postgres
+        .set_default_isolation_level(isolation_level)
+        .await
+        .map_err(CommandError::from)
+}
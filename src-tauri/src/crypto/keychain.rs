@@ -0,0 +1,52 @@
+use super::CryptoError;
+use keyring::Entry;
+
+/// Service name under which connection passwords are stored in the OS secret store
+const SERVICE: &str = "com.datatool.DataTool";
+
+/// Stored in `encrypted_password` in place of ciphertext when the password lives in the keychain
+pub const KEYCHAIN_SENTINEL: &str = "keychain";
+
+fn entry(connection_id: &str) -> Result<Entry, CryptoError> {
+    Entry::new(SERVICE, connection_id).map_err(|e| CryptoError::Keychain(e.to_string()))
+}
+
+/// Stores a connection password in the OS keychain. The secret is read back to make sure
+/// the store actually persists it (headless Linux sessions and CI often have no usable one)
+pub fn store_secret(connection_id: &str, password: &str) -> Result<(), CryptoError> {
+    entry(connection_id)?
+        .set_password(password)
+        .map_err(|e| CryptoError::Keychain(e.to_string()))?;
+
+    if load_secret(connection_id)? != password {
+        return Err(CryptoError::Keychain(
+            "The secret store did not persist the password".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Loads a connection password from the OS keychain
+pub fn load_secret(connection_id: &str) -> Result<String, CryptoError> {
+    entry(connection_id)?
+        .get_password()
+        .map_err(|e| CryptoError::Keychain(e.to_string()))
+}
+
+/// Loads a secret from the OS keychain, or None when it has no entry for `account`. Unlike
+/// load_secret, a missing entry is told apart from a keychain that can't be used
+pub fn find_secret(account: &str) -> Result<Option<String>, CryptoError> {
+    match entry(account)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(CryptoError::Keychain(e.to_string())),
+    }
+}
+
+/// Removes a connection password from the OS keychain. Missing entries are not an error
+pub fn delete_secret(connection_id: &str) -> Result<(), CryptoError> {
+    match entry(connection_id)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(CryptoError::Keychain(e.to_string())),
+    }
+}
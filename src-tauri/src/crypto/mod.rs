@@ -1,11 +1,41 @@
+pub mod keychain;
+
+use crate::db::metadata;
 use aes_gcm::{
     aead::{Aead, KeyInit},
     Aes256Gcm, Nonce,
 };
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use once_cell::sync::Lazy;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::env;
+use std::sync::Mutex;
 use thiserror::Error;
 
+/// Leading byte of blobs encrypted under an Argon2id-derived key. Blobs written
+/// before versioning was introduced have no version byte (nonce + ciphertext only)
+const VERSION_ARGON2: u8 = 1;
+
+/// Leading byte of blobs encrypted under a passphrase-derived key
+const VERSION_PASSPHRASE: u8 = 2;
+
+/// Keychain account, and app state key when there is no usable keychain, holding the
+/// per-install KDF salt
+const KDF_SALT_KEY: &str = "kdf_salt";
+
+/// Machine identity and salt a key was derived from
+type KeyInput = (String, Vec<u8>);
+
+/// Argon2 is deliberately slow, so derived keys are cached per machine identity and salt
+static KEY_CACHE: Lazy<Mutex<HashMap<KeyInput, [u8; 32]>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Held while the salt is looked up, so a first run generates and moves it only once
+static SALT_LOCK: Mutex<()> = Mutex::new(());
+
 #[derive(Error, Debug)]
 pub enum CryptoError {
     #[error("Encryption failed")]
@@ -14,16 +44,113 @@ pub enum CryptoError {
     DecryptionFailed,
     #[error("Invalid data format")]
     InvalidFormat,
+    #[error("Key derivation failed: {0}")]
+    KeyDerivationFailed(String),
+    #[error("Keychain error: {0}")]
+    Keychain(String),
+}
+
+/// Describes how the password encryption key is obtained, so stored passwords
+/// can be migrated from one scheme (or machine identity) to another
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum KeyContext {
+    /// Key derived from the current OS user name
+    #[default]
+    Machine,
+    /// Key derived from a given user name, e.g. for passwords saved before the account was renamed
+    MachineUser { user: String },
 }
 
-/// Derives a 32-byte key from machine-specific identifiers
-fn derive_key() -> [u8; 32] {
+impl KeyContext {
+    fn machine_id(&self) -> String {
+        match self {
+            KeyContext::Machine => current_user(),
+            KeyContext::MachineUser { user } => user.clone(),
+        }
+    }
+
+    fn key(&self) -> Result<[u8; 32], CryptoError> {
+        derive_key(&self.machine_id())
+    }
+
+    fn legacy_key(&self) -> [u8; 32] {
+        derive_legacy_key(&self.machine_id())
+    }
+}
+
+fn current_user() -> String {
+    env::var("USER")
+        .or_else(|_| env::var("USERNAME"))
+        .unwrap_or_else(|_| "default_user".to_string())
+}
+
+/// Derives a 32-byte key from machine-specific identifiers with Argon2id,
+/// salted with a random value generated once per install
+fn derive_key(machine_id: &str) -> Result<[u8; 32], CryptoError> {
+    let salt = install_salt()?;
+    let cache_key = (machine_id.to_string(), salt);
+    if let Some(key) = KEY_CACHE
+        .lock()
+        .ok()
+        .and_then(|cache| cache.get(&cache_key).copied())
+    {
+        return Ok(key);
+    }
+
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(machine_id.as_bytes(), &cache_key.1, &mut key)
+        .map_err(|e| CryptoError::KeyDerivationFailed(e.to_string()))?;
+
+    if let Ok(mut cache) = KEY_CACHE.lock() {
+        cache.insert(cache_key, key);
+    }
+    Ok(key)
+}
+
+/// Gets the per-install salt, generating it on first use. It is kept in the OS keychain,
+/// so a copy of the metadata DB alone can't be used to derive the key. Only where there
+/// is no usable keychain does it live in the DB; a salt from an older install is moved
+/// out of the DB once the keychain takes it
+fn install_salt() -> Result<Vec<u8>, CryptoError> {
+    let _lock = SALT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let metadata_error =
+        |e: metadata::MetadataError| CryptoError::KeyDerivationFailed(e.to_string());
+
+    let salt = match keychain::find_secret(KDF_SALT_KEY) {
+        Ok(Some(salt)) => salt,
+        Ok(None) => {
+            let salt = match metadata::get_app_state(KDF_SALT_KEY).map_err(metadata_error)? {
+                Some(salt) => salt,
+                None => fresh_salt(),
+            };
+            if keychain::store_secret(KDF_SALT_KEY, &salt).is_ok() {
+                metadata::delete_app_state(KDF_SALT_KEY).map_err(metadata_error)?;
+            } else {
+                metadata::set_app_state(KDF_SALT_KEY, &salt).map_err(metadata_error)?;
+            }
+            salt
+        }
+        Err(_) => metadata::get_or_insert_app_state(KDF_SALT_KEY, &fresh_salt())
+            .map_err(metadata_error)?,
+    };
+    STANDARD
+        .decode(salt)
+        .map_err(|e| CryptoError::KeyDerivationFailed(e.to_string()))
+}
+
+/// A random 16-byte salt, base64-encoded
+fn fresh_salt() -> String {
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill(&mut salt);
+    STANDARD.encode(salt)
+}
+
+/// The original key derivation, kept only to decrypt blobs written before Argon2
+fn derive_legacy_key(machine_id: &str) -> [u8; 32] {
     // Use a combination of machine-specific values and a fixed salt
     // In production, you might want to use OS keychain APIs
-    let machine_id = env::var("USER")
-        .or_else(|_| env::var("USERNAME"))
-        .unwrap_or_else(|_| "default_user".to_string());
-    
     let salt = "datatool_encryption_salt_v1";
     let combined = format!("{}{}", machine_id, salt);
     
@@ -37,10 +164,106 @@ fn derive_key() -> [u8; 32] {
 }
 
 /// Encrypts a password using AES-256-GCM
-/// Returns a base64-encoded string containing nonce + ciphertext
+/// Returns a base64-encoded string containing version + nonce + ciphertext
 pub fn encrypt_password(password: &str) -> Result<String, CryptoError> {
-    let key = derive_key();
-    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|_| CryptoError::EncryptionFailed)?;
+    encrypt_password_with(password, &KeyContext::default())
+}
+
+/// Encrypts a password under the key described by `context`
+pub fn encrypt_password_with(password: &str, context: &KeyContext) -> Result<String, CryptoError> {
+    let key = context.key()?;
+    
+    let mut combined = vec![VERSION_ARGON2];
+    combined.extend(seal(&key, password)?);
+    
+    Ok(STANDARD.encode(&combined))
+}
+
+/// Decrypts a password that was encrypted with encrypt_password
+pub fn decrypt_password(encrypted: &str) -> Result<String, CryptoError> {
+    decrypt_password_with(encrypted, &KeyContext::default())
+}
+
+/// Decrypts a password that was encrypted under the key described by `context`.
+/// Blobs from before the Argon2 migration are decrypted with the legacy key;
+/// re-encrypting them (see rekey_all_connections) upgrades them.
+pub fn decrypt_password_with(encrypted: &str, context: &KeyContext) -> Result<String, CryptoError> {
+    let combined = STANDARD
+        .decode(encrypted)
+        .map_err(|_| CryptoError::InvalidFormat)?;
+    
+    // A legacy blob starts with a random nonce, which can coincide with the version byte,
+    // so fall through to the legacy path if the versioned one fails
+    if let Some((&VERSION_ARGON2, sealed)) = combined.split_first() {
+        if let Ok(password) = open(&context.key()?, sealed) {
+            return Ok(password);
+        }
+    }
+    
+    open(&context.legacy_key(), &combined)
+}
+
+/// A key derived with Argon2id from a user-chosen passphrase instead of the machine
+/// identity, so what it encrypts can be opened on another machine
+pub struct PassphraseKey {
+    key: [u8; 32],
+    salt: [u8; 16],
+}
+
+impl PassphraseKey {
+    /// Derives a key under a fresh random salt
+    pub fn generate(passphrase: &str) -> Result<Self, CryptoError> {
+        let mut salt = [0u8; 16];
+        rand::thread_rng().fill(&mut salt);
+        Self::derive(passphrase, salt)
+    }
+
+    /// Derives the key again from the passphrase and the salt it was generated with
+    pub fn with_salt(passphrase: &str, salt: &str) -> Result<Self, CryptoError> {
+        let salt = STANDARD
+            .decode(salt)
+            .ok()
+            .and_then(|salt| <[u8; 16]>::try_from(salt).ok())
+            .ok_or(CryptoError::InvalidFormat)?;
+        Self::derive(passphrase, salt)
+    }
+
+    fn derive(passphrase: &str, salt: [u8; 16]) -> Result<Self, CryptoError> {
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| CryptoError::KeyDerivationFailed(e.to_string()))?;
+        Ok(Self { key, salt })
+    }
+
+    /// The salt, base64-encoded, to be stored next to what the key encrypts
+    pub fn salt(&self) -> String {
+        STANDARD.encode(self.salt)
+    }
+
+    /// Encrypts a secret, returning base64 of version + nonce + ciphertext
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, CryptoError> {
+        let mut combined = vec![VERSION_PASSPHRASE];
+        combined.extend(seal(&self.key, plaintext)?);
+        Ok(STANDARD.encode(&combined))
+    }
+
+    /// Decrypts a secret encrypted with `encrypt`. Fails with DecryptionFailed when the
+    /// passphrase is wrong
+    pub fn decrypt(&self, encrypted: &str) -> Result<String, CryptoError> {
+        let combined = STANDARD
+            .decode(encrypted)
+            .map_err(|_| CryptoError::InvalidFormat)?;
+        match combined.split_first() {
+            Some((&VERSION_PASSPHRASE, sealed)) => open(&self.key, sealed),
+            _ => Err(CryptoError::InvalidFormat),
+        }
+    }
+}
+
+/// Encrypts with AES-256-GCM, returning nonce + ciphertext
+fn seal(key: &[u8; 32], plaintext: &str) -> Result<Vec<u8>, CryptoError> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| CryptoError::EncryptionFailed)?;
     
     // Generate random 12-byte nonce
     let mut nonce_bytes = [0u8; 12];
@@ -49,26 +272,18 @@ pub fn encrypt_password(password: &str) -> Result<String, CryptoError> {
     
     // Encrypt the password
     let ciphertext = cipher
-        .encrypt(nonce, password.as_bytes())
+        .encrypt(nonce, plaintext.as_bytes())
         .map_err(|_| CryptoError::EncryptionFailed)?;
     
-    // Combine nonce + ciphertext and encode as base64
+    // Combine nonce + ciphertext
     let mut combined = Vec::with_capacity(12 + ciphertext.len());
     combined.extend_from_slice(&nonce_bytes);
     combined.extend_from_slice(&ciphertext);
-    
-    use base64::{engine::general_purpose::STANDARD, Engine};
-    Ok(STANDARD.encode(&combined))
+    Ok(combined)
 }
 
-/// Decrypts a password that was encrypted with encrypt_password
-pub fn decrypt_password(encrypted: &str) -> Result<String, CryptoError> {
-    use base64::{engine::general_purpose::STANDARD, Engine};
-    
-    let combined = STANDARD
-        .decode(encrypted)
-        .map_err(|_| CryptoError::InvalidFormat)?;
-    
+/// Decrypts nonce + ciphertext produced by seal
+fn open(key: &[u8; 32], combined: &[u8]) -> Result<String, CryptoError> {
     if combined.len() < 12 {
         return Err(CryptoError::InvalidFormat);
     }
@@ -76,8 +291,7 @@ pub fn decrypt_password(encrypted: &str) -> Result<String, CryptoError> {
     let (nonce_bytes, ciphertext) = combined.split_at(12);
     let nonce = Nonce::from_slice(nonce_bytes);
     
-    let key = derive_key();
-    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|_| CryptoError::DecryptionFailed)?;
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| CryptoError::DecryptionFailed)?;
     
     let plaintext = cipher
         .decrypt(nonce, ciphertext)
@@ -89,13 +303,69 @@ pub fn decrypt_password(encrypted: &str) -> Result<String, CryptoError> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::metadata::tests::init_test_database;
 
     #[test]
     fn test_encrypt_decrypt() {
+        init_test_database();
         let password = "my_secret_password";
         let encrypted = encrypt_password(password).unwrap();
         let decrypted = decrypt_password(&encrypted).unwrap();
         assert_eq!(password, decrypted);
     }
+
+    #[test]
+    fn test_decrypt_legacy_format() {
+        init_test_database();
+        let context = KeyContext::MachineUser {
+            user: "legacy_user".to_string(),
+        };
+
+        let legacy = STANDARD.encode(seal(&context.legacy_key(), "old_secret").unwrap());
+        assert_eq!(
+            decrypt_password_with(&legacy, &context).unwrap(),
+            "old_secret"
+        );
+
+        let upgraded = encrypt_password_with("old_secret", &context).unwrap();
+        assert_eq!(STANDARD.decode(&upgraded).unwrap()[0], VERSION_ARGON2);
+        assert_eq!(
+            decrypt_password_with(&upgraded, &context).unwrap(),
+            "old_secret"
+        );
+    }
+
+    #[test]
+    fn test_key_contexts() {
+        init_test_database();
+        let old = KeyContext::MachineUser {
+            user: "former_user".to_string(),
+        };
+        let encrypted = encrypt_password_with("secret", &old).unwrap();
+        assert!(decrypt_password_with(
+            &encrypted,
+            &KeyContext::MachineUser {
+                user: "someone_else".to_string()
+            }
+        )
+        .is_err());
+        assert_eq!(decrypt_password_with(&encrypted, &old).unwrap(), "secret");
+    }
+
+    #[test]
+    fn test_passphrase_key() {
+        let key = PassphraseKey::generate("correct horse").unwrap();
+        let encrypted = key.encrypt("secret").unwrap();
+
+        let same = PassphraseKey::with_salt("correct horse", &key.salt()).unwrap();
+        assert_eq!(same.decrypt(&encrypted).unwrap(), "secret");
+
+        let wrong = PassphraseKey::with_salt("battery staple", &key.salt()).unwrap();
+        assert!(matches!(
+            wrong.decrypt(&encrypted),
+            Err(CryptoError::DecryptionFailed)
+        ));
+        assert!(PassphraseKey::with_salt("correct horse", "c2hvcnQ=").is_err());
+    }
 }
 
@@ -0,0 +1,174 @@
+use crate::db::postgres::ColumnMeta;
+use arrow_array::builder::{
+    BooleanBuilder, Float32Builder, Float64Builder, Int16Builder, Int32Builder, Int64Builder,
+    StringBuilder,
+};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef};
+use serde_json::Value as JsonValue;
+use std::sync::Arc;
+
+/// Maps a Postgres type name to the Arrow type used to encode it.
+/// Anything without a native mapping is carried as UTF-8 text.
+pub fn arrow_type_for(pg_type: &str) -> DataType {
+    match pg_type {
+        "BOOL" => DataType::Boolean,
+        "INT2" => DataType::Int16,
+        "INT4" => DataType::Int32,
+        "INT8" => DataType::Int64,
+        "FLOAT4" => DataType::Float32,
+        "FLOAT8" => DataType::Float64,
+        _ => DataType::Utf8,
+    }
+}
+
+/// Builds the Arrow schema for a result set
+pub fn schema_for(columns: &[ColumnMeta]) -> SchemaRef {
+    let fields: Vec<Field> = columns
+        .iter()
+        .map(|col| Field::new(&col.name, arrow_type_for(&col.data_type), true))
+        .collect();
+    Arc::new(Schema::new(fields))
+}
+
+/// Converts a batch of JSON rows (as produced by row_to_json_values) into a RecordBatch
+pub fn rows_to_record_batch(
+    schema: &SchemaRef,
+    rows: &[Vec<JsonValue>],
+) -> Result<RecordBatch, ArrowError> {
+    let arrays: Vec<ArrayRef> = schema
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let values = rows
+                .iter()
+                .map(move |row| row.get(i).unwrap_or(&JsonValue::Null));
+            build_array(field.data_type(), values)
+        })
+        .collect();
+
+    RecordBatch::try_new(schema.clone(), arrays)
+}
+
+fn build_array<'a>(data_type: &DataType, values: impl Iterator<Item = &'a JsonValue>) -> ArrayRef {
+    match data_type {
+        DataType::Boolean => {
+            let mut builder = BooleanBuilder::new();
+            values.for_each(|v| builder.append_option(v.as_bool()));
+            Arc::new(builder.finish())
+        }
+        DataType::Int16 => {
+            let mut builder = Int16Builder::new();
+            values.for_each(|v| builder.append_option(v.as_i64().map(|n| n as i16)));
+            Arc::new(builder.finish())
+        }
+        DataType::Int32 => {
+            let mut builder = Int32Builder::new();
+            values.for_each(|v| builder.append_option(v.as_i64().map(|n| n as i32)));
+            Arc::new(builder.finish())
+        }
+        DataType::Int64 => {
+            let mut builder = Int64Builder::new();
+            values.for_each(|v| builder.append_option(v.as_i64()));
+            Arc::new(builder.finish())
+        }
+        DataType::Float32 => {
+            let mut builder = Float32Builder::new();
+            values.for_each(|v| builder.append_option(v.as_f64().map(|n| n as f32)));
+            Arc::new(builder.finish())
+        }
+        DataType::Float64 => {
+            let mut builder = Float64Builder::new();
+            values.for_each(|v| builder.append_option(v.as_f64()));
+            Arc::new(builder.finish())
+        }
+        _ => {
+            let mut builder = StringBuilder::new();
+            values.for_each(|v| match v {
+                JsonValue::Null => builder.append_null(),
+                JsonValue::String(s) => builder.append_value(s),
+                other => builder.append_value(other.to_string()),
+            });
+            Arc::new(builder.finish())
+        }
+    }
+}
+
+/// Incrementally writes record batches in the Arrow IPC streaming format
+pub struct IpcStreamEncoder {
+    schema: SchemaRef,
+    writer: StreamWriter<Vec<u8>>,
+}
+
+impl IpcStreamEncoder {
+    pub fn new(columns: &[ColumnMeta]) -> Result<Self, ArrowError> {
+        let schema = schema_for(columns);
+        let writer = StreamWriter::try_new(Vec::new(), &schema)?;
+        Ok(Self { schema, writer })
+    }
+
+    /// Appends one batch of rows to the stream
+    pub fn write_rows(&mut self, rows: &[Vec<JsonValue>]) -> Result<(), ArrowError> {
+        let batch = rows_to_record_batch(&self.schema, rows)?;
+        self.writer.write(&batch)
+    }
+
+    /// Finishes the stream and returns the encoded bytes
+    pub fn finish(mut self) -> Result<Vec<u8>, ArrowError> {
+        self.writer.finish()?;
+        self.writer.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::{Array, Int32Array, StringArray};
+    use arrow_ipc::reader::StreamReader;
+    use serde_json::json;
+
+    #[test]
+    fn test_ipc_round_trip() {
+        let columns = vec![
+            ColumnMeta {
+                name: "id".to_string(),
+                data_type: "INT4".to_string(),
+                ..Default::default()
+            },
+            ColumnMeta {
+                name: "name".to_string(),
+                data_type: "TEXT".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let mut encoder = IpcStreamEncoder::new(&columns).unwrap();
+        encoder
+            .write_rows(&[vec![json!(1), json!("a")], vec![json!(2), JsonValue::Null]])
+            .unwrap();
+        encoder.write_rows(&[vec![json!(3), json!("c")]]).unwrap();
+        let bytes = encoder.finish().unwrap();
+
+        let batches: Vec<RecordBatch> = StreamReader::try_new(bytes.as_slice(), None)
+            .unwrap()
+            .map(|b| b.unwrap())
+            .collect();
+        assert_eq!(batches.len(), 2);
+
+        let ids = batches[0]
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int32Array>()
+            .unwrap();
+        assert_eq!(ids.value(1), 2);
+
+        let names = batches[0]
+            .column(1)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert!(names.is_null(1));
+    }
+}
@@ -0,0 +1,94 @@
+use crate::db::postgres::QueryResult;
+use crate::db::script;
+use futures::TryStreamExt;
+use sqlx::mysql::MySqlQueryResult;
+use sqlx::postgres::PgQueryResult;
+use sqlx::sqlite::SqliteQueryResult;
+use sqlx::{Database, Either, Executor};
+
+/// sqlx gives each driver's query result a rows_affected of its own, but has no trait
+/// for it
+pub trait RowsAffected {
+    fn rows_affected(&self) -> u64;
+}
+
+impl RowsAffected for PgQueryResult {
+    fn rows_affected(&self) -> u64 {
+        self.rows_affected()
+    }
+}
+
+impl RowsAffected for MySqlQueryResult {
+    fn rows_affected(&self) -> u64 {
+        self.rows_affected()
+    }
+}
+
+impl RowsAffected for SqliteQueryResult {
+    fn rows_affected(&self) -> u64 {
+        self.rows_affected()
+    }
+}
+
+/// The rows and rows-affected count of one statement in a batch
+pub struct ResultSet<R> {
+    pub rows: Vec<R>,
+    pub rows_affected: u64,
+    /// Rows past the cap were read but not kept
+    pub truncated: bool,
+}
+
+/// Runs one or more statements as a single batch (the simple query protocol on Postgres,
+/// the text protocol on MySQL), collecting each result set. Every driver ends a
+/// statement's result with its rows-affected count, which sqlx yields as Either::Left.
+/// A result set keeps at most `max_rows` rows; the rest are still read so the
+/// statements after it run
+pub async fn fetch_result_sets<'e, DB, E>(
+    executor: E,
+    sql: &'e str,
+    max_rows: Option<u64>,
+) -> Result<Vec<ResultSet<DB::Row>>, sqlx::Error>
+where
+    DB: Database,
+    DB::QueryResult: RowsAffected,
+    E: Executor<'e, Database = DB>,
+{
+    let mut stream = executor.fetch_many(sqlx::raw_sql(sql));
+    let mut result_sets = Vec::new();
+    let mut rows = Vec::new();
+    let mut truncated = false;
+
+    while let Some(step) = stream.try_next().await? {
+        match step {
+            Either::Left(result) => result_sets.push(ResultSet {
+                rows: std::mem::take(&mut rows),
+                rows_affected: result.rows_affected(),
+                truncated: std::mem::take(&mut truncated),
+            }),
+            Either::Right(_) if max_rows.is_some_and(|max| rows.len() as u64 >= max) => {
+                truncated = true
+            }
+            Either::Right(row) => rows.push(row),
+        }
+    }
+
+    Ok(result_sets)
+}
+
+/// Converts the result sets of `sql` with the driver's `to_result`, which is given the
+/// rows, the rows-affected count and whether the statement modifies rows
+pub fn into_query_results<R>(
+    sql: &str,
+    result_sets: Vec<ResultSet<R>>,
+    to_result: impl Fn(Vec<R>, u64, bool) -> QueryResult,
+) -> Vec<QueryResult> {
+    let modifies_rows = script::result_sets_modify_rows(sql, result_sets.len());
+    result_sets
+        .into_iter()
+        .zip(modifies_rows)
+        .map(|(result_set, modifies_rows)| QueryResult {
+            truncated: result_set.truncated,
+            ..to_result(result_set.rows, result_set.rows_affected, modifies_rows)
+        })
+        .collect()
+}
@@ -0,0 +1,372 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::query::Query;
+use sqlx::{Database, Encode, Type};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// A column the data grid is sorted by
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SortColumn {
+    pub column: String,
+    #[serde(default)]
+    pub direction: SortDirection,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterOp {
+    Eq,
+    Neq,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    /// Case-insensitive LIKE against the column's text form, e.g. `%smith%`
+    Like,
+    IsNull,
+    IsNotNull,
+}
+
+/// A condition on one column of the data grid
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnFilter {
+    pub column: String,
+    pub op: FilterOp,
+    /// Ignored by is_null and is_not_null
+    #[serde(default)]
+    pub value: JsonValue,
+    /// Keys (or array indexes) leading to a value inside a JSON column, e.g.
+    /// `["address", "city"]`. The filter then compares the text found there instead of
+    /// the column, or its number when `value` is a number; a missing path counts as NULL
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub json_path: Vec<String>,
+}
+
+/// A value found in a column and the number of rows holding it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DistinctValue {
+    pub value: JsonValue,
+    pub count: i64,
+}
+
+/// How a driver spells the parts of a filter that differ between engines
+pub struct Dialect {
+    pub quote_ident: fn(&str) -> String,
+    /// Renders a LIKE between a quoted column of any type and a placeholder
+    pub like: fn(&str, &str) -> String,
+    /// Renders the text at a path inside a quoted JSON column, given one text placeholder
+    /// per path element. None where JSON path filters aren't supported
+    pub json_path: Option<fn(&str, &[String]) -> String>,
+    /// Converts text rendered by json_path, or a text placeholder, to a number; NULL
+    /// where the text isn't a JSON number. Without it, numbers are compared as text
+    pub json_number: Option<fn(&str) -> String>,
+}
+
+/// Builds one condition per filter, to be joined with AND, and the values to bind to
+/// them in order. `placeholder` renders the n-th (1-based) placeholder; it gets the
+/// compared column so a driver can cast the value to its type, or None for a LIKE
+/// pattern, a JSON path element, or a value compared with what is at a JSON path, which
+/// are always text; numbers are then compared through json_number. Columns missing from
+/// `columns` are rejected, since they end up in the SQL
+pub fn build_filters(
+    filters: &[ColumnFilter],
+    columns: &[String],
+    dialect: &Dialect,
+    mut placeholder: impl FnMut(usize, Option<&str>) -> String,
+) -> Result<(Vec<String>, Vec<JsonValue>), String> {
+    let mut conditions = Vec::with_capacity(filters.len());
+    let mut values = Vec::new();
+
+    for filter in filters {
+        let mut column = checked_column(&filter.column, columns, dialect)?;
+        if !filter.json_path.is_empty() {
+            let json_path = dialect
+                .json_path
+                .ok_or("JSON path filters aren't supported for this database")?;
+            let keys: Vec<String> = filter
+                .json_path
+                .iter()
+                .map(|key| {
+                    values.push(JsonValue::String(key.clone()));
+                    placeholder(values.len(), None)
+                })
+                .collect();
+            column = json_path(&column, &keys);
+        }
+        let operator = match filter.op {
+            FilterOp::IsNull => {
+                conditions.push(format!("{} IS NULL", column));
+                continue;
+            }
+            FilterOp::IsNotNull => {
+                conditions.push(format!("{} IS NOT NULL", column));
+                continue;
+            }
+            FilterOp::Eq => "=",
+            FilterOp::Neq => "<>",
+            FilterOp::Lt => "<",
+            FilterOp::Lte => "<=",
+            FilterOp::Gt => ">",
+            FilterOp::Gte => ">=",
+            FilterOp::Like => "LIKE",
+        };
+        if filter.value.is_null() {
+            return Err(format!(
+                "Filter on {} needs a value; use is_null to match NULL",
+                filter.column
+            ));
+        }
+
+        values.push(filter.value.clone());
+        conditions.push(if filter.op == FilterOp::Like {
+            (dialect.like)(&column, &placeholder(values.len(), None))
+        } else if filter.json_path.is_empty() {
+            let value = placeholder(values.len(), Some(&filter.column));
+            format!("{} {} {}", column, operator, value)
+        } else {
+            let value = placeholder(values.len(), None);
+            match dialect.json_number.filter(|_| filter.value.is_number()) {
+                // As text, 10 would sort before 9
+                Some(json_number) => {
+                    let (column, value) = (json_number(&column), json_number(&value));
+                    format!("{} {} {}", column, operator, value)
+                }
+                None => format!("{} {} {}", column, operator, value),
+            }
+        });
+    }
+
+    Ok((conditions, values))
+}
+
+/// Builds the ORDER BY items for `order`, rejecting columns missing from `columns`
+pub fn build_order_by(
+    order: &[SortColumn],
+    columns: &[String],
+    dialect: &Dialect,
+) -> Result<Vec<String>, String> {
+    order
+        .iter()
+        .map(|sort| {
+            let column = checked_column(&sort.column, columns, dialect)?;
+            Ok(match sort.direction {
+                SortDirection::Asc => format!("{} ASC", column),
+                SortDirection::Desc => format!("{} DESC", column),
+            })
+        })
+        .collect()
+}
+
+/// Renders ` WHERE a AND b`, or nothing without conditions
+pub fn where_clause(conditions: &[String]) -> String {
+    if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", conditions.join(" AND "))
+    }
+}
+
+/// Renders ` ORDER BY a, b`, or nothing without items
+pub fn order_by_clause(items: &[String]) -> String {
+    if items.is_empty() {
+        String::new()
+    } else {
+        format!(" ORDER BY {}", items.join(", "))
+    }
+}
+
+/// Builds a query for the distinct values of `column`, most frequent first, with the row
+/// limit bound to `limit_placeholder`. The column must be one of `columns`
+pub fn distinct_values_sql(
+    table_ref: &str,
+    column: &str,
+    columns: &[String],
+    dialect: &Dialect,
+    limit_placeholder: &str,
+) -> Result<String, String> {
+    let column = checked_column(column, columns, dialect)?;
+    Ok(format!(
+        "SELECT {column}, COUNT(*) AS value_count FROM {} GROUP BY {column} \
+         ORDER BY value_count DESC, {column} LIMIT {}",
+        table_ref,
+        limit_placeholder,
+        column = column
+    ))
+}
+
+/// Binds a filter value or parameter by its JSON type: integers as BIGINT, other numbers
+/// as DOUBLE, and arrays and objects as their JSON text. Null binds as a text NULL
+pub fn bind_json<'q, DB>(
+    query: Query<'q, DB, DB::Arguments<'q>>,
+    value: &JsonValue,
+) -> Query<'q, DB, DB::Arguments<'q>>
+where
+    DB: Database,
+    bool: Encode<'q, DB> + Type<DB>,
+    i64: Encode<'q, DB> + Type<DB>,
+    String: Encode<'q, DB> + Type<DB>,
+    Option<f64>: Encode<'q, DB> + Type<DB>,
+    Option<String>: Encode<'q, DB> + Type<DB>,
+{
+    match value {
+        JsonValue::Null => query.bind(None::<String>),
+        JsonValue::Bool(b) => query.bind(*b),
+        JsonValue::Number(n) => match n.as_i64() {
+            Some(i) => query.bind(i),
+            None => query.bind(n.as_f64()),
+        },
+        JsonValue::String(s) => query.bind(s.clone()),
+        JsonValue::Array(_) | JsonValue::Object(_) => query.bind(value.to_string()),
+    }
+}
+
+fn checked_column(column: &str, columns: &[String], dialect: &Dialect) -> Result<String, String> {
+    if columns.iter().any(|c| c == column) {
+        Ok((dialect.quote_ident)(column))
+    } else {
+        Err(format!("Unknown column {}", column))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const DIALECT: Dialect = Dialect {
+        quote_ident: crate::db::postgres::quote_ident,
+        like: |column, pattern| format!("{}::text ILIKE {}", column, pattern),
+        json_path: Some(|column, keys| format!("({} #>> ARRAY[{}])", column, keys.join(", "))),
+        json_number: Some(|text| format!("num({})", text)),
+    };
+
+    #[test]
+    fn test_build_filters() {
+        let columns = vec![
+            "id".to_string(),
+            "full name".to_string(),
+            "data".to_string(),
+        ];
+        let filters = vec![
+            ColumnFilter {
+                column: "id".to_string(),
+                op: FilterOp::Gte,
+                value: json!(10),
+                json_path: vec![],
+            },
+            ColumnFilter {
+                column: "full name".to_string(),
+                op: FilterOp::IsNotNull,
+                value: JsonValue::Null,
+                json_path: vec![],
+            },
+            ColumnFilter {
+                column: "full name".to_string(),
+                op: FilterOp::Like,
+                value: json!("%smith%"),
+                json_path: vec![],
+            },
+            ColumnFilter {
+                column: "data".to_string(),
+                op: FilterOp::Eq,
+                value: json!("active"),
+                json_path: vec!["meta".to_string(), "status".to_string()],
+            },
+            ColumnFilter {
+                column: "data".to_string(),
+                op: FilterOp::IsNull,
+                value: JsonValue::Null,
+                json_path: vec!["deleted_at".to_string()],
+            },
+            ColumnFilter {
+                column: "data".to_string(),
+                op: FilterOp::Gt,
+                value: json!(9),
+                json_path: vec!["count".to_string()],
+            },
+        ];
+
+        let (conditions, values) = build_filters(&filters, &columns, &DIALECT, |i, column| {
+            format!("${}{}", i, column.map_or("", |_| "::int"))
+        })
+        .unwrap();
+        assert_eq!(
+            conditions,
+            vec![
+                r#""id" >= $1::int"#,
+                r#""full name" IS NOT NULL"#,
+                r#""full name"::text ILIKE $2"#,
+                r#"("data" #>> ARRAY[$3, $4]) = $5"#,
+                r#"("data" #>> ARRAY[$6]) IS NULL"#,
+                r#"num(("data" #>> ARRAY[$7])) > num($8)"#,
+            ]
+        );
+        assert_eq!(
+            values,
+            vec![
+                json!(10),
+                json!("%smith%"),
+                json!("meta"),
+                json!("status"),
+                json!("active"),
+                json!("deleted_at"),
+                json!("count"),
+                json!(9),
+            ]
+        );
+
+        let unknown = vec![ColumnFilter {
+            column: "id\" OR 1=1 --".to_string(),
+            op: FilterOp::Eq,
+            value: json!(1),
+            json_path: vec![],
+        }];
+        assert!(build_filters(&unknown, &columns, &DIALECT, |_, _| "?".to_string()).is_err());
+        let missing_value = vec![ColumnFilter {
+            column: "id".to_string(),
+            op: FilterOp::Eq,
+            value: JsonValue::Null,
+            json_path: vec![],
+        }];
+        assert!(build_filters(&missing_value, &columns, &DIALECT, |_, _| "?".to_string()).is_err());
+
+        let order = vec![
+            SortColumn {
+                column: "full name".to_string(),
+                direction: SortDirection::Desc,
+            },
+            SortColumn {
+                column: "id".to_string(),
+                direction: SortDirection::Asc,
+            },
+        ];
+        assert_eq!(
+            build_order_by(&order, &columns, &DIALECT).unwrap(),
+            vec![r#""full name" DESC"#, r#""id" ASC"#]
+        );
+        let bad_order = vec![SortColumn {
+            column: "nope".to_string(),
+            direction: SortDirection::Asc,
+        }];
+        assert!(build_order_by(&bad_order, &columns, &DIALECT).is_err());
+    }
+
+    #[test]
+    fn test_distinct_values_sql() {
+        let columns = vec!["status".to_string()];
+        assert_eq!(
+            distinct_values_sql("\"public\".\"orders\"", "status", &columns, &DIALECT, "$1")
+                .unwrap(),
+            "SELECT \"status\", COUNT(*) AS value_count FROM \"public\".\"orders\" \
+             GROUP BY \"status\" ORDER BY value_count DESC, \"status\" LIMIT $1"
+        );
+        assert!(distinct_values_sql("t", "status; --", &columns, &DIALECT, "$1").is_err());
+    }
+}
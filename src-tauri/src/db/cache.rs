@@ -0,0 +1,238 @@
+use crate::db::postgres::QueryResult;
+use crate::db::script;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a cached result is served before the query runs again
+const QUERY_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Results kept at most; the oldest one makes way for a new one
+const MAX_CACHE_ENTRIES: usize = 100;
+
+/// Whether a result came from the query cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheStatus {
+    Hit,
+    Miss,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    connection_id: String,
+    /// The SQL as normalized by normalize_sql
+    sql: String,
+    /// A result capped at one row count is no answer for another
+    max_rows: Option<u64>,
+    /// Whether NULL cells are record::null_cell's sentinel
+    distinguish_nulls: bool,
+}
+
+impl CacheKey {
+    fn new(connection_id: &str, sql: &str, max_rows: Option<u64>, distinguish_nulls: bool) -> Self {
+        Self {
+            connection_id: connection_id.to_string(),
+            sql: normalize_sql(sql),
+            max_rows,
+            distinguish_nulls,
+        }
+    }
+}
+
+struct CacheEntry {
+    result: QueryResult,
+    stored_at: Instant,
+    /// The connection's DataVersion when the query started
+    data_version: u64,
+}
+
+/// Counts changes to the data a connection sees, so results cached before one are known
+/// to be stale. Each manager keeps one and bumps it for writes, commits, and switching
+/// to another database or search_path
+#[derive(Debug, Default)]
+pub struct DataVersion(AtomicU64);
+
+impl DataVersion {
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    pub fn bump(&self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Bumps the version now and again when the returned guard is dropped, so that
+    /// results read while the change was underway are stale too
+    pub fn begin(&self) -> DataChange<'_> {
+        self.bump();
+        DataChange(self)
+    }
+
+    /// Like begin, unless `sql` only reads
+    pub fn change(&self, sql: &str) -> Option<DataChange<'_>> {
+        (!script::is_read_only_query(sql)).then(|| self.begin())
+    }
+}
+
+/// A change in progress; see DataVersion::begin
+pub struct DataChange<'a>(&'a DataVersion);
+
+impl Drop for DataChange<'_> {
+    fn drop(&mut self) {
+        self.0.bump();
+    }
+}
+
+/// Results of read-only queries, per connection, for callers that opt in
+#[derive(Default)]
+pub struct QueryCache {
+    entries: Mutex<HashMap<CacheKey, CacheEntry>>,
+}
+
+impl QueryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets a stored result that hasn't expired and was read at `data_version`, the
+    /// connection's current DataVersion
+    pub fn get(
+        &self,
+        connection_id: &str,
+        sql: &str,
+        max_rows: Option<u64>,
+        distinguish_nulls: bool,
+        data_version: u64,
+    ) -> Option<QueryResult> {
+        let mut entries = self.entries.lock().ok()?;
+        let key = CacheKey::new(connection_id, sql, max_rows, distinguish_nulls);
+
+        let entry = entries.get(&key)?;
+        if entry.stored_at.elapsed() < QUERY_CACHE_TTL && entry.data_version == data_version {
+            return Some(entry.result.clone());
+        }
+        entries.remove(&key);
+        None
+    }
+
+    /// Stores a result, evicting expired entries and, when still full, the oldest one.
+    /// `data_version` is the connection's DataVersion from before the query ran
+    pub fn insert(
+        &self,
+        connection_id: &str,
+        sql: &str,
+        max_rows: Option<u64>,
+        distinguish_nulls: bool,
+        data_version: u64,
+        result: QueryResult,
+    ) {
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+
+        entries.retain(|_, entry| entry.stored_at.elapsed() < QUERY_CACHE_TTL);
+        if entries.len() >= MAX_CACHE_ENTRIES {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.stored_at)
+                .map(|(key, _)| key.clone());
+            if let Some(key) = oldest {
+                entries.remove(&key);
+            }
+        }
+
+        entries.insert(
+            CacheKey::new(connection_id, sql, max_rows, distinguish_nulls),
+            CacheEntry {
+                result,
+                stored_at: Instant::now(),
+                data_version,
+            },
+        );
+    }
+
+    /// Drops the results of one connection, or of all of them. Returns how many were dropped
+    pub fn clear(&self, connection_id: Option<&str>) -> usize {
+        let Ok(mut entries) = self.entries.lock() else {
+            return 0;
+        };
+
+        let before = entries.len();
+        match connection_id {
+            Some(connection_id) => entries.retain(|key, _| key.connection_id != connection_id),
+            None => entries.clear(),
+        }
+        before - entries.len()
+    }
+}
+
+/// Reduces SQL to its cache key, so that surrounding whitespace and a trailing semicolon
+/// don't make a query look new
+fn normalize_sql(sql: &str) -> String {
+    sql.trim().trim_end_matches(';').trim_end().to_string()
+}
+
+/// Thread-safe wrapper for use with Tauri state
+pub type QueryCacheState = Arc<QueryCache>;
+
+pub fn create_query_cache_state() -> QueryCacheState {
+    Arc::new(QueryCache::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(row_count: usize) -> QueryResult {
+        QueryResult {
+            row_count,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_query_cache() {
+        let cache = QueryCache::new();
+        cache.insert("a", "SELECT 1", None, false, 0, result(1));
+
+        assert_eq!(
+            cache
+                .get("a", "  SELECT 1;\n", None, false, 0)
+                .map(|r| r.row_count),
+            Some(1)
+        );
+        assert!(cache.get("b", "SELECT 1", None, false, 0).is_none());
+        assert!(cache.get("a", "SELECT 1", Some(10), false, 0).is_none());
+        assert!(cache.get("a", "SELECT 1", None, true, 0).is_none());
+        assert!(cache.get("a", "select 1", None, false, 0).is_none());
+
+        cache.insert("b", "SELECT 1", None, false, 0, result(2));
+        assert_eq!(cache.clear(Some("a")), 1);
+        assert!(cache.get("a", "SELECT 1", None, false, 0).is_none());
+        assert_eq!(cache.clear(None), 1);
+    }
+
+    #[test]
+    fn test_data_version() {
+        let cache = QueryCache::new();
+        let version = DataVersion::default();
+        cache.insert("a", "SELECT 1", None, false, version.get(), result(1));
+
+        assert!(version.change("SELECT 2").is_none());
+        assert!(cache
+            .get("a", "SELECT 1", None, false, version.get())
+            .is_some());
+
+        let change = version.change("DELETE FROM t");
+        // A result read while the change runs is stale once it's done
+        let during = version.get();
+        drop(change);
+        assert_ne!(version.get(), during);
+        assert!(cache
+            .get("a", "SELECT 1", None, false, version.get())
+            .is_none());
+    }
+}
@@ -0,0 +1,208 @@
+use crate::db::postgres::{query_error, PostgresError};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+/// Every identifier the editor can complete: schemas with their relations and columns,
+/// plus the functions callable from SQL
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SchemaCatalog {
+    pub schemas: Vec<CatalogSchema>,
+    pub functions: Vec<CatalogFunction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogSchema {
+    pub name: String,
+    pub tables: Vec<CatalogTable>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogTable {
+    pub name: String,
+    /// Same labels as TableInfo, plus "MATERIALIZED VIEW"
+    pub table_type: String,
+    pub columns: Vec<CatalogColumn>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogColumn {
+    pub name: String,
+    pub data_type: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogFunction {
+    pub schema: String,
+    pub name: String,
+    pub arguments: String,
+    /// None for procedures
+    pub return_type: Option<String>,
+    /// function, procedure, aggregate or window
+    pub kind: String,
+}
+
+/// One column of a relation, or a relation without columns (`column` is None)
+type RelationRow = (String, String, String, Option<String>, Option<String>);
+
+/// Reads the catalog in three queries. System schemas are left out except for the
+/// functions in pg_catalog, which is where the built-ins live
+pub async fn fetch_catalog(pool: &PgPool) -> Result<SchemaCatalog, PostgresError> {
+    let schemas: Vec<(String,)> = sqlx::query_as(
+        r#"
+        SELECT nspname
+        FROM pg_namespace
+        WHERE nspname NOT IN ('pg_catalog', 'information_schema')
+          AND nspname NOT LIKE 'pg\_toast%'
+          AND nspname NOT LIKE 'pg\_temp\_%'
+        ORDER BY nspname
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(query_error)?;
+
+    let relations: Vec<RelationRow> = sqlx::query_as(
+        r#"
+        SELECT n.nspname, c.relname, c.relkind::text, a.attname::text,
+               format_type(a.atttypid, a.atttypmod)
+        FROM pg_class c
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        LEFT JOIN pg_attribute a
+            ON a.attrelid = c.oid AND a.attnum > 0 AND NOT a.attisdropped
+        WHERE c.relkind IN ('r', 'p', 'v', 'm', 'f')
+          AND n.nspname NOT IN ('pg_catalog', 'information_schema')
+          AND n.nspname NOT LIKE 'pg\_toast%'
+          AND n.nspname NOT LIKE 'pg\_temp\_%'
+        ORDER BY n.nspname, c.relname, a.attnum
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(query_error)?;
+
+    let functions = sqlx::query_as::<_, (String, String, String, Option<String>, String)>(
+        r#"
+        SELECT n.nspname, p.proname, pg_get_function_identity_arguments(p.oid),
+               pg_get_function_result(p.oid), p.prokind::text
+        FROM pg_proc p
+        JOIN pg_namespace n ON n.oid = p.pronamespace
+        WHERE n.nspname <> 'information_schema'
+          AND n.nspname NOT LIKE 'pg\_toast%'
+          AND n.nspname NOT LIKE 'pg\_temp\_%'
+        ORDER BY n.nspname, p.proname
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(query_error)?
+    .into_iter()
+    .map(
+        |(schema, name, arguments, return_type, kind)| CatalogFunction {
+            schema,
+            name,
+            arguments,
+            return_type,
+            kind: function_kind(&kind).to_string(),
+        },
+    )
+    .collect();
+
+    Ok(SchemaCatalog {
+        schemas: group_relations(schemas.into_iter().map(|(name,)| name), relations),
+        functions,
+    })
+}
+
+/// Nests relation rows, ordered by schema, relation and column position, under their
+/// schemas. Schemas without relations are kept so they can still be completed
+fn group_relations(
+    schemas: impl IntoIterator<Item = String>,
+    relations: Vec<RelationRow>,
+) -> Vec<CatalogSchema> {
+    let mut schemas: Vec<CatalogSchema> = schemas
+        .into_iter()
+        .map(|name| CatalogSchema {
+            name,
+            tables: Vec::new(),
+        })
+        .collect();
+
+    for (schema, table, relkind, column, data_type) in relations {
+        let Some(entry) = schemas.iter_mut().find(|s| s.name == schema) else {
+            continue;
+        };
+
+        if entry.tables.last().map(|t| &t.name) != Some(&table) {
+            entry.tables.push(CatalogTable {
+                name: table,
+                table_type: relation_type(&relkind).to_string(),
+                columns: Vec::new(),
+            });
+        }
+        if let (Some(table), Some(name), Some(data_type)) =
+            (entry.tables.last_mut(), column, data_type)
+        {
+            table.columns.push(CatalogColumn { name, data_type });
+        }
+    }
+
+    schemas
+}
+
+/// Maps `pg_class.relkind` to the labels information_schema uses
+fn relation_type(relkind: &str) -> &'static str {
+    match relkind {
+        "v" => "VIEW",
+        "m" => "MATERIALIZED VIEW",
+        "f" => "FOREIGN",
+        _ => "BASE TABLE",
+    }
+}
+
+fn function_kind(prokind: &str) -> &'static str {
+    match prokind {
+        "p" => "procedure",
+        "a" => "aggregate",
+        "w" => "window",
+        _ => "function",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_relations() {
+        let row = |schema: &str, table: &str, kind: &str, column: Option<&str>| {
+            (
+                schema.to_string(),
+                table.to_string(),
+                kind.to_string(),
+                column.map(str::to_string),
+                column.map(|_| "integer".to_string()),
+            )
+        };
+
+        let schemas = group_relations(
+            ["empty".to_string(), "public".to_string()],
+            vec![
+                row("public", "orders", "r", Some("id")),
+                row("public", "orders", "r", Some("user_id")),
+                row("public", "stats", "m", Some("total")),
+                row("public", "nothing", "v", None),
+            ],
+        );
+
+        assert_eq!(schemas.len(), 2);
+        assert!(schemas[0].tables.is_empty());
+
+        let tables = &schemas[1].tables;
+        assert_eq!(tables.len(), 3);
+        assert_eq!(tables[0].columns.len(), 2);
+        assert_eq!(tables[0].columns[1].name, "user_id");
+        assert_eq!(tables[1].table_type, "MATERIALIZED VIEW");
+        assert_eq!(tables[2].table_type, "VIEW");
+        assert!(tables[2].columns.is_empty());
+    }
+}
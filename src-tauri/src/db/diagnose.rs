@@ -0,0 +1,284 @@
+use crate::db::manager::{DatabaseError, Driver};
+use crate::db::metadata::SavedConnection;
+use crate::db::postgres::{self, SslMode};
+use crate::db::{mysql, sqlite};
+use serde::{Deserialize, Serialize};
+use sqlx::mysql::{MySqlConnection, MySqlDatabaseError};
+use sqlx::postgres::PgConnection;
+use sqlx::sqlite::SqliteConnection;
+use sqlx::Connection;
+use std::io::ErrorKind;
+use std::time::{Duration, Instant};
+
+/// Why a connection attempt failed, as far as the error tells
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionErrorKind {
+    /// The host name doesn't resolve
+    Dns,
+    /// Nothing accepted the connection: wrong host or port, server down, or no socket file
+    Refused,
+    /// No answer within the connect timeout, often a firewall dropping packets
+    Timeout,
+    /// The TLS handshake failed or the server certificate was rejected
+    Tls,
+    /// The server rejected the user name or password
+    Authentication,
+    /// The server has no database of that name, or the SQLite file doesn't exist
+    DatabaseNotFound,
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionTestResult {
+    /// The server answered, even if only to turn the login down
+    pub reachable: bool,
+    pub authenticated: bool,
+    pub server_version: Option<String>,
+    /// Round trip of a query once connected
+    pub latency_ms: Option<u64>,
+    /// None when the connection came up
+    pub error_kind: Option<ConnectionErrorKind>,
+    pub message: Option<String>,
+}
+
+impl ConnectionTestResult {
+    fn failed(kind: ConnectionErrorKind, message: String) -> Self {
+        Self {
+            reachable: matches!(
+                kind,
+                ConnectionErrorKind::Tls
+                    | ConnectionErrorKind::Authentication
+                    | ConnectionErrorKind::DatabaseNotFound
+            ),
+            // Servers only look the database up once the login has been accepted
+            authenticated: kind == ConnectionErrorKind::DatabaseNotFound,
+            server_version: None,
+            latency_ms: None,
+            error_kind: Some(kind),
+            message: Some(message),
+        }
+    }
+}
+
+/// Tries a saved connection on a connection of its own, so the active one is left alone,
+/// and reports how far the attempt got. Only settings that can't be used at all (an
+/// unknown driver or sslmode) are returned as errors
+pub async fn test_connection(
+    saved_conn: &SavedConnection,
+    password: &str,
+) -> Result<ConnectionTestResult, DatabaseError> {
+    let driver = saved_conn.driver.parse::<Driver>()?;
+    let ssl_mode = saved_conn.sslmode.parse::<SslMode>()?;
+    let timeout_secs = saved_conn.pool_settings.connect_timeout_secs;
+
+    let probe = probe(driver, saved_conn, password, ssl_mode);
+    let result = match tokio::time::timeout(Duration::from_secs(timeout_secs), probe).await {
+        Ok(Ok((server_version, latency_ms))) => ConnectionTestResult {
+            reachable: true,
+            authenticated: true,
+            server_version: Some(server_version),
+            latency_ms: Some(latency_ms),
+            error_kind: None,
+            message: None,
+        },
+        Ok(Err(e)) => ConnectionTestResult::failed(classify(&e), e.to_string()),
+        Err(_) => ConnectionTestResult::failed(
+            ConnectionErrorKind::Timeout,
+            format!("No answer within {}s", timeout_secs),
+        ),
+    };
+    Ok(result)
+}
+
+/// Connects, then times a query for the server version and closes the connection
+async fn probe(
+    driver: Driver,
+    saved_conn: &SavedConnection,
+    password: &str,
+    ssl_mode: SslMode,
+) -> Result<(String, u64), sqlx::Error> {
+    let started;
+    let server_version: String;
+    match driver {
+        Driver::Postgres => {
+            let options = postgres::connect_options(
+                &saved_conn.host,
+                saved_conn.port,
+                &saved_conn.database,
+                &saved_conn.user,
+                password,
+                ssl_mode,
+                saved_conn.ca_cert_path.as_deref(),
+                saved_conn.read_only,
+                saved_conn.statement_cache_capacity,
+                &postgres::application_name(
+                    &saved_conn.name,
+                    saved_conn.application_name.as_deref(),
+                ),
+            );
+            let mut conn = PgConnection::connect_with(&options).await?;
+            started = Instant::now();
+            server_version = sqlx::query_scalar("SHOW server_version")
+                .fetch_one(&mut conn)
+                .await?;
+            conn.close().await.ok();
+        }
+        Driver::Mysql => {
+            let options = mysql::connect_options(
+                &saved_conn.host,
+                saved_conn.port,
+                &saved_conn.database,
+                &saved_conn.user,
+                password,
+                ssl_mode,
+                saved_conn.ca_cert_path.as_deref(),
+            );
+            let mut conn = MySqlConnection::connect_with(&options).await?;
+            started = Instant::now();
+            server_version = sqlx::query_scalar("SELECT VERSION()")
+                .fetch_one(&mut conn)
+                .await?;
+            conn.close().await.ok();
+        }
+        Driver::Sqlite => {
+            let options = sqlite::connect_options(&saved_conn.database, saved_conn.read_only);
+            let mut conn = SqliteConnection::connect_with(&options).await?;
+            started = Instant::now();
+            server_version = sqlx::query_scalar("SELECT sqlite_version()")
+                .fetch_one(&mut conn)
+                .await?;
+            conn.close().await.ok();
+        }
+    }
+    Ok((server_version, started.elapsed().as_millis() as u64))
+}
+
+/// Works out from a connect error which step failed
+fn classify(error: &sqlx::Error) -> ConnectionErrorKind {
+    match error {
+        sqlx::Error::Io(e) => match e.kind() {
+            ErrorKind::ConnectionRefused | ErrorKind::NotFound => ConnectionErrorKind::Refused,
+            ErrorKind::TimedOut => ConnectionErrorKind::Timeout,
+            // Resolver failures have no ErrorKind of their own
+            _ if is_dns_failure(&e.to_string()) => ConnectionErrorKind::Dns,
+            _ => ConnectionErrorKind::Other,
+        },
+        sqlx::Error::Tls(_) => ConnectionErrorKind::Tls,
+        sqlx::Error::PoolTimedOut => ConnectionErrorKind::Timeout,
+        sqlx::Error::Database(e) => {
+            // MySQL reports an unknown database under the catch-all SQLSTATE 42000
+            if let Some(e) = e.try_downcast_ref::<MySqlDatabaseError>() {
+                if e.number() == 1049 {
+                    return ConnectionErrorKind::DatabaseNotFound;
+                }
+            }
+            match e.code().as_deref() {
+                // invalid_password, invalid_authorization_specification
+                Some("28P01" | "28000") => ConnectionErrorKind::Authentication,
+                // invalid_catalog_name; SQLITE_CANTOPEN
+                Some("3D000" | "14") => ConnectionErrorKind::DatabaseNotFound,
+                _ => ConnectionErrorKind::Other,
+            }
+        }
+        _ => ConnectionErrorKind::Other,
+    }
+}
+
+fn is_dns_failure(message: &str) -> bool {
+    ["failed to lookup address", "No such host is known"]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::postgres::PgConnectOptions;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_classify() {
+        let io = |kind, message: &str| sqlx::Error::Io(std::io::Error::new(kind, message));
+
+        assert_eq!(
+            classify(&io(ErrorKind::ConnectionRefused, "refused")),
+            ConnectionErrorKind::Refused
+        );
+        assert_eq!(
+            classify(&io(ErrorKind::TimedOut, "timed out")),
+            ConnectionErrorKind::Timeout
+        );
+        assert_eq!(
+            classify(&io(
+                ErrorKind::Other,
+                "failed to lookup address information: Name or service not known"
+            )),
+            ConnectionErrorKind::Dns
+        );
+        assert_eq!(
+            classify(&io(ErrorKind::Other, "broken")),
+            ConnectionErrorKind::Other
+        );
+        assert_eq!(
+            classify(&sqlx::Error::Tls("bad certificate".into())),
+            ConnectionErrorKind::Tls
+        );
+        assert_eq!(
+            classify(&sqlx::Error::PoolTimedOut),
+            ConnectionErrorKind::Timeout
+        );
+    }
+
+    #[tokio::test]
+    async fn test_test_connection() {
+        let missing_file = SavedConnection {
+            driver: "sqlite".to_string(),
+            database: "/nonexistent/datatool/test.db".to_string(),
+            ..Default::default()
+        };
+        let result = test_connection(&missing_file, "").await.unwrap();
+        assert_eq!(
+            result.error_kind,
+            Some(ConnectionErrorKind::DatabaseNotFound)
+        );
+
+        let refused = SavedConnection {
+            host: "127.0.0.1".to_string(),
+            port: 1,
+            sslmode: "disable".to_string(),
+            ..Default::default()
+        };
+        let result = test_connection(&refused, "").await.unwrap();
+        assert_eq!(result.error_kind, Some(ConnectionErrorKind::Refused));
+
+        // The rest needs a server, see postgres::tests
+        let Ok(url) = std::env::var("DATATOOL_TEST_PG_URL") else {
+            return;
+        };
+        let options = PgConnectOptions::from_str(&url).unwrap();
+        let server = SavedConnection {
+            host: options.get_host().to_string(),
+            port: options.get_port(),
+            user: options.get_username().to_string(),
+            database: options.get_database().unwrap_or("postgres").to_string(),
+            sslmode: "disable".to_string(),
+            ..Default::default()
+        };
+        let result = test_connection(&server, "").await.unwrap();
+        assert_eq!(result.error_kind, None);
+        assert!(result.authenticated);
+        assert!(result.server_version.is_some());
+
+        let no_database = SavedConnection {
+            database: "datatool_no_such_database".to_string(),
+            ..server
+        };
+        let result = test_connection(&no_database, "").await.unwrap();
+        assert_eq!(
+            result.error_kind,
+            Some(ConnectionErrorKind::DatabaseNotFound)
+        );
+        assert!(result.reachable && result.authenticated);
+    }
+}
@@ -0,0 +1,461 @@
+use crate::db::browse::{ColumnFilter, DistinctValue, SortColumn};
+use crate::db::metadata::SavedConnection;
+use crate::db::mysql::{MySqlError, MySqlState};
+use crate::db::postgres::{
+    self, ColumnInfo, PaginatedResult, PoolSettings, PostgresError, PostgresState, QueryResult,
+    SslMode, TableFilter, TableInfo, TablePreview,
+};
+use crate::db::script;
+use crate::db::sqlite::{SqliteError, SqliteState};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+#[derive(Error, Debug)]
+pub enum DatabaseError {
+    #[error(transparent)]
+    Postgres(#[from] PostgresError),
+    #[error(transparent)]
+    MySql(#[from] MySqlError),
+    #[error(transparent)]
+    Sqlite(#[from] SqliteError),
+    #[error("Unknown driver: {0}")]
+    UnknownDriver(String),
+}
+
+/// Database engine a saved connection talks to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Driver {
+    #[default]
+    Postgres,
+    Mysql,
+    /// A local database file; `database` holds its path and host/port/user are unused
+    Sqlite,
+}
+
+impl Driver {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Driver::Postgres => "postgres",
+            Driver::Mysql => "mysql",
+            Driver::Sqlite => "sqlite",
+        }
+    }
+}
+
+impl std::str::FromStr for Driver {
+    type Err = DatabaseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "postgres" => Ok(Driver::Postgres),
+            "mysql" => Ok(Driver::Mysql),
+            "sqlite" => Ok(Driver::Sqlite),
+            other => Err(DatabaseError::UnknownDriver(other.to_string())),
+        }
+    }
+}
+
+/// The manager behind a connection, so that the commands shared by every driver can
+/// dispatch without knowing which one is active. Postgres-only features keep using
+/// `PostgresState` directly
+#[derive(Clone)]
+pub enum DatabaseManager {
+    Postgres(PostgresState),
+    MySql(MySqlState),
+    Sqlite(SqliteState),
+}
+
+impl DatabaseManager {
+    pub fn driver(&self) -> Driver {
+        match self {
+            DatabaseManager::Postgres(_) => Driver::Postgres,
+            DatabaseManager::MySql(_) => Driver::Mysql,
+            DatabaseManager::Sqlite(_) => Driver::Sqlite,
+        }
+    }
+
+    /// Connects to a saved connection using the already decrypted password. For SQLite
+    /// the `database` field is the path of the file to open
+    pub async fn connect(
+        &self,
+        saved_conn: &SavedConnection,
+        password: &str,
+    ) -> Result<(), DatabaseError> {
+        match self {
+            DatabaseManager::Postgres(postgres) => Ok(postgres
+                .connect(
+                    &saved_conn.id,
+                    &saved_conn.host,
+                    saved_conn.port,
+                    &saved_conn.database,
+                    &saved_conn.user,
+                    password,
+                    saved_conn.sslmode.parse::<SslMode>()?,
+                    saved_conn.ca_cert_path.as_deref(),
+                    saved_conn.pool_settings,
+                    saved_conn.read_only,
+                    saved_conn.statement_cache_capacity,
+                    &postgres::application_name(
+                        &saved_conn.name,
+                        saved_conn.application_name.as_deref(),
+                    ),
+                    saved_conn.default_search_path.as_deref(),
+                )
+                .await?),
+            DatabaseManager::MySql(mysql) => Ok(mysql
+                .connect(
+                    &saved_conn.id,
+                    &saved_conn.host,
+                    saved_conn.port,
+                    &saved_conn.database,
+                    &saved_conn.user,
+                    password,
+                    saved_conn.sslmode.parse::<SslMode>()?,
+                    saved_conn.ca_cert_path.as_deref(),
+                    saved_conn.read_only,
+                )
+                .await?),
+            DatabaseManager::Sqlite(sqlite) => Ok(sqlite
+                .connect(&saved_conn.id, &saved_conn.database, saved_conn.read_only)
+                .await?),
+        }
+    }
+
+    pub async fn disconnect(&self) {
+        match self {
+            DatabaseManager::Postgres(postgres) => postgres.disconnect().await,
+            DatabaseManager::MySql(mysql) => mysql.disconnect().await,
+            DatabaseManager::Sqlite(sqlite) => sqlite.disconnect().await,
+        }
+    }
+
+    /// Whether an explicit transaction is open; only Postgres supports them
+    pub async fn in_transaction(&self) -> bool {
+        match self {
+            DatabaseManager::Postgres(postgres) => postgres.transaction_status().await.active,
+            DatabaseManager::MySql(_) | DatabaseManager::Sqlite(_) => false,
+        }
+    }
+
+    pub async fn get_connection_id(&self) -> Option<String> {
+        match self {
+            DatabaseManager::Postgres(postgres) => postgres.get_connection_id().await,
+            DatabaseManager::MySql(mysql) => mysql.get_connection_id().await,
+            DatabaseManager::Sqlite(sqlite) => sqlite.get_connection_id().await,
+        }
+    }
+
+    /// Changes whenever the data the connection sees may have; cached results are only
+    /// served while it stays the same
+    pub fn data_version(&self) -> u64 {
+        match self {
+            DatabaseManager::Postgres(postgres) => postgres.data_version(),
+            DatabaseManager::MySql(mysql) => mysql.data_version(),
+            DatabaseManager::Sqlite(sqlite) => sqlite.data_version(),
+        }
+    }
+
+    /// Executes a raw SQL query. The query id (for cancel_query) and the timeout are only
+    /// supported on Postgres. A single SELECT without a LIMIT of its own returns at most
+    /// `max_rows` rows; one extra row is fetched to tell whether the result was cut short.
+    /// With `distinguish_nulls`, NULL cells come back as record::null_cell's sentinel
+    pub async fn execute_query(
+        &self,
+        sql: &str,
+        query_id: Option<&str>,
+        timeout_ms: Option<u64>,
+        max_rows: Option<u64>,
+        distinguish_nulls: bool,
+    ) -> Result<QueryResult, DatabaseError> {
+        let limited = max_rows.and_then(|max_rows| script::limit_select(sql, max_rows + 1));
+        let run_sql = limited.as_deref().unwrap_or(sql);
+
+        let mut result = match self {
+            DatabaseManager::Postgres(postgres) => postgres
+                .execute_query_tracked(run_sql, query_id, timeout_ms, distinguish_nulls)
+                .await
+                .map_err(|e| match limited {
+                    Some(_) => e.map_position(|p| script::unlimited_position(sql, p)),
+                    None => e,
+                })?,
+            DatabaseManager::MySql(mysql) => match (&limited, max_rows) {
+                (Some(limited), Some(max_rows)) => {
+                    mysql
+                        .execute_limited_query(sql, limited, max_rows + 1, distinguish_nulls)
+                        .await?
+                }
+                _ => mysql.execute_query(sql, distinguish_nulls).await?,
+            },
+            DatabaseManager::Sqlite(sqlite) => {
+                sqlite.execute_query(run_sql, distinguish_nulls).await?
+            }
+        };
+
+        if let Some(max_rows) = max_rows.filter(|_| limited.is_some()) {
+            if result.rows.len() as u64 > max_rows {
+                result.rows.truncate(max_rows as usize);
+                result.row_count = result.rows.len();
+                result.truncated = true;
+            }
+        }
+        Ok(result)
+    }
+
+    /// Executes one or more statements, returning a result per result set with at most
+    /// `max_rows` rows each. The query id and the timeout are only supported on Postgres.
+    /// `distinguish_nulls` works as in execute_query
+    pub async fn execute_query_multi(
+        &self,
+        sql: &str,
+        query_id: Option<&str>,
+        timeout_ms: Option<u64>,
+        max_rows: Option<u64>,
+        distinguish_nulls: bool,
+    ) -> Result<Vec<QueryResult>, DatabaseError> {
+        match self {
+            DatabaseManager::Postgres(postgres) => Ok(postgres
+                .execute_query_multi(sql, query_id, timeout_ms, max_rows, distinguish_nulls)
+                .await?),
+            DatabaseManager::MySql(mysql) => Ok(mysql
+                .execute_query_multi(sql, max_rows, distinguish_nulls)
+                .await?),
+            DatabaseManager::Sqlite(sqlite) => Ok(sqlite
+                .execute_query_multi(sql, max_rows, distinguish_nulls)
+                .await?),
+        }
+    }
+
+    pub async fn fetch_tables(
+        &self,
+        filter: &TableFilter,
+    ) -> Result<Vec<TableInfo>, DatabaseError> {
+        match self {
+            DatabaseManager::Postgres(postgres) => Ok(postgres.fetch_tables(filter).await?),
+            DatabaseManager::MySql(mysql) => Ok(mysql.fetch_tables(filter).await?),
+            DatabaseManager::Sqlite(sqlite) => Ok(sqlite.fetch_tables(filter).await?),
+        }
+    }
+
+    pub async fn fetch_columns(
+        &self,
+        schema: &str,
+        table: &str,
+    ) -> Result<Vec<ColumnInfo>, DatabaseError> {
+        match self {
+            DatabaseManager::Postgres(postgres) => {
+                Ok(postgres.fetch_columns(schema, table).await?)
+            }
+            DatabaseManager::MySql(mysql) => Ok(mysql.fetch_columns(schema, table).await?),
+            DatabaseManager::Sqlite(sqlite) => Ok(sqlite.fetch_columns(schema, table).await?),
+        }
+    }
+
+    /// Fetches a page of table data. Keyset paging with `after` is only supported on
+    /// Postgres; the other drivers always page by `page`. `distinguish_nulls` works as in
+    /// execute_query
+    #[allow(clippy::too_many_arguments)]
+    pub async fn fetch_table_data(
+        &self,
+        schema: &str,
+        table: &str,
+        page: i32,
+        page_size: i32,
+        after: Option<&[JsonValue]>,
+        with_count: bool,
+        order_by: &[SortColumn],
+        filters: &[ColumnFilter],
+        distinguish_nulls: bool,
+    ) -> Result<PaginatedResult, DatabaseError> {
+        match self {
+            DatabaseManager::Postgres(postgres) => Ok(postgres
+                .fetch_table_data(
+                    schema,
+                    table,
+                    page,
+                    page_size,
+                    after,
+                    with_count,
+                    order_by,
+                    filters,
+                    distinguish_nulls,
+                )
+                .await?),
+            DatabaseManager::MySql(mysql) => Ok(mysql
+                .fetch_table_data(
+                    schema,
+                    table,
+                    page,
+                    page_size,
+                    with_count,
+                    order_by,
+                    filters,
+                    distinguish_nulls,
+                )
+                .await?),
+            DatabaseManager::Sqlite(sqlite) => Ok(sqlite
+                .fetch_table_data(
+                    schema,
+                    table,
+                    page,
+                    page_size,
+                    with_count,
+                    order_by,
+                    filters,
+                    distinguish_nulls,
+                )
+                .await?),
+        }
+    }
+
+    /// Fetches up to `limit` rows of a table without counting it. Only Postgres samples
+    /// large tables; the other drivers return the first page
+    pub async fn preview_table(
+        &self,
+        schema: &str,
+        table: &str,
+        limit: i64,
+    ) -> Result<TablePreview, DatabaseError> {
+        let page = match self {
+            DatabaseManager::Postgres(postgres) => {
+                return Ok(postgres.preview_table(schema, table, limit).await?)
+            }
+            DatabaseManager::MySql(mysql) => {
+                mysql
+                    .fetch_table_data(schema, table, 1, limit as i32, false, &[], &[], false)
+                    .await?
+            }
+            DatabaseManager::Sqlite(sqlite) => {
+                sqlite
+                    .fetch_table_data(schema, table, 1, limit as i32, false, &[], &[], false)
+                    .await?
+            }
+        };
+        Ok(TablePreview {
+            columns: page.columns,
+            rows: page.rows,
+            sampled: false,
+            estimated_rows: None,
+        })
+    }
+
+    /// Fetches up to `limit` distinct values of a column, most frequent first
+    pub async fn fetch_distinct_values(
+        &self,
+        schema: &str,
+        table: &str,
+        column: &str,
+        limit: i64,
+    ) -> Result<Vec<DistinctValue>, DatabaseError> {
+        match self {
+            DatabaseManager::Postgres(postgres) => Ok(postgres
+                .fetch_distinct_values(schema, table, column, limit)
+                .await?),
+            DatabaseManager::MySql(mysql) => Ok(mysql
+                .fetch_distinct_values(schema, table, column, limit)
+                .await?),
+            DatabaseManager::Sqlite(sqlite) => Ok(sqlite
+                .fetch_distinct_values(schema, table, column, limit)
+                .await?),
+        }
+    }
+}
+
+/// Tracks which manager serves the active connection
+pub struct ActiveDatabase {
+    postgres: PostgresState,
+    mysql: MySqlState,
+    sqlite: SqliteState,
+    active: RwLock<DatabaseManager>,
+}
+
+impl ActiveDatabase {
+    pub fn new(postgres: PostgresState, mysql: MySqlState, sqlite: SqliteState) -> Self {
+        Self {
+            active: RwLock::new(DatabaseManager::Postgres(postgres.clone())),
+            postgres,
+            mysql,
+            sqlite,
+        }
+    }
+
+    /// Returns the manager for a driver, whether or not it is the active one
+    pub fn manager_for(&self, driver: Driver) -> DatabaseManager {
+        match driver {
+            Driver::Postgres => DatabaseManager::Postgres(self.postgres.clone()),
+            Driver::Mysql => DatabaseManager::MySql(self.mysql.clone()),
+            Driver::Sqlite => DatabaseManager::Sqlite(self.sqlite.clone()),
+        }
+    }
+
+    /// Returns the manager serving the active connection (Postgres when nothing is connected)
+    pub async fn current(&self) -> DatabaseManager {
+        self.active.read().await.clone()
+    }
+
+    /// Connects a saved connection with the manager for its driver, closing the previous
+    /// connection if it was served by a different manager
+    pub async fn connect(
+        &self,
+        saved_conn: &SavedConnection,
+        password: &str,
+    ) -> Result<(), DatabaseError> {
+        let manager = self.manager_for(saved_conn.driver.parse()?);
+
+        let mut active = self.active.write().await;
+        if active.driver() != manager.driver() {
+            active.disconnect().await;
+        }
+        manager.connect(saved_conn, password).await?;
+        *active = manager;
+
+        Ok(())
+    }
+
+    /// Connects Postgres with a full connection string (see PostgresManager::connect_from_url)
+    pub async fn connect_postgres_url(
+        &self,
+        connection_id: &str,
+        url: &str,
+        pool_settings: PoolSettings,
+        application_name: &str,
+    ) -> Result<(), DatabaseError> {
+        let mut active = self.active.write().await;
+        if active.driver() != Driver::Postgres {
+            active.disconnect().await;
+        }
+        self.postgres
+            .connect_from_url(connection_id, url, pool_settings, application_name)
+            .await?;
+        *active = DatabaseManager::Postgres(self.postgres.clone());
+
+        Ok(())
+    }
+}
+
+/// Thread-safe wrapper for use with Tauri state
+pub type DatabaseState = Arc<ActiveDatabase>;
+
+pub fn create_database_state(
+    postgres: PostgresState,
+    mysql: MySqlState,
+    sqlite: SqliteState,
+) -> DatabaseState {
+    Arc::new(ActiveDatabase::new(postgres, mysql, sqlite))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_driver_round_trip() {
+        for driver in [Driver::Postgres, Driver::Mysql, Driver::Sqlite] {
+            assert_eq!(driver.as_str().parse::<Driver>().unwrap(), driver);
+        }
+        assert!("oracle".parse::<Driver>().is_err());
+    }
+}
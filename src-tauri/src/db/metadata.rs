@@ -1,14 +1,34 @@
+use crate::db::postgres::{
+    PoolSettings, DEFAULT_ACQUIRE_TIMEOUT_SECS, DEFAULT_CONNECT_TIMEOUT_SECS,
+    DEFAULT_IDLE_TIMEOUT_SECS, DEFAULT_MAX_CONNECTIONS, DEFAULT_STATEMENT_CACHE_CAPACITY,
+};
+use crate::db::search::like_pattern;
+use crate::db::template::QueryParameter;
 use directories::ProjectDirs;
 use once_cell::sync::OnceCell;
 use rusqlite::{params, Connection, Result as SqliteResult};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use serde_json::Value as JsonValue;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use thiserror::Error;
 use uuid::Uuid;
 
 static DB_CONNECTION: OnceCell<Mutex<Connection>> = OnceCell::new();
 
+/// Environment variable that overrides where the metadata database is stored
+pub const DATA_DIR_ENV: &str = "DATATOOL_DATA_DIR";
+
+/// Group that connections without an explicit one belong to
+pub const DEFAULT_CONNECTION_GROUP: &str = "Default";
+
+/// app_state key the editor content was kept under before editor tabs existed
+const LEGACY_EDITOR_CONTENT_KEY: &str = "editor_content";
+
+/// Title of tabs created without one
+pub const DEFAULT_EDITOR_TAB_TITLE: &str = "Untitled";
+
 #[derive(Error, Debug)]
 pub enum MetadataError {
     #[error("Database error: {0}")]
@@ -19,6 +39,14 @@ pub enum MetadataError {
     NotInitialized,
     #[error("Connection not found")]
     ConnectionNotFound,
+    #[error("Saved query not found")]
+    SavedQueryNotFound,
+    #[error("Explain plan not found")]
+    ExplainPlanNotFound,
+    #[error("Editor tab not found")]
+    EditorTabNotFound,
+    #[error("Failed to re-encrypt password for connection {id}: {reason}")]
+    ReencryptionFailed { id: String, reason: String },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,6 +59,51 @@ pub struct SavedConnection {
     pub user: String,
     pub encrypted_password: String,
     pub created_at: String,
+    pub sslmode: String,
+    pub ca_cert_path: Option<String>,
+    pub use_keychain: bool,
+    pub driver: String,
+    pub pool_settings: PoolSettings,
+    pub group: String,
+    pub color: Option<String>,
+    pub is_favorite: bool,
+    /// Sessions are opened read-only, so writes and DDL are rejected by the server
+    pub read_only: bool,
+    /// Prepared statements cached per connection (Postgres only); 0 disables reuse
+    pub statement_cache_capacity: usize,
+    /// application_name for the sessions (Postgres only); None for the default, which
+    /// names the connection
+    pub application_name: Option<String>,
+    /// search_path set on every session as SET search_path takes it, e.g. `app, public`
+    /// (Postgres only); None keeps the server's
+    pub default_search_path: Option<String>,
+}
+
+impl Default for SavedConnection {
+    fn default() -> Self {
+        Self {
+            id: String::new(),
+            name: String::new(),
+            host: String::new(),
+            port: 0,
+            database: String::new(),
+            user: String::new(),
+            encrypted_password: String::new(),
+            created_at: String::new(),
+            sslmode: "prefer".to_string(),
+            ca_cert_path: None,
+            use_keychain: false,
+            driver: "postgres".to_string(),
+            pool_settings: PoolSettings::default(),
+            group: DEFAULT_CONNECTION_GROUP.to_string(),
+            color: None,
+            is_favorite: false,
+            read_only: false,
+            statement_cache_capacity: DEFAULT_STATEMENT_CACHE_CAPACITY,
+            application_name: None,
+            default_search_path: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,23 +113,73 @@ pub struct SavedQuery {
     pub name: String,
     pub sql: String,
     pub created_at: String,
+    pub tags: Vec<String>,
+    /// Definitions of the `:name` placeholders in the SQL
+    pub parameters: Vec<QueryParameter>,
 }
 
-/// Gets the path to the SQLite database file
-fn get_db_path() -> Result<PathBuf, MetadataError> {
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryHistoryEntry {
+    pub id: String,
+    pub connection_id: Option<String>,
+    pub sql: String,
+    pub executed_at: String,
+    pub duration_ms: u64,
+    pub row_count: Option<u64>,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// An editor tab. Tabs without a connection are shown when none is selected
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditorTab {
+    pub id: String,
+    pub connection_id: Option<String>,
+    pub title: String,
+    pub content: String,
+    pub position: i64,
+    pub updated_at: String,
+}
+
+/// An EXPLAIN (FORMAT JSON) plan captured for later comparison
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedExplainPlan {
+    pub id: String,
+    pub connection_id: Option<String>,
+    pub sql: String,
+    pub plan: JsonValue,
+    pub captured_at: String,
+    pub label: Option<String>,
+}
+
+/// Resolves the data directory, preferring an explicit override over the OS app-data directory
+fn resolve_data_dir(override_dir: Option<OsString>) -> Result<PathBuf, MetadataError> {
+    if let Some(dir) = override_dir.filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+
     let proj_dirs = ProjectDirs::from("com", "datatool", "DataTool")
         .ok_or(MetadataError::NoAppDataDir)?;
-    
-    let data_dir = proj_dirs.data_dir();
-    std::fs::create_dir_all(data_dir).map_err(|_| MetadataError::NoAppDataDir)?;
-    
+    Ok(proj_dirs.data_dir().to_path_buf())
+}
+
+/// Gets the path to the SQLite database file
+pub fn get_db_path() -> Result<PathBuf, MetadataError> {
+    let data_dir = resolve_data_dir(std::env::var_os(DATA_DIR_ENV))?;
     Ok(data_dir.join("metadata.db"))
 }
 
 /// Initializes the SQLite database and creates tables if they don't exist
 pub fn init_database() -> Result<(), MetadataError> {
-    let db_path = get_db_path()?;
-    let conn = Connection::open(&db_path)?;
+    init_database_at(&get_db_path()?)
+}
+
+/// Initializes the SQLite database at a specific path, creating its directory if needed
+pub fn init_database_at(db_path: &Path) -> Result<(), MetadataError> {
+    if let Some(data_dir) = db_path.parent() {
+        std::fs::create_dir_all(data_dir).map_err(|_| MetadataError::NoAppDataDir)?;
+    }
+    let conn = Connection::open(db_path)?;
     
     // Create connections table
     conn.execute(
@@ -73,6 +196,70 @@ pub fn init_database() -> Result<(), MetadataError> {
         [],
     )?;
     
+    // Columns added after the initial schema
+    add_column_if_missing(
+        &conn,
+        "connections",
+        "sslmode",
+        "TEXT NOT NULL DEFAULT 'prefer'",
+    )?;
+    add_column_if_missing(&conn, "connections", "ca_cert_path", "TEXT")?;
+    add_column_if_missing(
+        &conn,
+        "connections",
+        "use_keychain",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    add_column_if_missing(
+        &conn,
+        "connections",
+        "driver",
+        "TEXT NOT NULL DEFAULT 'postgres'",
+    )?;
+    for (column, default) in [
+        ("max_connections", DEFAULT_MAX_CONNECTIONS as u64),
+        ("acquire_timeout_secs", DEFAULT_ACQUIRE_TIMEOUT_SECS),
+        ("idle_timeout_secs", DEFAULT_IDLE_TIMEOUT_SECS),
+        ("connect_timeout_secs", DEFAULT_CONNECT_TIMEOUT_SECS),
+    ] {
+        add_column_if_missing(
+            &conn,
+            "connections",
+            column,
+            &format!("INTEGER NOT NULL DEFAULT {}", default),
+        )?;
+    }
+    add_column_if_missing(
+        &conn,
+        "connections",
+        "group_name",
+        &format!("TEXT NOT NULL DEFAULT '{}'", DEFAULT_CONNECTION_GROUP),
+    )?;
+    add_column_if_missing(&conn, "connections", "color", "TEXT")?;
+    add_column_if_missing(
+        &conn,
+        "connections",
+        "is_favorite",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    add_column_if_missing(
+        &conn,
+        "connections",
+        "read_only",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    add_column_if_missing(
+        &conn,
+        "connections",
+        "statement_cache_capacity",
+        &format!(
+            "INTEGER NOT NULL DEFAULT {}",
+            DEFAULT_STATEMENT_CACHE_CAPACITY
+        ),
+    )?;
+    add_column_if_missing(&conn, "connections", "application_name", "TEXT")?;
+    add_column_if_missing(&conn, "connections", "default_search_path", "TEXT")?;
+    
     // Create saved_queries table
     conn.execute(
         "CREATE TABLE IF NOT EXISTS saved_queries (
@@ -85,7 +272,51 @@ pub fn init_database() -> Result<(), MetadataError> {
         )",
         [],
     )?;
+    // JSON array of tag names
+    add_column_if_missing(&conn, "saved_queries", "tags", "TEXT NOT NULL DEFAULT '[]'")?;
+    // JSON array of QueryParameter
+    add_column_if_missing(
+        &conn,
+        "saved_queries",
+        "parameters",
+        "TEXT NOT NULL DEFAULT '[]'",
+    )?;
     
+    // Create query_history table
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS query_history (
+            id TEXT PRIMARY KEY,
+            connection_id TEXT,
+            sql TEXT NOT NULL,
+            executed_at TEXT NOT NULL,
+            duration_ms INTEGER NOT NULL,
+            row_count INTEGER,
+            success INTEGER NOT NULL,
+            error TEXT,
+            FOREIGN KEY (connection_id) REFERENCES connections(id) ON DELETE SET NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_query_history_executed_at
+         ON query_history (connection_id, executed_at)",
+        [],
+    )?;
+    
+    // Create explain_plans table
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS explain_plans (
+            id TEXT PRIMARY KEY,
+            connection_id TEXT,
+            sql TEXT NOT NULL,
+            plan_json TEXT NOT NULL,
+            captured_at TEXT NOT NULL,
+            label TEXT,
+            FOREIGN KEY (connection_id) REFERENCES connections(id) ON DELETE SET NULL
+        )",
+        [],
+    )?;
+
     // Create app_state table for storing last active connection, etc.
     conn.execute(
         "CREATE TABLE IF NOT EXISTS app_state (
@@ -94,6 +325,36 @@ pub fn init_database() -> Result<(), MetadataError> {
         )",
         [],
     )?;
+
+    // Create matview_refreshes table: when each materialized view was last refreshed from
+    // the app, since Postgres doesn't record it
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS matview_refreshes (
+            connection_id TEXT NOT NULL,
+            database TEXT NOT NULL,
+            schema TEXT NOT NULL,
+            name TEXT NOT NULL,
+            refreshed_at TEXT NOT NULL,
+            PRIMARY KEY (connection_id, database, schema, name),
+            FOREIGN KEY (connection_id) REFERENCES connections(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Create editor_tabs table
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS editor_tabs (
+            id TEXT PRIMARY KEY,
+            connection_id TEXT,
+            title TEXT NOT NULL,
+            content TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (connection_id) REFERENCES connections(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    migrate_editor_content(&conn)?;
     
     DB_CONNECTION
         .set(Mutex::new(conn))
@@ -102,6 +363,65 @@ pub fn init_database() -> Result<(), MetadataError> {
     Ok(())
 }
 
+/// Moves the single editor blob that predates editor tabs into a tab without a connection
+fn migrate_editor_content(conn: &Connection) -> Result<(), MetadataError> {
+    let content: Option<String> = match conn.query_row(
+        "SELECT value FROM app_state WHERE key = ?1",
+        params![LEGACY_EDITOR_CONTENT_KEY],
+        |row| row.get(0),
+    ) {
+        Ok(content) => Some(content),
+        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(e) => return Err(MetadataError::Database(e)),
+    };
+    let Some(content) = content else {
+        return Ok(());
+    };
+
+    if !content.trim().is_empty() {
+        conn.execute(
+            "INSERT INTO editor_tabs (id, connection_id, title, content, position, updated_at)
+             VALUES (?1, NULL, ?2, ?3, 0, ?4)",
+            params![
+                Uuid::new_v4().to_string(),
+                DEFAULT_EDITOR_TAB_TITLE,
+                content,
+                chrono::Utc::now().to_rfc3339()
+            ],
+        )?;
+    }
+    conn.execute(
+        "DELETE FROM app_state WHERE key = ?1",
+        params![LEGACY_EDITOR_CONTENT_KEY],
+    )?;
+
+    Ok(())
+}
+
+/// Adds a column to an existing table unless it is already there (lightweight migration)
+fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), MetadataError> {
+    let exists = conn
+        .prepare(&format!(
+            "SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1",
+            table
+        ))?
+        .exists(params![column])?;
+    
+    if !exists {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
+    }
+    
+    Ok(())
+}
+
 fn get_connection() -> Result<std::sync::MutexGuard<'static, Connection>, MetadataError> {
     DB_CONNECTION
         .get()
@@ -112,56 +432,118 @@ fn get_connection() -> Result<std::sync::MutexGuard<'static, Connection>, Metada
 
 // ============ Connection CRUD ============
 
-pub fn create_connection(
-    name: &str,
-    host: &str,
-    port: u16,
-    database: &str,
-    user: &str,
-    encrypted_password: &str,
-) -> Result<SavedConnection, MetadataError> {
-    let conn = get_connection()?;
-    let id = Uuid::new_v4().to_string();
-    let created_at = chrono::Utc::now().to_rfc3339();
-    
-    conn.execute(
-        "INSERT INTO connections (id, name, host, port, database, user, encrypted_password, created_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-        params![id, name, host, port, database, user, encrypted_password, created_at],
-    )?;
-    
+const CONNECTION_COLUMNS: &str =
+    "id, name, host, port, database, user, encrypted_password, created_at, sslmode, ca_cert_path, use_keychain, driver, \
+     max_connections, acquire_timeout_secs, idle_timeout_secs, connect_timeout_secs, group_name, \
+     color, is_favorite, read_only, statement_cache_capacity, application_name, default_search_path";
+
+fn connection_from_row(row: &rusqlite::Row) -> SqliteResult<SavedConnection> {
     Ok(SavedConnection {
-        id,
-        name: name.to_string(),
-        host: host.to_string(),
-        port,
-        database: database.to_string(),
-        user: user.to_string(),
-        encrypted_password: encrypted_password.to_string(),
-        created_at,
+        id: row.get(0)?,
+        name: row.get(1)?,
+        host: row.get(2)?,
+        port: row.get(3)?,
+        database: row.get(4)?,
+        user: row.get(5)?,
+        encrypted_password: row.get(6)?,
+        created_at: row.get(7)?,
+        sslmode: row.get(8)?,
+        ca_cert_path: row.get(9)?,
+        use_keychain: row.get(10)?,
+        driver: row.get(11)?,
+        pool_settings: PoolSettings {
+            max_connections: row.get(12)?,
+            acquire_timeout_secs: row.get(13)?,
+            idle_timeout_secs: row.get(14)?,
+            connect_timeout_secs: row.get(15)?,
+        },
+        group: row.get(16)?,
+        color: row.get(17)?,
+        is_favorite: row.get(18)?,
+        read_only: row.get(19)?,
+        statement_cache_capacity: row.get(20)?,
+        application_name: row.get(21)?,
+        default_search_path: row.get(22)?,
     })
 }
 
+/// The values of CONNECTION_COLUMNS, in order
+fn connection_params(connection: &SavedConnection) -> [&dyn rusqlite::ToSql; 23] {
+    [
+        &connection.id,
+        &connection.name,
+        &connection.host,
+        &connection.port,
+        &connection.database,
+        &connection.user,
+        &connection.encrypted_password,
+        &connection.created_at,
+        &connection.sslmode,
+        &connection.ca_cert_path,
+        &connection.use_keychain,
+        &connection.driver,
+        &connection.pool_settings.max_connections,
+        &connection.pool_settings.acquire_timeout_secs,
+        &connection.pool_settings.idle_timeout_secs,
+        &connection.pool_settings.connect_timeout_secs,
+        &connection.group,
+        &connection.color,
+        &connection.is_favorite,
+        &connection.read_only,
+        &connection.statement_cache_capacity,
+        &connection.application_name,
+        &connection.default_search_path,
+    ]
+}
+
+/// Saves a new connection with all its settings in one INSERT; the id and created_at are
+/// assigned here
+pub fn create_connection(connection: &SavedConnection) -> Result<SavedConnection, MetadataError> {
+    let mut saved = create_connections(std::slice::from_ref(connection))?;
+    Ok(saved.remove(0))
+}
+
+/// Saves new connections in one transaction, so either all of them are added or none
+pub fn create_connections(
+    connections: &[SavedConnection],
+) -> Result<Vec<SavedConnection>, MetadataError> {
+    let placeholders = (1..=CONNECTION_COLUMNS.split(',').count())
+        .map(|i| format!("?{}", i))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let sql = format!(
+        "INSERT INTO connections ({}) VALUES ({})",
+        CONNECTION_COLUMNS, placeholders
+    );
+
+    let mut conn = get_connection()?;
+    let tx = conn.transaction()?;
+    let saved = connections
+        .iter()
+        .map(|connection| {
+            let saved = SavedConnection {
+                id: Uuid::new_v4().to_string(),
+                created_at: chrono::Utc::now().to_rfc3339(),
+                ..connection.clone()
+            };
+            tx.execute(&sql, connection_params(&saved).as_slice())?;
+            Ok(saved)
+        })
+        .collect::<Result<Vec<_>, MetadataError>>()?;
+    tx.commit()?;
+
+    Ok(saved)
+}
+
 pub fn list_connections() -> Result<Vec<SavedConnection>, MetadataError> {
     let conn = get_connection()?;
-    let mut stmt = conn.prepare(
-        "SELECT id, name, host, port, database, user, encrypted_password, created_at 
-         FROM connections ORDER BY created_at DESC"
-    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM connections ORDER BY is_favorite DESC, created_at DESC",
+        CONNECTION_COLUMNS
+    ))?;
     
     let connections = stmt
-        .query_map([], |row| {
-            Ok(SavedConnection {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                host: row.get(2)?,
-                port: row.get(3)?,
-                database: row.get(4)?,
-                user: row.get(5)?,
-                encrypted_password: row.get(6)?,
-                created_at: row.get(7)?,
-            })
-        })?
+        .query_map([], connection_from_row)?
         .collect::<SqliteResult<Vec<_>>>()?;
     
     Ok(connections)
@@ -169,80 +551,211 @@ pub fn list_connections() -> Result<Vec<SavedConnection>, MetadataError> {
 
 pub fn get_connection_by_id(id: &str) -> Result<SavedConnection, MetadataError> {
     let conn = get_connection()?;
-    let mut stmt = conn.prepare(
-        "SELECT id, name, host, port, database, user, encrypted_password, created_at 
-         FROM connections WHERE id = ?1"
-    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM connections WHERE id = ?1",
+        CONNECTION_COLUMNS
+    ))?;
     
-    stmt.query_row(params![id], |row| {
-        Ok(SavedConnection {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            host: row.get(2)?,
-            port: row.get(3)?,
-            database: row.get(4)?,
-            user: row.get(5)?,
-            encrypted_password: row.get(6)?,
-            created_at: row.get(7)?,
-        })
-    })
+    stmt.query_row(params![id], connection_from_row)
     .map_err(|e| match e {
         rusqlite::Error::QueryReturnedNoRows => MetadataError::ConnectionNotFound,
         _ => MetadataError::Database(e),
     })
 }
 
-pub fn update_connection(
+/// Writes every setting of a connection but its id and created_at in a single UPDATE, so
+/// an edit is saved whole or not at all
+pub fn update_connection(connection: &SavedConnection) -> Result<SavedConnection, MetadataError> {
+    // Each column is set from the parameter at its position in CONNECTION_COLUMNS
+    let assignments = CONNECTION_COLUMNS
+        .split(',')
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, column)| !matches!(*column, "id" | "created_at"))
+        .map(|(i, column)| format!("{} = ?{}", column, i + 1))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut conn = get_connection()?;
+    let tx = conn.transaction()?;
+    let updated = tx.execute(
+        &format!("UPDATE connections SET {} WHERE id = ?1", assignments),
+        connection_params(connection).as_slice(),
+    )?;
+    if updated == 0 {
+        return Err(MetadataError::ConnectionNotFound);
+    }
+    tx.commit()?;
+    drop(conn);
+
+    get_connection_by_id(&connection.id)
+}
+
+/// Replaces a connection's stored password and records whether it lives in the OS keychain
+pub fn set_connection_password(
     id: &str,
-    name: &str,
-    host: &str,
-    port: u16,
-    database: &str,
-    user: &str,
-    encrypted_password: Option<&str>,
+    encrypted_password: &str,
+    use_keychain: bool,
 ) -> Result<SavedConnection, MetadataError> {
-    let conn = get_connection()?;
-    
-    if let Some(password) = encrypted_password {
-        conn.execute(
-            "UPDATE connections 
-             SET name = ?2, host = ?3, port = ?4, database = ?5, user = ?6, encrypted_password = ?7
-             WHERE id = ?1",
-            params![id, name, host, port, database, user, password],
-        )?;
-    } else {
-        conn.execute(
-            "UPDATE connections 
-             SET name = ?2, host = ?3, port = ?4, database = ?5, user = ?6
-             WHERE id = ?1",
-            params![id, name, host, port, database, user],
-        )?;
+    get_connection()?.execute(
+        "UPDATE connections SET encrypted_password = ?2, use_keychain = ?3 WHERE id = ?1",
+        params![id, encrypted_password, use_keychain],
+    )?;
+    get_connection_by_id(id)
+}
+
+/// Renames a connection
+pub fn rename_connection(id: &str, name: &str) -> Result<SavedConnection, MetadataError> {
+    let updated = get_connection()?.execute(
+        "UPDATE connections SET name = ?2 WHERE id = ?1",
+        params![id, name],
+    )?;
+    if updated == 0 {
+        return Err(MetadataError::ConnectionNotFound);
     }
-    
     get_connection_by_id(id)
 }
 
+/// Copies a connection under a new id and name, settings and stored password included.
+/// For a password kept in the OS keychain that is only the sentinel: the copy still needs
+/// a keychain entry of its own, since those are keyed by id
+pub fn duplicate_connection(id: &str, name: &str) -> Result<SavedConnection, MetadataError> {
+    let conn = get_connection()?;
+    let new_id = Uuid::new_v4().to_string();
+    let created_at = chrono::Utc::now().to_rfc3339();
+
+    // Every other column is copied as is
+    let copied_columns = CONNECTION_COLUMNS
+        .split(',')
+        .map(str::trim)
+        .map(|column| match column {
+            "id" => "?2",
+            "name" => "?3",
+            "created_at" => "?4",
+            column => column,
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let copied = conn.execute(
+        &format!(
+            "INSERT INTO connections ({}) SELECT {} FROM connections WHERE id = ?1",
+            CONNECTION_COLUMNS, copied_columns
+        ),
+        params![id, new_id, name, created_at],
+    )?;
+    drop(conn);
+    if copied == 0 {
+        return Err(MetadataError::ConnectionNotFound);
+    }
+
+    get_connection_by_id(&new_id)
+}
+
 pub fn delete_connection(id: &str) -> Result<(), MetadataError> {
     let conn = get_connection()?;
     conn.execute("DELETE FROM connections WHERE id = ?1", params![id])?;
     Ok(())
 }
 
+/// Re-encrypts every stored password with `reencrypt` inside a single transaction,
+/// skipping those kept in the OS keychain.
+/// If any connection fails, nothing is changed. Returns the number of connections migrated.
+pub fn reencrypt_passwords<F>(mut reencrypt: F) -> Result<usize, MetadataError>
+where
+    F: FnMut(&str) -> Result<String, String>,
+{
+    let mut conn = get_connection()?;
+    let tx = conn.transaction()?;
+
+    let passwords = {
+        let mut stmt =
+            tx.prepare("SELECT id, encrypted_password FROM connections WHERE use_keychain = 0")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        rows.collect::<SqliteResult<Vec<(String, String)>>>()?
+    };
+
+    for (id, encrypted_password) in &passwords {
+        let reencrypted =
+            reencrypt(encrypted_password).map_err(|reason| MetadataError::ReencryptionFailed {
+                id: id.clone(),
+                reason,
+            })?;
+        tx.execute(
+            "UPDATE connections SET encrypted_password = ?2 WHERE id = ?1",
+            params![id, reencrypted],
+        )?;
+    }
+
+    // Dropping the transaction without committing (on any early return above) rolls it back
+    tx.commit()?;
+    Ok(passwords.len())
+}
+
 // ============ Saved Queries CRUD ============
 
+const SAVED_QUERY_COLUMNS: &str = "id, connection_id, name, sql, created_at, tags, parameters";
+
+fn saved_query_from_row(row: &rusqlite::Row) -> SqliteResult<SavedQuery> {
+    Ok(SavedQuery {
+        id: row.get(0)?,
+        connection_id: row.get(1)?,
+        name: row.get(2)?,
+        sql: row.get(3)?,
+        created_at: row.get(4)?,
+        tags: json_column(row, 5)?,
+        parameters: json_column(row, 6)?,
+    })
+}
+
+fn json_column<T: serde::de::DeserializeOwned>(
+    row: &rusqlite::Row,
+    index: usize,
+) -> SqliteResult<T> {
+    let json: String = row.get(index)?;
+    serde_json::from_str(&json).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
+
+/// Trims tags and drops empty and repeated ones (ignoring case), keeping the first spelling
+fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        if !normalized.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            normalized.push(tag.to_string());
+        }
+    }
+    normalized
+}
+
 pub fn create_saved_query(
     connection_id: Option<&str>,
     name: &str,
     sql: &str,
+    tags: &[String],
+    parameters: &[QueryParameter],
 ) -> Result<SavedQuery, MetadataError> {
     let conn = get_connection()?;
     let id = Uuid::new_v4().to_string();
     let created_at = chrono::Utc::now().to_rfc3339();
+    let tags = normalize_tags(tags);
+    let tags_json = JsonValue::from(tags.clone()).to_string();
+    let parameters_json = serde_json::json!(parameters).to_string();
     
     conn.execute(
-        "INSERT INTO saved_queries (id, connection_id, name, sql, created_at)
-         VALUES (?1, ?2, ?3, ?4, ?5)",
-        params![id, connection_id, name, sql, created_at],
+        "INSERT INTO saved_queries (id, connection_id, name, sql, created_at, tags, parameters)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            id,
+            connection_id,
+            name,
+            sql,
+            created_at,
+            tags_json,
+            parameters_json
+        ],
     )?;
     
     Ok(SavedQuery {
@@ -251,37 +764,468 @@ pub fn create_saved_query(
         name: name.to_string(),
         sql: sql.to_string(),
         created_at,
+        tags,
+        parameters: parameters.to_vec(),
     })
 }
 
 pub fn list_saved_queries() -> Result<Vec<SavedQuery>, MetadataError> {
+    let conn = get_connection()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM saved_queries ORDER BY created_at DESC",
+        SAVED_QUERY_COLUMNS
+    ))?;
+    
+    let queries = stmt
+        .query_map([], saved_query_from_row)?
+        .collect::<SqliteResult<Vec<_>>>()?;
+    
+    Ok(queries)
+}
+
+/// Saved queries carrying `tag` (case-insensitive), newest first
+pub fn list_saved_queries_by_tag(tag: &str) -> Result<Vec<SavedQuery>, MetadataError> {
+    let conn = get_connection()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM saved_queries
+         WHERE EXISTS (SELECT 1 FROM json_each(tags) WHERE lower(value) = lower(?1))
+         ORDER BY created_at DESC",
+        SAVED_QUERY_COLUMNS
+    ))?;
+
+    let queries = stmt
+        .query_map(params![tag.trim()], saved_query_from_row)?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    Ok(queries)
+}
+
+/// Every tag in use, sorted case-insensitively. Spellings differing only in case are
+/// listed once
+pub fn list_saved_query_tags() -> Result<Vec<String>, MetadataError> {
     let conn = get_connection()?;
     let mut stmt = conn.prepare(
-        "SELECT id, connection_id, name, sql, created_at 
-         FROM saved_queries ORDER BY created_at DESC"
+        "SELECT min(value) FROM saved_queries, json_each(saved_queries.tags)
+         GROUP BY lower(value)
+         ORDER BY lower(value)",
     )?;
+
+    let tags = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    Ok(tags)
+}
+
+/// Replaces the tags of a saved query
+pub fn set_saved_query_tags(id: &str, tags: &[String]) -> Result<SavedQuery, MetadataError> {
+    let tags_json = JsonValue::from(normalize_tags(tags)).to_string();
+    {
+        let conn = get_connection()?;
+        let updated = conn.execute(
+            "UPDATE saved_queries SET tags = ?2 WHERE id = ?1",
+            params![id, tags_json],
+        )?;
+        if updated == 0 {
+            return Err(MetadataError::SavedQueryNotFound);
+        }
+    }
+
+    get_saved_query_by_id(id)
+}
+
+/// Replaces the parameter definitions of a saved query
+pub fn set_saved_query_parameters(
+    id: &str,
+    parameters: &[QueryParameter],
+) -> Result<SavedQuery, MetadataError> {
+    {
+        let conn = get_connection()?;
+        let updated = conn.execute(
+            "UPDATE saved_queries SET parameters = ?2 WHERE id = ?1",
+            params![id, serde_json::json!(parameters).to_string()],
+        )?;
+        if updated == 0 {
+            return Err(MetadataError::SavedQueryNotFound);
+        }
+    }
+
+    get_saved_query_by_id(id)
+}
+
+pub fn get_saved_query_by_id(id: &str) -> Result<SavedQuery, MetadataError> {
+    let conn = get_connection()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM saved_queries WHERE id = ?1",
+        SAVED_QUERY_COLUMNS
+    ))?;
     
+    stmt.query_row(params![id], saved_query_from_row)
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => MetadataError::SavedQueryNotFound,
+            _ => MetadataError::Database(e),
+        })
+}
+
+/// Saved queries whose name or SQL contains `term` (case-insensitive), newest first
+pub fn search_saved_queries(term: &str) -> Result<Vec<SavedQuery>, MetadataError> {
+    let conn = get_connection()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM saved_queries
+         WHERE name LIKE ?1 ESCAPE '\\' OR sql LIKE ?1 ESCAPE '\\'
+         ORDER BY created_at DESC",
+        SAVED_QUERY_COLUMNS
+    ))?;
+
     let queries = stmt
-        .query_map([], |row| {
-            Ok(SavedQuery {
+        .query_map(params![like_pattern(term)], saved_query_from_row)?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    Ok(queries)
+}
+
+pub fn delete_saved_query(id: &str) -> Result<(), MetadataError> {
+    let conn = get_connection()?;
+    conn.execute("DELETE FROM saved_queries WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+// ============ Query History ============
+
+pub fn record_query_history(
+    connection_id: Option<&str>,
+    sql: &str,
+    duration_ms: u64,
+    row_count: Option<u64>,
+    error: Option<&str>,
+) -> Result<QueryHistoryEntry, MetadataError> {
+    let conn = get_connection()?;
+    let id = Uuid::new_v4().to_string();
+    let executed_at = chrono::Utc::now().to_rfc3339();
+    let success = error.is_none();
+    
+    conn.execute(
+        "INSERT INTO query_history (id, connection_id, sql, executed_at, duration_ms, row_count, success, error)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![id, connection_id, sql, executed_at, duration_ms, row_count, success, error],
+    )?;
+    
+    Ok(QueryHistoryEntry {
+        id,
+        connection_id: connection_id.map(str::to_string),
+        sql: sql.to_string(),
+        executed_at,
+        duration_ms,
+        row_count,
+        success,
+        error: error.map(str::to_string),
+    })
+}
+
+/// Lists history entries, newest first, optionally only those of one connection
+pub fn list_query_history(
+    connection_id: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<QueryHistoryEntry>, MetadataError> {
+    let conn = get_connection()?;
+    let mut stmt = conn.prepare(
+        "SELECT id, connection_id, sql, executed_at, duration_ms, row_count, success, error
+         FROM query_history
+         WHERE ?1 IS NULL OR connection_id = ?1
+         ORDER BY executed_at DESC
+         LIMIT ?2 OFFSET ?3"
+    )?;
+    
+    let entries = stmt
+        .query_map(params![connection_id, limit, offset], |row| {
+            Ok(QueryHistoryEntry {
                 id: row.get(0)?,
                 connection_id: row.get(1)?,
-                name: row.get(2)?,
-                sql: row.get(3)?,
-                created_at: row.get(4)?,
+                sql: row.get(2)?,
+                executed_at: row.get(3)?,
+                duration_ms: row.get(4)?,
+                row_count: row.get(5)?,
+                success: row.get(6)?,
+                error: row.get(7)?,
             })
         })?
         .collect::<SqliteResult<Vec<_>>>()?;
     
-    Ok(queries)
+    Ok(entries)
 }
 
-pub fn delete_saved_query(id: &str) -> Result<(), MetadataError> {
+/// History entries whose SQL contains `term` (case-insensitive), newest first, optionally
+/// only those of one connection
+pub fn search_query_history(
+    term: &str,
+    connection_id: Option<&str>,
+) -> Result<Vec<QueryHistoryEntry>, MetadataError> {
     let conn = get_connection()?;
-    conn.execute("DELETE FROM saved_queries WHERE id = ?1", params![id])?;
+    let mut stmt = conn.prepare(
+        "SELECT id, connection_id, sql, executed_at, duration_ms, row_count, success, error
+         FROM query_history
+         WHERE sql LIKE ?1 ESCAPE '\\' AND (?2 IS NULL OR connection_id = ?2)
+         ORDER BY executed_at DESC",
+    )?;
+
+    let entries = stmt
+        .query_map(params![like_pattern(term), connection_id], |row| {
+            Ok(QueryHistoryEntry {
+                id: row.get(0)?,
+                connection_id: row.get(1)?,
+                sql: row.get(2)?,
+                executed_at: row.get(3)?,
+                duration_ms: row.get(4)?,
+                row_count: row.get(5)?,
+                success: row.get(6)?,
+                error: row.get(7)?,
+            })
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    Ok(entries)
+}
+
+/// Deletes history entries, optionally only those of one connection. Returns how many
+pub fn clear_query_history(connection_id: Option<&str>) -> Result<usize, MetadataError> {
+    let conn = get_connection()?;
+    let deleted = conn.execute(
+        "DELETE FROM query_history WHERE ?1 IS NULL OR connection_id = ?1",
+        params![connection_id],
+    )?;
+    Ok(deleted)
+}
+
+// ============ Editor Tabs ============
+
+const EDITOR_TAB_COLUMNS: &str = "id, connection_id, title, content, position, updated_at";
+
+fn editor_tab_from_row(row: &rusqlite::Row) -> SqliteResult<EditorTab> {
+    Ok(EditorTab {
+        id: row.get(0)?,
+        connection_id: row.get(1)?,
+        title: row.get(2)?,
+        content: row.get(3)?,
+        position: row.get(4)?,
+        updated_at: row.get(5)?,
+    })
+}
+
+/// Creates a tab after the last one of the same connection
+pub fn create_editor_tab(
+    connection_id: Option<&str>,
+    title: &str,
+    content: &str,
+) -> Result<EditorTab, MetadataError> {
+    let conn = get_connection()?;
+    let id = Uuid::new_v4().to_string();
+    let updated_at = chrono::Utc::now().to_rfc3339();
+
+    let position: i64 = conn.query_row(
+        "SELECT COALESCE(MAX(position) + 1, 0) FROM editor_tabs WHERE connection_id IS ?1",
+        params![connection_id],
+        |row| row.get(0),
+    )?;
+    conn.execute(
+        "INSERT INTO editor_tabs (id, connection_id, title, content, position, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![id, connection_id, title, content, position, updated_at],
+    )?;
+
+    Ok(EditorTab {
+        id,
+        connection_id: connection_id.map(str::to_string),
+        title: title.to_string(),
+        content: content.to_string(),
+        position,
+        updated_at,
+    })
+}
+
+/// Lists the tabs of one connection (or those without a connection) in tab order
+pub fn list_editor_tabs(connection_id: Option<&str>) -> Result<Vec<EditorTab>, MetadataError> {
+    let conn = get_connection()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM editor_tabs WHERE connection_id IS ?1 ORDER BY position, updated_at",
+        EDITOR_TAB_COLUMNS
+    ))?;
+
+    let tabs = stmt
+        .query_map(params![connection_id], editor_tab_from_row)?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    Ok(tabs)
+}
+
+pub fn get_editor_tab_by_id(id: &str) -> Result<EditorTab, MetadataError> {
+    let conn = get_connection()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM editor_tabs WHERE id = ?1",
+        EDITOR_TAB_COLUMNS
+    ))?;
+
+    stmt.query_row(params![id], editor_tab_from_row)
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => MetadataError::EditorTabNotFound,
+            _ => MetadataError::Database(e),
+        })
+}
+
+/// Updates the given fields of a tab, leaving the others as they are
+pub fn update_editor_tab(
+    id: &str,
+    title: Option<&str>,
+    content: Option<&str>,
+    position: Option<i64>,
+) -> Result<EditorTab, MetadataError> {
+    let updated = get_connection()?.execute(
+        "UPDATE editor_tabs
+         SET title = COALESCE(?2, title), content = COALESCE(?3, content),
+             position = COALESCE(?4, position), updated_at = ?5
+         WHERE id = ?1",
+        params![
+            id,
+            title,
+            content,
+            position,
+            chrono::Utc::now().to_rfc3339()
+        ],
+    )?;
+    if updated == 0 {
+        return Err(MetadataError::EditorTabNotFound);
+    }
+    get_editor_tab_by_id(id)
+}
+
+pub fn delete_editor_tab(id: &str) -> Result<(), MetadataError> {
+    let conn = get_connection()?;
+    conn.execute("DELETE FROM editor_tabs WHERE id = ?1", params![id])?;
     Ok(())
 }
 
+// ============ Explain Plans ============
+
+const EXPLAIN_PLAN_COLUMNS: &str = "id, connection_id, sql, plan_json, captured_at, label";
+
+fn explain_plan_from_row(row: &rusqlite::Row) -> SqliteResult<SavedExplainPlan> {
+    let plan_json: String = row.get(3)?;
+    let plan = serde_json::from_str(&plan_json).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(e))
+    })?;
+
+    Ok(SavedExplainPlan {
+        id: row.get(0)?,
+        connection_id: row.get(1)?,
+        sql: row.get(2)?,
+        plan,
+        captured_at: row.get(4)?,
+        label: row.get(5)?,
+    })
+}
+
+pub fn save_explain_plan(
+    connection_id: Option<&str>,
+    sql: &str,
+    plan: &JsonValue,
+    label: Option<&str>,
+) -> Result<SavedExplainPlan, MetadataError> {
+    let conn = get_connection()?;
+    let id = Uuid::new_v4().to_string();
+    let captured_at = chrono::Utc::now().to_rfc3339();
+
+    conn.execute(
+        "INSERT INTO explain_plans (id, connection_id, sql, plan_json, captured_at, label)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![id, connection_id, sql, plan.to_string(), captured_at, label],
+    )?;
+
+    Ok(SavedExplainPlan {
+        id,
+        connection_id: connection_id.map(str::to_string),
+        sql: sql.to_string(),
+        plan: plan.clone(),
+        captured_at,
+        label: label.map(str::to_string),
+    })
+}
+
+/// Lists saved plans, newest first, optionally only those of one connection
+pub fn list_explain_plans(
+    connection_id: Option<&str>,
+) -> Result<Vec<SavedExplainPlan>, MetadataError> {
+    let conn = get_connection()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM explain_plans
+         WHERE ?1 IS NULL OR connection_id = ?1
+         ORDER BY captured_at DESC",
+        EXPLAIN_PLAN_COLUMNS
+    ))?;
+
+    let plans = stmt
+        .query_map(params![connection_id], explain_plan_from_row)?
+        .collect::<SqliteResult<Vec<_>>>()?;
+
+    Ok(plans)
+}
+
+pub fn get_explain_plan_by_id(id: &str) -> Result<SavedExplainPlan, MetadataError> {
+    let conn = get_connection()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM explain_plans WHERE id = ?1",
+        EXPLAIN_PLAN_COLUMNS
+    ))?;
+
+    stmt.query_row(params![id], explain_plan_from_row)
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => MetadataError::ExplainPlanNotFound,
+            _ => MetadataError::Database(e),
+        })
+}
+
+pub fn delete_explain_plan(id: &str) -> Result<(), MetadataError> {
+    let conn = get_connection()?;
+    conn.execute("DELETE FROM explain_plans WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+// ============ Materialized View Refreshes ============
+
+/// Records that a materialized view was just refreshed and returns the timestamp stored
+pub fn record_matview_refresh(
+    connection_id: &str,
+    database: &str,
+    schema: &str,
+    name: &str,
+) -> Result<String, MetadataError> {
+    let refreshed_at = chrono::Utc::now().to_rfc3339();
+    get_connection()?.execute(
+        "INSERT OR REPLACE INTO matview_refreshes (connection_id, database, schema, name, refreshed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![connection_id, database, schema, name, refreshed_at],
+    )?;
+    Ok(refreshed_at)
+}
+
+/// Gets when a materialized view was last refreshed from the app, if ever
+pub fn get_matview_refresh(
+    connection_id: &str,
+    database: &str,
+    schema: &str,
+    name: &str,
+) -> Result<Option<String>, MetadataError> {
+    match get_connection()?.query_row(
+        "SELECT refreshed_at FROM matview_refreshes
+         WHERE connection_id = ?1 AND database = ?2 AND schema = ?3 AND name = ?4",
+        params![connection_id, database, schema, name],
+        |row| row.get(0),
+    ) {
+        Ok(refreshed_at) => Ok(Some(refreshed_at)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(MetadataError::Database(e)),
+    }
+}
+
 // ============ App State ============
 
 pub fn get_app_state(key: &str) -> Result<Option<String>, MetadataError> {
@@ -304,3 +1248,382 @@ pub fn set_app_state(key: &str, value: &str) -> Result<(), MetadataError> {
     Ok(())
 }
 
+pub fn delete_app_state(key: &str) -> Result<(), MetadataError> {
+    let conn = get_connection()?;
+    conn.execute("DELETE FROM app_state WHERE key = ?1", params![key])?;
+    Ok(())
+}
+
+/// Gets a value from app state, storing `default` first if the key is not set yet.
+/// Atomic, so concurrent callers always observe the same value
+pub fn get_or_insert_app_state(key: &str, default: &str) -> Result<String, MetadataError> {
+    let conn = get_connection()?;
+    conn.execute(
+        "INSERT OR IGNORE INTO app_state (key, value) VALUES (?1, ?2)",
+        params![key, default],
+    )?;
+    let value = conn.query_row(
+        "SELECT value FROM app_state WHERE key = ?1",
+        params![key],
+        |row| row.get(0),
+    )?;
+    Ok(value)
+}
+
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::Once;
+
+    static INIT: Once = Once::new();
+
+    /// Points the metadata layer at a throwaway database in the temp directory
+    pub(crate) fn init_test_database() {
+        INIT.call_once(|| {
+            let db_path = std::env::temp_dir()
+                .join(format!("datatool-test-{}", Uuid::new_v4()))
+                .join("metadata.db");
+            init_database_at(&db_path).expect("failed to initialize test database");
+        });
+    }
+
+    #[test]
+    fn test_resolve_data_dir_override() {
+        let dir = resolve_data_dir(Some(OsString::from("/tmp/datatool-portable"))).unwrap();
+        assert_eq!(dir, PathBuf::from("/tmp/datatool-portable"));
+        assert_ne!(
+            resolve_data_dir(Some(OsString::new())).unwrap(),
+            PathBuf::new()
+        );
+    }
+
+    #[test]
+    fn test_add_column_if_missing() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE connections (id TEXT PRIMARY KEY);
+             INSERT INTO connections (id) VALUES ('existing');",
+        )
+        .unwrap();
+
+        for _ in 0..2 {
+            add_column_if_missing(
+                &conn,
+                "connections",
+                "sslmode",
+                "TEXT NOT NULL DEFAULT 'prefer'",
+            )
+            .unwrap();
+        }
+
+        let sslmode: String = conn
+            .query_row("SELECT sslmode FROM connections", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(sslmode, "prefer");
+    }
+
+    #[test]
+    fn test_reencrypt_passwords_is_atomic() {
+        init_test_database();
+
+        let first = create_connection(&SavedConnection {
+            name: "a".to_string(),
+            encrypted_password: "old:1".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let second = create_connection(&SavedConnection {
+            name: "b".to_string(),
+            encrypted_password: "old:2".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+
+        // A failure partway through leaves every password untouched
+        let result = reencrypt_passwords(|p| match p {
+            "old:2" => Err("bad key".to_string()),
+            p => Ok(p.replace("old", "new")),
+        });
+        assert!(matches!(
+            result,
+            Err(MetadataError::ReencryptionFailed { .. })
+        ));
+        assert_eq!(
+            get_connection_by_id(&first.id).unwrap().encrypted_password,
+            "old:1"
+        );
+
+        let migrated = reencrypt_passwords(|p| Ok(p.replace("old", "new"))).unwrap();
+        assert!(migrated >= 2);
+        assert_eq!(
+            get_connection_by_id(&second.id).unwrap().encrypted_password,
+            "new:2"
+        );
+
+        delete_connection(&first.id).unwrap();
+        delete_connection(&second.id).unwrap();
+    }
+
+    #[test]
+    fn test_duplicate_connection() {
+        init_test_database();
+
+        let original = create_connection(&SavedConnection {
+            name: "primary".to_string(),
+            host: "db.example.com".to_string(),
+            encrypted_password: "secret".to_string(),
+            sslmode: "require".to_string(),
+            group: "prod".to_string(),
+            read_only: true,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let copy = duplicate_connection(&original.id, "replica").unwrap();
+        assert_ne!(copy.id, original.id);
+        assert_eq!(copy.name, "replica");
+        assert_eq!(copy.encrypted_password, "secret");
+        assert_eq!(copy.group, "prod");
+        assert!(copy.read_only);
+
+        let renamed = rename_connection(&copy.id, "replica 2").unwrap();
+        assert_eq!(renamed.name, "replica 2");
+        assert_eq!(get_connection_by_id(&original.id).unwrap().name, "primary");
+        assert!(matches!(
+            duplicate_connection("missing", "x"),
+            Err(MetadataError::ConnectionNotFound)
+        ));
+        assert!(matches!(
+            rename_connection("missing", "x"),
+            Err(MetadataError::ConnectionNotFound)
+        ));
+
+        delete_connection(&original.id).unwrap();
+        delete_connection(&copy.id).unwrap();
+    }
+
+    #[test]
+    fn test_update_connection() {
+        init_test_database();
+
+        let original = create_connection(&SavedConnection {
+            name: "edited".to_string(),
+            color: Some("red".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        let updated = update_connection(&SavedConnection {
+            host: "db.example.com".to_string(),
+            read_only: true,
+            statement_cache_capacity: 0,
+            application_name: Some("reports".to_string()),
+            ..original.clone()
+        })
+        .unwrap();
+        assert_eq!(updated.host, "db.example.com");
+        assert_eq!(updated.color.as_deref(), Some("red"));
+        assert!(updated.read_only);
+        assert_eq!(updated.statement_cache_capacity, 0);
+        assert_eq!(updated.application_name.as_deref(), Some("reports"));
+        assert_eq!(updated.created_at, original.created_at);
+
+        assert!(matches!(
+            update_connection(&SavedConnection {
+                id: "missing".to_string(),
+                ..original.clone()
+            }),
+            Err(MetadataError::ConnectionNotFound)
+        ));
+
+        delete_connection(&original.id).unwrap();
+    }
+
+    #[test]
+    fn test_query_history() {
+        init_test_database();
+
+        let connection = create_connection(&SavedConnection {
+            name: "history".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let conn_id = Some(connection.id.as_str());
+        record_query_history(conn_id, "SELECT 1", 3, Some(1), None).unwrap();
+        record_query_history(conn_id, "SELEC 2", 1, None, Some("syntax error")).unwrap();
+
+        let history = list_query_history(conn_id, 10, 0).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].sql, "SELEC 2");
+        assert!(!history[0].success);
+        assert_eq!(history[1].row_count, Some(1));
+        assert_eq!(
+            list_query_history(conn_id, 1, 1).unwrap()[0].sql,
+            "SELECT 1"
+        );
+
+        let found = search_query_history("selec", conn_id).unwrap();
+        assert_eq!(found.len(), 2);
+        assert!(search_query_history("1%", conn_id).unwrap().is_empty());
+
+        assert_eq!(clear_query_history(conn_id).unwrap(), 2);
+        assert!(list_query_history(conn_id, 10, 0).unwrap().is_empty());
+
+        delete_connection(&connection.id).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_editor_content() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE app_state (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+             CREATE TABLE editor_tabs (
+                 id TEXT PRIMARY KEY, connection_id TEXT, title TEXT NOT NULL,
+                 content TEXT NOT NULL, position INTEGER NOT NULL, updated_at TEXT NOT NULL
+             );
+             INSERT INTO app_state (key, value) VALUES ('editor_content', 'SELECT 1');",
+        )
+        .unwrap();
+
+        for _ in 0..2 {
+            migrate_editor_content(&conn).unwrap();
+        }
+
+        let tabs: Vec<(Option<String>, String)> = conn
+            .prepare("SELECT connection_id, content FROM editor_tabs")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<SqliteResult<_>>()
+            .unwrap();
+        assert_eq!(tabs, vec![(None, "SELECT 1".to_string())]);
+    }
+
+    #[test]
+    fn test_editor_tabs() {
+        init_test_database();
+
+        let connection = create_connection(&SavedConnection {
+            name: "tabs".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        let conn_id = Some(connection.id.as_str());
+
+        let first = create_editor_tab(conn_id, "first", "SELECT 1").unwrap();
+        let second = create_editor_tab(conn_id, "second", "").unwrap();
+        assert_eq!((first.position, second.position), (0, 1));
+
+        let updated = update_editor_tab(&second.id, None, Some("SELECT 2"), Some(-1)).unwrap();
+        assert_eq!(updated.title, "second");
+        assert_eq!(updated.content, "SELECT 2");
+
+        let tabs = list_editor_tabs(conn_id).unwrap();
+        assert_eq!(tabs.len(), 2);
+        assert_eq!(tabs[0].id, second.id);
+        assert!(!list_editor_tabs(None)
+            .unwrap()
+            .iter()
+            .any(|t| t.id == first.id));
+
+        delete_editor_tab(&first.id).unwrap();
+        assert!(matches!(
+            update_editor_tab(&first.id, Some("gone"), None, None),
+            Err(MetadataError::EditorTabNotFound)
+        ));
+
+        // Tabs go away with their connection
+        delete_connection(&connection.id).unwrap();
+        assert!(list_editor_tabs(conn_id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_explain_plans() {
+        init_test_database();
+
+        let plan = serde_json::json!([{ "Plan": { "Node Type": "Seq Scan" } }]);
+        let saved = save_explain_plan(None, "SELECT 1", &plan, Some("before index")).unwrap();
+
+        let loaded = get_explain_plan_by_id(&saved.id).unwrap();
+        assert_eq!(loaded.plan, plan);
+        assert_eq!(loaded.label.as_deref(), Some("before index"));
+        assert!(list_explain_plans(None)
+            .unwrap()
+            .iter()
+            .any(|p| p.id == saved.id));
+
+        delete_explain_plan(&saved.id).unwrap();
+        assert!(matches!(
+            get_explain_plan_by_id(&saved.id),
+            Err(MetadataError::ExplainPlanNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_saved_query_round_trip() {
+        init_test_database();
+
+        let saved = create_saved_query(None, "active users", "SELECT 1", &[], &[]).unwrap();
+        let loaded = get_saved_query_by_id(&saved.id).unwrap();
+        assert_eq!(loaded.sql, "SELECT 1");
+        assert!(loaded.tags.is_empty());
+
+        let parameters = vec![QueryParameter {
+            name: "id".to_string(),
+            param_type: crate::db::template::ParameterType::Integer,
+            default: Some(serde_json::json!(1)),
+        }];
+        let updated = set_saved_query_parameters(&saved.id, &parameters).unwrap();
+        assert_eq!(updated.parameters, parameters);
+
+        delete_saved_query(&saved.id).unwrap();
+        assert!(matches!(
+            get_saved_query_by_id(&saved.id),
+            Err(MetadataError::SavedQueryNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_saved_query_tags() {
+        init_test_database();
+
+        let tags = |names: &[&str]| names.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        let report = create_saved_query(
+            None,
+            "daily signups",
+            "SELECT 1",
+            &tags(&[" Reports ", "reports", "", "kpi"]),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(report.tags, tags(&["Reports", "kpi"]));
+        let adhoc =
+            create_saved_query(None, "scratch", "SELECT 2", &tags(&["adhoc"]), &[]).unwrap();
+
+        let found = list_saved_queries_by_tag("REPORTS").unwrap();
+        assert!(found.iter().any(|q| q.id == report.id));
+        assert!(!found.iter().any(|q| q.id == adhoc.id));
+
+        let all = list_saved_query_tags().unwrap();
+        for tag in ["adhoc", "kpi", "Reports"] {
+            assert_eq!(
+                all.iter().filter(|t| t.eq_ignore_ascii_case(tag)).count(),
+                1
+            );
+        }
+
+        let retagged = set_saved_query_tags(&adhoc.id, &tags(&["migrations"])).unwrap();
+        assert_eq!(retagged.tags, tags(&["migrations"]));
+        assert!(!list_saved_queries_by_tag("adhoc")
+            .unwrap()
+            .iter()
+            .any(|q| q.id == adhoc.id));
+        assert!(matches!(
+            set_saved_query_tags("missing", &[]),
+            Err(MetadataError::SavedQueryNotFound)
+        ));
+
+        delete_saved_query(&report.id).unwrap();
+        delete_saved_query(&adhoc.id).unwrap();
+    }
+}
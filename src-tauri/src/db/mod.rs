@@ -1,3 +1,18 @@
+pub mod arrow;
+pub mod batch;
+pub mod browse;
+pub mod cache;
+pub mod catalog;
+pub mod conninfo;
+pub mod diagnose;
+pub mod manager;
 pub mod metadata;
+pub mod mysql;
+pub mod notices;
 pub mod postgres;
+pub mod record;
+pub mod script;
+pub mod search;
+pub mod sqlite;
+pub mod template;
 
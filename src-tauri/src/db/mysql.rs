@@ -0,0 +1,627 @@
+use crate::db::batch;
+use crate::db::browse::{self, ColumnFilter, Dialect, DistinctValue, SortColumn};
+use crate::db::cache::DataVersion;
+use crate::db::postgres::{
+    ColumnInfo, ColumnMeta, PaginatedResult, QueryResult, SslMode, TableFilter, TableInfo,
+    TableOrder,
+};
+use crate::db::record;
+use crate::db::script;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use futures::TryStreamExt;
+use serde_json::Value as JsonValue;
+use sqlx::mysql::{MySqlConnectOptions, MySqlPool, MySqlPoolOptions, MySqlRow, MySqlSslMode};
+use sqlx::{Column, Either, Executor, Row, TypeInfo, ValueRef};
+use std::sync::Arc;
+use std::time::Instant;
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+/// Schemas that belong to the server itself and are hidden from the table list
+const SYSTEM_SCHEMAS: &str = "'mysql', 'information_schema', 'performance_schema', 'sys'";
+
+const MYSQL_DIALECT: Dialect = Dialect {
+    quote_ident,
+    like: |column, pattern| format!("CAST({} AS CHAR) LIKE {}", column, pattern),
+    json_path: None,
+    json_number: None,
+};
+
+#[derive(Error, Debug)]
+pub enum MySqlError {
+    #[error("Connection failed: {0}")]
+    ConnectionFailed(String),
+    #[error("Query execution failed: {0}")]
+    QueryFailed(#[source] sqlx::Error),
+    #[error("No active connection")]
+    NoActiveConnection,
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+}
+
+impl From<SslMode> for MySqlSslMode {
+    fn from(mode: SslMode) -> Self {
+        match mode {
+            SslMode::Disable => MySqlSslMode::Disabled,
+            SslMode::Prefer => MySqlSslMode::Preferred,
+            SslMode::Require => MySqlSslMode::Required,
+            SslMode::VerifyCa => MySqlSslMode::VerifyCa,
+            SslMode::VerifyFull => MySqlSslMode::VerifyIdentity,
+        }
+    }
+}
+
+/// Builds the options for connecting to a server
+pub fn connect_options(
+    host: &str,
+    port: u16,
+    database: &str,
+    user: &str,
+    password: &str,
+    ssl_mode: SslMode,
+    ca_cert_path: Option<&str>,
+) -> MySqlConnectOptions {
+    let options = MySqlConnectOptions::new()
+        .host(host)
+        .port(port)
+        .database(database)
+        .username(user)
+        .password(password)
+        .ssl_mode(ssl_mode.into());
+    match ca_cert_path {
+        Some(path) => options.ssl_ca(path),
+        None => options,
+    }
+}
+
+/// Global MySQL/MariaDB connection pool
+pub struct MySqlManager {
+    pool: RwLock<Option<MySqlPool>>,
+    connection_id: RwLock<Option<String>>,
+    data_version: DataVersion,
+}
+
+impl MySqlManager {
+    pub fn new() -> Self {
+        Self {
+            pool: RwLock::new(None),
+            connection_id: RwLock::new(None),
+            data_version: DataVersion::default(),
+        }
+    }
+
+    /// Connects to a MySQL or MariaDB database. On a `read_only` connection every session
+    /// is made read-only, so the server rejects writes
+    #[allow(clippy::too_many_arguments)]
+    pub async fn connect(
+        &self,
+        connection_id: &str,
+        host: &str,
+        port: u16,
+        database: &str,
+        user: &str,
+        password: &str,
+        ssl_mode: SslMode,
+        ca_cert_path: Option<&str>,
+        read_only: bool,
+    ) -> Result<(), MySqlError> {
+        // Disconnect existing pool if any
+        self.disconnect().await;
+
+        let options = connect_options(host, port, database, user, password, ssl_mode, ca_cert_path);
+        let pool = MySqlPoolOptions::new()
+            .max_connections(5)
+            .after_connect(move |conn, _meta| {
+                Box::pin(async move {
+                    if read_only {
+                        sqlx::query("SET SESSION TRANSACTION READ ONLY")
+                            .execute(&mut *conn)
+                            .await?;
+                    }
+                    Ok(())
+                })
+            })
+            .connect_with(options)
+            .await
+            .map_err(|e| MySqlError::ConnectionFailed(e.to_string()))?;
+
+        *self.pool.write().await = Some(pool);
+        *self.connection_id.write().await = Some(connection_id.to_string());
+
+        Ok(())
+    }
+
+    /// Disconnects from the current database
+    pub async fn disconnect(&self) {
+        if let Some(pool) = self.pool.write().await.take() {
+            pool.close().await;
+        }
+        *self.connection_id.write().await = None;
+    }
+
+    /// Gets the current connection ID
+    pub async fn get_connection_id(&self) -> Option<String> {
+        self.connection_id.read().await.clone()
+    }
+
+    /// Changes whenever SQL run here may have changed the data; see
+    /// PostgresManager::data_version
+    pub fn data_version(&self) -> u64 {
+        self.data_version.get()
+    }
+
+    /// Executes a raw SQL query and returns results as JSON. With `distinguish_nulls`,
+    /// NULL cells come back as record::null_cell's sentinel
+    pub async fn execute_query(
+        &self,
+        sql: &str,
+        distinguish_nulls: bool,
+    ) -> Result<QueryResult, MySqlError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(MySqlError::NoActiveConnection)?;
+        let _change = self.data_version.change(sql);
+
+        fetch_query(pool, sql, None, distinguish_nulls)
+            .await
+            .map_err(MySqlError::QueryFailed)
+    }
+
+    /// Executes `limited`, the query limit_select built from `sql`. MySQL rejects that
+    /// wrapping when the query has two columns of the same name, as a join selecting both
+    /// tables' id does; `sql` itself then runs and only its first `row_cap` rows are read.
+    /// `distinguish_nulls` works as in execute_query
+    pub async fn execute_limited_query(
+        &self,
+        sql: &str,
+        limited: &str,
+        row_cap: u64,
+        distinguish_nulls: bool,
+    ) -> Result<QueryResult, MySqlError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(MySqlError::NoActiveConnection)?;
+        let _change = self.data_version.change(sql);
+
+        let result = match fetch_query(pool, limited, None, distinguish_nulls).await {
+            Err(e) if is_duplicate_column(&e) => {
+                fetch_query(pool, sql, Some(row_cap), distinguish_nulls).await
+            }
+            result => result,
+        };
+        result.map_err(MySqlError::QueryFailed)
+    }
+
+    /// Executes one or more statements and returns a QueryResult per result set, in the
+    /// order they arrived. Every result carries the duration of the whole batch, and
+    /// each keeps at most `max_rows` rows. `distinguish_nulls` works as in execute_query
+    pub async fn execute_query_multi(
+        &self,
+        sql: &str,
+        max_rows: Option<u64>,
+        distinguish_nulls: bool,
+    ) -> Result<Vec<QueryResult>, MySqlError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(MySqlError::NoActiveConnection)?;
+        let _change = self.data_version.change(sql);
+
+        let started = Instant::now();
+        let result_sets = batch::fetch_result_sets(pool, sql, max_rows)
+            .await
+            .map_err(MySqlError::QueryFailed)?;
+        let duration_ms = started.elapsed().as_millis() as u64;
+
+        let mut results =
+            batch::into_query_results(sql, result_sets, |rows, rows_affected, modifies_rows| {
+                rows_to_query_result(rows, rows_affected, modifies_rows, distinguish_nulls)
+            });
+        for result in &mut results {
+            result.duration_ms = duration_ms;
+        }
+        Ok(results)
+    }
+
+    /// Fetches the tables in every user schema (MySQL calls databases schemas) that match
+    /// `filter`. Row counts are InnoDB's estimates
+    pub async fn fetch_tables(&self, filter: &TableFilter) -> Result<Vec<TableInfo>, MySqlError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(MySqlError::NoActiveConnection)?;
+
+        let order_by = match filter.order {
+            TableOrder::Name => "table_schema, table_name",
+            // NULLs sort first in MySQL, so views go last explicitly
+            TableOrder::EstimatedRows => {
+                "table_rows IS NULL, table_rows DESC, table_schema, table_name"
+            }
+        };
+        // information_schema columns come back as binary strings on some server versions
+        let sql = format!(
+            r#"
+            SELECT
+                CAST(table_schema AS CHAR),
+                CAST(table_name AS CHAR),
+                CAST(table_type AS CHAR),
+                CAST(table_rows AS SIGNED)
+            FROM information_schema.tables
+            WHERE table_schema NOT IN ({})
+              AND (? IS NULL OR table_schema = ?)
+              AND (? IS NULL OR LOWER(table_name) LIKE LOWER(?))
+            ORDER BY {}
+            LIMIT ? OFFSET ?
+            "#,
+            SYSTEM_SCHEMAS, order_by
+        );
+
+        let tables: Vec<TableInfo> =
+            sqlx::query_as::<_, (String, String, String, Option<i64>)>(&sql)
+                .bind(filter.schema.as_deref())
+                .bind(filter.schema.as_deref())
+                .bind(filter.name_pattern.as_deref())
+                .bind(filter.name_pattern.as_deref())
+                // MySQL has no LIMIT ALL
+                .bind(filter.limit.unwrap_or(i64::MAX))
+                .bind(filter.offset.unwrap_or(0))
+                .fetch_all(pool)
+                .await
+                .map_err(MySqlError::QueryFailed)?
+                .into_iter()
+                .map(|(schema, name, table_type, estimated_rows)| TableInfo {
+                    schema,
+                    name,
+                    table_type,
+                    estimated_rows,
+                })
+                .collect();
+
+        Ok(tables)
+    }
+
+    /// Fetches columns for a specific table
+    pub async fn fetch_columns(
+        &self,
+        schema: &str,
+        table: &str,
+    ) -> Result<Vec<ColumnInfo>, MySqlError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(MySqlError::NoActiveConnection)?;
+
+        let columns: Vec<ColumnInfo> =
+            sqlx::query_as::<_, (String, String, String, Option<String>, String, String)>(
+                r#"
+                SELECT
+                    CAST(column_name AS CHAR),
+                    CAST(data_type AS CHAR),
+                    CAST(is_nullable AS CHAR),
+                    CAST(column_default AS CHAR),
+                    CAST(column_key AS CHAR),
+                    CAST(column_comment AS CHAR)
+                FROM information_schema.columns
+                WHERE table_schema = ? AND table_name = ?
+                ORDER BY ordinal_position
+                "#,
+            )
+            .bind(schema)
+            .bind(table)
+            .fetch_all(pool)
+            .await
+            .map_err(MySqlError::QueryFailed)?
+            .into_iter()
+            .map(
+                |(name, data_type, is_nullable, column_default, column_key, comment)| ColumnInfo {
+                    name,
+                    data_type,
+                    is_nullable: is_nullable == "YES",
+                    column_default,
+                    is_primary_key: column_key == "PRI",
+                    is_unique_key: column_key == "UNI",
+                    // MySQL reports a missing comment as an empty string
+                    comment: (!comment.is_empty()).then_some(comment),
+                    udt_schema: None,
+                    udt_name: None,
+                    is_enum: false,
+                },
+            )
+            .collect();
+
+        Ok(columns)
+    }
+
+    /// Fetches paginated table data, running the COUNT(*) only when `with_count` is set.
+    /// `filters` narrow both the rows and the count. `distinguish_nulls` works as in
+    /// execute_query
+    #[allow(clippy::too_many_arguments)]
+    pub async fn fetch_table_data(
+        &self,
+        schema: &str,
+        table: &str,
+        page: i32,
+        page_size: i32,
+        with_count: bool,
+        order_by: &[SortColumn],
+        filters: &[ColumnFilter],
+        distinguish_nulls: bool,
+    ) -> Result<PaginatedResult, MySqlError> {
+        let columns: Vec<String> = if order_by.is_empty() && filters.is_empty() {
+            Vec::new()
+        } else {
+            self.fetch_columns(schema, table)
+                .await?
+                .into_iter()
+                .map(|column| column.name)
+                .collect()
+        };
+        let (conditions, values) =
+            browse::build_filters(filters, &columns, &MYSQL_DIALECT, |_, _| "?".to_string())
+                .map_err(MySqlError::InvalidInput)?;
+        let order_items = browse::build_order_by(order_by, &columns, &MYSQL_DIALECT)
+            .map_err(MySqlError::InvalidInput)?;
+
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(MySqlError::NoActiveConnection)?;
+
+        let offset = (page - 1) * page_size;
+        let table_ref = format!("{}.{}", quote_ident(schema), quote_ident(table));
+        let where_clause = browse::where_clause(&conditions);
+
+        let total_count = if with_count {
+            let count_sql = format!("SELECT COUNT(*) FROM {}{}", table_ref, where_clause);
+            let count: i64 = values
+                .iter()
+                .fold(sqlx::query(&count_sql), browse::bind_json)
+                .fetch_one(pool)
+                .await
+                .map_err(MySqlError::QueryFailed)?
+                .get(0);
+            Some(count)
+        } else {
+            None
+        };
+
+        let data_sql = format!(
+            "SELECT * FROM {}{}{} LIMIT {} OFFSET {}",
+            table_ref,
+            where_clause,
+            browse::order_by_clause(&order_items),
+            page_size,
+            offset
+        );
+        let rows: Vec<MySqlRow> = values
+            .iter()
+            .fold(sqlx::query(&data_sql), browse::bind_json)
+            .fetch_all(pool)
+            .await
+            .map_err(MySqlError::QueryFailed)?;
+
+        Ok(PaginatedResult {
+            columns: rows.first().map(column_meta).unwrap_or_default(),
+            rows: rows
+                .iter()
+                .map(|row| row_to_json_values(row, distinguish_nulls))
+                .collect(),
+            total_count,
+            page,
+            page_size,
+            next_after: None,
+        })
+    }
+
+    /// Fetches up to `limit` distinct values of a column, most frequent first
+    pub async fn fetch_distinct_values(
+        &self,
+        schema: &str,
+        table: &str,
+        column: &str,
+        limit: i64,
+    ) -> Result<Vec<DistinctValue>, MySqlError> {
+        let column_names: Vec<String> = self
+            .fetch_columns(schema, table)
+            .await?
+            .into_iter()
+            .map(|column| column.name)
+            .collect();
+
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(MySqlError::NoActiveConnection)?;
+
+        let table_ref = format!("{}.{}", quote_ident(schema), quote_ident(table));
+        let sql =
+            browse::distinct_values_sql(&table_ref, column, &column_names, &MYSQL_DIALECT, "?")
+                .map_err(MySqlError::InvalidInput)?;
+
+        let rows: Vec<MySqlRow> = sqlx::query(&sql)
+            .bind(limit)
+            .fetch_all(pool)
+            .await
+            .map_err(MySqlError::QueryFailed)?;
+
+        Ok(rows
+            .iter()
+            .map(|row| DistinctValue {
+                value: row_to_json_values(row, false).swap_remove(0),
+                count: row.get(1),
+            })
+            .collect())
+    }
+}
+
+impl Default for MySqlManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Quotes an identifier with backticks, doubling any embedded backtick
+pub fn quote_ident(ident: &str) -> String {
+    format!("`{}`", ident.replace('`', "``"))
+}
+
+/// Runs a query, reading no more than `row_cap` rows when given
+async fn fetch_query(
+    pool: &MySqlPool,
+    sql: &str,
+    row_cap: Option<u64>,
+    distinguish_nulls: bool,
+) -> Result<QueryResult, sqlx::Error> {
+    // fetch_many, unlike fetch_all, also yields the rows-affected count
+    let started = Instant::now();
+    let mut stream = pool.fetch_many(sqlx::query(sql));
+    let mut rows: Vec<MySqlRow> = Vec::new();
+    let mut rows_affected = 0;
+    while let Some(step) = stream.try_next().await? {
+        match step {
+            Either::Left(result) => rows_affected += result.rows_affected(),
+            Either::Right(row) => rows.push(row),
+        }
+        if row_cap.is_some_and(|row_cap| rows.len() as u64 >= row_cap) {
+            break;
+        }
+    }
+    let duration_ms = started.elapsed().as_millis() as u64;
+
+    Ok(QueryResult {
+        duration_ms,
+        ..rows_to_query_result(
+            rows,
+            rows_affected,
+            script::modifies_rows(sql),
+            distinguish_nulls,
+        )
+    })
+}
+
+/// Whether MySQL refused a derived table for having two columns of the same name
+/// (ER_DUP_FIELDNAME)
+fn is_duplicate_column(error: &sqlx::Error) -> bool {
+    error
+        .as_database_error()
+        .and_then(|e| e.code())
+        .is_some_and(|code| code == "42S21")
+}
+
+/// Converts fetched rows into a QueryResult, leaving `duration_ms` for the caller.
+/// A SELECT's rows-affected count would only repeat the row count, so it is reported
+/// only for statements without rows or, with `modifies_rows`, for DML returning rows.
+/// `distinguish_nulls` is passed on to row_to_json_values
+fn rows_to_query_result(
+    rows: Vec<MySqlRow>,
+    rows_affected: u64,
+    modifies_rows: bool,
+    distinguish_nulls: bool,
+) -> QueryResult {
+    let columns = rows.first().map(column_meta).unwrap_or_default();
+    let json_rows: Vec<Vec<JsonValue>> = rows
+        .iter()
+        .map(|row| row_to_json_values(row, distinguish_nulls))
+        .collect();
+
+    QueryResult {
+        columns,
+        row_count: json_rows.len(),
+        affected_rows: (json_rows.is_empty() || modifies_rows).then_some(rows_affected),
+        rows: json_rows,
+        notices: Vec::new(),
+        duration_ms: 0,
+        truncated: false,
+        cache_status: None,
+    }
+}
+
+/// Extracts column names and type names from a row
+fn column_meta(row: &MySqlRow) -> Vec<ColumnMeta> {
+    row.columns()
+        .iter()
+        .map(|col| ColumnMeta {
+            name: col.name().to_string(),
+            data_type: col.type_info().name().to_string(),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Converts a MySqlRow to a vector of JSON values. Only SQL NULL becomes null, or
+/// record::null_cell's sentinel with `distinguish_nulls`; a value that fails to decode
+/// is replaced with record::decode_error
+fn row_to_json_values(row: &MySqlRow, distinguish_nulls: bool) -> Vec<JsonValue> {
+    row.columns()
+        .iter()
+        .enumerate()
+        .map(|(i, col)| {
+            let Ok(value) = row.try_get_raw(i) else {
+                return JsonValue::Null;
+            };
+            if value.is_null() {
+                return record::null_cell(distinguish_nulls);
+            }
+            let type_name = col.type_info().name();
+
+            let json = match type_name {
+                "BOOLEAN" => row.try_get::<bool, _>(i).ok().map(JsonValue::Bool),
+                "TINYINT" | "SMALLINT" | "MEDIUMINT" | "INT" | "BIGINT" => row
+                    .try_get::<i64, _>(i)
+                    .ok()
+                    .map(|v| JsonValue::Number(v.into())),
+                "TINYINT UNSIGNED" | "SMALLINT UNSIGNED" | "MEDIUMINT UNSIGNED"
+                | "INT UNSIGNED" | "BIGINT UNSIGNED" => row
+                    .try_get::<u64, _>(i)
+                    .ok()
+                    .map(|v| JsonValue::Number(v.into())),
+                "YEAR" => row
+                    .try_get_unchecked::<u16, _>(i)
+                    .ok()
+                    .map(|v| JsonValue::Number(v.into())),
+                "FLOAT" | "DOUBLE" => row.try_get::<f64, _>(i).ok().map(|v| {
+                    serde_json::Number::from_f64(v).map_or(JsonValue::Null, JsonValue::Number)
+                }),
+                // DECIMAL arrives as its exact decimal text; keep it a string like Postgres NUMERIC
+                "DECIMAL" => row
+                    .try_get_unchecked::<String, _>(i)
+                    .ok()
+                    .map(JsonValue::String),
+                // JSON null is a value of its own, not a decode failure, and a JSON value
+                // can look like the sentinel, so an undecodable one is shown as text
+                "JSON" => Some(row.try_get::<JsonValue, _>(i).unwrap_or_else(|_| {
+                    row.try_get_unchecked::<Vec<u8>, _>(i)
+                        .map(|v| JsonValue::String(String::from_utf8_lossy(&v).into_owned()))
+                        .unwrap_or(JsonValue::Null)
+                })),
+                "DATETIME" => row
+                    .try_get::<NaiveDateTime, _>(i)
+                    .ok()
+                    .map(|v| JsonValue::String(v.format(record::ISO_TIMESTAMP_FORMAT).to_string())),
+                "TIMESTAMP" => row
+                    .try_get::<DateTime<Utc>, _>(i)
+                    .ok()
+                    .map(|v| JsonValue::String(v.to_rfc3339())),
+                "DATE" => row
+                    .try_get::<NaiveDate, _>(i)
+                    .ok()
+                    .map(|v| JsonValue::String(v.to_string())),
+                "TIME" => row
+                    .try_get::<NaiveTime, _>(i)
+                    .ok()
+                    .map(|v| JsonValue::String(v.to_string())),
+                _ => {
+                    // Default to string representation
+                    row.try_get::<String, _>(i).ok().map(JsonValue::String)
+                }
+            };
+            json.unwrap_or_else(|| record::decode_error(type_name))
+        })
+        .collect()
+}
+
+/// Thread-safe wrapper for use with Tauri state
+pub type MySqlState = Arc<MySqlManager>;
+
+pub fn create_mysql_state() -> MySqlState {
+    Arc::new(MySqlManager::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_ident() {
+        assert_eq!(quote_ident("orders"), "`orders`");
+        assert_eq!(quote_ident("we`ird"), "`we``ird`");
+    }
+}
@@ -0,0 +1,88 @@
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use tracing::field::{Field, Visit};
+use tracing::instrument::WithSubscriber;
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Interest;
+use tracing::{Event, Metadata, Subscriber};
+
+/// sqlx has no API for server notices (RAISE NOTICE, WARNING, ...); it only emits them
+/// as events under this target while reading the connection
+const NOTICE_TARGET: &str = "sqlx::postgres::notice";
+
+/// Subscriber that collects the message of every notice event and ignores everything
+/// else. It is only the default while the captured future is being polled
+struct NoticeCollector {
+    notices: Arc<Mutex<Vec<String>>>,
+}
+
+struct MessageVisitor<'a>(&'a mut Option<String>);
+
+impl Visit for MessageVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            *self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            *self.0 = Some(format!("{:?}", value));
+        }
+    }
+}
+
+impl Subscriber for NoticeCollector {
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        // Other dispatchers may care about this callsite, so never cache a verdict
+        if metadata.target() == NOTICE_TARGET {
+            Interest::sometimes()
+        } else {
+            Interest::never()
+        }
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.target() == NOTICE_TARGET
+    }
+
+    // RAISE INFO and LOG arrive at trace level
+    fn max_level_hint(&self) -> Option<tracing::level_filters::LevelFilter> {
+        Some(tracing::level_filters::LevelFilter::TRACE)
+    }
+
+    fn new_span(&self, _: &Attributes<'_>) -> Id {
+        Id::from_u64(1)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut message = None;
+        event.record(&mut MessageVisitor(&mut message));
+        if let Some(message) = message {
+            self.notices.lock().unwrap().push(message);
+        }
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+/// Runs `future` and returns its output along with the notices the server sent while
+/// it ran. A connection is read by the task that awaits the query, so notices of queries
+/// running concurrently in other tasks are not mixed in
+pub async fn capture<F: Future>(future: F) -> (F::Output, Vec<String>) {
+    let notices = Arc::new(Mutex::new(Vec::new()));
+    let collector = NoticeCollector {
+        notices: notices.clone(),
+    };
+    let output = future.with_subscriber(collector).await;
+    let captured = std::mem::take(&mut *notices.lock().unwrap());
+    (output, captured)
+}
@@ -1,28 +1,224 @@
+use crate::db::arrow::IpcStreamEncoder;
+use crate::db::batch::{self, ResultSet};
+use crate::db::browse::{self, ColumnFilter, Dialect, DistinctValue, SortColumn};
+use crate::db::cache::{CacheStatus, DataChange, DataVersion};
+use crate::db::catalog::{self, SchemaCatalog};
+use crate::db::conninfo;
+use crate::db::notices;
+use crate::db::record;
+use crate::db::script;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use futures::TryStreamExt;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
-use sqlx::postgres::{PgPool, PgPoolOptions, PgRow};
-use sqlx::{Column, Row, TypeInfo};
-use std::sync::Arc;
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::pool::PoolConnection;
+use sqlx::postgres::types::{Oid, PgInterval};
+use sqlx::postgres::{
+    PgArgumentBuffer, PgArguments, PgConnectOptions, PgDatabaseError, PgErrorPosition, PgListener,
+    PgPool, PgPoolOptions, PgRow, PgSslMode, PgTypeInfo,
+};
+use sqlx::query::Query;
+use sqlx::{Column, Connection, Either, Executor, PgConnection, Postgres, Row, TypeInfo, ValueRef};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
 use thiserror::Error;
-use tokio::sync::RwLock;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::{Mutex, MutexGuard, RwLock};
+use tokio::task::JoinHandle;
+
+/// Name of the server-side cursor used for memory-bounded scans
+const CURSOR_NAME: &str = "datatool_cursor";
+
+/// A cursor left idle for this long is closed and its connection released
+const CURSOR_IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// An explicit transaction left idle for this long is rolled back and its connection
+/// released, so a forgotten transaction can't hold locks indefinitely
+const TRANSACTION_IDLE_TIMEOUT: Duration = Duration::from_secs(1800);
+
+/// How often the idle cursor and transaction watchdogs wake up
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long fetch_table_sizes lets pgstattuple_approx scan before giving up on bloat
+const BLOAT_SCAN_TIMEOUT_MS: u64 = 30_000;
+
+/// Leading keywords of the statements PREPARE accepts, for validate_query
+const VALIDATABLE_STATEMENTS: &[&str] = &[
+    "SELECT", "INSERT", "UPDATE", "DELETE", "MERGE", "VALUES", "WITH", "TABLE",
+];
+
+/// Bytes read from the source per CopyData message sent by copy_in
+const COPY_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Pool defaults for connections that don't configure their own
+pub const DEFAULT_MAX_CONNECTIONS: u32 = 10;
+pub const DEFAULT_ACQUIRE_TIMEOUT_SECS: u64 = 30;
+pub const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 600;
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+
+/// Prepared statements kept per connection, sqlx's own default; 0 disables the cache
+pub const DEFAULT_STATEMENT_CACHE_CAPACITY: usize = 100;
+
+/// What sessions report as application_name in pg_stat_activity, followed by the
+/// connection's name, unless the connection sets its own
+pub const DEFAULT_APPLICATION_NAME: &str = "datatool";
+
+/// Bytes of application_name the server keeps (NAMEDATALEN - 1)
+const MAX_APPLICATION_NAME_LEN: usize = 63;
+
+/// Tables estimated to hold more rows than this are previewed from a sample
+const PREVIEW_SAMPLE_ROWS: f64 = 100_000.0;
+
+/// How many times the rows wanted a preview samples, as TABLESAMPLE SYSTEM picks whole
+/// pages and returns only roughly the share asked for
+const PREVIEW_OVERSAMPLE: f64 = 4.0;
 
 #[derive(Error, Debug)]
 pub enum PostgresError {
     #[error("Connection failed: {0}")]
     ConnectionFailed(String),
+    #[error("Connection timed out after {0}s")]
+    ConnectionTimedOut(u64),
     #[error("Query execution failed: {0}")]
     QueryFailed(String),
     #[error("No active connection")]
     NoActiveConnection,
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+    #[error("Serialization failure, the transaction can be retried: {0}")]
+    SerializationFailure(String),
+    #[error("Failed to encode result: {0}")]
+    EncodingFailed(String),
+    #[error("The connection is read-only")]
+    ReadOnly,
+    #[error("Operation cancelled")]
+    Cancelled,
+    #[error("Query exceeded the statement timeout")]
+    QueryTimeout,
+    #[error("Connection lost: {0}")]
+    ConnectionLost(String),
+    #[error("The {0} extension is not installed in this database")]
+    ExtensionNotInstalled(String),
+    #[error("{}", .0.message)]
+    Server(Box<ServerError>),
     #[error("SQLx error: {0}")]
     Sqlx(#[from] sqlx::Error),
 }
 
+/// An error raised by the server, with the fields it reports beside the message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerError {
+    /// SQLSTATE, e.g. 42601 for a syntax error
+    pub code: String,
+    pub message: String,
+    pub detail: Option<String>,
+    pub hint: Option<String>,
+    /// Constraint a statement violated, e.g. the unique index of a duplicate key
+    pub constraint: Option<String>,
+    /// 1-based character offset of the error in the SQL, when the server gives one
+    pub position: Option<usize>,
+}
+
+impl ServerError {
+    /// The server's error inside a sqlx error, if it is one
+    pub fn from_sqlx(e: &sqlx::Error) -> Option<Self> {
+        let pg_err = e
+            .as_database_error()?
+            .try_downcast_ref::<PgDatabaseError>()?;
+        Some(Self {
+            code: pg_err.code().to_string(),
+            message: pg_err.message().to_string(),
+            detail: pg_err.detail().map(str::to_string),
+            hint: pg_err.hint().map(str::to_string),
+            constraint: pg_err.constraint().map(str::to_string),
+            // An internal position points into a query the server generated, e.g. in a
+            // PL/pgSQL function, not into the one sent
+            position: match pg_err.position() {
+                Some(PgErrorPosition::Original(position)) => Some(position),
+                _ => None,
+            },
+        })
+    }
+}
+
+impl PostgresError {
+    /// Rebases the error position of SQL that ran wrapped in other text onto the SQL
+    /// that was wrapped. `rebase` gets the position in what ran and returns None when it
+    /// falls in the wrapping
+    pub fn map_position(self, rebase: impl FnOnce(usize) -> Option<usize>) -> Self {
+        match self {
+            PostgresError::Server(mut server_error) => {
+                server_error.position = server_error.position.and_then(rebase);
+                PostgresError::Server(server_error)
+            }
+            e => e,
+        }
+    }
+}
+
+/// The position of an error in SQL that ran with `prefix` in front of it, None when the
+/// error is in the prefix
+pub fn position_after(prefix: &str, position: usize) -> Option<usize> {
+    position
+        .checked_sub(prefix.chars().count())
+        .filter(|&p| p > 0)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TableInfo {
     pub schema: String,
     pub name: String,
     pub table_type: String,
+    /// Row count estimated from planner statistics; None for views, tables never
+    /// analyzed and drivers without statistics
+    pub estimated_rows: Option<i64>,
+}
+
+/// The query behind a view or materialized view
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewDefinition {
+    /// Database the view lives in, which may differ from the saved one after switch_database
+    pub database: String,
+    pub schema: String,
+    pub name: String,
+    /// "VIEW" or "MATERIALIZED VIEW", as in TableInfo
+    pub table_type: String,
+    /// The SELECT statement, as reconstructed by pg_get_viewdef
+    pub definition: String,
+    /// Whether a materialized view holds data; None for plain views
+    pub is_populated: Option<bool>,
+    /// Last refresh made through refresh_materialized_view. Postgres itself doesn't record
+    /// when a materialized view was refreshed
+    pub last_refreshed: Option<String>,
+}
+
+/// How fetch_tables orders its results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TableOrder {
+    /// By schema, then name
+    #[default]
+    Name,
+    /// Largest estimated row count first
+    EstimatedRows,
+}
+
+/// Narrows fetch_tables for databases with many tables. Every field is optional; the
+/// default lists all tables
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TableFilter {
+    /// Only tables in this schema
+    pub schema: Option<String>,
+    /// Case-insensitive LIKE pattern on the table name, e.g. `%order%`
+    pub name_pattern: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    pub order: TableOrder,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,35 +228,562 @@ pub struct ColumnInfo {
     pub is_nullable: bool,
     pub column_default: Option<String>,
     pub is_primary_key: bool,
+    pub is_unique_key: bool,
+    /// Set with COMMENT ON COLUMN (the column comment on MySQL)
+    pub comment: Option<String>,
+    /// Schema and name of the column's type in pg_type, which data_type reports only as
+    /// USER-DEFINED for enums and other custom types. None on MySQL and SQLite
+    pub udt_schema: Option<String>,
+    pub udt_name: Option<String>,
+    /// The type is an enum, whose values fetch_enum_values lists
+    pub is_enum: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IndexInfo {
+    pub name: String,
+    /// Key columns in index order; expression keys are rendered as their expression
+    pub columns: Vec<String>,
+    pub is_unique: bool,
+    pub is_primary: bool,
+    /// Access method: btree, hash, gin, gist, brin, ...
+    pub method: String,
+    /// The full CREATE INDEX statement
+    pub definition: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConstraintInfo {
+    pub name: String,
+    /// primary_key, foreign_key, unique, check, exclusion, not_null or trigger
+    pub constraint_type: String,
+    pub columns: Vec<String>,
+    /// The constraint as it would appear in CREATE TABLE, e.g. `CHECK (price > 0)`
+    pub definition: String,
+    /// For foreign keys, the referenced `schema.table` and its columns
+    pub referenced_table: Option<String>,
+    pub referenced_columns: Vec<String>,
+}
+
+/// A column as CREATE TABLE needs it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ColumnDefinition {
+    pub name: String,
+    /// The type with its modifiers, e.g. `character varying(255)` or `numeric(10,2)`
+    pub data_type: String,
+    pub not_null: bool,
+    pub default: Option<String>,
+    /// ALWAYS or BY DEFAULT for identity columns
+    pub identity: Option<String>,
+    /// Expression of a stored generated column
+    pub generated: Option<String>,
+    /// True when the column owns the sequence its default draws from, i.e. it was
+    /// declared as serial
+    pub is_serial: bool,
+}
+
+/// A sequence as CREATE SEQUENCE needs it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SequenceDefinition {
+    pub name: String,
+    pub data_type: String,
+    pub start_value: i64,
+    pub min_value: i64,
+    pub max_value: i64,
+    pub increment_by: i64,
+    pub cache_size: i64,
+    pub cycle: bool,
+}
+
+/// An enum, domain or composite type as CREATE TYPE or CREATE DOMAIN needs it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypeDefinition {
+    pub name: String,
+    /// enum, domain or composite
+    pub kind: String,
+    /// What follows AS: an enum's labels, a domain's base type with its default and
+    /// constraints, or a composite type's attributes
+    pub definition: String,
+    /// Other types of the same schema this one is built from
+    pub depends_on: Vec<String>,
+}
+
+/// How a table takes part in declarative partitioning
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartitionInfo {
+    pub table: String,
+    /// For a partitioned table, what follows PARTITION BY, e.g. `RANGE (created_at)`
+    pub partition_key: Option<String>,
+    /// For a partition, its parent table and what follows PARTITION OF it, e.g.
+    /// `FOR VALUES FROM ('2024-01-01') TO ('2025-01-01')` or `DEFAULT`
+    pub parent_schema: Option<String>,
+    pub parent_table: Option<String>,
+    pub bound: Option<String>,
+}
+
+/// A foreign key as an edge between two tables. Columns pair up by position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForeignKeyInfo {
+    pub name: String,
+    pub source_schema: String,
+    pub source_table: String,
+    pub source_columns: Vec<String>,
+    pub target_schema: String,
+    pub target_table: String,
+    pub target_columns: Vec<String>,
+    /// NO ACTION, RESTRICT, CASCADE, SET NULL or SET DEFAULT
+    pub on_delete: String,
+    pub on_update: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RowKeyStrategy {
+    PrimaryKey,
+    UniqueIndex,
+    None,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RowKey {
+    pub strategy: RowKeyStrategy,
+    pub columns: Vec<String>,
+    pub index_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct QueryResult {
     pub columns: Vec<ColumnMeta>,
     pub rows: Vec<Vec<JsonValue>>,
     pub row_count: usize,
     pub affected_rows: Option<u64>,
+    /// Notices the server sent while the query ran, e.g. from RAISE NOTICE
+    pub notices: Vec<String>,
+    /// Client-side round trip in milliseconds, network latency included. EXPLAIN ANALYZE
+    /// reports the server's own planning and execution times
+    pub duration_ms: u64,
+    /// Whether rows were left out because the query hit its row cap
+    pub truncated: bool,
+    /// Hit or miss when the caller asked for the query cache, None otherwise
+    pub cache_status: Option<CacheStatus>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ColumnMeta {
     pub name: String,
     pub data_type: String,
+    /// OID of the table the column is read from. This and the fields below are None for
+    /// computed expressions, and on drivers other than Postgres
+    pub table_oid: Option<u32>,
+    /// Position of the column in that table (pg_attribute.attnum)
+    pub column_number: Option<i16>,
+    pub source_schema: Option<String>,
+    pub source_table: Option<String>,
+    pub source_column: Option<String>,
+    /// Whether the source column is declared NOT NULL. The result can still hold NULLs,
+    /// e.g. from an outer join
+    pub column_not_null: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CursorBatch {
+    pub columns: Vec<ColumnMeta>,
+    pub rows: Vec<Vec<JsonValue>>,
+    pub row_count: usize,
+    pub exhausted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatementResult {
+    pub sql: String,
+    pub rows_affected: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// Outcome of validate_query. A statement the server can't parse or plan is reported with
+/// `valid` false rather than as an error
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryValidation {
+    pub valid: bool,
+    pub error: Option<String>,
+    /// SQLSTATE of the error
+    pub error_code: Option<String>,
+    /// 1-based character offset of the error in the validated SQL, when the server gives one
+    pub error_position: Option<usize>,
+    /// Inferred types of the `$n` parameters
+    pub parameter_types: Vec<String>,
+    /// Types of the result columns, empty for statements that return no rows. None on
+    /// servers before PostgreSQL 16, which don't report them
+    pub result_types: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptResult {
+    /// One entry per statement that ran; execution stops at the first failure
+    pub statements: Vec<StatementResult>,
+    pub committed: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PaginatedResult {
     pub columns: Vec<ColumnMeta>,
     pub rows: Vec<Vec<JsonValue>>,
-    pub total_count: i64,
+    /// None when the count was skipped
+    pub total_count: Option<i64>,
     pub page: i32,
     pub page_size: i32,
+    /// Row key of the last row, for fetching the next page by key instead of by offset
+    pub next_after: Option<Vec<JsonValue>>,
+}
+
+/// Rows for a quick look at a table, fetched without counting it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TablePreview {
+    pub columns: Vec<ColumnMeta>,
+    pub rows: Vec<Vec<JsonValue>>,
+    /// Whether the rows are a sample from all over the table rather than the first ones
+    pub sampled: bool,
+    /// The planner's row estimate; None when the table was never analyzed
+    pub estimated_rows: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicaInfo {
+    pub client_addr: Option<String>,
+    pub application_name: Option<String>,
+    pub state: Option<String>,
+    pub sent_lsn: Option<String>,
+    pub write_lsn: Option<String>,
+    pub flush_lsn: Option<String>,
+    pub replay_lsn: Option<String>,
+    pub replay_lag_seconds: Option<f64>,
+    pub replay_lag_bytes: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "role", rename_all = "snake_case")]
+pub enum ReplicationStatus {
+    Primary {
+        replicas: Vec<ReplicaInfo>,
+    },
+    Replica {
+        receive_lsn: Option<String>,
+        replay_lsn: Option<String>,
+        replay_lag_seconds: Option<f64>,
+        replay_lag_bytes: Option<i64>,
+    },
+}
+
+/// Pool usage and server facts for a status panel. Server-side fields are None when
+/// their query fails, e.g. for lack of privilege
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionStats {
+    /// Open pooled connections, idle ones included
+    pub pool_size: u32,
+    pub pool_idle: usize,
+    pub pool_max: u32,
+    /// Server sessions of the connected user, from any client
+    pub user_sessions: Option<i64>,
+    pub server_version: Option<String>,
+    pub database_size_bytes: Option<i64>,
+    pub uptime_seconds: Option<f64>,
+}
+
+/// A server process from pg_stat_activity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub pid: i32,
+    pub user: Option<String>,
+    pub database: Option<String>,
+    pub application_name: Option<String>,
+    pub client_addr: Option<String>,
+    /// "client backend" for sessions; autovacuum workers, walsenders and the like too
+    pub backend_type: Option<String>,
+    pub state: Option<String>,
+    pub wait_event_type: Option<String>,
+    pub wait_event: Option<String>,
+    /// The running query, or the last one for idle sessions
+    pub query: Option<String>,
+    pub query_start: Option<DateTime<Utc>>,
+    pub transaction_start: Option<DateTime<Utc>>,
+    /// PIDs holding the locks this session is waiting for
+    pub blocked_by: Vec<i32>,
+}
+
+/// A lock from pg_locks, held or waited for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockInfo {
+    pub pid: i32,
+    pub lock_type: String,
+    pub mode: String,
+    pub granted: bool,
+    /// The locked table or index, for relation locks
+    pub relation: Option<String>,
+    /// The locked transaction id, for transactionid locks
+    pub transaction_id: Option<String>,
+    /// PIDs standing in the way of a lock that isn't granted yet
+    pub blocked_by: Vec<i32>,
+}
+
+/// How fetch_top_queries ranks statements, always highest first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TopQueryOrder {
+    #[default]
+    TotalTime,
+    MeanTime,
+    Calls,
+}
+
+/// A normalized statement from pg_stat_statements, with its totals since the last reset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopQuery {
+    /// None for statements of other users without pg_read_all_stats
+    pub query_id: Option<i64>,
+    pub query: Option<String>,
+    pub user: Option<String>,
+    pub database: Option<String>,
+    pub calls: i64,
+    pub total_exec_time_ms: f64,
+    pub mean_exec_time_ms: f64,
+    /// Rows retrieved or affected, summed over all calls
+    pub rows: i64,
+}
+
+/// Disk usage of a table or materialized view, from pg_total_relation_size and friends
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableSize {
+    pub schema: String,
+    pub name: String,
+    /// Everything below together, plus the free space and visibility maps
+    pub total_bytes: i64,
+    /// The main data fork alone
+    pub table_bytes: i64,
+    pub indexes_bytes: i64,
+    /// The TOAST table and its index
+    pub toast_bytes: i64,
+    /// None until the table is first analyzed
+    pub estimated_rows: Option<i64>,
+    /// Dead tuples and free space as a share of the table, from pgstattuple_approx. Only
+    /// filled in when asked for, pgstattuple is installed and the user may run it
+    pub bloat: Option<TableBloat>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableBloat {
+    pub dead_tuple_percent: f64,
+    pub free_percent: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IsolationLevel {
+    ReadCommitted,
+    RepeatableRead,
+    Serializable,
+}
+
+impl IsolationLevel {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            IsolationLevel::ReadCommitted => "READ COMMITTED",
+            IsolationLevel::RepeatableRead => "REPEATABLE READ",
+            IsolationLevel::Serializable => "SERIALIZABLE",
+        }
+    }
+}
+
+/// TLS mode for a connection, named as in libpq's `sslmode`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SslMode {
+    Disable,
+    #[default]
+    Prefer,
+    Require,
+    VerifyCa,
+    VerifyFull,
+}
+
+impl SslMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SslMode::Disable => "disable",
+            SslMode::Prefer => "prefer",
+            SslMode::Require => "require",
+            SslMode::VerifyCa => "verify-ca",
+            SslMode::VerifyFull => "verify-full",
+        }
+    }
+}
+
+impl std::str::FromStr for SslMode {
+    type Err = PostgresError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "disable" => Ok(SslMode::Disable),
+            "prefer" => Ok(SslMode::Prefer),
+            "require" => Ok(SslMode::Require),
+            "verify-ca" => Ok(SslMode::VerifyCa),
+            "verify-full" => Ok(SslMode::VerifyFull),
+            other => Err(PostgresError::InvalidInput(format!(
+                "Unknown sslmode: {}",
+                other
+            ))),
+        }
+    }
+}
+
+impl From<SslMode> for PgSslMode {
+    fn from(mode: SslMode) -> Self {
+        match mode {
+            SslMode::Disable => PgSslMode::Disable,
+            SslMode::Prefer => PgSslMode::Prefer,
+            SslMode::Require => PgSslMode::Require,
+            SslMode::VerifyCa => PgSslMode::VerifyCa,
+            SslMode::VerifyFull => PgSslMode::VerifyFull,
+        }
+    }
+}
+
+/// Connection pool sizing and timeouts. Missing fields take their defaults
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PoolSettings {
+    pub max_connections: u32,
+    /// How long a query waits for a free pooled connection
+    pub acquire_timeout_secs: u64,
+    /// Idle connections are closed after this long, so stale ones get replaced
+    pub idle_timeout_secs: u64,
+    /// How long opening the connection may take before giving up
+    pub connect_timeout_secs: u64,
+}
+
+impl Default for PoolSettings {
+    fn default() -> Self {
+        Self {
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            acquire_timeout_secs: DEFAULT_ACQUIRE_TIMEOUT_SECS,
+            idle_timeout_secs: DEFAULT_IDLE_TIMEOUT_SECS,
+            connect_timeout_secs: DEFAULT_CONNECT_TIMEOUT_SECS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionStatus {
+    pub active: bool,
+    pub isolation_level: Option<IsolationLevel>,
+    pub default_isolation_level: Option<IsolationLevel>,
+    /// Savepoints set with savepoint that are still in effect, oldest first
+    pub savepoints: Vec<String>,
+}
+
+/// An explicit transaction holding a dedicated connection out of the pool
+struct TransactionSession {
+    id: u64,
+    conn: PoolConnection<Postgres>,
+    isolation_level: Option<IsolationLevel>,
+    savepoints: Vec<String>,
+    last_used: Instant,
+    watchdog: JoinHandle<()>,
+}
+
+/// The locked transaction slot. Dropping it, which happens once the statement run through
+/// it has completed, counts as activity for the transaction's watchdog
+struct TransactionGuard<'a>(MutexGuard<'a, Option<TransactionSession>>);
+
+impl std::ops::Deref for TransactionGuard<'_> {
+    type Target = Option<TransactionSession>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl std::ops::DerefMut for TransactionGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Drop for TransactionGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(session) = self.0.as_mut() {
+            session.last_used = Instant::now();
+        }
+    }
+}
+
+/// A server-side cursor declared inside a transaction on a dedicated connection
+struct CursorSession {
+    id: u64,
+    conn: PoolConnection<Postgres>,
+    columns: Vec<ColumnMeta>,
+    last_used: Instant,
+    watchdog: JoinHandle<()>,
+}
+
+/// Registry entry for an in-flight query; removed again when dropped, even if the
+/// query future is dropped before it completes
+struct RunningQuery {
+    registry: Arc<StdMutex<HashMap<String, i32>>>,
+    query_id: String,
+}
+
+impl RunningQuery {
+    fn register(registry: Arc<StdMutex<HashMap<String, i32>>>, query_id: &str, pid: i32) -> Self {
+        if let Ok(mut running) = registry.lock() {
+            running.insert(query_id.to_string(), pid);
+        }
+        Self {
+            registry,
+            query_id: query_id.to_string(),
+        }
+    }
+}
+
+impl Drop for RunningQuery {
+    fn drop(&mut self) {
+        if let Ok(mut running) = self.registry.lock() {
+            running.remove(&self.query_id);
+        }
+    }
 }
 
 /// Global PostgreSQL connection pool
 pub struct PostgresManager {
     pool: RwLock<Option<PgPool>>,
     connection_id: RwLock<Option<String>>,
+    /// Settings the current pool was built with, reused when it is rebuilt
+    pool_settings: RwLock<PoolSettings>,
+    polling_tasks: Mutex<HashMap<String, JoinHandle<()>>>,
+    poll_results: RwLock<HashMap<String, QueryResult>>,
+    transaction: Arc<Mutex<Option<TransactionSession>>>,
+    next_transaction_id: AtomicU64,
+    default_isolation_level: Arc<RwLock<Option<IsolationLevel>>>,
+    /// search_path set on every pooled connection, as SET search_path takes it; None keeps
+    /// the server's
+    search_path: Arc<RwLock<Option<String>>>,
+    cursor: Arc<Mutex<Option<CursorSession>>>,
+    next_cursor_id: AtomicU64,
+    exports: Mutex<HashMap<String, Arc<AtomicBool>>>,
+    /// Backend PIDs of in-flight queries, keyed by the caller-supplied query id
+    running_queries: Arc<StdMutex<HashMap<String, i32>>>,
+    /// Backend PIDs of the pool's connections with when each backend started, which tells
+    /// them apart from a later process that got the same PID
+    own_sessions: Arc<StdMutex<HashMap<i32, DateTime<Utc>>>>,
+    /// Rebuild the pool and retry once when a query fails with ConnectionLost
+    auto_reconnect: AtomicBool,
+    monitor_task: Mutex<Option<JoinHandle<()>>>,
+    /// Tasks forwarding LISTEN notifications, keyed by channel
+    listen_tasks: Mutex<HashMap<String, JoinHandle<()>>>,
+    /// Schema catalogs for autocompletion, keyed by connection id
+    catalogs: RwLock<HashMap<String, SchemaCatalog>>,
+    data_version: DataVersion,
+    /// Fraction digits MONEY amounts are rendered with, see read_money_scale
+    money_scale: Arc<AtomicU32>,
 }
 
 impl PostgresManager {
@@ -68,10 +791,92 @@ impl PostgresManager {
         Self {
             pool: RwLock::new(None),
             connection_id: RwLock::new(None),
+            pool_settings: RwLock::new(PoolSettings::default()),
+            polling_tasks: Mutex::new(HashMap::new()),
+            poll_results: RwLock::new(HashMap::new()),
+            transaction: Arc::new(Mutex::new(None)),
+            next_transaction_id: AtomicU64::new(0),
+            default_isolation_level: Arc::new(RwLock::new(None)),
+            search_path: Arc::new(RwLock::new(None)),
+            cursor: Arc::new(Mutex::new(None)),
+            next_cursor_id: AtomicU64::new(0),
+            exports: Mutex::new(HashMap::new()),
+            running_queries: Arc::new(StdMutex::new(HashMap::new())),
+            own_sessions: Arc::new(StdMutex::new(HashMap::new())),
+            auto_reconnect: AtomicBool::new(false),
+            monitor_task: Mutex::new(None),
+            listen_tasks: Mutex::new(HashMap::new()),
+            catalogs: RwLock::new(HashMap::new()),
+            data_version: DataVersion::default(),
+            money_scale: Arc::new(AtomicU32::new(record::DEFAULT_MONEY_SCALE)),
         }
     }
 
-    /// Connects to a PostgreSQL database
+    /// Builds pool options that apply the session default isolation level and search_path
+    /// to new connections
+    fn pool_options(&self, settings: PoolSettings) -> PgPoolOptions {
+        let default_isolation_level = self.default_isolation_level.clone();
+        let search_path = self.search_path.clone();
+        let own_sessions = self.own_sessions.clone();
+        let money_scale = self.money_scale.clone();
+        // The pool's sessions share the server's lc_monetary, so its first one is asked
+        let money_scale_read = Arc::new(AtomicBool::new(false));
+
+        PgPoolOptions::new()
+            .max_connections(settings.max_connections.max(1))
+            .acquire_timeout(Duration::from_secs(settings.acquire_timeout_secs))
+            .idle_timeout(Duration::from_secs(settings.idle_timeout_secs))
+            .after_connect(move |conn, _meta| {
+                let default_isolation_level = default_isolation_level.clone();
+                let search_path = search_path.clone();
+                let own_sessions = own_sessions.clone();
+                let money_scale = money_scale.clone();
+                let money_scale_read = money_scale_read.clone();
+                Box::pin(async move {
+                    if let Some(level) = *default_isolation_level.read().await {
+                        let sql = format!(
+                            "SET SESSION CHARACTERISTICS AS TRANSACTION ISOLATION LEVEL {}",
+                            level.as_sql()
+                        );
+                        sqlx::query(&sql).execute(&mut *conn).await?;
+                    }
+                    if let Some(search_path) = search_path.read().await.as_deref() {
+                        sqlx::query("SELECT set_config('search_path', $1, false)")
+                            .bind(search_path)
+                            .execute(&mut *conn)
+                            .await?;
+                    }
+                    // Only needed to refuse terminating the session, so a server without
+                    // pg_stat_activity may still connect
+                    let session: Option<(i32, DateTime<Utc>)> = sqlx::query_as(
+                        "SELECT pid, backend_start FROM pg_stat_activity \
+                         WHERE pid = pg_backend_pid()",
+                    )
+                    .fetch_one(&mut *conn)
+                    .await
+                    .ok();
+                    if let Some((pid, started)) = session {
+                        own_sessions.lock().unwrap().insert(pid, started);
+                    }
+                    if !money_scale_read.swap(true, Ordering::Relaxed) {
+                        money_scale.store(read_money_scale(conn).await, Ordering::Relaxed);
+                    }
+                    Ok(())
+                })
+            })
+    }
+
+    /// Connects to a PostgreSQL database. A `read_only` connection starts every session
+    /// with `default_transaction_read_only` on, so the server rejects writes and DDL.
+    /// That is only a default a session may change, so statements that could (see
+    /// script::lifts_read_only) are refused before they reach the server.
+    /// `statement_cache_capacity` is how many prepared statements each connection keeps
+    /// for reuse; 0 prepares every query anew, which avoids "cached plan must not change
+    /// result type" errors after the tables behind a cached statement are altered.
+    /// See connect_options for how `host` is interpreted and application_name for the
+    /// name the sessions go by. `search_path`, e.g. `app, public`, is set on every session
+    /// as it opens; set_search_path replaces it until the next connect
+    #[allow(clippy::too_many_arguments)]
     pub async fn connect(
         &self,
         connection_id: &str,
@@ -80,334 +885,4141 @@ impl PostgresManager {
         database: &str,
         user: &str,
         password: &str,
+        ssl_mode: SslMode,
+        ca_cert_path: Option<&str>,
+        pool_settings: PoolSettings,
+        read_only: bool,
+        statement_cache_capacity: usize,
+        application_name: &str,
+        search_path: Option<&str>,
     ) -> Result<(), PostgresError> {
-        // Disconnect existing pool if any
-        self.disconnect().await;
-
-        let connection_string = format!(
-            "postgres://{}:{}@{}:{}/{}",
-            user, password, host, port, database
+        let options = connect_options(
+            host,
+            port,
+            database,
+            user,
+            password,
+            ssl_mode,
+            ca_cert_path,
+            read_only,
+            statement_cache_capacity,
+            application_name,
         );
+        self.connect_with_options(connection_id, options, pool_settings, search_path)
+            .await
+    }
 
-        let pool = PgPoolOptions::new()
-            .max_connections(5)
-            .connect(&connection_string)
+    /// Connects with a full connection string: a `postgres://` URL or libpq keyword/value
+    /// pairs. Parameters beyond the usual fields (application_name, options, ...) apply too;
+    /// `application_name` is used when the string sets none
+    pub async fn connect_from_url(
+        &self,
+        connection_id: &str,
+        url: &str,
+        pool_settings: PoolSettings,
+        application_name: &str,
+    ) -> Result<(), PostgresError> {
+        let mut options = conninfo::parse(url)?.options;
+        if options.get_application_name().is_none() {
+            options = options.application_name(application_name);
+        }
+        self.connect_with_options(connection_id, options, pool_settings, None)
             .await
-            .map_err(|e| PostgresError::ConnectionFailed(e.to_string()))?;
+    }
+
+    async fn connect_with_options(
+        &self,
+        connection_id: &str,
+        options: PgConnectOptions,
+        pool_settings: PoolSettings,
+        search_path: Option<&str>,
+    ) -> Result<(), PostgresError> {
+        if let Some(search_path) = search_path {
+            validate_search_path(search_path)?;
+        }
+
+        // Disconnect existing pool if any
+        self.disconnect().await;
+
+        // Set before the pool opens so that its first connections get it too
+        *self.search_path.write().await = search_path.map(str::to_string);
+        let pool = match self.open_pool(options, pool_settings).await {
+            Ok(pool) => pool,
+            Err(e) => {
+                *self.search_path.write().await = None;
+                return Err(e);
+            }
+        };
 
         *self.pool.write().await = Some(pool);
         *self.connection_id.write().await = Some(connection_id.to_string());
+        *self.pool_settings.write().await = pool_settings;
 
         Ok(())
     }
 
-    /// Disconnects from the current database
-    pub async fn disconnect(&self) {
-        if let Some(pool) = self.pool.write().await.take() {
-            pool.close().await;
-        }
-        *self.connection_id.write().await = None;
+    async fn open_pool(
+        &self,
+        options: PgConnectOptions,
+        pool_settings: PoolSettings,
+    ) -> Result<PgPool, PostgresError> {
+        // Bound the whole attempt so an unreachable host fails fast instead of hanging
+        let connect_timeout = pool_settings.connect_timeout_secs;
+        tokio::time::timeout(
+            Duration::from_secs(connect_timeout),
+            self.pool_options(pool_settings).connect_with(options),
+        )
+        .await
+        .map_err(|_| PostgresError::ConnectionTimedOut(connect_timeout))?
+        .map_err(|e| match e {
+            sqlx::Error::PoolTimedOut => PostgresError::ConnectionTimedOut(connect_timeout),
+            // Keep what the server said, e.g. that the password was wrong
+            e => match ServerError::from_sqlx(&e) {
+                Some(server_error) => PostgresError::Server(Box::new(server_error)),
+                None => PostgresError::ConnectionFailed(e.to_string()),
+            },
+        })
     }
 
-    /// Gets the current connection ID
-    pub async fn get_connection_id(&self) -> Option<String> {
-        self.connection_id.read().await.clone()
+    fn money_scale(&self) -> u32 {
+        self.money_scale.load(Ordering::Relaxed)
     }
 
-    /// Tests if the connection is still valid
-    pub async fn test_connection(&self) -> Result<bool, PostgresError> {
-        let pool = self.pool.read().await;
-        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+    /// Replaces the pool with a fresh one built from the same connect options, keeping the
+    /// connection id. An open transaction or cursor lived on the old connections and is dropped
+    pub async fn reconnect(&self) -> Result<(), PostgresError> {
+        if let Some(session) = self.transaction.lock().await.take() {
+            session.watchdog.abort();
+        }
+        if let Some(session) = self.cursor.lock().await.take() {
+            session.watchdog.abort();
+        }
 
-        sqlx::query("SELECT 1")
-            .fetch_one(pool)
+        let connect_options = self
+            .pool
+            .read()
             .await
-            .map(|_| true)
-            .map_err(|e| PostgresError::QueryFailed(e.to_string()))
-    }
-
-    /// Executes a raw SQL query and returns results as JSON
-    pub async fn execute_query(&self, sql: &str) -> Result<QueryResult, PostgresError> {
-        let pool = self.pool.read().await;
-        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+            .as_ref()
+            .ok_or(PostgresError::NoActiveConnection)?
+            .connect_options();
+        let connection_id = self.connection_id.read().await.clone();
 
-        let rows: Vec<PgRow> = sqlx::query(sql)
-            .fetch_all(pool)
-            .await
-            .map_err(|e| PostgresError::QueryFailed(e.to_string()))?;
+        // Connect before taking the write lock, so queries aren't held up behind it
+        let new_pool = self
+            .open_pool((*connect_options).clone(), *self.pool_settings.read().await)
+            .await?;
 
-        if rows.is_empty() {
-            return Ok(QueryResult {
-                columns: vec![],
-                rows: vec![],
-                row_count: 0,
-                affected_rows: None,
-            });
+        let mut pool = self.pool.write().await;
+        // Disconnected or switched to another connection in the meantime
+        if pool.is_none() || *self.connection_id.read().await != connection_id {
+            drop(pool);
+            new_pool.close().await;
+            return Err(PostgresError::NoActiveConnection);
+        }
+        if let Some(old_pool) = pool.replace(new_pool) {
+            // close() waits for checked-out connections, which may be stuck on the dead socket
+            tokio::spawn(async move { old_pool.close().await });
         }
 
-        // Extract column metadata from the first row
-        let columns: Vec<ColumnMeta> = rows[0]
-            .columns()
-            .iter()
-            .map(|col| ColumnMeta {
-                name: col.name().to_string(),
-                data_type: col.type_info().name().to_string(),
-            })
-            .collect();
-
-        // Convert rows to JSON values
-        let json_rows: Vec<Vec<JsonValue>> = rows
-            .iter()
-            .map(|row| row_to_json_values(row))
-            .collect();
-
-        let row_count = json_rows.len();
+        Ok(())
+    }
 
-        Ok(QueryResult {
-            columns,
-            rows: json_rows,
-            row_count,
-            affected_rows: None,
+    /// Whether the sessions start read-only, from the connect options or the connection
+    /// string's `options`
+    pub async fn is_read_only(&self) -> bool {
+        self.pool.read().await.as_ref().is_some_and(|pool| {
+            pool.connect_options()
+                .get_options()
+                .is_some_and(|options| options.contains("default_transaction_read_only=on"))
         })
     }
 
-    /// Fetches all tables in the database
-    pub async fn fetch_tables(&self) -> Result<Vec<TableInfo>, PostgresError> {
-        let pool = self.pool.read().await;
-        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+    /// Refuses SQL that could switch a read-only connection's session to read-write.
+    /// Otherwise returns the change `sql` makes to the data, to be held while it runs
+    async fn check_read_only(&self, sql: &str) -> Result<Option<DataChange<'_>>, PostgresError> {
+        if script::lifts_read_only(sql) && self.is_read_only().await {
+            return Err(PostgresError::ReadOnly);
+        }
+        Ok(self.data_version.change(sql))
+    }
 
-        let tables: Vec<TableInfo> = sqlx::query_as::<_, (String, String, String)>(
-            r#"
-            SELECT table_schema, table_name, table_type
-            FROM information_schema.tables
-            WHERE table_schema NOT IN ('pg_catalog', 'information_schema')
-            ORDER BY table_schema, table_name
+    /// Changes whenever the data this connection sees may have: after writes, commits,
+    /// and switching databases or search_path. Cached results are keyed on it
+    pub fn data_version(&self) -> u64 {
+        self.data_version.get()
+    }
+
+    /// Turns retrying a query once on a fresh pool after ConnectionLost on or off
+    pub fn set_auto_reconnect(&self, enabled: bool) {
+        self.auto_reconnect.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn auto_reconnect(&self) -> bool {
+        self.auto_reconnect.load(Ordering::Relaxed)
+    }
+
+    /// Registers the background connection monitor, aborting any previous one
+    pub async fn register_monitor_task(&self, handle: JoinHandle<()>) {
+        if let Some(previous) = self.monitor_task.lock().await.replace(handle) {
+            previous.abort();
+        }
+    }
+
+    /// Stops the background connection monitor. Returns false if none was running
+    pub async fn stop_monitor_task(&self) -> bool {
+        match self.monitor_task.lock().await.take() {
+            Some(handle) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Opens a listener subscribed to `channel` (case-sensitive, as NOTIFY sees it once
+    /// unquoted names are lowercased). It gets a connection of its own, outside the pool,
+    /// so it neither takes a pool slot nor waits behind queries
+    pub async fn listen(&self, channel: &str) -> Result<PgListener, PostgresError> {
+        if channel.is_empty() {
+            return Err(PostgresError::InvalidInput(
+                "Channel name must not be empty".to_string(),
+            ));
+        }
+
+        let connect_options = self
+            .pool
+            .read()
+            .await
+            .as_ref()
+            .ok_or(PostgresError::NoActiveConnection)?
+            .connect_options();
+
+        // A pool of one, which PgListener uses to reconnect and re-subscribe if it drops
+        let pool = PgPoolOptions::new()
+            .max_connections(1)
+            .connect_with((*connect_options).clone())
+            .await
+            .map_err(|e| PostgresError::ConnectionFailed(e.to_string()))?;
+        let mut listener = PgListener::connect_with(&pool).await.map_err(query_error)?;
+        listener.listen(channel).await.map_err(query_error)?;
+
+        Ok(listener)
+    }
+
+    /// Registers the task forwarding a channel's notifications, aborting any previous one
+    /// for the same channel
+    pub async fn register_listen_task(&self, channel: &str, handle: JoinHandle<()>) {
+        if let Some(previous) = self
+            .listen_tasks
+            .lock()
+            .await
+            .insert(channel.to_string(), handle)
+        {
+            previous.abort();
+        }
+    }
+
+    /// Stops listening on a channel, closing its connection. Returns false if it wasn't
+    /// being listened on
+    pub async fn stop_listen_task(&self, channel: &str) -> bool {
+        match self.listen_tasks.lock().await.remove(channel) {
+            Some(handle) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Channels currently listened on, sorted
+    pub async fn listened_channels(&self) -> Vec<String> {
+        let mut channels: Vec<String> = self
+            .listen_tasks
+            .lock()
+            .await
+            .iter()
+            .filter(|(_, handle)| !handle.is_finished())
+            .map(|(channel, _)| channel.clone())
+            .collect();
+        channels.sort();
+        channels
+    }
+
+    /// Disconnects from the current database
+    pub async fn disconnect(&self) {
+        // The monitor may be waiting to reconnect, or holding the pool while it does
+        self.stop_monitor_task().await;
+        // Closing the pool waits for its connections to come back, and a query left
+        // running would keep its backend busy on the server after we are gone
+        self.cancel_all_queries().await.ok();
+        self.stop_all_polling().await;
+        for (_, handle) in self.listen_tasks.lock().await.drain() {
+            handle.abort();
+        }
+        self.close_cursor().await.ok();
+        for cancelled in self.exports.lock().await.values() {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+
+        if let Some(mut session) = self.transaction.lock().await.take() {
+            session.watchdog.abort();
+            sqlx::query("ROLLBACK")
+                .execute(&mut *session.conn)
+                .await
+                .ok();
+        }
+
+        if let Some(pool) = self.pool.write().await.take() {
+            pool.close().await;
+        }
+        *self.connection_id.write().await = None;
+        *self.default_isolation_level.write().await = None;
+        *self.search_path.write().await = None;
+    }
+
+    /// Rebuilds the pool against another database on the same server, keeping the
+    /// connection id, credentials and pool settings. The old pool stays in use if the new
+    /// database can't be reached. Cursors, listeners and polls belong to the old database
+    /// and are stopped, and the search_path is reset since schemas differ per database
+    pub async fn switch_database(&self, database: &str) -> Result<(), PostgresError> {
+        let database = database.trim();
+        if database.is_empty() {
+            return Err(PostgresError::InvalidInput(
+                "Database name must not be empty".to_string(),
+            ));
+        }
+        if self.transaction.lock().await.is_some() {
+            return Err(PostgresError::InvalidInput(
+                "Cannot switch databases while a transaction is in progress".to_string(),
+            ));
+        }
+
+        let mut pool = self.pool.write().await;
+        let connect_options = pool
+            .as_ref()
+            .ok_or(PostgresError::NoActiveConnection)?
+            .connect_options()
+            .as_ref()
+            .clone()
+            .database(database);
+
+        let previous_search_path = self.search_path.write().await.take();
+        let new_pool = match self
+            .open_pool(connect_options, *self.pool_settings.read().await)
+            .await
+        {
+            Ok(new_pool) => new_pool,
+            Err(e) => {
+                *self.search_path.write().await = previous_search_path;
+                return Err(e);
+            }
+        };
+
+        self.stop_all_polling().await;
+        for (_, handle) in self.listen_tasks.lock().await.drain() {
+            handle.abort();
+        }
+        self.close_cursor().await.ok();
+        if let Some(connection_id) = self.get_connection_id().await {
+            self.catalogs.write().await.remove(&connection_id);
+        }
+
+        if let Some(old_pool) = pool.replace(new_pool) {
+            old_pool.close().await;
+        }
+        self.data_version.bump();
+
+        Ok(())
+    }
+
+    /// Sets `schema` as the search_path of every pooled connection, so unqualified names
+    /// resolve in it; None restores the server default
+    pub async fn set_search_path(&self, schema: Option<&str>) -> Result<(), PostgresError> {
+        if self.transaction.lock().await.is_some() {
+            return Err(PostgresError::InvalidInput(
+                "Cannot change the search_path while a transaction is in progress".to_string(),
+            ));
+        }
+
+        let mut pool = self.pool.write().await;
+        let current = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        if let Some(schema) = schema {
+            let exists: bool =
+                sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pg_namespace WHERE nspname = $1)")
+                    .bind(schema)
+                    .fetch_one(current)
+                    .await
+                    .map_err(query_error)?;
+            if !exists {
+                return Err(PostgresError::InvalidInput(format!(
+                    "Schema {} does not exist",
+                    schema
+                )));
+            }
+        }
+
+        let connect_options = current.connect_options();
+        let previous = std::mem::replace(
+            &mut *self.search_path.write().await,
+            schema.map(quote_ident),
+        );
+
+        // Rebuild the pool so connections that are already open pick up the new path
+        let new_pool = match self
+            .open_pool((*connect_options).clone(), *self.pool_settings.read().await)
+            .await
+        {
+            Ok(new_pool) => new_pool,
+            Err(e) => {
+                *self.search_path.write().await = previous;
+                return Err(e);
+            }
+        };
+
+        if let Some(old_pool) = pool.replace(new_pool) {
+            old_pool.close().await;
+        }
+        self.data_version.bump();
+
+        Ok(())
+    }
+
+    /// Starts an explicit transaction on a dedicated connection. A transaction left idle
+    /// for TRANSACTION_IDLE_TIMEOUT is rolled back, by the server too in case the app
+    /// goes away without closing the connection
+    pub async fn begin_transaction(
+        &self,
+        isolation_level: Option<IsolationLevel>,
+    ) -> Result<(), PostgresError> {
+        let mut transaction = self.transaction.lock().await;
+        if transaction.is_some() {
+            return Err(PostgresError::InvalidInput(
+                "A transaction is already in progress".to_string(),
+            ));
+        }
+
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let mut conn = pool
+            .acquire()
+            .await
+            .map_err(|e| PostgresError::ConnectionFailed(e.to_string()))?;
+        // Never hand a connection back to the pool while it may still be inside a transaction
+        conn.close_on_drop();
+
+        sqlx::query("BEGIN")
+            .execute(&mut *conn)
+            .await
+            .map_err(query_error)?;
+        sqlx::query(&format!(
+            "SET LOCAL idle_in_transaction_session_timeout = '{}s'",
+            TRANSACTION_IDLE_TIMEOUT.as_secs()
+        ))
+        .execute(&mut *conn)
+        .await
+        .map_err(query_error)?;
+
+        if let Some(level) = isolation_level {
+            sqlx::query(&format!(
+                "SET TRANSACTION ISOLATION LEVEL {}",
+                level.as_sql()
+            ))
+            .execute(&mut *conn)
+            .await
+            .map_err(query_error)?;
+        }
+
+        let id = self.next_transaction_id.fetch_add(1, Ordering::Relaxed);
+        let watchdog = tokio::spawn(transaction_watchdog(self.transaction.clone(), id));
+
+        *transaction = Some(TransactionSession {
+            id,
+            conn,
+            isolation_level,
+            savepoints: Vec::new(),
+            last_used: Instant::now(),
+            watchdog,
+        });
+
+        Ok(())
+    }
+
+    /// Commits the open transaction
+    pub async fn commit_transaction(&self) -> Result<(), PostgresError> {
+        self.end_transaction("COMMIT").await
+    }
+
+    /// Rolls back the open transaction
+    pub async fn rollback_transaction(&self) -> Result<(), PostgresError> {
+        self.end_transaction("ROLLBACK").await
+    }
+
+    async fn end_transaction(&self, statement: &str) -> Result<(), PostgresError> {
+        let mut session = self
+            .transaction
+            .lock()
+            .await
+            .take()
+            .ok_or_else(no_transaction)?;
+        session.watchdog.abort();
+
+        let _change = self.data_version.begin();
+        sqlx::query(statement)
+            .execute(&mut *session.conn)
+            .await
+            .map(|_| ())
+            .map_err(query_error)
+    }
+
+    /// Sets a savepoint in the open transaction. Like Postgres, a name can be set again;
+    /// rolling back then goes to the latest one
+    pub async fn savepoint(&self, name: &str) -> Result<(), PostgresError> {
+        let mut transaction = self.lock_transaction().await;
+        let session = transaction.as_mut().ok_or_else(no_transaction)?;
+
+        sqlx::query(&format!("SAVEPOINT {}", quote_ident(name)))
+            .execute(&mut *session.conn)
+            .await
+            .map_err(query_error)?;
+        session.savepoints.push(name.to_string());
+
+        Ok(())
+    }
+
+    /// Undoes everything done in the open transaction since a savepoint, which also
+    /// recovers a transaction aborted by an error. The savepoint stays set; those set
+    /// after it are gone
+    pub async fn rollback_to_savepoint(&self, name: &str) -> Result<(), PostgresError> {
+        let mut transaction = self.lock_transaction().await;
+        let session = transaction.as_mut().ok_or_else(no_transaction)?;
+        let position = session
+            .savepoints
+            .iter()
+            .rposition(|savepoint| savepoint == name)
+            .ok_or_else(|| PostgresError::InvalidInput(format!("No savepoint named {}", name)))?;
+
+        sqlx::query(&format!("ROLLBACK TO SAVEPOINT {}", quote_ident(name)))
+            .execute(&mut *session.conn)
+            .await
+            .map_err(query_error)?;
+        session.savepoints.truncate(position + 1);
+
+        Ok(())
+    }
+
+    /// Reports whether a transaction is open and which isolation levels apply
+    pub async fn transaction_status(&self) -> TransactionStatus {
+        let transaction = self.transaction.lock().await;
+        TransactionStatus {
+            active: transaction.is_some(),
+            isolation_level: transaction.as_ref().and_then(|t| t.isolation_level),
+            default_isolation_level: *self.default_isolation_level.read().await,
+            savepoints: transaction
+                .as_ref()
+                .map(|t| t.savepoints.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Locks the open transaction, if any, counting both this and the release of the lock
+    /// as activity for its watchdog
+    async fn lock_transaction(&self) -> TransactionGuard<'_> {
+        let mut transaction = self.transaction.lock().await;
+        if let Some(session) = transaction.as_mut() {
+            session.last_used = Instant::now();
+        }
+        TransactionGuard(transaction)
+    }
+
+    /// Sets the default isolation level for every pooled connection of this session. The
+    /// old pool and level stay in use if the new pool can't be opened
+    pub async fn set_default_isolation_level(
+        &self,
+        isolation_level: Option<IsolationLevel>,
+    ) -> Result<(), PostgresError> {
+        if self.transaction.lock().await.is_some() {
+            return Err(PostgresError::InvalidInput(
+                "Cannot change the default isolation level while a transaction is in progress"
+                    .to_string(),
+            ));
+        }
+
+        let mut pool = self.pool.write().await;
+        let connect_options = pool
+            .as_ref()
+            .ok_or(PostgresError::NoActiveConnection)?
+            .connect_options();
+        let previous = std::mem::replace(
+            &mut *self.default_isolation_level.write().await,
+            isolation_level,
+        );
+
+        // Rebuild the pool so connections that are already open pick up the new default
+        let new_pool = match self
+            .open_pool((*connect_options).clone(), *self.pool_settings.read().await)
+            .await
+        {
+            Ok(new_pool) => new_pool,
+            Err(e) => {
+                *self.default_isolation_level.write().await = previous;
+                return Err(e);
+            }
+        };
+
+        if let Some(old_pool) = pool.replace(new_pool) {
+            old_pool.close().await;
+        }
+
+        Ok(())
+    }
+
+    /// Declares a server-side cursor for `sql`, replacing any cursor that is already open
+    pub async fn declare_cursor(&self, sql: &str) -> Result<(), PostgresError> {
+        let _change = self.check_read_only(sql).await?;
+
+        self.close_cursor().await?;
+
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let mut conn = pool
+            .acquire()
+            .await
+            .map_err(|e| PostgresError::ConnectionFailed(e.to_string()))?;
+        // The cursor lives inside a transaction, so the connection must not go back to the pool
+        conn.close_on_drop();
+
+        sqlx::query("BEGIN READ ONLY")
+            .execute(&mut *conn)
+            .await
+            .map_err(query_error)?;
+        sqlx::query(&format!(
+            "DECLARE {} NO SCROLL CURSOR FOR {}",
+            CURSOR_NAME,
+            sql.trim().trim_end_matches(';')
+        ))
+        .execute(&mut *conn)
+        .await
+        .map_err(query_error)?;
+
+        let id = self.next_cursor_id.fetch_add(1, Ordering::Relaxed);
+        let watchdog = tokio::spawn(cursor_watchdog(self.cursor.clone(), id));
+
+        *self.cursor.lock().await = Some(CursorSession {
+            id,
+            conn,
+            columns: vec![],
+            last_used: Instant::now(),
+            watchdog,
+        });
+
+        Ok(())
+    }
+
+    /// Fetches the next batch of rows from the open cursor
+    pub async fn fetch_cursor(&self, batch_size: u32) -> Result<CursorBatch, PostgresError> {
+        let mut cursor = self.cursor.lock().await;
+        let session = cursor
+            .as_mut()
+            .ok_or_else(|| PostgresError::InvalidInput("No cursor is open".to_string()))?;
+        session.last_used = Instant::now();
+
+        let rows: Vec<PgRow> = sqlx::query(&format!(
+            "FETCH FORWARD {} FROM {}",
+            batch_size, CURSOR_NAME
+        ))
+        .fetch_all(&mut *session.conn)
+        .await
+        .map_err(query_error)?;
+
+        // An empty batch carries no column metadata, so reuse what earlier batches reported
+        if let Some(row) = rows.first() {
+            session.columns = column_meta(row);
+        }
+
+        let row_count = rows.len();
+        Ok(CursorBatch {
+            columns: session.columns.clone(),
+            rows: rows
+                .iter()
+                .map(|row| row_to_json_values(row, false, self.money_scale()))
+                .collect(),
+            row_count,
+            exhausted: row_count < batch_size as usize,
+        })
+    }
+
+    /// Closes the open cursor and ends its transaction. Does nothing if no cursor is open
+    pub async fn close_cursor(&self) -> Result<(), PostgresError> {
+        let Some(mut session) = self.cursor.lock().await.take() else {
+            return Ok(());
+        };
+        session.watchdog.abort();
+
+        sqlx::query("ROLLBACK")
+            .execute(&mut *session.conn)
+            .await
+            .map(|_| ())
+            .map_err(query_error)
+    }
+
+    /// Registers a background polling task, aborting any existing task for the same key
+    pub async fn register_poll_task(&self, key: &str, handle: JoinHandle<()>) {
+        if let Some(previous) = self
+            .polling_tasks
+            .lock()
+            .await
+            .insert(key.to_string(), handle)
+        {
+            previous.abort();
+        }
+    }
+
+    /// Stops a polling task and drops its cached result. Returns false if none was running
+    pub async fn stop_poll_task(&self, key: &str) -> bool {
+        self.poll_results.write().await.remove(key);
+        match self.polling_tasks.lock().await.remove(key) {
+            Some(handle) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stops every polling task, e.g. when the connection goes away
+    pub async fn stop_all_polling(&self) {
+        for (_, handle) in self.polling_tasks.lock().await.drain() {
+            handle.abort();
+        }
+        self.poll_results.write().await.clear();
+    }
+
+    /// Stores the latest result produced by a polling task
+    pub async fn cache_poll_result(&self, key: &str, result: QueryResult) {
+        self.poll_results
+            .write()
+            .await
+            .insert(key.to_string(), result);
+    }
+
+    /// Gets the latest cached result of a polling task
+    pub async fn get_poll_result(&self, key: &str) -> Option<QueryResult> {
+        self.poll_results.read().await.get(key).cloned()
+    }
+
+    /// Registers a running export and returns the flag that signals its cancellation
+    pub async fn begin_export(&self, key: &str) -> Arc<AtomicBool> {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.exports
+            .lock()
+            .await
+            .insert(key.to_string(), cancelled.clone());
+        cancelled
+    }
+
+    /// Requests cancellation of a running export. Returns false if none was running
+    pub async fn cancel_export(&self, key: &str) -> bool {
+        match self.exports.lock().await.get(key) {
+            Some(cancelled) => {
+                cancelled.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forgets a finished export
+    pub async fn end_export(&self, key: &str) {
+        self.exports.lock().await.remove(key);
+    }
+
+    /// Gets the current connection ID
+    pub async fn get_connection_id(&self) -> Option<String> {
+        self.connection_id.read().await.clone()
+    }
+
+    /// Tests if the connection is still valid
+    pub async fn test_connection(&self) -> Result<bool, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        sqlx::query("SELECT 1")
+            .fetch_one(pool)
+            .await
+            .map(|_| true)
+            .map_err(query_error)
+    }
+
+    /// Executes a raw SQL query and returns results as JSON.
+    /// Runs inside the open transaction when there is one.
+    pub async fn execute_query(&self, sql: &str) -> Result<QueryResult, PostgresError> {
+        self.execute_query_tracked(sql, None, None, false).await
+    }
+
+    /// Executes a raw SQL query, registering it under `query_id` (when given) so that
+    /// it can be aborted with cancel_query while it runs. With `timeout_ms` the server
+    /// aborts the query once it runs longer than that, failing with QueryTimeout.
+    /// With auto-reconnect on, a read-only query outside a transaction that fails with
+    /// ConnectionLost is retried once on a fresh pool. Anything else is not, as the server
+    /// may have committed it before the connection dropped. With `distinguish_nulls`,
+    /// NULL cells come back as record::null_cell's sentinel
+    pub async fn execute_query_tracked(
+        &self,
+        sql: &str,
+        query_id: Option<&str>,
+        timeout_ms: Option<u64>,
+        distinguish_nulls: bool,
+    ) -> Result<QueryResult, PostgresError> {
+        let _change = self.check_read_only(sql).await?;
+
+        let started = Instant::now();
+        let (result, captured) = notices::capture(async {
+            match self
+                .execute_query_once(sql, query_id, timeout_ms, distinguish_nulls)
+                .await
+            {
+                Err(PostgresError::ConnectionLost(_))
+                    if self.auto_reconnect()
+                        && script::is_read_only_query(sql)
+                        && self.transaction.lock().await.is_none() =>
+                {
+                    self.reconnect().await?;
+                    self.execute_query_once(sql, query_id, timeout_ms, distinguish_nulls)
+                        .await
+                }
+                result => result,
+            }
+        })
+        .await;
+
+        let duration_ms = started.elapsed().as_millis() as u64;
+
+        let mut result = result?;
+        self.describe_column_origins(&mut result.columns).await;
+        Ok(QueryResult {
+            notices: captured,
+            duration_ms,
+            ..result
+        })
+    }
+
+    async fn execute_query_once(
+        &self,
+        sql: &str,
+        query_id: Option<&str>,
+        timeout_ms: Option<u64>,
+        distinguish_nulls: bool,
+    ) -> Result<QueryResult, PostgresError> {
+        if let Some(session) = self.lock_transaction().await.as_mut() {
+            let (rows, rows_affected) = self
+                .fetch_all_tracked(&mut session.conn, sql, query_id, timeout_ms)
+                .await?;
+            // Only the transaction's session keeps a SET lc_monetary for what runs next
+            if sql.to_ascii_lowercase().contains("lc_monetary") {
+                let scale = read_money_scale(&mut session.conn).await;
+                self.money_scale.store(scale, Ordering::Relaxed);
+            }
+            return Ok(rows_to_query_result(
+                rows,
+                rows_affected,
+                script::modifies_rows(sql),
+                distinguish_nulls,
+                self.money_scale(),
+            ));
+        }
+
+        if query_id.is_none() && timeout_ms.is_none() {
+            return self
+                .execute_query_on_pool(sql, &[], distinguish_nulls)
+                .await;
+        }
+
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+        let mut conn = pool.acquire().await.map_err(query_error)?;
+
+        let (rows, rows_affected) = self
+            .fetch_all_tracked(&mut conn, sql, query_id, timeout_ms)
+            .await?;
+        Ok(rows_to_query_result(
+            rows,
+            rows_affected,
+            script::modifies_rows(sql),
+            distinguish_nulls,
+            self.money_scale(),
+        ))
+    }
+
+    async fn fetch_all_tracked(
+        &self,
+        conn: &mut PgConnection,
+        sql: &str,
+        query_id: Option<&str>,
+        timeout_ms: Option<u64>,
+    ) -> Result<(Vec<PgRow>, u64), PostgresError> {
+        let _running = self.track_query(conn, query_id).await?;
+        set_statement_timeout(conn, timeout_ms).await?;
+
+        let rows = fetch_with_rows_affected(&mut *conn, sqlx::query(sql))
+            .await
+            .map_err(query_error);
+
+        reset_statement_timeout(conn, timeout_ms).await;
+        rows
+    }
+
+    /// Executes one or more statements and returns a QueryResult per result set, in the
+    /// order the server sent them. Tracking, the timeout and the open transaction work
+    /// as in execute_query_tracked, but a lost connection is not retried since part of
+    /// the batch may already have run. The statements go over the simple query protocol,
+    /// in which arrays and row values come back as Postgres' text rendering. Every result
+    /// carries the duration of the whole batch, and the last one its notices. Each result
+    /// set keeps at most `max_rows` rows. `distinguish_nulls` works as in
+    /// execute_query_tracked
+    pub async fn execute_query_multi(
+        &self,
+        sql: &str,
+        query_id: Option<&str>,
+        timeout_ms: Option<u64>,
+        max_rows: Option<u64>,
+        distinguish_nulls: bool,
+    ) -> Result<Vec<QueryResult>, PostgresError> {
+        let _change = self.check_read_only(sql).await?;
+
+        let started = Instant::now();
+        let (result, captured) = notices::capture(self.execute_query_multi_once(
+            sql,
+            query_id,
+            timeout_ms,
+            max_rows,
+            distinguish_nulls,
+        ))
+        .await;
+        let duration_ms = started.elapsed().as_millis() as u64;
+
+        let mut results = result?;
+        for result in &mut results {
+            self.describe_column_origins(&mut result.columns).await;
+            result.duration_ms = duration_ms;
+        }
+        if let Some(last) = results.last_mut() {
+            last.notices = captured;
+        }
+        Ok(results)
+    }
+
+    async fn execute_query_multi_once(
+        &self,
+        sql: &str,
+        query_id: Option<&str>,
+        timeout_ms: Option<u64>,
+        max_rows: Option<u64>,
+        distinguish_nulls: bool,
+    ) -> Result<Vec<QueryResult>, PostgresError> {
+        let result_sets = if let Some(session) = self.lock_transaction().await.as_mut() {
+            self.fetch_result_sets_tracked(&mut session.conn, sql, query_id, timeout_ms, max_rows)
+                .await?
+        } else {
+            let pool = self.pool.read().await;
+            let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+            let mut conn = pool.acquire().await.map_err(query_error)?;
+            self.fetch_result_sets_tracked(&mut conn, sql, query_id, timeout_ms, max_rows)
+                .await?
+        };
+
+        Ok(batch::into_query_results(
+            sql,
+            result_sets,
+            |rows, rows_affected, modifies_rows| {
+                rows_to_query_result(
+                    rows,
+                    rows_affected,
+                    modifies_rows,
+                    distinguish_nulls,
+                    self.money_scale(),
+                )
+            },
+        ))
+    }
+
+    async fn fetch_result_sets_tracked(
+        &self,
+        conn: &mut PgConnection,
+        sql: &str,
+        query_id: Option<&str>,
+        timeout_ms: Option<u64>,
+        max_rows: Option<u64>,
+    ) -> Result<Vec<ResultSet<PgRow>>, PostgresError> {
+        let _running = self.track_query(conn, query_id).await?;
+        set_statement_timeout(conn, timeout_ms).await?;
+
+        let result_sets = batch::fetch_result_sets(&mut *conn, sql, max_rows)
+            .await
+            .map_err(query_error);
+
+        reset_statement_timeout(conn, timeout_ms).await;
+        result_sets
+    }
+
+    /// Registers the backend running on `conn` under `query_id` for cancel_query.
+    /// The registration lasts as long as the returned guard
+    async fn track_query(
+        &self,
+        conn: &mut PgConnection,
+        query_id: Option<&str>,
+    ) -> Result<Option<RunningQuery>, PostgresError> {
+        let Some(query_id) = query_id else {
+            return Ok(None);
+        };
+
+        let (pid,): (i32,) = sqlx::query_as("SELECT pg_backend_pid()")
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(query_error)?;
+        Ok(Some(RunningQuery::register(
+            self.running_queries.clone(),
+            query_id,
+            pid,
+        )))
+    }
+
+    /// Executes a raw SQL query without buffering the whole result, handing rows to
+    /// `on_chunk` in batches of `batch_size`. Runs inside the open transaction when there
+    /// is one and can be aborted with cancel_query. Returns the total number of rows
+    pub async fn execute_query_stream<F>(
+        &self,
+        sql: &str,
+        query_id: Option<&str>,
+        batch_size: usize,
+        mut on_chunk: F,
+    ) -> Result<usize, PostgresError>
+    where
+        F: FnMut(&[ColumnMeta], Vec<Vec<JsonValue>>) -> Result<(), String>,
+    {
+        let _change = self.check_read_only(sql).await?;
+
+        if let Some(session) = self.lock_transaction().await.as_mut() {
+            return self
+                .stream_chunks(&mut session.conn, sql, query_id, batch_size, &mut on_chunk)
+                .await;
+        }
+
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+        let mut conn = pool
+            .acquire()
+            .await
+            .map_err(|e| PostgresError::ConnectionFailed(e.to_string()))?;
+
+        self.stream_chunks(&mut conn, sql, query_id, batch_size, &mut on_chunk)
+            .await
+    }
+
+    async fn stream_chunks<F>(
+        &self,
+        conn: &mut PgConnection,
+        sql: &str,
+        query_id: Option<&str>,
+        batch_size: usize,
+        on_chunk: &mut F,
+    ) -> Result<usize, PostgresError>
+    where
+        F: FnMut(&[ColumnMeta], Vec<Vec<JsonValue>>) -> Result<(), String>,
+    {
+        let _running = self.track_query(conn, query_id).await?;
+
+        let mut stream = sqlx::query(sql).fetch(&mut *conn);
+        let mut columns: Vec<ColumnMeta> = vec![];
+        let mut chunk = Vec::with_capacity(batch_size);
+        let mut row_count = 0;
+
+        while let Some(row) = stream.try_next().await.map_err(query_error)? {
+            if columns.is_empty() {
+                columns = column_meta(&row);
+            }
+            chunk.push(row_to_json_values(&row, false, self.money_scale()));
+            row_count += 1;
+
+            if chunk.len() >= batch_size {
+                let rows = std::mem::replace(&mut chunk, Vec::with_capacity(batch_size));
+                on_chunk(&columns, rows).map_err(PostgresError::EncodingFailed)?;
+            }
+        }
+
+        if !chunk.is_empty() {
+            on_chunk(&columns, chunk).map_err(PostgresError::EncodingFailed)?;
+        }
+
+        Ok(row_count)
+    }
+
+    /// Cancels an in-flight query via pg_cancel_backend. Returns false if no query
+    /// with that id is running
+    pub async fn cancel_query(&self, query_id: &str) -> Result<bool, PostgresError> {
+        let pid = self
+            .running_queries
+            .lock()
+            .ok()
+            .and_then(|running| running.get(query_id).copied());
+        let Some(pid) = pid else {
+            return Ok(false);
+        };
+
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let (cancelled,): (bool,) = sqlx::query_as("SELECT pg_cancel_backend($1)")
+            .bind(pid)
+            .fetch_one(pool)
+            .await
+            .map_err(query_error)?;
+
+        Ok(cancelled)
+    }
+
+    /// Cancels every in-flight query via pg_cancel_backend. Returns how many backends
+    /// the server signalled
+    pub async fn cancel_all_queries(&self) -> Result<usize, PostgresError> {
+        let pids: Vec<i32> = self
+            .running_queries
+            .lock()
+            .map(|running| running.values().copied().collect())
+            .unwrap_or_default();
+        if pids.is_empty() {
+            return Ok(0);
+        }
+
+        // The queries to cancel may hold every pooled connection, so the cancel would
+        // wait for them to finish
+        let connect_options = self
+            .pool
+            .read()
+            .await
+            .as_ref()
+            .ok_or(PostgresError::NoActiveConnection)?
+            .connect_options();
+        let mut conn = PgConnection::connect_with(&connect_options)
+            .await
+            .map_err(query_error)?;
+
+        let (cancelled,): (i64,) = sqlx::query_as(
+            "SELECT count(*) FILTER (WHERE pg_cancel_backend(pid)) FROM unnest($1::int4[]) AS pid",
+        )
+        .bind(&pids)
+        .fetch_one(&mut conn)
+        .await
+        .map_err(query_error)?;
+        conn.close().await.ok();
+
+        Ok(cancelled as usize)
+    }
+
+    /// Executes a raw SQL query on the pool, bypassing any open transaction. `params` are
+    /// bound to its `$1`, `$2`, ... placeholders as in query_with_params
+    pub async fn execute_query_on_pool(
+        &self,
+        sql: &str,
+        params: &[JsonValue],
+        distinguish_nulls: bool,
+    ) -> Result<QueryResult, PostgresError> {
+        let _change = self.check_read_only(sql).await?;
+
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let query = params.iter().fold(sqlx::query(sql), bind_json);
+        let started = Instant::now();
+        let (rows, rows_affected) = fetch_with_rows_affected(pool, query)
+            .await
+            .map_err(query_error)?;
+        let duration_ms = started.elapsed().as_millis() as u64;
+
+        Ok(QueryResult {
+            duration_ms,
+            ..rows_to_query_result(
+                rows,
+                rows_affected,
+                script::modifies_rows(sql),
+                distinguish_nulls,
+                self.money_scale(),
+            )
+        })
+    }
+
+    /// Executes a query and encodes the result as an Arrow IPC stream,
+    /// streaming rows from the server and writing `batch_size` rows per record batch
+    pub async fn execute_query_arrow(
+        &self,
+        sql: &str,
+        batch_size: usize,
+    ) -> Result<Vec<u8>, PostgresError> {
+        let _change = self.check_read_only(sql).await?;
+
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let encoding_error =
+            |e: arrow_schema::ArrowError| PostgresError::EncodingFailed(e.to_string());
+
+        let mut stream = sqlx::query(sql).fetch(pool);
+
+        let first = match stream.try_next().await.map_err(query_error)? {
+            Some(row) => row,
+            None => {
+                return IpcStreamEncoder::new(&[])
+                    .and_then(|encoder| encoder.finish())
+                    .map_err(encoding_error)
+            }
+        };
+
+        let mut encoder = IpcStreamEncoder::new(&column_meta(&first)).map_err(encoding_error)?;
+        let mut batch = Vec::with_capacity(batch_size);
+        batch.push(row_to_json_values(&first, false, self.money_scale()));
+
+        while let Some(row) = stream.try_next().await.map_err(query_error)? {
+            batch.push(row_to_json_values(&row, false, self.money_scale()));
+            if batch.len() >= batch_size {
+                encoder.write_rows(&batch).map_err(encoding_error)?;
+                batch.clear();
+            }
+        }
+
+        if !batch.is_empty() {
+            encoder.write_rows(&batch).map_err(encoding_error)?;
+        }
+
+        encoder.finish().map_err(encoding_error)
+    }
+
+    /// Streams a query's rows one at a time into `on_row` without buffering the result set.
+    /// Stops with `Cancelled` as soon as the `cancelled` flag is raised.
+    pub async fn stream_query<F>(
+        &self,
+        sql: &str,
+        cancelled: &AtomicBool,
+        mut on_row: F,
+    ) -> Result<(), PostgresError>
+    where
+        F: FnMut(&[ColumnMeta], &[JsonValue]) -> Result<(), String>,
+    {
+        let _change = self.check_read_only(sql).await?;
+
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let mut stream = sqlx::query(sql).fetch(pool);
+        let mut columns: Option<Vec<ColumnMeta>> = None;
+
+        while let Some(row) = stream.try_next().await.map_err(query_error)? {
+            if cancelled.load(Ordering::Relaxed) {
+                return Err(PostgresError::Cancelled);
+            }
+
+            let columns = columns.get_or_insert_with(|| column_meta(&row));
+            on_row(
+                columns,
+                &row_to_json_values(&row, false, self.money_scale()),
+            )
+            .map_err(PostgresError::EncodingFailed)?;
+        }
+
+        Ok(())
+    }
+
+    /// Fetches all tables in the database
+    pub async fn fetch_tables(
+        &self,
+        filter: &TableFilter,
+    ) -> Result<Vec<TableInfo>, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let order_by = match filter.order {
+            TableOrder::Name => "t.table_schema, t.table_name",
+            TableOrder::EstimatedRows => {
+                "estimated_rows DESC NULLS LAST, t.table_schema, t.table_name"
+            }
+        };
+        // reltuples is -1 until the table is first analyzed (0 before PG 14)
+        let sql = format!(
+            r#"
+            SELECT
+                t.table_schema,
+                t.table_name,
+                t.table_type,
+                CASE WHEN c.relkind IN ('r', 'm') AND c.reltuples >= 0
+                    THEN c.reltuples::bigint
+                END AS estimated_rows
+            FROM (
+                SELECT table_schema::text, table_name::text, table_type::text
+                FROM information_schema.tables
+                UNION ALL
+                SELECT schemaname::text, matviewname::text, 'MATERIALIZED VIEW'
+                FROM pg_matviews
+            ) t
+            LEFT JOIN pg_namespace n ON n.nspname = t.table_schema
+            LEFT JOIN pg_class c ON c.relnamespace = n.oid AND c.relname = t.table_name
+            WHERE t.table_schema NOT IN ('pg_catalog', 'information_schema')
+              AND ($1::text IS NULL OR t.table_schema = $1)
+              AND ($2::text IS NULL OR t.table_name ILIKE $2)
+            ORDER BY {}
+            LIMIT $3 OFFSET $4
+            "#,
+            order_by
+        );
+
+        let tables: Vec<TableInfo> =
+            sqlx::query_as::<_, (String, String, String, Option<i64>)>(&sql)
+                .bind(filter.schema.as_deref())
+                .bind(filter.name_pattern.as_deref())
+                .bind(filter.limit)
+                .bind(filter.offset.unwrap_or(0))
+                .fetch_all(pool)
+                .await
+                .map_err(query_error)?
+                .into_iter()
+                .map(|(schema, name, table_type, estimated_rows)| TableInfo {
+                    schema,
+                    name,
+                    table_type,
+                    estimated_rows,
+                })
+                .collect();
+
+        Ok(tables)
+    }
+
+    /// Gets the definition of a view or materialized view, or None if there is no such view.
+    /// `last_refreshed` is left for the caller, which keeps track of refreshes
+    pub async fn fetch_view_definition(
+        &self,
+        schema: &str,
+        name: &str,
+    ) -> Result<Option<ViewDefinition>, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let row = sqlx::query_as::<_, (String, bool, String, bool)>(
+            r#"
+            SELECT current_database()::text, c.relkind = 'm', pg_get_viewdef(c.oid, true),
+                   c.relispopulated
+            FROM pg_class c
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            WHERE n.nspname = $1 AND c.relname = $2 AND c.relkind IN ('v', 'm')
+            "#,
+        )
+        .bind(schema)
+        .bind(name)
+        .fetch_optional(pool)
+        .await
+        .map_err(query_error)?;
+
+        Ok(row.map(
+            |(database, materialized, definition, is_populated)| ViewDefinition {
+                database,
+                schema: schema.to_string(),
+                name: name.to_string(),
+                table_type: if materialized {
+                    "MATERIALIZED VIEW"
+                } else {
+                    "VIEW"
+                }
+                .to_string(),
+                definition,
+                is_populated: materialized.then_some(is_populated),
+                last_refreshed: None,
+            },
+        ))
+    }
+
+    /// Gets the schema catalog of the current connection, reading it from the server only
+    /// on first use or when `refresh` is set. The cache outlives disconnecting, so switching
+    /// back to a connection is instant; execute_ddl drops it
+    pub async fn fetch_schema_catalog(
+        &self,
+        refresh: bool,
+    ) -> Result<SchemaCatalog, PostgresError> {
+        let connection_id = self
+            .get_connection_id()
+            .await
+            .ok_or(PostgresError::NoActiveConnection)?;
+
+        if !refresh {
+            if let Some(catalog) = self.catalogs.read().await.get(&connection_id) {
+                return Ok(catalog.clone());
+            }
+        }
+
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+        let catalog = catalog::fetch_catalog(pool).await?;
+
+        self.catalogs
+            .write()
+            .await
+            .insert(connection_id, catalog.clone());
+        Ok(catalog)
+    }
+
+    /// Fetches columns for a specific table
+    pub async fn fetch_columns(
+        &self,
+        schema: &str,
+        table: &str,
+    ) -> Result<Vec<ColumnInfo>, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        // ordinal_position is the column's attnum
+        let columns: Vec<ColumnInfo> = sqlx::query_as::<
+            _,
+            (
+                String,
+                String,
+                String,
+                Option<String>,
+                Option<String>,
+                String,
+                String,
+                bool,
+            ),
+        >(
+            r#"
+                SELECT
+                    c.column_name,
+                    c.data_type,
+                    c.is_nullable,
+                    c.column_default,
+                    col_description(
+                        (quote_ident(c.table_schema) || '.' || quote_ident(c.table_name))::regclass,
+                        c.ordinal_position::int
+                    ),
+                    c.udt_schema,
+                    c.udt_name,
+                    EXISTS (
+                        SELECT 1
+                        FROM pg_type t
+                        JOIN pg_namespace n ON n.oid = t.typnamespace
+                        WHERE n.nspname = c.udt_schema
+                          AND t.typname = c.udt_name
+                          AND t.typtype = 'e'
+                    )
+                FROM information_schema.columns c
+                WHERE c.table_schema = $1 AND c.table_name = $2
+                ORDER BY c.ordinal_position
+                "#,
+        )
+        .bind(schema)
+        .bind(table)
+        .fetch_all(pool)
+        .await
+        .map_err(query_error)?
+        .into_iter()
+        .map(
+            |(
+                name,
+                data_type,
+                is_nullable,
+                column_default,
+                comment,
+                udt_schema,
+                udt_name,
+                is_enum,
+            )| ColumnInfo {
+                name,
+                data_type,
+                is_nullable: is_nullable == "YES",
+                column_default,
+                is_primary_key: false, // Will be updated below
+                is_unique_key: false,
+                comment,
+                udt_schema: Some(udt_schema),
+                udt_name: Some(udt_name),
+                is_enum,
+            },
+        )
+        .collect();
+
+        // Fetch the primary key, or a unique index standing in for one
+        let row_key = detect_row_key(pool, schema, table).await?;
+
+        // Update is_primary_key / is_unique_key fields
+        let columns: Vec<ColumnInfo> = columns
+            .into_iter()
+            .map(|mut col| {
+                let is_key = row_key.columns.contains(&col.name);
+                col.is_primary_key = is_key && row_key.strategy == RowKeyStrategy::PrimaryKey;
+                col.is_unique_key = is_key && row_key.strategy == RowKeyStrategy::UniqueIndex;
+                col
+            })
+            .collect();
+
+        Ok(columns)
+    }
+
+    /// Lists the values of an enum type in their sort order. Without a schema the type is
+    /// looked up on the search_path. None means there is no such enum
+    pub async fn fetch_enum_values(
+        &self,
+        schema: Option<&str>,
+        type_name: &str,
+    ) -> Result<Option<Vec<String>>, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let values: Option<(Vec<String>,)> = sqlx::query_as(
+            r#"
+            SELECT COALESCE(
+                array_agg(e.enumlabel::text ORDER BY e.enumsortorder)
+                    FILTER (WHERE e.enumlabel IS NOT NULL),
+                '{}'
+            )
+            FROM pg_type t
+            JOIN pg_namespace n ON n.oid = t.typnamespace
+            LEFT JOIN pg_enum e ON e.enumtypid = t.oid
+            WHERE t.typname = $2
+              AND t.typtype = 'e'
+              AND (n.nspname = $1 OR ($1::text IS NULL AND pg_type_is_visible(t.oid)))
+            GROUP BY t.oid
+            "#,
+        )
+        .bind(schema)
+        .bind(type_name)
+        .fetch_optional(pool)
+        .await
+        .map_err(query_error)?;
+
+        Ok(values.map(|(values,)| values))
+    }
+
+    /// Gets the comment set with COMMENT ON for a table or view. The outer None means there
+    /// is no such relation
+    pub async fn fetch_table_comment(
+        &self,
+        schema: &str,
+        table: &str,
+    ) -> Result<Option<Option<String>>, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        sqlx::query_scalar::<_, Option<String>>(
+            r#"
+            SELECT obj_description(c.oid, 'pg_class')
+            FROM pg_class c
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            WHERE n.nspname = $1 AND c.relname = $2
+            "#,
+        )
+        .bind(schema)
+        .bind(table)
+        .fetch_optional(pool)
+        .await
+        .map_err(query_error)
+    }
+
+    /// Detects the columns that identify a row: the primary key, falling back to a
+    /// single-column unique index over a NOT NULL column when no primary key is declared
+    pub async fn detect_row_key(&self, schema: &str, table: &str) -> Result<RowKey, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        detect_row_key(pool, schema, table).await
+    }
+
+    /// Fetches the indexes of a table, the primary key's first
+    pub async fn fetch_indexes(
+        &self,
+        schema: &str,
+        table: &str,
+    ) -> Result<Vec<IndexInfo>, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let indexes = sqlx::query_as::<_, (String, Vec<String>, bool, bool, String, String)>(
+            r#"
+            SELECT
+                ic.relname,
+                ARRAY(
+                    SELECT pg_get_indexdef(i.indexrelid, k, true)
+                    FROM generate_series(1, i.indnkeyatts) AS k
+                    ORDER BY k
+                ),
+                i.indisunique,
+                i.indisprimary,
+                am.amname,
+                pg_get_indexdef(i.indexrelid)
+            FROM pg_index i
+            JOIN pg_class c ON c.oid = i.indrelid
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            JOIN pg_class ic ON ic.oid = i.indexrelid
+            JOIN pg_am am ON am.oid = ic.relam
+            WHERE n.nspname = $1 AND c.relname = $2
+            ORDER BY i.indisprimary DESC, ic.relname
+            "#,
+        )
+        .bind(schema)
+        .bind(table)
+        .fetch_all(pool)
+        .await
+        .map_err(query_error)?
+        .into_iter()
+        .map(
+            |(name, columns, is_unique, is_primary, method, definition)| IndexInfo {
+                name,
+                columns,
+                is_unique,
+                is_primary,
+                method,
+                definition,
+            },
+        )
+        .collect();
+
+        Ok(indexes)
+    }
+
+    /// Fetches the constraints of a table: keys, foreign keys, checks and exclusions
+    pub async fn fetch_constraints(
+        &self,
+        schema: &str,
+        table: &str,
+    ) -> Result<Vec<ConstraintInfo>, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let constraints = sqlx::query_as::<
+            _,
+            (
+                String,
+                String,
+                Vec<String>,
+                String,
+                Option<String>,
+                Vec<String>,
+            ),
+        >(
+            r#"
+            SELECT
+                con.conname,
+                con.contype::text,
+                ARRAY(
+                    SELECT a.attname::text
+                    FROM unnest(con.conkey) WITH ORDINALITY AS k(attnum, ord)
+                    JOIN pg_attribute a ON a.attrelid = con.conrelid AND a.attnum = k.attnum
+                    ORDER BY k.ord
+                ),
+                pg_get_constraintdef(con.oid, true),
+                CASE WHEN con.confrelid <> 0
+                    THEN format('%s.%s', fn.nspname, fc.relname)
+                END,
+                ARRAY(
+                    SELECT a.attname::text
+                    FROM unnest(con.confkey) WITH ORDINALITY AS k(attnum, ord)
+                    JOIN pg_attribute a ON a.attrelid = con.confrelid AND a.attnum = k.attnum
+                    ORDER BY k.ord
+                )
+            FROM pg_constraint con
+            JOIN pg_class c ON c.oid = con.conrelid
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            LEFT JOIN pg_class fc ON fc.oid = con.confrelid
+            LEFT JOIN pg_namespace fn ON fn.oid = fc.relnamespace
+            WHERE n.nspname = $1 AND c.relname = $2
+            ORDER BY con.contype, con.conname
+            "#,
+        )
+        .bind(schema)
+        .bind(table)
+        .fetch_all(pool)
+        .await
+        .map_err(query_error)?
+        .into_iter()
+        .map(
+            |(name, contype, columns, definition, referenced_table, referenced_columns)| {
+                ConstraintInfo {
+                    name,
+                    constraint_type: constraint_type(&contype).to_string(),
+                    columns,
+                    definition,
+                    referenced_table,
+                    referenced_columns,
+                }
+            },
+        )
+        .collect();
+
+        Ok(constraints)
+    }
+
+    /// Fetches the columns of a table with everything needed to declare them again
+    pub async fn fetch_column_definitions(
+        &self,
+        schema: &str,
+        table: &str,
+    ) -> Result<Vec<ColumnDefinition>, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let columns = sqlx::query_as::<_, ColumnDefinitionRow>(
+            r#"
+            SELECT
+                a.attname::text,
+                format_type(a.atttypid, a.atttypmod),
+                a.attnotnull,
+                CASE WHEN a.attgenerated = '' THEN pg_get_expr(d.adbin, d.adrelid) END,
+                CASE a.attidentity WHEN 'a' THEN 'ALWAYS' WHEN 'd' THEN 'BY DEFAULT' END,
+                CASE WHEN a.attgenerated = 's' THEN pg_get_expr(d.adbin, d.adrelid) END,
+                a.attidentity = ''
+                    AND pg_get_serial_sequence(format('%I.%I', n.nspname, c.relname), a.attname)
+                        IS NOT NULL
+            FROM pg_attribute a
+            JOIN pg_class c ON c.oid = a.attrelid
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            LEFT JOIN pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum
+            WHERE n.nspname = $1 AND c.relname = $2 AND a.attnum > 0 AND NOT a.attisdropped
+            ORDER BY a.attnum
+            "#,
+        )
+        .bind(schema)
+        .bind(table)
+        .fetch_all(pool)
+        .await
+        .map_err(query_error)?
+        .into_iter()
+        .map(
+            |(name, data_type, not_null, default, identity, generated, is_serial)| {
+                ColumnDefinition {
+                    name,
+                    data_type,
+                    not_null,
+                    default,
+                    identity,
+                    generated,
+                    is_serial,
+                }
+            },
+        )
+        .collect();
+
+        Ok(columns)
+    }
+
+    /// Fetches the sequences of a schema, leaving out those owned by a serial or identity
+    /// column, which come back with their column
+    pub async fn fetch_sequences(
+        &self,
+        schema: &str,
+    ) -> Result<Vec<SequenceDefinition>, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let sequences = sqlx::query_as::<_, (String, String, i64, i64, i64, i64, i64, bool)>(
+            r#"
+            SELECT s.sequencename::text, s.data_type::text, s.start_value, s.min_value,
+                   s.max_value, s.increment_by, s.cache_size, s.cycle
+            FROM pg_sequences s
+            JOIN pg_namespace n ON n.nspname = s.schemaname
+            JOIN pg_class c ON c.relnamespace = n.oid AND c.relname = s.sequencename
+            WHERE s.schemaname = $1
+              AND NOT EXISTS (
+                  SELECT 1 FROM pg_depend d
+                  WHERE d.classid = 'pg_class'::regclass AND d.objid = c.oid
+                    AND d.refclassid = 'pg_class'::regclass AND d.deptype IN ('a', 'i')
+              )
+            ORDER BY s.sequencename
+            "#,
+        )
+        .bind(schema)
+        .fetch_all(pool)
+        .await
+        .map_err(query_error)?
+        .into_iter()
+        .map(
+            |(
+                name,
+                data_type,
+                start_value,
+                min_value,
+                max_value,
+                increment_by,
+                cache_size,
+                cycle,
+            )| {
+                SequenceDefinition {
+                    name,
+                    data_type,
+                    start_value,
+                    min_value,
+                    max_value,
+                    increment_by,
+                    cache_size,
+                    cycle,
+                }
+            },
+        )
+        .collect();
+
+        Ok(sequences)
+    }
+
+    /// Fetches the enum, domain and composite types of a schema. Types that belong to an
+    /// extension, and the row types of tables, are left out
+    pub async fn fetch_type_definitions(
+        &self,
+        schema: &str,
+    ) -> Result<Vec<TypeDefinition>, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let types = sqlx::query_as::<_, (String, String, String, Vec<String>)>(
+            r#"
+            SELECT
+                t.typname::text,
+                CASE t.typtype WHEN 'e' THEN 'enum' WHEN 'd' THEN 'domain' ELSE 'composite' END,
+                CASE t.typtype
+                    WHEN 'e' THEN '(' || COALESCE((
+                        SELECT string_agg(quote_literal(e.enumlabel), ', ' ORDER BY e.enumsortorder)
+                        FROM pg_enum e
+                        WHERE e.enumtypid = t.oid
+                    ), '') || ')'
+                    WHEN 'd' THEN format_type(t.typbasetype, t.typtypmod)
+                        || COALESCE(' DEFAULT ' || t.typdefault, '')
+                        || CASE WHEN t.typnotnull THEN ' NOT NULL' ELSE '' END
+                        || COALESCE((
+                            SELECT string_agg(
+                                ' CONSTRAINT ' || quote_ident(con.conname) || ' '
+                                    || pg_get_constraintdef(con.oid, true),
+                                '' ORDER BY con.conname
+                            )
+                            FROM pg_constraint con
+                            WHERE con.contypid = t.oid
+                        ), '')
+                    ELSE '(' || COALESCE((
+                        SELECT string_agg(
+                            quote_ident(a.attname) || ' ' || format_type(a.atttypid, a.atttypmod),
+                            ', ' ORDER BY a.attnum
+                        )
+                        FROM pg_attribute a
+                        WHERE a.attrelid = t.typrelid AND a.attnum > 0 AND NOT a.attisdropped
+                    ), '') || ')'
+                END,
+                ARRAY(
+                    SELECT DISTINCT d.typname::text
+                    FROM pg_type r
+                    JOIN pg_type d
+                      ON d.oid = CASE WHEN r.typcategory = 'A' THEN r.typelem ELSE r.oid END
+                    WHERE d.typnamespace = t.typnamespace AND d.oid <> t.oid
+                      AND (r.oid = t.typbasetype OR r.oid IN (
+                          SELECT a.atttypid FROM pg_attribute a
+                          WHERE a.attrelid = t.typrelid AND a.attnum > 0 AND NOT a.attisdropped
+                      ))
+                )
+            FROM pg_type t
+            JOIN pg_namespace n ON n.oid = t.typnamespace
+            LEFT JOIN pg_class c ON c.oid = t.typrelid
+            WHERE n.nspname = $1
+              AND (t.typtype IN ('e', 'd') OR (t.typtype = 'c' AND c.relkind = 'c'))
+              AND NOT EXISTS (
+                  SELECT 1 FROM pg_depend d
+                  WHERE d.classid = 'pg_type'::regclass AND d.objid = t.oid AND d.deptype = 'e'
+              )
+            ORDER BY t.typname
+            "#,
+        )
+        .bind(schema)
+        .fetch_all(pool)
+        .await
+        .map_err(query_error)?
+        .into_iter()
+        .map(|(name, kind, definition, depends_on)| TypeDefinition {
+            name,
+            kind,
+            definition,
+            depends_on,
+        })
+        .collect();
+
+        Ok(types)
+    }
+
+    /// Fetches the partitioned tables and partitions of a schema
+    pub async fn fetch_partitions(
+        &self,
+        schema: &str,
+    ) -> Result<Vec<PartitionInfo>, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let partitions = sqlx::query_as::<
+            _,
+            (
+                String,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+            ),
+        >(
+            r#"
+            SELECT
+                c.relname::text,
+                CASE WHEN c.relkind = 'p' THEN pg_get_partkeydef(c.oid) END,
+                pn.nspname::text,
+                p.relname::text,
+                CASE WHEN c.relispartition THEN pg_get_expr(c.relpartbound, c.oid) END
+            FROM pg_class c
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            LEFT JOIN pg_inherits i ON i.inhrelid = c.oid AND c.relispartition
+            LEFT JOIN pg_class p ON p.oid = i.inhparent
+            LEFT JOIN pg_namespace pn ON pn.oid = p.relnamespace
+            WHERE n.nspname = $1 AND (c.relkind = 'p' OR c.relispartition)
+            ORDER BY c.relname
             "#,
         )
+        .bind(schema)
         .fetch_all(pool)
         .await
-        .map_err(|e| PostgresError::QueryFailed(e.to_string()))?
+        .map_err(query_error)?
         .into_iter()
-        .map(|(schema, name, table_type)| TableInfo {
-            schema,
-            name,
-            table_type,
+        .map(
+            |(table, partition_key, parent_schema, parent_table, bound)| PartitionInfo {
+                table,
+                partition_key,
+                parent_schema,
+                parent_table,
+                bound,
+            },
+        )
+        .collect();
+
+        Ok(partitions)
+    }
+
+    /// Fetches the CREATE OR REPLACE statements of the functions and procedures in a schema,
+    /// without a trailing semicolon. Functions that belong to an extension are left out
+    pub async fn fetch_function_definitions(
+        &self,
+        schema: &str,
+    ) -> Result<Vec<String>, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let definitions = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT pg_get_functiondef(p.oid)
+            FROM pg_proc p
+            JOIN pg_namespace n ON n.oid = p.pronamespace
+            WHERE n.nspname = $1 AND p.prokind IN ('f', 'p')
+              AND NOT EXISTS (
+                  SELECT 1 FROM pg_depend d
+                  WHERE d.classid = 'pg_proc'::regclass AND d.objid = p.oid AND d.deptype = 'e'
+              )
+            ORDER BY p.proname, p.oid
+            "#,
+        )
+        .bind(schema)
+        .fetch_all(pool)
+        .await
+        .map_err(query_error)?;
+
+        Ok(definitions)
+    }
+
+    /// Fetches (view, dependency) name pairs for the views and materialized views of a
+    /// schema that select from other views of the same schema
+    pub async fn fetch_view_dependencies(
+        &self,
+        schema: &str,
+    ) -> Result<Vec<(String, String)>, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let dependencies = sqlx::query_as::<_, (String, String)>(
+            r#"
+            SELECT DISTINCT v.relname::text, d.relname::text
+            FROM pg_depend dep
+            JOIN pg_rewrite r ON r.oid = dep.objid
+            JOIN pg_class v ON v.oid = r.ev_class
+            JOIN pg_class d ON d.oid = dep.refobjid
+            JOIN pg_namespace n ON n.oid = v.relnamespace
+            WHERE dep.classid = 'pg_rewrite'::regclass
+              AND dep.refclassid = 'pg_class'::regclass
+              AND n.nspname = $1
+              AND d.relnamespace = v.relnamespace
+              AND v.relkind IN ('v', 'm') AND d.relkind IN ('v', 'm')
+              AND d.oid <> v.oid
+            "#,
+        )
+        .bind(schema)
+        .fetch_all(pool)
+        .await
+        .map_err(query_error)?;
+
+        Ok(dependencies)
+    }
+
+    /// Fetches the foreign keys declared by tables in `schema`. With `table`, only those
+    /// that reference it or that it declares, so incoming and outgoing edges both show up
+    pub async fn fetch_foreign_keys(
+        &self,
+        schema: &str,
+        table: Option<&str>,
+    ) -> Result<Vec<ForeignKeyInfo>, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let rows = sqlx::query_as::<_, ForeignKeyRow>(
+            r#"
+            SELECT
+                con.conname,
+                n.nspname,
+                c.relname,
+                ARRAY(
+                    SELECT a.attname::text
+                    FROM unnest(con.conkey) WITH ORDINALITY AS k(attnum, ord)
+                    JOIN pg_attribute a ON a.attrelid = con.conrelid AND a.attnum = k.attnum
+                    ORDER BY k.ord
+                ),
+                fn.nspname,
+                fc.relname,
+                ARRAY(
+                    SELECT a.attname::text
+                    FROM unnest(con.confkey) WITH ORDINALITY AS k(attnum, ord)
+                    JOIN pg_attribute a ON a.attrelid = con.confrelid AND a.attnum = k.attnum
+                    ORDER BY k.ord
+                ),
+                con.confdeltype::text,
+                con.confupdtype::text
+            FROM pg_constraint con
+            JOIN pg_class c ON c.oid = con.conrelid
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            JOIN pg_class fc ON fc.oid = con.confrelid
+            JOIN pg_namespace fn ON fn.oid = fc.relnamespace
+            WHERE con.contype = 'f'
+                AND (
+                    ($2::text IS NULL AND n.nspname = $1)
+                    OR (n.nspname = $1 AND c.relname = $2)
+                    OR (fn.nspname = $1 AND fc.relname = $2)
+                )
+            ORDER BY n.nspname, c.relname, con.conname
+            "#,
+        )
+        .bind(schema)
+        .bind(table)
+        .fetch_all(pool)
+        .await
+        .map_err(query_error)?;
+
+        Ok(rows
+            .into_iter()
+            .map(
+                |(
+                    name,
+                    source_schema,
+                    source_table,
+                    source_columns,
+                    target_schema,
+                    target_table,
+                    target_columns,
+                    on_delete,
+                    on_update,
+                )| ForeignKeyInfo {
+                    name,
+                    source_schema,
+                    source_table,
+                    source_columns,
+                    target_schema,
+                    target_table,
+                    target_columns,
+                    on_delete: foreign_key_action(&on_delete).to_string(),
+                    on_update: foreign_key_action(&on_update).to_string(),
+                },
+            )
+            .collect())
+    }
+
+    /// Fetches the type of every column of a table as `format_type` spells it, length
+    /// modifier included, so it can be used as a cast target that keeps e.g. a char(3)
+    /// value from being cut to char(1)
+    pub async fn fetch_column_types(
+        &self,
+        schema: &str,
+        table: &str,
+    ) -> Result<HashMap<String, String>, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        column_types(pool, schema, table).await
+    }
+
+    /// Fetches paginated table data, ordered by the row key when the table has one.
+    /// With `after` (the row key values of the last row already seen) the page starts
+    /// right after that row instead of at an OFFSET, which stays fast on deep pages;
+    /// without a row key it falls back to `page`. The COUNT(*) is skipped unless
+    /// `with_count` is set. `filters` narrow the rows and count; `order_by` sorts ahead of
+    /// the row key, which then only breaks ties, so paging goes by `page` again.
+    /// `distinguish_nulls` works as in execute_query_tracked
+    #[allow(clippy::too_many_arguments)]
+    pub async fn fetch_table_data(
+        &self,
+        schema: &str,
+        table: &str,
+        page: i32,
+        page_size: i32,
+        after: Option<&[JsonValue]>,
+        with_count: bool,
+        order_by: &[SortColumn],
+        filters: &[ColumnFilter],
+        distinguish_nulls: bool,
+    ) -> Result<PaginatedResult, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let table_ref = format!("{}.{}", quote_ident(schema), quote_ident(table));
+        let key_columns = detect_row_key(pool, schema, table).await?.columns;
+        let keyset = after.filter(|_| !key_columns.is_empty() && order_by.is_empty());
+
+        // Column types double as the list of valid column names
+        let types = if keyset.is_some() || !order_by.is_empty() || !filters.is_empty() {
+            column_types(pool, schema, table).await?
+        } else {
+            HashMap::new()
+        };
+        let cast = |i: usize, column: Option<&str>| {
+            let data_type = column
+                .and_then(|column| types.get(column))
+                .map_or("text", String::as_str);
+            format!("${}::{}", i, data_type)
+        };
+        let column_names: Vec<String> = types.keys().cloned().collect();
+        if let Some(filter) = filters.iter().find(|filter| {
+            !filter.json_path.is_empty()
+                && types
+                    .get(&filter.column)
+                    .is_some_and(|data_type| data_type != "json" && data_type != "jsonb")
+        }) {
+            return Err(PostgresError::InvalidInput(format!(
+                "Column {} is not json or jsonb, so it can't be filtered by a JSON path",
+                filter.column
+            )));
+        }
+
+        let (mut conditions, mut values) =
+            browse::build_filters(filters, &column_names, &PG_DIALECT, cast)
+                .map_err(PostgresError::InvalidInput)?;
+        let filter_count = values.len();
+
+        let total_count = if with_count {
+            let count_sql = format!(
+                "SELECT COUNT(*) FROM {}{}",
+                table_ref,
+                browse::where_clause(&conditions)
+            );
+            let count: i64 = values
+                .iter()
+                .fold(unprepared_query(&count_sql), bind_json)
+                .fetch_one(pool)
+                .await
+                .map_err(query_error)?
+                .get(0);
+            Some(count)
+        } else {
+            None
+        };
+
+        let key_list = key_columns
+            .iter()
+            .map(|c| quote_ident(c))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut order_items = browse::build_order_by(order_by, &column_names, &PG_DIALECT)
+            .map_err(PostgresError::InvalidInput)?;
+        if !key_columns.is_empty() {
+            order_items.push(key_list.clone());
+        }
+        let order_clause = browse::order_by_clause(&order_items);
+
+        let data_sql = match keyset {
+            Some(after) => {
+                if after.len() != key_columns.len() {
+                    return Err(PostgresError::InvalidInput(format!(
+                        "Expected {} key values, got {}",
+                        key_columns.len(),
+                        after.len()
+                    )));
+                }
+
+                let placeholders = key_columns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, column)| cast(filter_count + i + 1, Some(column)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                conditions.push(format!("({}) > ({})", key_list, placeholders));
+                values.extend(after.iter().cloned());
+
+                format!(
+                    "SELECT * FROM {}{}{} LIMIT {}",
+                    table_ref,
+                    browse::where_clause(&conditions),
+                    order_clause,
+                    page_size
+                )
+            }
+            None => format!(
+                "SELECT * FROM {}{}{} LIMIT {} OFFSET {}",
+                table_ref,
+                browse::where_clause(&conditions),
+                order_clause,
+                page_size,
+                (page - 1) * page_size
+            ),
+        };
+
+        let rows: Vec<PgRow> = values
+            .iter()
+            .fold(unprepared_query(&data_sql), bind_json)
+            .fetch_all(pool)
+            .await
+            .map_err(query_error)?;
+
+        if rows.is_empty() {
+            return Ok(PaginatedResult {
+                columns: vec![],
+                rows: vec![],
+                total_count,
+                page,
+                page_size,
+                next_after: None,
+            });
+        }
+
+        let columns = column_meta(&rows[0]);
+
+        let json_rows: Vec<Vec<JsonValue>> = rows
+            .iter()
+            .map(|row| row_to_json_values(row, distinguish_nulls, self.money_scale()))
+            .collect();
+
+        // The key of the last row, never NULL, to pass back as `after` for the next page
+        let next_after = json_rows
+            .last()
+            .filter(|_| !key_columns.is_empty() && order_by.is_empty())
+            .map(|row| {
+                key_columns
+                    .iter()
+                    .filter_map(|key| columns.iter().position(|c| &c.name == key))
+                    .map(|i| row[i].clone())
+                    .collect()
+            });
+
+        Ok(PaginatedResult {
+            columns,
+            rows: json_rows,
+            total_count,
+            page,
+            page_size,
+            next_after,
+        })
+    }
+
+    /// Fetches up to `limit` rows of a table without counting it. Tables estimated at more
+    /// than PREVIEW_SAMPLE_ROWS rows are read with TABLESAMPLE SYSTEM, which is as quick
+    /// and spreads the rows over the whole table instead of its first pages
+    pub async fn preview_table(
+        &self,
+        schema: &str,
+        table: &str,
+        limit: i64,
+    ) -> Result<TablePreview, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        // Only tables and materialized views can be sampled. reltuples is -1 until the
+        // table is first analyzed (0 before PG 14)
+        let estimated_rows: Option<(f64,)> = sqlx::query_as(
+            r#"
+            SELECT c.reltuples::float8
+            FROM pg_class c
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            WHERE n.nspname = $1 AND c.relname = $2
+              AND c.relkind IN ('r', 'm') AND c.reltuples >= 0
+            "#,
+        )
+        .bind(schema)
+        .bind(table)
+        .fetch_optional(pool)
+        .await
+        .map_err(query_error)?;
+        let estimated_rows = estimated_rows.map(|(rows,)| rows);
+
+        let table_ref = format!("{}.{}", quote_ident(schema), quote_ident(table));
+        let sample_percent = estimated_rows
+            .filter(|&rows| rows > PREVIEW_SAMPLE_ROWS)
+            .map(|rows| (limit as f64 * PREVIEW_OVERSAMPLE / rows * 100.0).min(100.0));
+        let sql = match sample_percent {
+            // The sampled pages come back in table order; shuffling the few rows on them
+            // keeps the first page from filling the whole preview
+            Some(percent) => format!(
+                "SELECT * FROM {} TABLESAMPLE SYSTEM ({}) ORDER BY random() LIMIT $1",
+                table_ref, percent
+            ),
+            None => format!("SELECT * FROM {} LIMIT $1", table_ref),
+        };
+
+        let rows: Vec<PgRow> = unprepared_query(&sql)
+            .bind(limit)
+            .fetch_all(pool)
+            .await
+            .map_err(query_error)?;
+
+        Ok(TablePreview {
+            columns: rows.first().map(column_meta).unwrap_or_default(),
+            rows: rows
+                .iter()
+                .map(|row| row_to_json_values(row, false, self.money_scale()))
+                .collect(),
+            sampled: sample_percent.is_some(),
+            estimated_rows: estimated_rows.map(|rows| rows as i64),
+        })
+    }
+
+    /// Fetches up to `limit` distinct values of a column, most frequent first
+    pub async fn fetch_distinct_values(
+        &self,
+        schema: &str,
+        table: &str,
+        column: &str,
+        limit: i64,
+    ) -> Result<Vec<DistinctValue>, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let column_names: Vec<String> = column_types(pool, schema, table)
+            .await?
+            .into_keys()
+            .collect();
+        let table_ref = format!("{}.{}", quote_ident(schema), quote_ident(table));
+        let sql = browse::distinct_values_sql(&table_ref, column, &column_names, &PG_DIALECT, "$1")
+            .map_err(PostgresError::InvalidInput)?;
+
+        let rows: Vec<PgRow> = unprepared_query(&sql)
+            .bind(limit)
+            .fetch_all(pool)
+            .await
+            .map_err(query_error)?;
+
+        Ok(rows
+            .iter()
+            .map(|row| DistinctValue {
+                value: row_to_json_values(row, false, self.money_scale()).swap_remove(0),
+                count: row.get(1),
+            })
+            .collect())
+    }
+
+    /// Samples non-null values from a json/jsonb column
+    pub async fn sample_json_column(
+        &self,
+        schema: &str,
+        table: &str,
+        column: &str,
+        limit: i64,
+    ) -> Result<Vec<JsonValue>, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let data_type: Option<(String,)> = sqlx::query_as(
+            r#"
+            SELECT data_type
+            FROM information_schema.columns
+            WHERE table_schema = $1 AND table_name = $2 AND column_name = $3
+            "#,
+        )
+        .bind(schema)
+        .bind(table)
+        .bind(column)
+        .fetch_optional(pool)
+        .await
+        .map_err(query_error)?;
+
+        match data_type {
+            Some((data_type,)) if data_type == "json" || data_type == "jsonb" => {}
+            Some((data_type,)) => {
+                return Err(PostgresError::InvalidInput(format!(
+                    "Column \"{}\" is {}, not json/jsonb",
+                    column, data_type
+                )))
+            }
+            None => {
+                return Err(PostgresError::InvalidInput(format!(
+                    "Column \"{}\" not found on {}.{}",
+                    column, schema, table
+                )))
+            }
+        }
+
+        let sample_sql = format!(
+            "SELECT {column} FROM {}.{} WHERE {column} IS NOT NULL LIMIT $1",
+            quote_ident(schema),
+            quote_ident(table),
+            column = quote_ident(column)
+        );
+
+        let values = sqlx::query_as::<_, (JsonValue,)>(&sample_sql)
+            .bind(limit)
+            .fetch_all(pool)
+            .await
+            .map_err(query_error)?
+            .into_iter()
+            .map(|(value,)| value)
+            .collect();
+
+        Ok(values)
+    }
+
+    /// Runs EXPLAIN ANALYZE on a query and returns the JSON plan
+    pub async fn explain_query(&self, sql: &str) -> Result<JsonValue, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        const PREFIX: &str = "EXPLAIN (ANALYZE, FORMAT JSON, VERBOSE, BUFFERS) ";
+
+        // ANALYZE runs the statement, so its writes count as changes
+        let _change = self.check_read_only(sql).await?;
+        let row: (JsonValue,) = sqlx::query_as(&format!("{}{}", PREFIX, sql))
+            .fetch_one(pool)
+            .await
+            .map_err(|e| query_error(e).map_position(|p| position_after(PREFIX, p)))?;
+
+        Ok(row.0)
+    }
+
+    /// Executes a DDL statement directly on the pool, outside any open transaction
+    /// (required for statements such as CREATE INDEX CONCURRENTLY).
+    /// Refuses to run when the session is read-only.
+    pub async fn execute_ddl(&self, sql: &str) -> Result<(), PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let (read_only,): (String,) = sqlx::query_as("SHOW transaction_read_only")
+            .fetch_one(pool)
+            .await
+            .map_err(query_error)?;
+
+        if read_only == "on" {
+            return Err(PostgresError::ReadOnly);
+        }
+
+        if let Some(connection_id) = self.get_connection_id().await {
+            self.catalogs.write().await.remove(&connection_id);
+        }
+
+        let _change = self.data_version.begin();
+        sqlx::query(sql)
+            .execute(pool)
+            .await
+            .map(|_| ())
+            .map_err(query_error)
+    }
+
+    /// Executes a single statement with `params` bound to its placeholders in order.
+    /// Runs inside the open transaction when there is one. Returns the rows affected
+    pub async fn execute_with_params(
+        &self,
+        sql: &str,
+        params: &[JsonValue],
+    ) -> Result<u64, PostgresError> {
+        let _change = self.check_read_only(sql).await?;
+
+        let query = params.iter().fold(unprepared_query(sql), bind_json);
+
+        let result = if let Some(session) = self.lock_transaction().await.as_mut() {
+            query.execute(&mut *session.conn).await
+        } else {
+            let pool = self.pool.read().await;
+            let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+            query.execute(pool).await
+        };
+
+        result.map(|r| r.rows_affected()).map_err(query_error)
+    }
+
+    /// Like execute_with_params, but returns the rows the statement produces, e.g. from
+    /// a RETURNING clause
+    pub async fn query_with_params(
+        &self,
+        sql: &str,
+        params: &[JsonValue],
+    ) -> Result<QueryResult, PostgresError> {
+        let _change = self.check_read_only(sql).await?;
+
+        let query = params.iter().fold(unprepared_query(sql), bind_json);
+
+        let started = Instant::now();
+        let (result, captured) = notices::capture(async {
+            if let Some(session) = self.lock_transaction().await.as_mut() {
+                fetch_with_rows_affected(&mut *session.conn, query)
+                    .await
+                    .map_err(query_error)
+            } else {
+                let pool = self.pool.read().await;
+                let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+                fetch_with_rows_affected(pool, query)
+                    .await
+                    .map_err(query_error)
+            }
+        })
+        .await;
+        let duration_ms = started.elapsed().as_millis() as u64;
+        let (rows, rows_affected) = result?;
+
+        let mut result = rows_to_query_result(
+            rows,
+            rows_affected,
+            script::modifies_rows(sql),
+            false,
+            self.money_scale(),
+        );
+        self.describe_column_origins(&mut result.columns).await;
+        Ok(QueryResult {
+            notices: captured,
+            duration_ms,
+            ..result
+        })
+    }
+
+    /// Runs a single statement and returns the first column of its first row, decoded as
+    /// in query results. Runs inside the open transaction when there is one. Fails if the
+    /// statement produces no rows or no columns
+    pub async fn fetch_scalar(&self, sql: &str) -> Result<JsonValue, PostgresError> {
+        let _change = self.check_read_only(sql).await?;
+
+        let row = if let Some(session) = self.lock_transaction().await.as_mut() {
+            unprepared_query(sql)
+                .fetch_optional(&mut *session.conn)
+                .await
+                .map_err(query_error)?
+        } else {
+            let pool = self.pool.read().await;
+            let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+            unprepared_query(sql)
+                .fetch_optional(pool)
+                .await
+                .map_err(query_error)?
+        };
+
+        let row = row
+            .ok_or_else(|| PostgresError::QueryFailed("The query returned no rows".to_string()))?;
+        row_to_json_values(&row, false, self.money_scale())
+            .into_iter()
+            .next()
+            .ok_or_else(|| PostgresError::QueryFailed("The query returned no columns".to_string()))
+    }
+
+    /// Fills in the schema, table and column name of result columns read straight from a
+    /// table, and whether that column is NOT NULL. Best-effort: the lookup runs on the
+    /// pool, so tables created in the open transaction, like any failure, leave the
+    /// fields None
+    async fn describe_column_origins(&self, columns: &mut [ColumnMeta]) {
+        let (oids, numbers): (Vec<i64>, Vec<i16>) = columns
+            .iter()
+            .filter_map(|c| Some((i64::from(c.table_oid?), c.column_number?)))
+            .unzip();
+        if oids.is_empty() {
+            return;
+        }
+
+        let pool = self.pool.read().await;
+        let Some(pool) = pool.as_ref() else {
+            return;
+        };
+        let Ok(origins) = sqlx::query_as::<_, (i64, i16, String, String, String, bool)>(
+            r#"
+            SELECT c.oid::int8, a.attnum, n.nspname::text, c.relname::text, a.attname::text,
+                   a.attnotnull
+            FROM unnest($1::int8[], $2::int2[]) AS o(relid, attnum)
+            JOIN pg_attribute a ON a.attrelid = o.relid::oid AND a.attnum = o.attnum
+            JOIN pg_class c ON c.oid = a.attrelid
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            "#,
+        )
+        .bind(oids)
+        .bind(numbers)
+        .fetch_all(pool)
+        .await
+        else {
+            return;
+        };
+
+        for column in columns.iter_mut() {
+            let origin = origins.iter().find(|(oid, number, ..)| {
+                column.table_oid.map(i64::from) == Some(*oid)
+                    && column.column_number == Some(*number)
+            });
+            if let Some((_, _, schema, table, name, not_null)) = origin {
+                column.source_schema = Some(schema.clone());
+                column.source_table = Some(table.clone());
+                column.source_column = Some(name.clone());
+                column.column_not_null = Some(*not_null);
+            }
+        }
+    }
+
+    /// Executes several parameterized statements all-or-nothing: inside the open
+    /// transaction when there is one, otherwise in a transaction of their own.
+    /// Returns the total number of rows affected
+    pub async fn execute_batch_with_params(
+        &self,
+        statements: &[(String, Vec<JsonValue>)],
+    ) -> Result<u64, PostgresError> {
+        let _change = self.data_version.begin();
+        if let Some(session) = self.lock_transaction().await.as_mut() {
+            return execute_batch(&mut session.conn, statements).await;
+        }
+
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        // Rolled back on drop if a statement fails
+        let mut tx = pool.begin().await.map_err(query_error)?;
+        let rows_affected = execute_batch(&mut tx, statements).await?;
+        tx.commit().await.map_err(query_error)?;
+
+        Ok(rows_affected)
+    }
+
+    /// Streams `source` into a `COPY ... FROM STDIN` statement, inside the open transaction
+    /// when there is one. The copy is all-or-nothing; returns the rows copied
+    pub async fn copy_in(
+        &self,
+        statement: &str,
+        source: impl AsyncRead + Unpin,
+    ) -> Result<u64, PostgresError> {
+        let _change = self.data_version.begin();
+        if let Some(session) = self.lock_transaction().await.as_mut() {
+            return copy_in(&mut session.conn, statement, source).await;
+        }
+
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+        let mut conn = pool.acquire().await.map_err(query_error)?;
+        copy_in(&mut conn, statement, source).await
+    }
+
+    /// Runs a multi-statement script inside a single transaction. Commits only if every
+    /// statement succeeds; on the first failure the whole script is rolled back
+    pub async fn execute_script(&self, sql: &str) -> Result<ScriptResult, PostgresError> {
+        let _change = self.check_read_only(sql).await?;
+
+        if self.transaction.lock().await.is_some() {
+            return Err(PostgresError::InvalidInput(
+                "Commit or roll back the open transaction before running a script".to_string(),
+            ));
+        }
+
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        // Rolled back on drop if the script is abandoned halfway
+        let mut tx = pool.begin().await.map_err(query_error)?;
+        let mut statements = Vec::new();
+
+        for statement in script::split_statements(sql) {
+            // Simple query protocol, so utility statements don't fill the statement cache
+            let result = Executor::execute(&mut *tx, statement.as_str()).await;
+            let failed = result.is_err();
+
+            statements.push(StatementResult {
+                sql: statement,
+                rows_affected: result.as_ref().ok().map(|r| r.rows_affected()),
+                error: result.err().map(|e| query_error(e).to_string()),
+            });
+
+            if failed {
+                tx.rollback().await.map_err(query_error)?;
+                return Ok(ScriptResult {
+                    statements,
+                    committed: false,
+                });
+            }
+        }
+
+        tx.commit().await.map_err(query_error)?;
+        Ok(ScriptResult {
+            statements,
+            committed: true,
         })
-        .collect();
+    }
 
-        Ok(tables)
+    /// Has the server parse and plan a single statement without running it, by PREPAREing
+    /// it under a throwaway name and deallocating it again. Inside an open transaction
+    /// this happens in a savepoint, so a failure doesn't abort the transaction. PREPARE
+    /// only accepts SELECT, INSERT, UPDATE, DELETE, MERGE and VALUES (including those
+    /// starting with WITH or TABLE); anything else comes back as a syntax error
+    pub async fn validate_query(&self, sql: &str) -> Result<QueryValidation, PostgresError> {
+        let statements = script::split_statements(sql);
+        let [statement] = statements.as_slice() else {
+            return Err(PostgresError::InvalidInput(
+                "Validate one statement at a time".to_string(),
+            ));
+        };
+        // Error positions count from the start of the statement as the user wrote it
+        let offset = sql.chars().take_while(|c| c.is_whitespace()).count();
+
+        let mut validation = if let Some(session) = self.lock_transaction().await.as_mut() {
+            Executor::execute(&mut *session.conn, "SAVEPOINT _dt_validate")
+                .await
+                .map_err(query_error)?;
+            let validation = prepare_statement(&mut session.conn, statement, offset).await;
+            Executor::execute(
+                &mut *session.conn,
+                "ROLLBACK TO SAVEPOINT _dt_validate; RELEASE SAVEPOINT _dt_validate",
+            )
+            .await
+            .map_err(query_error)?;
+            validation?
+        } else {
+            let pool = self.pool.read().await;
+            let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+            let mut conn = pool.acquire().await.map_err(query_error)?;
+            prepare_statement(&mut conn, statement, offset).await?
+        };
+
+        let validatable = script::leading_keyword(statement)
+            .is_some_and(|keyword| VALIDATABLE_STATEMENTS.contains(&keyword.as_str()));
+        if !validatable && validation.error_code.as_deref() == Some("42601") {
+            if let Some(error) = validation.error.as_mut() {
+                error.push_str(
+                    " (only SELECT, INSERT, UPDATE, DELETE, MERGE and VALUES statements can be \
+                     validated)",
+                );
+            }
+        }
+
+        Ok(validation)
     }
 
-    /// Fetches columns for a specific table
-    pub async fn fetch_columns(
+    /// Gets the planner's row estimate for a table (`pg_class.reltuples`), if it has one
+    pub async fn estimate_table_rows(
         &self,
         schema: &str,
         table: &str,
-    ) -> Result<Vec<ColumnInfo>, PostgresError> {
+    ) -> Result<Option<f64>, PostgresError> {
         let pool = self.pool.read().await;
         let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
 
-        let columns: Vec<ColumnInfo> = sqlx::query_as::<_, (String, String, String, Option<String>)>(
+        let row: Option<(f64,)> = sqlx::query_as(
             r#"
-            SELECT 
-                c.column_name,
-                c.data_type,
-                c.is_nullable,
-                c.column_default
-            FROM information_schema.columns c
-            WHERE c.table_schema = $1 AND c.table_name = $2
-            ORDER BY c.ordinal_position
+            SELECT c.reltuples::float8
+            FROM pg_class c
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            WHERE n.nspname = $1 AND c.relname = $2
             "#,
         )
         .bind(schema)
         .bind(table)
+        .fetch_optional(pool)
+        .await
+        .map_err(query_error)?;
+
+        // reltuples is -1 for tables that have never been vacuumed or analyzed
+        Ok(row.map(|(rows,)| rows).filter(|rows| *rows >= 0.0))
+    }
+
+    /// Reports replication health, shaped by whether the server is a primary or a replica
+    pub async fn fetch_replication_status(&self) -> Result<ReplicationStatus, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let (in_recovery,): (bool,) = sqlx::query_as("SELECT pg_is_in_recovery()")
+            .fetch_one(pool)
+            .await
+            .map_err(query_error)?;
+
+        if in_recovery {
+            let (receive_lsn, replay_lsn, replay_lag_seconds, replay_lag_bytes) =
+                sqlx::query_as::<_, (Option<String>, Option<String>, Option<f64>, Option<i64>)>(
+                    r#"
+                    SELECT
+                        pg_last_wal_receive_lsn()::text,
+                        pg_last_wal_replay_lsn()::text,
+                        EXTRACT(EPOCH FROM now() - pg_last_xact_replay_timestamp())::float8,
+                        pg_wal_lsn_diff(pg_last_wal_receive_lsn(), pg_last_wal_replay_lsn())::bigint
+                    "#,
+                )
+                .fetch_one(pool)
+                .await
+                .map_err(query_error)?;
+
+            return Ok(ReplicationStatus::Replica {
+                receive_lsn,
+                replay_lsn,
+                replay_lag_seconds,
+                replay_lag_bytes,
+            });
+        }
+
+        let replicas = sqlx::query_as::<
+            _,
+            (
+                Option<String>,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+                Option<f64>,
+                Option<i64>,
+            ),
+        >(
+            r#"
+            SELECT
+                client_addr::text,
+                application_name,
+                state,
+                sent_lsn::text,
+                write_lsn::text,
+                flush_lsn::text,
+                replay_lsn::text,
+                EXTRACT(EPOCH FROM replay_lag)::float8,
+                pg_wal_lsn_diff(pg_current_wal_lsn(), replay_lsn)::bigint
+            FROM pg_stat_replication
+            ORDER BY application_name
+            "#,
+        )
         .fetch_all(pool)
         .await
-        .map_err(|e| PostgresError::QueryFailed(e.to_string()))?
+        .map_err(query_error)?
         .into_iter()
-        .map(|(name, data_type, is_nullable, column_default)| ColumnInfo {
-            name,
-            data_type,
-            is_nullable: is_nullable == "YES",
-            column_default,
-            is_primary_key: false, // Will be updated below
+        .map(
+            |(
+                client_addr,
+                application_name,
+                state,
+                sent_lsn,
+                write_lsn,
+                flush_lsn,
+                replay_lsn,
+                replay_lag_seconds,
+                replay_lag_bytes,
+            )| ReplicaInfo {
+                client_addr,
+                application_name,
+                state,
+                sent_lsn,
+                write_lsn,
+                flush_lsn,
+                replay_lsn,
+                replay_lag_seconds,
+                replay_lag_bytes,
+            },
+        )
+        .collect();
+
+        Ok(ReplicationStatus::Primary { replicas })
+    }
+
+    /// Gathers pool metrics and server info. The pool is measured before the server
+    /// queries borrow a connection, and the session running them is not counted. Each
+    /// query that fails leaves its field empty instead of failing the whole call
+    pub async fn connection_stats(&self) -> Result<ConnectionStats, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let pool_size = pool.size();
+        let pool_idle = pool.num_idle();
+
+        let mut conn = pool
+            .acquire()
+            .await
+            .map_err(|e| PostgresError::ConnectionFailed(e.to_string()))?;
+        let user_sessions = optional_scalar::<i64>(
+            &mut conn,
+            "SELECT count(*) FROM pg_stat_activity \
+             WHERE usename = current_user AND pid <> pg_backend_pid()",
+        )
+        .await;
+        let server_version =
+            optional_scalar::<String>(&mut conn, "SELECT current_setting('server_version')").await;
+        let database_size_bytes =
+            optional_scalar::<i64>(&mut conn, "SELECT pg_database_size(current_database())").await;
+        let uptime_seconds = optional_scalar::<f64>(
+            &mut conn,
+            "SELECT EXTRACT(EPOCH FROM now() - pg_postmaster_start_time())::float8",
+        )
+        .await;
+
+        Ok(ConnectionStats {
+            pool_size,
+            pool_idle,
+            pool_max: pool.options().get_max_connections(),
+            user_sessions,
+            server_version,
+            database_size_bytes,
+            uptime_seconds,
         })
+    }
+
+    /// Lists server processes, other than the one running this query, with the PIDs
+    /// each one is blocked by. Without pg_read_all_stats the query text and state of
+    /// other users' sessions are hidden
+    pub async fn list_sessions(&self) -> Result<Vec<SessionInfo>, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let sessions = sqlx::query_as::<
+            _,
+            (
+                i32,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+                Option<DateTime<Utc>>,
+                Option<DateTime<Utc>>,
+                Vec<i32>,
+            ),
+        >(
+            r#"
+            SELECT
+                pid,
+                usename::text,
+                datname::text,
+                application_name,
+                client_addr::text,
+                backend_type,
+                state,
+                wait_event_type,
+                wait_event,
+                query,
+                query_start,
+                xact_start,
+                pg_blocking_pids(pid)
+            FROM pg_stat_activity
+            WHERE pid <> pg_backend_pid()
+            ORDER BY query_start NULLS LAST, pid
+            "#,
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(query_error)?
+        .into_iter()
+        .map(
+            |(
+                pid,
+                user,
+                database,
+                application_name,
+                client_addr,
+                backend_type,
+                state,
+                wait_event_type,
+                wait_event,
+                query,
+                query_start,
+                transaction_start,
+                blocked_by,
+            )| SessionInfo {
+                pid,
+                user,
+                database,
+                application_name,
+                client_addr,
+                backend_type,
+                state,
+                wait_event_type,
+                wait_event,
+                query,
+                query_start,
+                transaction_start,
+                blocked_by,
+            },
+        )
         .collect();
 
-        // Fetch primary key columns
-        let pk_columns: Vec<String> = sqlx::query_as::<_, (String,)>(
+        Ok(sessions)
+    }
+
+    /// Lists the locks in the current database, waiting ones first. The virtualxid lock
+    /// every transaction holds on itself is left out unless someone is waiting for it
+    pub async fn list_locks(&self) -> Result<Vec<LockInfo>, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let locks = sqlx::query_as::<
+            _,
+            (
+                i32,
+                String,
+                String,
+                bool,
+                Option<String>,
+                Option<String>,
+                Vec<i32>,
+            ),
+        >(
             r#"
-            SELECT kcu.column_name
-            FROM information_schema.table_constraints tc
-            JOIN information_schema.key_column_usage kcu 
-                ON tc.constraint_name = kcu.constraint_name
-                AND tc.table_schema = kcu.table_schema
-            WHERE tc.constraint_type = 'PRIMARY KEY'
-                AND tc.table_schema = $1
-                AND tc.table_name = $2
+            SELECT
+                l.pid,
+                l.locktype,
+                l.mode,
+                l.granted,
+                l.relation::regclass::text,
+                l.transactionid::text,
+                CASE WHEN l.granted THEN '{}'::int4[] ELSE pg_blocking_pids(l.pid) END
+            FROM pg_locks l
+            WHERE l.pid IS NOT NULL
+              AND l.pid <> pg_backend_pid()
+              AND (l.database IS NULL
+                   OR l.database = (SELECT oid FROM pg_database WHERE datname = current_database()))
+              AND (l.locktype <> 'virtualxid' OR NOT l.granted)
+            ORDER BY l.granted, l.pid
             "#,
         )
-        .bind(schema)
-        .bind(table)
         .fetch_all(pool)
         .await
-        .map_err(|e| PostgresError::QueryFailed(e.to_string()))?
+        .map_err(query_error)?
         .into_iter()
-        .map(|(name,)| name)
+        .map(
+            |(pid, lock_type, mode, granted, relation, transaction_id, blocked_by)| LockInfo {
+                pid,
+                lock_type,
+                mode,
+                granted,
+                relation,
+                transaction_id,
+                blocked_by,
+            },
+        )
         .collect();
 
-        // Update is_primary_key field
-        let columns: Vec<ColumnInfo> = columns
-            .into_iter()
-            .map(|mut col| {
-                col.is_primary_key = pk_columns.contains(&col.name);
-                col
-            })
-            .collect();
+        Ok(locks)
+    }
 
-        Ok(columns)
+    /// Terminates a server process via pg_terminate_backend, rolling back its open
+    /// transaction. Returns false if no process has that PID. Refused on a read-only
+    /// connection, and for the sessions of this connection's own pool. Other sessions can
+    /// be terminated even when they share its application_name and user, as another
+    /// window of the app does
+    pub async fn terminate_session(&self, pid: i32) -> Result<bool, PostgresError> {
+        if self.is_read_only().await {
+            return Err(PostgresError::ReadOnly);
+        }
+
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let started: Option<Option<DateTime<Utc>>> =
+            sqlx::query_scalar("SELECT backend_start FROM pg_stat_activity WHERE pid = $1")
+                .bind(pid)
+                .fetch_optional(pool)
+                .await
+                .map_err(query_error)?;
+        let own = started
+            .flatten()
+            .is_some_and(|started| self.own_sessions.lock().unwrap().get(&pid) == Some(&started));
+        if own {
+            return Err(PostgresError::InvalidInput(
+                "Can't terminate a session of this connection".to_string(),
+            ));
+        }
+
+        let (terminated,): (bool,) = sqlx::query_as("SELECT pg_terminate_backend($1)")
+            .bind(pid)
+            .fetch_one(pool)
+            .await
+            .map_err(query_error)?;
+
+        Ok(terminated)
+    }
+
+    /// Lists the `limit` statements ranking highest by `order` in pg_stat_statements,
+    /// across all databases of the server. Fails with ExtensionNotInstalled when the
+    /// extension isn't created in the connected database
+    pub async fn fetch_top_queries(
+        &self,
+        order: TopQueryOrder,
+        limit: i64,
+    ) -> Result<Vec<TopQuery>, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+
+        let (schema, version): (Option<String>, i32) = sqlx::query_as(
+            r#"
+            SELECT
+                (SELECT n.nspname::text
+                 FROM pg_extension e
+                 JOIN pg_namespace n ON n.oid = e.extnamespace
+                 WHERE e.extname = 'pg_stat_statements'),
+                current_setting('server_version_num')::int4
+            "#,
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(query_error)?;
+        let schema = schema
+            .ok_or_else(|| PostgresError::ExtensionNotInstalled("pg_stat_statements".into()))?;
+
+        // The timing columns got their _exec_ names in Postgres 13
+        let (total_time, mean_time) = if version >= 130000 {
+            ("total_exec_time", "mean_exec_time")
+        } else {
+            ("total_time", "mean_time")
+        };
+        let order_by = match order {
+            TopQueryOrder::TotalTime => total_time,
+            TopQueryOrder::MeanTime => mean_time,
+            TopQueryOrder::Calls => "calls",
+        };
+        let sql = format!(
+            r#"
+            SELECT
+                s.queryid,
+                s.query,
+                r.rolname::text,
+                d.datname::text,
+                s.calls,
+                s.{total_time},
+                s.{mean_time},
+                s.rows
+            FROM {}.pg_stat_statements s
+            LEFT JOIN pg_roles r ON r.oid = s.userid
+            LEFT JOIN pg_database d ON d.oid = s.dbid
+            ORDER BY s.{order_by} DESC
+            LIMIT $1
+            "#,
+            quote_ident(&schema)
+        );
+
+        let queries = sqlx::query_as::<
+            _,
+            (
+                Option<i64>,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+                i64,
+                f64,
+                f64,
+                i64,
+            ),
+        >(&sql)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+        .map_err(query_error)?
+        .into_iter()
+        .map(
+            |(
+                query_id,
+                query,
+                user,
+                database,
+                calls,
+                total_exec_time_ms,
+                mean_exec_time_ms,
+                rows,
+            )| TopQuery {
+                query_id,
+                query,
+                user,
+                database,
+                calls,
+                total_exec_time_ms,
+                mean_exec_time_ms,
+                rows,
+            },
+        )
+        .collect();
+
+        Ok(queries)
+    }
+
+    /// Lists the tables and materialized views of `schema`, or of every non-system schema,
+    /// largest first. With `with_bloat`, pgstattuple installed and the privilege to run
+    /// it, each one is also scanned by pgstattuple_approx, which reads the pages the
+    /// visibility map doesn't vouch for. Should that take longer than
+    /// BLOAT_SCAN_TIMEOUT_MS, the sizes are listed without bloat
+    pub async fn fetch_table_sizes(
+        &self,
+        schema: Option<&str>,
+        with_bloat: bool,
+    ) -> Result<Vec<TableSize>, PostgresError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+        let mut conn = pool.acquire().await.map_err(query_error)?;
+
+        let pgstattuple_schema: Option<String> = if with_bloat {
+            sqlx::query_scalar(
+                r#"
+                SELECT n.nspname::text
+                FROM pg_extension e
+                JOIN pg_namespace n ON n.oid = e.extnamespace
+                WHERE e.extname = 'pgstattuple'
+                "#,
+            )
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(query_error)?
+        } else {
+            None
+        };
+        // EXECUTE is only granted to superusers and pg_stat_scan_tables by default
+        let ext_schema = match pgstattuple_schema {
+            Some(ext_schema) => sqlx::query_scalar::<_, bool>(
+                "SELECT has_function_privilege(format('%I.pgstattuple_approx(regclass)', $1), 'EXECUTE')",
+            )
+            .bind(&ext_schema)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(query_error)?
+            .then_some(ext_schema),
+            None => None,
+        };
+
+        let Some(ext_schema) = ext_schema else {
+            return query_table_sizes(&mut conn, schema, None).await;
+        };
+        let timeout_ms = Some(BLOAT_SCAN_TIMEOUT_MS);
+        set_statement_timeout(&mut conn, timeout_ms).await?;
+        let sizes = query_table_sizes(&mut conn, schema, Some(&ext_schema)).await;
+        reset_statement_timeout(&mut conn, timeout_ms).await;
+        match sizes {
+            Err(PostgresError::QueryTimeout) => query_table_sizes(&mut conn, schema, None).await,
+            sizes => sizes,
+        }
+    }
+}
+
+/// Runs the query behind fetch_table_sizes, with bloat columns from `pgstattuple_schema`'s
+/// pgstattuple_approx or left NULL
+async fn query_table_sizes(
+    conn: &mut PgConnection,
+    schema: Option<&str>,
+    pgstattuple_schema: Option<&str>,
+) -> Result<Vec<TableSize>, PostgresError> {
+    let bloat_columns = match pgstattuple_schema {
+        Some(ext_schema) => format!(
+            "b.dead_tuple_percent, b.approx_free_percent FROM sizes s \
+             LEFT JOIN LATERAL {}.pgstattuple_approx(s.oid) b ON true",
+            quote_ident(ext_schema)
+        ),
+        None => "NULL::float8, NULL::float8 FROM sizes s".to_string(),
+    };
+
+    // reltuples is -1 until the table is first analyzed (0 before PG 14)
+    let sql = format!(
+        r#"
+        WITH sizes AS (
+            SELECT
+                c.oid,
+                n.nspname::text AS schema,
+                c.relname::text AS name,
+                pg_total_relation_size(c.oid) AS total_bytes,
+                pg_relation_size(c.oid) AS table_bytes,
+                pg_indexes_size(c.oid) AS indexes_bytes,
+                COALESCE(pg_total_relation_size(NULLIF(c.reltoastrelid, 0)), 0) AS toast_bytes,
+                CASE WHEN c.reltuples >= 0 THEN c.reltuples::bigint END AS estimated_rows
+            FROM pg_class c
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            WHERE c.relkind IN ('r', 'm')
+              AND n.nspname NOT IN ('pg_catalog', 'information_schema')
+              AND n.nspname NOT LIKE 'pg\_toast%'
+              AND ($1::text IS NULL OR n.nspname = $1)
+        )
+        SELECT
+            s.schema,
+            s.name,
+            s.total_bytes,
+            s.table_bytes,
+            s.indexes_bytes,
+            s.toast_bytes,
+            s.estimated_rows,
+            {bloat_columns}
+        ORDER BY s.total_bytes DESC, s.schema, s.name
+        "#
+    );
+
+    let sizes = sqlx::query_as::<
+        _,
+        (
+            String,
+            String,
+            i64,
+            i64,
+            i64,
+            i64,
+            Option<i64>,
+            Option<f64>,
+            Option<f64>,
+        ),
+    >(&sql)
+    .bind(schema)
+    .fetch_all(conn)
+    .await
+    .map_err(query_error)?
+    .into_iter()
+    .map(
+        |(
+            schema,
+            name,
+            total_bytes,
+            table_bytes,
+            indexes_bytes,
+            toast_bytes,
+            estimated_rows,
+            dead_tuple_percent,
+            free_percent,
+        )| TableSize {
+            schema,
+            name,
+            total_bytes,
+            table_bytes,
+            indexes_bytes,
+            toast_bytes,
+            estimated_rows,
+            bloat: dead_tuple_percent.zip(free_percent).map(
+                |(dead_tuple_percent, free_percent)| TableBloat {
+                    dead_tuple_percent,
+                    free_percent,
+                },
+            ),
+        },
+    )
+    .collect();
+
+    Ok(sizes)
+}
+
+/// Builds the options for connecting to a server. As with libpq, a `host` starting with
+/// `/` is the directory holding the server's Unix domain socket. Sockets don't use TLS and
+/// usually authenticate by peer, so there the SSL settings are ignored and an empty
+/// password is not sent
+#[allow(clippy::too_many_arguments)]
+pub fn connect_options(
+    host: &str,
+    port: u16,
+    database: &str,
+    user: &str,
+    password: &str,
+    ssl_mode: SslMode,
+    ca_cert_path: Option<&str>,
+    read_only: bool,
+    statement_cache_capacity: usize,
+    application_name: &str,
+) -> PgConnectOptions {
+    let mut options = PgConnectOptions::new()
+        .port(port)
+        .database(database)
+        .username(user)
+        .statement_cache_capacity(statement_cache_capacity)
+        .application_name(application_name);
+    if host.starts_with('/') {
+        options = options.socket(host).ssl_mode(PgSslMode::Disable);
+        if !password.is_empty() {
+            options = options.password(password);
+        }
+    } else {
+        options = options
+            .host(host)
+            .password(password)
+            .ssl_mode(ssl_mode.into());
+        if let Some(path) = ca_cert_path {
+            options = options.ssl_root_cert(path);
+        }
+    }
+    if read_only {
+        options = options.options([("default_transaction_read_only", "on")]);
+    }
+    options
+}
+
+/// The application_name for a connection's sessions, so they can be told apart in
+/// pg_stat_activity: `custom` when set, otherwise "datatool - <connection name>". Cut to
+/// the length the server keeps
+pub fn application_name(connection_name: &str, custom: Option<&str>) -> String {
+    let mut name = match (custom.map(str::trim), connection_name.trim()) {
+        (Some(custom), _) if !custom.is_empty() => custom.to_string(),
+        (_, "") => DEFAULT_APPLICATION_NAME.to_string(),
+        (_, connection_name) => format!("{} - {}", DEFAULT_APPLICATION_NAME, connection_name),
+    };
+    if name.len() > MAX_APPLICATION_NAME_LEN {
+        let end = (0..=MAX_APPLICATION_NAME_LEN)
+            .rev()
+            .find(|&i| name.is_char_boundary(i))
+            .unwrap_or(0);
+        name.truncate(end);
+    }
+    name
+}
+
+/// Fetches a single value, or None if the query fails
+async fn optional_scalar<T>(conn: &mut PgConnection, sql: &str) -> Option<T>
+where
+    T: for<'r> sqlx::Decode<'r, Postgres> + sqlx::Type<Postgres> + Send + Unpin,
+{
+    sqlx::query_scalar(sql).fetch_one(conn).await.ok()
+}
+
+impl Default for PostgresManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Filters for the data grid compare values cast to the column's type; LIKE matches the
+/// text form case-insensitively
+const PG_DIALECT: Dialect = Dialect {
+    quote_ident,
+    like: |column, pattern| format!("{}::text ILIKE {}", column, pattern),
+    json_path: Some(|column, keys| format!("({} #>> ARRAY[{}])", column, keys.join(", "))),
+    json_number: Some(|text| {
+        format!(
+            r"(CASE WHEN {0} ~ '^-?(0|[1-9]\d*)(\.\d+)?([eE][-+]?\d+)?$' THEN {0}::numeric END)",
+            text
+        )
+    }),
+};
+
+/// Quotes an identifier for interpolation into SQL, doubling any embedded double quotes
+pub fn quote_ident(ident: &str) -> String {
+    format!("\"{}\"", ident.replace('"', "\"\""))
+}
+
+/// Checks that `value` is a comma separated list of plain or double-quoted names, as
+/// SET search_path takes it. The server would reject anything else in after_connect,
+/// which the pool only reports as a timeout
+pub fn validate_search_path(value: &str) -> Result<(), PostgresError> {
+    let invalid = || PostgresError::InvalidInput(format!("Invalid search_path: {}", value));
+    let mut chars = value.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.next_if_eq(&'"').is_some() {
+            let mut name_len = 0;
+            loop {
+                match chars.next() {
+                    Some('"') if chars.next_if_eq(&'"').is_none() => break,
+                    Some(_) => name_len += 1,
+                    None => return Err(invalid()),
+                }
+            }
+            if name_len == 0 {
+                return Err(invalid());
+            }
+        } else {
+            let mut name_len = 0;
+            while chars
+                .next_if(|c| !c.is_whitespace() && *c != ',' && *c != '"')
+                .is_some()
+            {
+                name_len += 1;
+            }
+            if name_len == 0 {
+                return Err(invalid());
+            }
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        match chars.next() {
+            Some(',') => continue,
+            None => return Ok(()),
+            Some(_) => return Err(invalid()),
+        }
+    }
+}
+
+/// Quotes a string literal for statements that take no parameters, such as COMMENT ON.
+/// Like Postgres' quote_literal, backslashes switch to an E'' string so the result doesn't
+/// depend on standard_conforming_strings
+pub fn quote_literal(value: &str) -> String {
+    let quoted = value.replace('\'', "''");
+    if quoted.contains('\\') {
+        format!("E'{}'", quoted.replace('\\', "\\\\"))
+    } else {
+        format!("'{}'", quoted)
+    }
+}
+
+/// Looks up each column's type as a cast target (see fetch_column_types)
+async fn column_types(
+    pool: &PgPool,
+    schema: &str,
+    table: &str,
+) -> Result<HashMap<String, String>, PostgresError> {
+    let column_types: HashMap<String, String> = sqlx::query_as::<_, (String, String)>(
+        r#"
+        SELECT a.attname, format_type(a.atttypid, a.atttypmod)
+        FROM pg_attribute a
+        JOIN pg_class c ON c.oid = a.attrelid
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        WHERE n.nspname = $1 AND c.relname = $2 AND a.attnum > 0 AND NOT a.attisdropped
+        "#,
+    )
+    .bind(schema)
+    .bind(table)
+    .fetch_all(pool)
+    .await
+    .map_err(query_error)?
+    .into_iter()
+    .collect();
+
+    if column_types.is_empty() {
+        return Err(PostgresError::InvalidInput(format!(
+            "Table {}.{} not found",
+            schema, table
+        )));
+    }
+
+    Ok(column_types)
+}
+
+/// Looks up the primary key of a table, falling back to a single-column UNIQUE NOT NULL index
+async fn detect_row_key(pool: &PgPool, schema: &str, table: &str) -> Result<RowKey, PostgresError> {
+    let pk_columns: Vec<String> = sqlx::query_as::<_, (String,)>(
+        r#"
+        SELECT kcu.column_name
+        FROM information_schema.table_constraints tc
+        JOIN information_schema.key_column_usage kcu 
+            ON tc.constraint_name = kcu.constraint_name
+            AND tc.table_schema = kcu.table_schema
+        WHERE tc.constraint_type = 'PRIMARY KEY'
+            AND tc.table_schema = $1
+            AND tc.table_name = $2
+        ORDER BY kcu.ordinal_position
+        "#,
+    )
+    .bind(schema)
+    .bind(table)
+    .fetch_all(pool)
+    .await
+    .map_err(query_error)?
+    .into_iter()
+    .map(|(name,)| name)
+    .collect();
+
+    if !pk_columns.is_empty() {
+        return Ok(RowKey {
+            strategy: RowKeyStrategy::PrimaryKey,
+            columns: pk_columns,
+            index_name: None,
+        });
+    }
+
+    let unique_index: Option<(String, String)> = sqlx::query_as(
+        r#"
+        SELECT ic.relname, a.attname
+        FROM pg_index i
+        JOIN pg_class c ON c.oid = i.indrelid
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        JOIN pg_class ic ON ic.oid = i.indexrelid
+        JOIN pg_attribute a ON a.attrelid = c.oid AND a.attnum = i.indkey[0]
+        WHERE n.nspname = $1
+            AND c.relname = $2
+            AND i.indisunique
+            AND i.indisvalid
+            AND i.indnkeyatts = 1
+            AND i.indpred IS NULL
+            AND i.indexprs IS NULL
+            AND a.attnotnull
+        ORDER BY ic.relname
+        LIMIT 1
+        "#,
+    )
+    .bind(schema)
+    .bind(table)
+    .fetch_optional(pool)
+    .await
+    .map_err(query_error)?;
+
+    Ok(match unique_index {
+        Some((index_name, column)) => RowKey {
+            strategy: RowKeyStrategy::UniqueIndex,
+            columns: vec![column],
+            index_name: Some(index_name),
+        },
+        None => RowKey {
+            strategy: RowKeyStrategy::None,
+            columns: vec![],
+            index_name: None,
+        },
+    })
+}
+
+/// Executes parameterized statements one after another on a single connection
+async fn execute_batch(
+    conn: &mut PgConnection,
+    statements: &[(String, Vec<JsonValue>)],
+) -> Result<u64, PostgresError> {
+    let mut rows_affected = 0;
+    for (sql, params) in statements {
+        rows_affected += params
+            .iter()
+            .fold(unprepared_query(sql), bind_json)
+            .execute(&mut *conn)
+            .await
+            .map_err(query_error)?
+            .rows_affected();
+    }
+    Ok(rows_affected)
+}
+
+/// Sends `source` to a COPY in chunks. Read errors abort the copy rather than passing
+/// through sqlx, which would report them as a lost connection
+async fn copy_in(
+    conn: &mut PgConnection,
+    statement: &str,
+    mut source: impl AsyncRead + Unpin,
+) -> Result<u64, PostgresError> {
+    let mut copy = conn.copy_in_raw(statement).await.map_err(copy_error)?;
+    let mut chunk = vec![0; COPY_CHUNK_SIZE];
+
+    loop {
+        let read = match source.read(&mut chunk).await {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) => {
+                copy.abort(e.to_string()).await.ok();
+                return Err(PostgresError::InvalidInput(format!(
+                    "Failed to read input: {}",
+                    e
+                )));
+            }
+        };
+        copy.send(&chunk[..read]).await.map_err(copy_error)?;
+    }
+
+    copy.finish().await.map_err(copy_error)
+}
+
+type ColumnDefinitionRow = (
+    String,
+    String,
+    bool,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    bool,
+);
+
+type ForeignKeyRow = (
+    String,
+    String,
+    String,
+    Vec<String>,
+    String,
+    String,
+    Vec<String>,
+    String,
+    String,
+);
+
+/// Maps `pg_constraint.confdeltype` / `confupdtype` to the SQL spelling of the action
+fn foreign_key_action(action: &str) -> &str {
+    match action {
+        "r" => "RESTRICT",
+        "c" => "CASCADE",
+        "n" => "SET NULL",
+        "d" => "SET DEFAULT",
+        _ => "NO ACTION",
+    }
+}
+
+/// Maps `pg_constraint.contype` to a readable name
+fn constraint_type(contype: &str) -> &str {
+    match contype {
+        "p" => "primary_key",
+        "f" => "foreign_key",
+        "u" => "unique",
+        "c" => "check",
+        "x" => "exclusion",
+        "n" => "not_null",
+        "t" => "trigger",
+        other => other,
+    }
+}
+
+/// A query that is not kept in the statement cache. The parameter types of a cached
+/// statement are fixed by its first execution, but bind_json picks them per value
+fn unprepared_query(sql: &str) -> Query<'_, Postgres, PgArguments> {
+    sqlx::query(sql).persistent(false)
+}
+
+/// PREPAREs `statement` under a name of its own, reads the inferred types back from
+/// pg_prepared_statements and deallocates it. The name is never reused, so a statement
+/// left behind by an interrupted validation can't collide with the next one. Parse and
+/// plan errors become an invalid QueryValidation; `offset` is added to the reported
+/// error position
+async fn prepare_statement(
+    conn: &mut PgConnection,
+    statement: &str,
+    offset: usize,
+) -> Result<QueryValidation, PostgresError> {
+    static NEXT_VALIDATION_ID: AtomicU64 = AtomicU64::new(0);
+    let name = format!(
+        "_dt_validate_{}",
+        NEXT_VALIDATION_ID.fetch_add(1, Ordering::Relaxed)
+    );
+    let prefix = format!("PREPARE {} AS ", name);
+
+    // Simple query protocol: PREPARE takes no bind parameters of its own
+    let prepare = format!("{}{}", prefix, statement);
+    if let Err(e) = Executor::execute(&mut *conn, prepare.as_str()).await {
+        let Some(db_err) = e.as_database_error() else {
+            return Err(query_error(e));
+        };
+        let pg_err = db_err.downcast_ref::<PgDatabaseError>();
+        let error_position = match pg_err.position() {
+            Some(PgErrorPosition::Original(position)) => position
+                .checked_sub(prefix.len())
+                .filter(|&p| p > 0)
+                .map(|p| p + offset),
+            _ => None,
+        };
+
+        return Ok(QueryValidation {
+            valid: false,
+            error: Some(pg_err.message().to_string()),
+            error_code: Some(pg_err.code().to_string()),
+            error_position,
+            parameter_types: Vec::new(),
+            result_types: None,
+        });
+    }
+
+    // Through to_jsonb, so the query works on servers without result_types
+    let types: Result<(Option<JsonValue>, Option<JsonValue>), _> = sqlx::query_as(
+        r#"
+        SELECT to_jsonb(p) -> 'parameter_types', to_jsonb(p) -> 'result_types'
+        FROM pg_prepared_statements p
+        WHERE name = $1
+        "#,
+    )
+    .bind(&name)
+    .fetch_one(&mut *conn)
+    .await;
+    let deallocated = Executor::execute(&mut *conn, format!("DEALLOCATE {}", name).as_str()).await;
+    let (parameter_types, result_types) = types.map_err(query_error)?;
+    deallocated.map_err(query_error)?;
+
+    Ok(QueryValidation {
+        valid: true,
+        error: None,
+        error_code: None,
+        error_position: None,
+        parameter_types: parameter_types
+            .and_then(|types| serde_json::from_value(types).ok())
+            .unwrap_or_default(),
+        result_types: result_types.and_then(|types| serde_json::from_value(types).ok()),
+    })
+}
+
+/// A NULL parameter sent without a type, so the server gives it whatever type the
+/// placeholder needs where it is used
+struct UntypedNull;
+
+impl sqlx::Type<Postgres> for UntypedNull {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_oid(Oid(0))
+    }
+}
+
+impl sqlx::Encode<'_, Postgres> for UntypedNull {
+    fn encode_by_ref(&self, _: &mut PgArgumentBuffer) -> Result<IsNull, BoxDynError> {
+        Ok(IsNull::Yes)
+    }
+}
+
+/// Binds a JSON value as a parameter with the closest matching Postgres type, so that
+/// numbers and booleans are not sent as text. Statements are expected to cast each
+/// placeholder to its column type where that differs; null fits any type
+fn bind_json<'q>(
+    query: Query<'q, Postgres, PgArguments>,
+    value: &JsonValue,
+) -> Query<'q, Postgres, PgArguments> {
+    match value {
+        JsonValue::Null => query.bind(UntypedNull),
+        JsonValue::Array(_) | JsonValue::Object(_) => query.bind(value.clone()),
+        _ => browse::bind_json(query, value),
+    }
+}
+
+/// Maps a query error, singling out serialization failures (SQLSTATE 40001) so callers can
+/// retry, and a dropped connection so the UI can offer to reconnect
+pub(crate) fn query_error(e: sqlx::Error) -> PostgresError {
+    if is_connection_lost(&e) {
+        return PostgresError::ConnectionLost(e.to_string());
+    }
+    if let Some(db_err) = e.as_database_error() {
+        match db_err.code().as_deref() {
+            Some("40001") => {
+                return PostgresError::SerializationFailure(db_err.message().to_string())
+            }
+            // read_only_sql_transaction
+            Some("25006") => return PostgresError::ReadOnly,
+            // query_canceled is raised both by pg_cancel_backend and by statement_timeout;
+            // only the message tells them apart
+            Some("57014") if db_err.message().contains("statement timeout") => {
+                return PostgresError::QueryTimeout
+            }
+            Some("57014") => return PostgresError::Cancelled,
+            _ => {}
+        }
+    }
+    match ServerError::from_sqlx(&e) {
+        Some(server_error) => PostgresError::Server(Box::new(server_error)),
+        None => PostgresError::QueryFailed(e.to_string()),
+    }
+}
+
+/// Like query_error, but keeps the context the server attaches to COPY errors, which
+/// names the line that failed
+fn copy_error(e: sqlx::Error) -> PostgresError {
+    let context = e
+        .as_database_error()
+        .and_then(|db_err| db_err.try_downcast_ref::<PgDatabaseError>())
+        .and_then(|pg_err| pg_err.r#where())
+        .map(str::to_string);
+
+    match (query_error(e), context) {
+        (PostgresError::Server(mut server_error), Some(context)) => {
+            server_error.message = format!("{} ({})", server_error.message, context);
+            PostgresError::Server(server_error)
+        }
+        (error, _) => error,
+    }
+}
+
+/// True for errors that mean the connection itself is gone: socket and TLS failures, the
+/// background worker dying, and the server reporting a connection exception (class 08) or
+/// shutting down (57P01-57P03)
+fn is_connection_lost(e: &sqlx::Error) -> bool {
+    match e {
+        sqlx::Error::Io(_) | sqlx::Error::Tls(_) | sqlx::Error::WorkerCrashed => true,
+        sqlx::Error::Database(db_err) => db_err.code().is_some_and(|code| {
+            code.starts_with("08") || matches!(&*code, "57P01" | "57P02" | "57P03")
+        }),
+        _ => false,
+    }
+}
+
+/// Drops the cursor with the given id once it has been idle for longer than CURSOR_IDLE_TIMEOUT.
+/// Dropping the session closes its connection, which aborts the cursor's transaction.
+async fn cursor_watchdog(cursor: Arc<Mutex<Option<CursorSession>>>, id: u64) {
+    loop {
+        tokio::time::sleep(WATCHDOG_INTERVAL).await;
+
+        let mut cursor = cursor.lock().await;
+        match cursor.as_ref() {
+            Some(session) if session.id == id => {
+                if session.last_used.elapsed() >= CURSOR_IDLE_TIMEOUT {
+                    cursor.take();
+                    return;
+                }
+            }
+            _ => return,
+        }
+    }
+}
+
+/// Rolls back the transaction with the given id once it has been idle for longer than
+/// TRANSACTION_IDLE_TIMEOUT. Dropping the session closes its connection, which ends the
+/// transaction on the server. A transaction whose lock is held, as while a statement runs
+/// in it, is not idle and is checked again on the next tick
+async fn transaction_watchdog(transaction: Arc<Mutex<Option<TransactionSession>>>, id: u64) {
+    loop {
+        tokio::time::sleep(WATCHDOG_INTERVAL).await;
+
+        let Ok(mut transaction) = transaction.try_lock() else {
+            continue;
+        };
+        match transaction.as_ref() {
+            Some(session) if session.id == id => {
+                if session.last_used.elapsed() >= TRANSACTION_IDLE_TIMEOUT {
+                    transaction.take();
+                    return;
+                }
+            }
+            _ => return,
+        }
     }
+}
 
-    /// Fetches paginated table data
-    pub async fn fetch_table_data(
-        &self,
-        schema: &str,
-        table: &str,
-        page: i32,
-        page_size: i32,
-    ) -> Result<PaginatedResult, PostgresError> {
-        let pool = self.pool.read().await;
-        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
-
-        let offset = (page - 1) * page_size;
+fn no_transaction() -> PostgresError {
+    PostgresError::InvalidInput("No transaction in progress".to_string())
+}
 
-        // Get total count
-        let count_sql = format!(
-            r#"SELECT COUNT(*) FROM "{}"."{}" "#,
-            schema, table
-        );
-        let total_count: (i64,) = sqlx::query_as(&count_sql)
-            .fetch_one(pool)
+async fn set_statement_timeout(
+    conn: &mut PgConnection,
+    timeout_ms: Option<u64>,
+) -> Result<(), PostgresError> {
+    if let Some(timeout_ms) = timeout_ms {
+        sqlx::query(&format!("SET statement_timeout = {}", timeout_ms))
+            .execute(&mut *conn)
             .await
-            .map_err(|e| PostgresError::QueryFailed(e.to_string()))?;
-
-        // Fetch paginated data
-        let data_sql = format!(
-            r#"SELECT * FROM "{}"."{}" LIMIT {} OFFSET {}"#,
-            schema, table, page_size, offset
-        );
+            .map_err(query_error)?;
+    }
+    Ok(())
+}
 
-        let rows: Vec<PgRow> = sqlx::query(&data_sql)
-            .fetch_all(pool)
+async fn reset_statement_timeout(conn: &mut PgConnection, timeout_ms: Option<u64>) {
+    // Inside a failed transaction this is refused, but the rollback undoes the SET anyway
+    if timeout_ms.is_some() {
+        sqlx::query("RESET statement_timeout")
+            .execute(&mut *conn)
             .await
-            .map_err(|e| PostgresError::QueryFailed(e.to_string()))?;
+            .ok();
+    }
+}
 
-        if rows.is_empty() {
-            return Ok(PaginatedResult {
-                columns: vec![],
-                rows: vec![],
-                total_count: total_count.0,
-                page,
-                page_size,
-            });
+/// Runs a query, collecting its rows along with the number of rows it affected, which
+/// fetch_all would discard
+async fn fetch_with_rows_affected<'e, 'q: 'e, E>(
+    executor: E,
+    query: Query<'q, Postgres, PgArguments>,
+) -> Result<(Vec<PgRow>, u64), sqlx::Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let mut stream = executor.fetch_many(query);
+    let mut rows = Vec::new();
+    let mut rows_affected = 0;
+
+    while let Some(step) = stream.try_next().await? {
+        match step {
+            Either::Left(result) => rows_affected += result.rows_affected(),
+            Either::Right(row) => rows.push(row),
         }
+    }
 
-        let columns: Vec<ColumnMeta> = rows[0]
-            .columns()
-            .iter()
-            .map(|col| ColumnMeta {
-                name: col.name().to_string(),
-                data_type: col.type_info().name().to_string(),
-            })
-            .collect();
+    Ok((rows, rows_affected))
+}
 
-        let json_rows: Vec<Vec<JsonValue>> = rows
-            .iter()
-            .map(|row| row_to_json_values(row))
-            .collect();
+/// Reads how many fraction digits MONEY amounts have under the session's lc_monetary. The
+/// server sends them as a count of the currency's smallest unit, and money::numeric keeps
+/// the fraction digits. Servers without MONEY get DEFAULT_MONEY_SCALE rather than failing
+/// to connect
+async fn read_money_scale(conn: &mut PgConnection) -> u32 {
+    sqlx::query_scalar::<_, i32>("SELECT scale(0::money::numeric)")
+        .fetch_one(conn)
+        .await
+        .map_or(record::DEFAULT_MONEY_SCALE, |scale| scale as u32)
+}
 
-        Ok(PaginatedResult {
-            columns,
-            rows: json_rows,
-            total_count: total_count.0,
-            page,
-            page_size,
-        })
+/// Converts fetched rows into a QueryResult. `rows_affected` is reported for statements
+/// that return no rows (DDL, or DML without RETURNING) and, when `modifies_rows`, for
+/// DML that returns rows too; for a SELECT it would only repeat the row count.
+/// `duration_ms` is left for the caller, which times the round trip. `distinguish_nulls`
+/// is passed on to row_to_json_values
+fn rows_to_query_result(
+    rows: Vec<PgRow>,
+    rows_affected: u64,
+    modifies_rows: bool,
+    distinguish_nulls: bool,
+    money_scale: u32,
+) -> QueryResult {
+    if rows.is_empty() {
+        return QueryResult {
+            columns: vec![],
+            rows: vec![],
+            row_count: 0,
+            affected_rows: Some(rows_affected),
+            notices: Vec::new(),
+            duration_ms: 0,
+            truncated: false,
+            cache_status: None,
+        };
     }
 
-    /// Runs EXPLAIN ANALYZE on a query and returns the JSON plan
-    pub async fn explain_query(&self, sql: &str) -> Result<JsonValue, PostgresError> {
-        let pool = self.pool.read().await;
-        let pool = pool.as_ref().ok_or(PostgresError::NoActiveConnection)?;
+    // Extract column metadata from the first row
+    let columns = column_meta(&rows[0]);
 
-        let explain_sql = format!(
-            "EXPLAIN (ANALYZE, FORMAT JSON, VERBOSE, BUFFERS) {}",
-            sql
-        );
+    // Convert rows to JSON values
+    let json_rows: Vec<Vec<JsonValue>> = rows
+        .iter()
+        .map(|row| row_to_json_values(row, distinguish_nulls, money_scale))
+        .collect();
 
-        let row: (JsonValue,) = sqlx::query_as(&explain_sql)
-            .fetch_one(pool)
-            .await
-            .map_err(|e| PostgresError::QueryFailed(e.to_string()))?;
+    let row_count = json_rows.len();
 
-        Ok(row.0)
+    QueryResult {
+        columns,
+        rows: json_rows,
+        row_count,
+        affected_rows: modifies_rows.then_some(rows_affected),
+        notices: Vec::new(),
+        duration_ms: 0,
+        truncated: false,
+        cache_status: None,
     }
 }
 
-impl Default for PostgresManager {
-    fn default() -> Self {
-        Self::new()
-    }
+/// Extracts column metadata from a row. Only the table OID and column number of the source
+/// are known here; describe_column_origins looks up the rest
+fn column_meta(row: &PgRow) -> Vec<ColumnMeta> {
+    row.columns()
+        .iter()
+        .map(|col| ColumnMeta {
+            name: col.name().to_string(),
+            data_type: col.type_info().name().to_string(),
+            table_oid: col.relation_id().map(|oid| oid.0),
+            column_number: col.relation_attribute_no(),
+            ..Default::default()
+        })
+        .collect()
 }
 
-/// Converts a PgRow to a vector of JSON values
-fn row_to_json_values(row: &PgRow) -> Vec<JsonValue> {
+/// Converts a PgRow to a vector of JSON values. Only SQL NULL becomes null, or
+/// record::null_cell's sentinel with `distinguish_nulls`, so a JSON null value stays
+/// null; a value that fails to decode is replaced with record::decode_error
+fn row_to_json_values(row: &PgRow, distinguish_nulls: bool, money_scale: u32) -> Vec<JsonValue> {
     row.columns()
         .iter()
         .enumerate()
         .map(|(i, col)| {
+            let Ok(value) = row.try_get_raw(i) else {
+                return JsonValue::Null;
+            };
+            if value.is_null() {
+                return record::null_cell(distinguish_nulls);
+            }
             let type_name = col.type_info().name();
-            
+
+            // The record helpers return null only for values they failed to decode
+            let decoded = |json: JsonValue| Some(json).filter(|json| !json.is_null());
+
+            // Row values (anonymous records and composite types) and arrays are decoded
+            // element by element
+            if record::is_array_type(col.type_info()) {
+                return decoded(record::array_to_json(value, money_scale))
+                    .unwrap_or_else(|| record::decode_error(type_name));
+            }
+            if record::is_record_type(col.type_info()) {
+                return decoded(record::record_to_json(value, money_scale))
+                    .unwrap_or_else(|| record::decode_error(type_name));
+            }
+
             // Handle different PostgreSQL types
-            match type_name {
-                "BOOL" => row
-                    .try_get::<bool, _>(i)
-                    .map(JsonValue::Bool)
-                    .unwrap_or(JsonValue::Null),
-                "INT2" | "INT4" => row
+            let json = match type_name {
+                "BOOL" => row.try_get::<bool, _>(i).ok().map(JsonValue::Bool),
+                "INT4" => row
                     .try_get::<i32, _>(i)
-                    .map(|v| JsonValue::Number(v.into()))
-                    .unwrap_or(JsonValue::Null),
+                    .ok()
+                    .map(|v| JsonValue::Number(v.into())),
                 "INT8" => row
                     .try_get::<i64, _>(i)
-                    .map(|v| JsonValue::Number(v.into()))
-                    .unwrap_or(JsonValue::Null),
-                "FLOAT4" | "FLOAT8" => row
-                    .try_get::<f64, _>(i)
-                    .map(|v| {
-                        serde_json::Number::from_f64(v)
-                            .map(JsonValue::Number)
-                            .unwrap_or(JsonValue::Null)
-                    })
-                    .unwrap_or(JsonValue::Null),
-                "JSON" | "JSONB" => row
-                    .try_get::<JsonValue, _>(i)
-                    .unwrap_or(JsonValue::Null),
+                    .ok()
+                    .map(|v| JsonValue::Number(v.into())),
+                "FLOAT4" => row.try_get::<f32, _>(i).ok().map(|v| {
+                    serde_json::Number::from_f64(v as f64)
+                        .map_or(JsonValue::Null, JsonValue::Number)
+                }),
+                "FLOAT8" => row.try_get::<f64, _>(i).ok().map(|v| {
+                    serde_json::Number::from_f64(v).map_or(JsonValue::Null, JsonValue::Number)
+                }),
+                "NUMERIC" => decoded(record::numeric_to_json(value)),
+                // JSON null is a value of its own, not a decode failure, and a JSON value
+                // can look like the sentinel, so an undecodable one is shown as text
+                "JSON" | "JSONB" => Some(
+                    row.try_get::<JsonValue, _>(i)
+                        .unwrap_or_else(|_| record::value_to_json(value, money_scale)),
+                ),
+                "BYTEA" => row
+                    .try_get::<Vec<u8>, _>(i)
+                    .ok()
+                    .map(|v| JsonValue::String(record::binary_encoding().encode(&v))),
+                "INET" | "CIDR" | "MACADDR" | "MACADDR8" => {
+                    decoded(record::value_to_json(value, money_scale))
+                }
                 "UUID" => row
                     .try_get::<uuid::Uuid, _>(i)
-                    .map(|v| JsonValue::String(v.to_string()))
-                    .unwrap_or(JsonValue::Null),
+                    .ok()
+                    .map(|v| JsonValue::String(v.to_string())),
+                "TIMESTAMP" => row
+                    .try_get::<NaiveDateTime, _>(i)
+                    .ok()
+                    .map(|v| JsonValue::String(v.format(record::ISO_TIMESTAMP_FORMAT).to_string())),
+                "TIMESTAMPTZ" => row
+                    .try_get::<DateTime<Utc>, _>(i)
+                    .ok()
+                    .map(|v| JsonValue::String(v.to_rfc3339())),
+                "DATE" => row
+                    .try_get::<NaiveDate, _>(i)
+                    .ok()
+                    .map(|v| JsonValue::String(v.to_string())),
+                "TIME" => row
+                    .try_get::<NaiveTime, _>(i)
+                    .ok()
+                    .map(|v| JsonValue::String(v.to_string())),
+                // sqlx only decodes the binary format; over the simple query protocol the
+                // server's own rendering is kept
+                "INTERVAL" => row
+                    .try_get::<PgInterval, _>(i)
+                    .ok()
+                    .map(|v| JsonValue::String(interval_to_iso8601(&v)))
+                    .or_else(|| decoded(record::value_to_json(value, money_scale))),
                 _ => {
-                    // Default to string representation
+                    // Default to string representation, decoding whatever isn't text-like
+                    // (enums, network addresses, ranges, ...) from its binary form
                     row.try_get::<String, _>(i)
+                        .ok()
                         .map(JsonValue::String)
-                        .unwrap_or(JsonValue::Null)
+                        .or_else(|| decoded(record::value_to_json(value, money_scale)))
                 }
-            }
+            };
+            json.unwrap_or_else(|| record::decode_error(type_name))
         })
         .collect()
 }
 
+/// Renders an interval as an ISO-8601 duration, e.g. `P1Y2M3DT4H5M6.5S`.
+/// Like Postgres' iso_8601 IntervalStyle, each component carries its own sign.
+fn interval_to_iso8601(interval: &PgInterval) -> String {
+    let mut out = String::from("P");
+
+    let (years, months) = (interval.months / 12, interval.months % 12);
+    for (value, unit) in [
+        (years as i64, 'Y'),
+        (months as i64, 'M'),
+        (interval.days as i64, 'D'),
+    ] {
+        if value != 0 {
+            out.push_str(&format!("{}{}", value, unit));
+        }
+    }
+
+    let micros = interval.microseconds;
+    let (hours, minutes) = (micros / 3_600_000_000, micros / 60_000_000 % 60);
+    let sub_minute = micros % 60_000_000;
+
+    if hours != 0 || minutes != 0 || sub_minute != 0 {
+        out.push('T');
+        if hours != 0 {
+            out.push_str(&format!("{}H", hours));
+        }
+        if minutes != 0 {
+            out.push_str(&format!("{}M", minutes));
+        }
+        if sub_minute != 0 {
+            let seconds = format!("{:.6}", sub_minute as f64 / 1_000_000.0);
+            out.push_str(seconds.trim_end_matches('0').trim_end_matches('.'));
+            out.push('S');
+        }
+    }
+
+    if out == "P" {
+        out.push_str("T0S");
+    }
+    out
+}
+
 /// Thread-safe wrapper for use with Tauri state
 pub type PostgresState = Arc<PostgresManager>;
 
@@ -415,3 +5027,777 @@ pub fn create_postgres_state() -> PostgresState {
     Arc::new(PostgresManager::new())
 }
 
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Postgres URL the tests that need a server run against, e.g.
+    /// `postgres://postgres@localhost/postgres`. Without it they pass without running
+    const TEST_DATABASE_URL_ENV: &str = "DATATOOL_TEST_PG_URL";
+
+    pub(crate) async fn test_database() -> Option<PostgresManager> {
+        let url = std::env::var(TEST_DATABASE_URL_ENV).ok()?;
+        let manager = PostgresManager::new();
+        manager
+            .connect_from_url("test", &url, PoolSettings::default(), "datatool - tests")
+            .await
+            .expect("connect to the test database");
+        Some(manager)
+    }
+
+    #[tokio::test]
+    async fn test_validate_query() {
+        let Some(pg) = test_database().await else {
+            return;
+        };
+
+        let valid = pg.validate_query("SELECT $1::int + 1 AS n").await.unwrap();
+        assert!(valid.valid);
+        assert_eq!(valid.parameter_types, ["integer"]);
+
+        // Positions count from the statement as written, past its leading whitespace
+        let invalid = pg
+            .validate_query("  SELECT * FROM no_such_table")
+            .await
+            .unwrap();
+        assert!(!invalid.valid);
+        assert_eq!(invalid.error_code.as_deref(), Some("42P01"));
+        assert_eq!(invalid.error_position, Some(17));
+
+        // Each validation prepares under a fresh name, so repeating one never collides
+        for _ in 0..2 {
+            assert!(pg.validate_query("SELECT 1").await.unwrap().valid);
+        }
+
+        let utility = pg.validate_query("VACUUM").await.unwrap();
+        assert!(utility.error.unwrap().contains("can be validated"));
+
+        pg.begin_transaction(None).await.unwrap();
+        assert!(!pg.validate_query("SELECT nope").await.unwrap().valid);
+        assert!(pg.validate_query("SELECT 1").await.unwrap().valid);
+        pg.rollback_transaction().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fetch_column_types() {
+        let Some(pg) = test_database().await else {
+            return;
+        };
+        let table = format!("dt_column_types_{}", std::process::id());
+        let pool = pg.pool.read().await.clone().unwrap();
+
+        sqlx::raw_sql(&format!(
+            "CREATE TABLE {} (code char(3), flags bit(3), price numeric(10, 2), note text)",
+            table
+        ))
+        .execute(&pool)
+        .await
+        .unwrap();
+        let types = pg.fetch_column_types("public", &table).await;
+        sqlx::raw_sql(&format!("DROP TABLE {}", table))
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let types = types.unwrap();
+        assert_eq!(types["code"], "character(3)");
+        assert_eq!(types["flags"], "bit(3)");
+        assert_eq!(types["price"], "numeric(10,2)");
+        assert_eq!(types["note"], "text");
+    }
+
+    #[tokio::test]
+    async fn test_decode_record_columns() {
+        let Some(pg) = test_database().await else {
+            return;
+        };
+
+        let result = pg
+            .execute_query(
+                "SELECT (1, 'a', NULL::int) AS anonymous, t AS composite \
+                 FROM (VALUES (1, 'x')) t(a, b)",
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&result.rows).unwrap(),
+            serde_json::json!([[[1, "a", null], [1, "x"]]])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_decode_array_columns() {
+        let Some(pg) = test_database().await else {
+            return;
+        };
+
+        let result = pg
+            .execute_query(
+                "SELECT ARRAY[1, 2], ARRAY[['a', 'b'], ['c', NULL]], '{}'::int[], \
+                 '[2:3]={7,8}'::int[], ARRAY['2024-01-02'::date]",
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&result.rows).unwrap(),
+            serde_json::json!([[
+                [1, 2],
+                [["a", "b"], ["c", null]],
+                [],
+                [7, 8],
+                ["2024-01-02"]
+            ]])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_decode_date_time_columns() {
+        let Some(pg) = test_database().await else {
+            return;
+        };
+
+        let result = pg
+            .execute_query(
+                "SELECT '2024-01-02'::date, '12:34:56.5'::time, '12:34:56+02'::timetz, \
+                 '2024-01-02 03:04:05'::timestamp, '2024-01-02 03:04:05+00'::timestamptz, \
+                 '1 year 2 mons 3 days 04:05:06.5'::interval, ARRAY['01:02'::time]",
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&result.rows).unwrap(),
+            serde_json::json!([[
+                "2024-01-02",
+                "12:34:56.500",
+                "12:34:56+02:00",
+                "2024-01-02T03:04:05",
+                "2024-01-02T03:04:05+00:00",
+                "P1Y2M3DT4H5M6.5S",
+                ["01:02:00"]
+            ]])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_with_params() {
+        let Some(pg) = test_database().await else {
+            return;
+        };
+
+        let params = serde_json::json!([41, 1.5, true, "x", null, { "a": [1, 2] }]);
+        let result = pg
+            .query_with_params(
+                "SELECT $1 + 1 AS i, $2 * 2 AS f, NOT $3 AS b, upper($4) AS s, \
+                 $5::int IS NULL AS n, $6 -> 'a' AS j",
+                params.as_array().unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            result.rows,
+            [serde_json::json!([42, 3.0, false, "X", true, [1, 2]])
+                .as_array()
+                .unwrap()
+                .clone()]
+        );
+
+        // Values are bound, never spliced into the SQL
+        let injection = serde_json::json!(["'; DROP TABLE x; --"]);
+        let result = pg
+            .query_with_params("SELECT $1 AS s", injection.as_array().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(result.rows[0][0], "'; DROP TABLE x; --");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_scalar() {
+        let Some(pg) = test_database().await else {
+            return;
+        };
+
+        assert_eq!(
+            pg.fetch_scalar("SELECT 41 + 1, 'ignored'").await.unwrap(),
+            42
+        );
+        assert!(pg.fetch_scalar("SELECT NULL").await.unwrap().is_null());
+        assert!(matches!(
+            pg.fetch_scalar("SELECT 1 WHERE false").await,
+            Err(PostgresError::QueryFailed(message)) if message.contains("no rows")
+        ));
+        assert!(matches!(
+            pg.fetch_scalar("SELECT FROM generate_series(1, 2)").await,
+            Err(PostgresError::QueryFailed(message)) if message.contains("no columns")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_query_notices() {
+        let Some(pg) = test_database().await else {
+            return;
+        };
+
+        let result = pg
+            .execute_query_tracked(
+                "DO $$ BEGIN RAISE NOTICE 'first'; RAISE WARNING 'second'; END $$",
+                None,
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.notices, ["first", "second"]);
+
+        let result = pg
+            .execute_query_tracked("SELECT 1", None, None, false)
+            .await
+            .unwrap();
+        assert!(result.notices.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_read_only_connection() {
+        let Ok(url) = std::env::var(TEST_DATABASE_URL_ENV) else {
+            return;
+        };
+        let separator = if url.contains('?') { '&' } else { '?' };
+        let read_only_url = format!(
+            "{}{}options=-c%20default_transaction_read_only%3Don",
+            url, separator
+        );
+        let pg = PostgresManager::new();
+        pg.connect_from_url("test", &read_only_url, PoolSettings::default(), "datatool")
+            .await
+            .unwrap();
+        assert!(pg.is_read_only().await);
+
+        assert!(pg.execute_query("SELECT 1").await.is_ok());
+        for sql in [
+            "SET default_transaction_read_only = off",
+            "BEGIN READ WRITE",
+            "SET SESSION CHARACTERISTICS AS TRANSACTION READ WRITE",
+        ] {
+            assert!(matches!(
+                pg.execute_query(sql).await,
+                Err(PostgresError::ReadOnly)
+            ));
+            assert!(matches!(
+                pg.execute_script(sql).await,
+                Err(PostgresError::ReadOnly)
+            ));
+        }
+        // What gets past the check is still turned down by the server
+        assert!(matches!(
+            pg.execute_query("CREATE TABLE dt_read_only (a int)").await,
+            Err(PostgresError::ReadOnly)
+        ));
+        assert!(matches!(
+            pg.terminate_session(1).await,
+            Err(PostgresError::ReadOnly)
+        ));
+
+        let Some(writable) = test_database().await else {
+            return;
+        };
+        assert!(!writable.is_read_only().await);
+    }
+
+    #[tokio::test]
+    async fn test_terminate_own_session() {
+        let Some(pg) = test_database().await else {
+            return;
+        };
+
+        let pid = pg.fetch_scalar("SELECT pg_backend_pid()").await.unwrap();
+        let pid = pid.as_i64().unwrap() as i32;
+        assert!(matches!(
+            pg.terminate_session(pid).await,
+            Err(PostgresError::InvalidInput(_))
+        ));
+        assert!(!pg.terminate_session(i32::MAX).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_connection_stats() {
+        let Some(pg) = test_database().await else {
+            return;
+        };
+
+        let stats = pg.connection_stats().await.unwrap();
+        // Nothing was running when the pool was measured
+        assert_eq!(stats.pool_idle, stats.pool_size as usize);
+        assert!(stats.server_version.is_some());
+        assert!(stats.user_sessions.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_transaction_last_used() {
+        let Some(pg) = test_database().await else {
+            return;
+        };
+
+        pg.begin_transaction(None).await.unwrap();
+        pg.execute_query_tracked("SELECT pg_sleep(0.3)", None, None, false)
+            .await
+            .unwrap();
+        // Idle time counts from when the statement finished, not when it started
+        let transaction = pg.transaction.lock().await;
+        let idle = transaction.as_ref().unwrap().last_used.elapsed();
+        drop(transaction);
+        assert!(idle < Duration::from_millis(200), "{:?}", idle);
+        pg.rollback_transaction().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancel_all_queries_with_full_pool() {
+        let Ok(url) = std::env::var(TEST_DATABASE_URL_ENV) else {
+            return;
+        };
+        let pg = Arc::new(PostgresManager::new());
+        let settings = PoolSettings {
+            max_connections: 1,
+            acquire_timeout_secs: 2,
+            ..PoolSettings::default()
+        };
+        pg.connect_from_url("test", &url, settings, "datatool - tests")
+            .await
+            .unwrap();
+
+        let running = tokio::spawn({
+            let pg = pg.clone();
+            async move {
+                pg.execute_query_tracked("SELECT pg_sleep(30)", Some("sleep"), None, false)
+                    .await
+            }
+        });
+        while !pg.running_queries.lock().unwrap().contains_key("sleep") {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        assert_eq!(pg.cancel_all_queries().await.unwrap(), 1);
+        assert!(matches!(
+            running.await.unwrap(),
+            Err(PostgresError::Cancelled)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_network_and_money_values() {
+        let Some(pg) = test_database().await else {
+            return;
+        };
+
+        let result = pg
+            .execute_query(
+                "SELECT '1234.56'::money, '10.0.0.1'::inet, '08:00:2b:01:02:03'::macaddr",
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.rows[0], ["1234.56", "10.0.0.1", "08:00:2b:01:02:03"]);
+        assert_eq!(pg.money_scale(), 2);
+
+        // Amounts inside arrays are rendered with the same scale
+        let result = pg
+            .execute_query("SELECT ARRAY['5'::money, '-0.07'::money]")
+            .await
+            .unwrap();
+        assert_eq!(result.rows[0][0], serde_json::json!(["5.00", "-0.07"]));
+    }
+
+    #[tokio::test]
+    async fn test_terminate_foreign_session() {
+        let Some(pg) = test_database().await else {
+            return;
+        };
+
+        // Another window of the app connects under the same application_name and user
+        let options = pg.pool.read().await.as_ref().unwrap().connect_options();
+        let mut other = PgConnection::connect_with(&options).await.unwrap();
+        let (pid,): (i32,) = sqlx::query_as("SELECT pg_backend_pid()")
+            .fetch_one(&mut other)
+            .await
+            .unwrap();
+
+        assert!(pg.terminate_session(pid).await.unwrap());
+        assert!(sqlx::query("SELECT 1").execute(&mut other).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_distinguish_nulls() {
+        let Some(pg) = test_database().await else {
+            return;
+        };
+
+        let result = pg
+            .execute_query_tracked("SELECT NULL::text, 'null'::jsonb, ''", None, None, true)
+            .await
+            .unwrap();
+        assert_eq!(
+            result.rows[0],
+            [
+                record::null_cell(true),
+                JsonValue::Null,
+                JsonValue::from("")
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_query_multi() {
+        let Some(pg) = test_database().await else {
+            return;
+        };
+
+        let results = pg
+            .execute_query_multi(
+                "CREATE TEMP TABLE multi_test (id int);
+                 INSERT INTO multi_test SELECT generate_series(1, 3);
+                 SELECT id FROM multi_test ORDER BY id;
+                 SELECT 'one'",
+                None,
+                None,
+                Some(2),
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[1].affected_rows, Some(3));
+        assert_eq!(
+            results[2].rows,
+            [[JsonValue::from(1)], [JsonValue::from(2)]]
+        );
+        assert!(results[2].truncated);
+        assert_eq!(results[3].rows, [["one"]]);
+        assert!(!results[3].truncated);
+    }
+
+    #[tokio::test]
+    async fn test_null_and_json_values() {
+        let Some(pg) = test_database().await else {
+            return;
+        };
+
+        let result = pg
+            .execute_query(r#"SELECT NULL::timestamp, '{"__decode_error__": "x"}'::jsonb"#)
+            .await
+            .unwrap();
+        assert_eq!(result.rows[0][0], JsonValue::Null);
+        assert_eq!(
+            result.rows[0][1],
+            serde_json::from_str::<JsonValue>(r#"{"__decode_error__": "x"}"#).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_table_sizes_bloat() {
+        let Some(pg) = test_database().await else {
+            return;
+        };
+
+        for sql in [
+            "CREATE EXTENSION IF NOT EXISTS pgstattuple",
+            "DROP TABLE IF EXISTS public.table_sizes_test",
+            "CREATE TABLE public.table_sizes_test AS SELECT generate_series(1, 100) AS id",
+            "DO $$ BEGIN
+                IF NOT EXISTS (SELECT 1 FROM pg_roles WHERE rolname = 'datatool_no_scan') THEN
+                    CREATE ROLE datatool_no_scan LOGIN;
+                END IF;
+            END $$",
+        ] {
+            pg.execute_query(sql).await.unwrap();
+        }
+        let bloat = |sizes: Vec<TableSize>| {
+            sizes
+                .into_iter()
+                .find(|size| size.name == "table_sizes_test")
+                .unwrap()
+                .bloat
+        };
+
+        let sizes = pg.fetch_table_sizes(Some("public"), true).await.unwrap();
+        assert!(bloat(sizes).is_some());
+
+        // Without EXECUTE on pgstattuple_approx the sizes still come back
+        let url = std::env::var(TEST_DATABASE_URL_ENV).unwrap();
+        let options = conninfo::parse(&url)
+            .unwrap()
+            .options
+            .username("datatool_no_scan");
+        let unprivileged = PostgresManager::new();
+        unprivileged
+            .connect_with_options("test", options, PoolSettings::default(), None)
+            .await
+            .unwrap();
+        let sizes = unprivileged
+            .fetch_table_sizes(Some("public"), true)
+            .await
+            .unwrap();
+        assert!(bloat(sizes).is_none());
+
+        pg.execute_query("DROP TABLE public.table_sizes_test")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_json_path_number_filter() {
+        let Some(pg) = test_database().await else {
+            return;
+        };
+
+        for sql in [
+            "DROP TABLE IF EXISTS public.json_filter_test",
+            r#"CREATE TABLE public.json_filter_test AS
+               SELECT * FROM (VALUES
+                   (1, '{"n": 9}'::jsonb), (2, '{"n": 10}'), (3, '{"n": "many"}'), (4, '{}')
+               ) AS t(id, data)"#,
+        ] {
+            pg.execute_query(sql).await.unwrap();
+        }
+
+        let filters = [ColumnFilter {
+            column: "data".to_string(),
+            op: browse::FilterOp::Gt,
+            value: serde_json::json!(9),
+            json_path: vec!["n".to_string()],
+        }];
+        let order_by = [SortColumn {
+            column: "id".to_string(),
+            direction: browse::SortDirection::Asc,
+        }];
+        let result = pg
+            .fetch_table_data(
+                "public",
+                "json_filter_test",
+                1,
+                10,
+                None,
+                false,
+                &order_by,
+                &filters,
+                false,
+            )
+            .await
+            .unwrap();
+        // Compared as text, "10" would not be greater than "9", but "many" would
+        let ids: Vec<&JsonValue> = result.rows.iter().map(|row| &row[0]).collect();
+        assert_eq!(ids, [2]);
+
+        pg.execute_query("DROP TABLE public.json_filter_test")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_data_version() {
+        let Some(pg) = test_database().await else {
+            return;
+        };
+
+        let mut version = pg.data_version();
+        pg.execute_query("SELECT 1").await.unwrap();
+        pg.fetch_scalar("SELECT 1").await.unwrap();
+        assert_eq!(pg.data_version(), version);
+
+        let mut changed = |pg: &PostgresManager| {
+            let changed = pg.data_version() != version;
+            version = pg.data_version();
+            changed
+        };
+        pg.execute_query("CREATE TEMP TABLE data_version_test (id int)")
+            .await
+            .unwrap();
+        assert!(changed(&pg));
+        pg.execute_batch_with_params(&[("SELECT $1::int".to_string(), vec![serde_json::json!(1)])])
+            .await
+            .unwrap();
+        assert!(changed(&pg));
+        pg.begin_transaction(None).await.unwrap();
+        pg.commit_transaction().await.unwrap();
+        assert!(changed(&pg));
+        pg.set_search_path(Some("public")).await.unwrap();
+        assert!(changed(&pg));
+        pg.set_search_path(None).await.unwrap();
+        assert!(changed(&pg));
+    }
+
+    #[tokio::test]
+    async fn test_ddl_invalidates_cached_results() {
+        let Some(pg) = test_database().await else {
+            return;
+        };
+
+        pg.execute_ddl("DROP MATERIALIZED VIEW IF EXISTS cache_refresh_test")
+            .await
+            .unwrap();
+        pg.execute_ddl("DROP TABLE IF EXISTS cache_refresh_source")
+            .await
+            .unwrap();
+        pg.execute_ddl("CREATE TABLE cache_refresh_source (id int)")
+            .await
+            .unwrap();
+        pg.execute_ddl(
+            "CREATE MATERIALIZED VIEW cache_refresh_test AS SELECT count(*) AS n FROM cache_refresh_source",
+        )
+        .await
+        .unwrap();
+
+        let cache = crate::db::cache::QueryCache::new();
+        let sql = "SELECT n FROM cache_refresh_test";
+        let version = pg.data_version();
+        let result = pg.execute_query(sql).await.unwrap();
+        cache.insert("pg", sql, None, false, version, result);
+        assert!(cache
+            .get("pg", sql, None, false, pg.data_version())
+            .is_some());
+
+        pg.execute_ddl("INSERT INTO cache_refresh_source VALUES (1)")
+            .await
+            .unwrap();
+        pg.execute_ddl("REFRESH MATERIALIZED VIEW cache_refresh_test")
+            .await
+            .unwrap();
+        assert!(cache
+            .get("pg", sql, None, false, pg.data_version())
+            .is_none());
+
+        let version = pg.data_version();
+        pg.explain_query("INSERT INTO cache_refresh_source VALUES (2)")
+            .await
+            .unwrap();
+        assert_ne!(pg.data_version(), version);
+
+        pg.execute_ddl("DROP MATERIALIZED VIEW cache_refresh_test")
+            .await
+            .unwrap();
+        pg.execute_ddl("DROP TABLE cache_refresh_source")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reconnect() {
+        let Some(pg) = test_database().await else {
+            return;
+        };
+
+        pg.reconnect().await.unwrap();
+        assert_eq!(pg.execute_query("SELECT 1").await.unwrap().row_count, 1);
+        assert_eq!(pg.get_connection_id().await.as_deref(), Some("test"));
+
+        pg.disconnect().await;
+        assert!(matches!(
+            pg.reconnect().await,
+            Err(PostgresError::NoActiveConnection)
+        ));
+    }
+
+    #[test]
+    fn test_interval_to_iso8601() {
+        let interval = |months, days, microseconds| PgInterval {
+            months,
+            days,
+            microseconds,
+        };
+
+        assert_eq!(
+            interval_to_iso8601(&interval(14, 3, 14_706_500_000)),
+            "P1Y2M3DT4H5M6.5S"
+        );
+        assert_eq!(interval_to_iso8601(&interval(0, 0, 0)), "PT0S");
+        assert_eq!(
+            interval_to_iso8601(&interval(0, -1, -90_000_000)),
+            "P-1DT-1M-30S"
+        );
+    }
+
+    #[test]
+    fn test_is_connection_lost() {
+        let io = std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset");
+        assert!(is_connection_lost(&sqlx::Error::Io(io)));
+        assert!(is_connection_lost(&sqlx::Error::WorkerCrashed));
+        assert!(!is_connection_lost(&sqlx::Error::PoolClosed));
+        assert!(!is_connection_lost(&sqlx::Error::RowNotFound));
+    }
+
+    #[test]
+    fn test_connect_options_socket() {
+        let options = |host| {
+            connect_options(
+                host,
+                5432,
+                "app",
+                "u",
+                "",
+                SslMode::Require,
+                Some("/etc/ca.pem"),
+                false,
+                DEFAULT_STATEMENT_CACHE_CAPACITY,
+                "datatool",
+            )
+        };
+
+        // A directory is the socket's location, reached without TLS
+        let socket = options("/var/run/postgresql");
+        assert_eq!(
+            socket.get_socket(),
+            Some(&std::path::PathBuf::from("/var/run/postgresql"))
+        );
+        assert!(matches!(socket.get_ssl_mode(), PgSslMode::Disable));
+
+        let tcp = options("db.example.com");
+        assert_eq!(tcp.get_socket(), None);
+        assert_eq!(tcp.get_host(), "db.example.com");
+        assert!(matches!(tcp.get_ssl_mode(), PgSslMode::Require));
+    }
+
+    #[test]
+    fn test_application_name() {
+        assert_eq!(application_name("prod", None), "datatool - prod");
+        assert_eq!(application_name("prod", Some(" ")), "datatool - prod");
+        assert_eq!(application_name("prod", Some("etl-debug")), "etl-debug");
+        assert_eq!(application_name("", None), "datatool");
+
+        // Cut at 63 bytes would split an é
+        let long = application_name(&format!("x{}", "é".repeat(40)), None);
+        assert_eq!(long.len(), 62);
+        assert!(long.ends_with('é'));
+    }
+
+    #[test]
+    fn test_quote_ident() {
+        assert_eq!(quote_ident("orders"), r#""orders""#);
+        assert_eq!(quote_ident("order items"), r#""order items""#);
+        assert_eq!(
+            quote_ident(r#"foo"; DROP TABLE bar; --"#),
+            r#""foo""; DROP TABLE bar; --""#
+        );
+        assert_eq!(quote_literal("it's"), "'it''s'");
+    }
+
+    #[test]
+    fn test_validate_search_path() {
+        let valid = [
+            "public",
+            "app, public",
+            r#""$user", public"#,
+            r#""My ""Schema""",x"#,
+        ];
+        for valid in valid {
+            assert!(validate_search_path(valid).is_ok(), "{}", valid);
+        }
+        let invalid = [
+            "",
+            "a b",
+            "app,",
+            ",app",
+            r#""unterminated"#,
+            r#""""#,
+            r#"a"b"#,
+        ];
+        for invalid in invalid {
+            assert!(validate_search_path(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
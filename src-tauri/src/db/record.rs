@@ -0,0 +1,860 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use sqlx::postgres::{PgTypeInfo, PgTypeKind, PgValueFormat, PgValueRef};
+use sqlx::{TypeInfo, ValueRef};
+use std::fmt::Write;
+use std::net::IpAddr;
+use std::sync::RwLock;
+
+/// ISO-8601 rendering used for timestamps without time zone
+pub const ISO_TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+/// Key of the object a cell is replaced with when its non-null value can't be decoded
+pub const DECODE_ERROR_KEY: &str = "__decode_error__";
+
+/// Key of the object that stands in for SQL NULL when nulls are told apart, see null_cell
+pub const NULL_KEY: &str = "__null__";
+
+/// App state key the BYTEA rendering preference is saved under
+pub const BINARY_ENCODING_KEY: &str = "binary_encoding";
+
+static BINARY_ENCODING: RwLock<BinaryEncoding> = RwLock::new(BinaryEncoding::Hex);
+
+/// Fraction digits of a MONEY amount when the server's lc_monetary can't be asked, as
+/// under the C locale
+pub const DEFAULT_MONEY_SCALE: u32 = 2;
+
+// Built-in type OIDs from pg_type.dat (these never change between servers)
+const BOOL_OID: u32 = 16;
+const BYTEA_OID: u32 = 17;
+const INT8_OID: u32 = 20;
+const INT2_OID: u32 = 21;
+const INT4_OID: u32 = 23;
+const OID_OID: u32 = 26;
+const JSON_OID: u32 = 114;
+const CIDR_OID: u32 = 650;
+const MACADDR8_OID: u32 = 774;
+const MONEY_OID: u32 = 790;
+const MACADDR_OID: u32 = 829;
+const INET_OID: u32 = 869;
+const FLOAT4_OID: u32 = 700;
+const FLOAT8_OID: u32 = 701;
+const DATE_OID: u32 = 1082;
+const TIME_OID: u32 = 1083;
+const TIMESTAMP_OID: u32 = 1114;
+const TIMESTAMPTZ_OID: u32 = 1184;
+const TIMETZ_OID: u32 = 1266;
+const NUMERIC_OID: u32 = 1700;
+const RECORD_OID: u32 = 2249;
+const UUID_OID: u32 = 2950;
+const JSONB_OID: u32 = 3802;
+
+// Range flags, from rangetypes.h
+const RANGE_EMPTY: u8 = 0x01;
+const RANGE_LB_INC: u8 = 0x02;
+const RANGE_UB_INC: u8 = 0x04;
+const RANGE_LB_INF: u8 = 0x08;
+const RANGE_UB_INF: u8 = 0x10;
+
+/// How BYTEA values are rendered as JSON strings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BinaryEncoding {
+    /// `\x` followed by hex digits, as psql shows them
+    #[default]
+    Hex,
+    Base64,
+}
+
+impl BinaryEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BinaryEncoding::Hex => "hex",
+            BinaryEncoding::Base64 => "base64",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [BinaryEncoding::Hex, BinaryEncoding::Base64]
+            .into_iter()
+            .find(|encoding| encoding.as_str() == name)
+    }
+
+    pub fn encode(&self, data: &[u8]) -> String {
+        match self {
+            BinaryEncoding::Hex => format!("\\x{}", hex::encode(data)),
+            BinaryEncoding::Base64 => STANDARD.encode(data),
+        }
+    }
+}
+
+/// The encoding BYTEA values are currently rendered with
+pub fn binary_encoding() -> BinaryEncoding {
+    BINARY_ENCODING.read().map(|e| *e).unwrap_or_default()
+}
+
+/// Changes the encoding BYTEA values are rendered with, for every later query
+pub fn set_binary_encoding(encoding: BinaryEncoding) {
+    if let Ok(mut current) = BINARY_ENCODING.write() {
+        *current = encoding;
+    }
+}
+
+/// Returns true for column types that hold a row value (anonymous RECORD or a named composite)
+pub fn is_record_type(type_info: &PgTypeInfo) -> bool {
+    type_info.name() == "RECORD" || matches!(type_info.kind(), PgTypeKind::Composite(_))
+}
+
+/// Decodes a record/composite value into JSON. Named composites become objects keyed by
+/// field name; anonymous records become arrays since their fields have no names.
+pub fn record_to_json(value: PgValueRef<'_>, money_scale: u32) -> JsonValue {
+    if value.is_null() {
+        return JsonValue::Null;
+    }
+
+    let type_info = value.type_info().into_owned();
+    let format = value.format();
+    let Ok(bytes) = value.as_bytes() else {
+        return JsonValue::Null;
+    };
+
+    match format {
+        PgValueFormat::Binary => {
+            let fields = match type_info.kind() {
+                PgTypeKind::Composite(fields) => Some(&fields[..]),
+                _ => None,
+            };
+            decode_binary_record(bytes, fields, money_scale).unwrap_or(JsonValue::Null)
+        }
+        // Text-format results already carry Postgres' own `(a,b)` rendering
+        PgValueFormat::Text => JsonValue::String(String::from_utf8_lossy(bytes).into_owned()),
+    }
+}
+
+/// Returns true for array column types (`INT4[]`, `TEXT[]`, ...)
+pub fn is_array_type(type_info: &PgTypeInfo) -> bool {
+    matches!(type_info.kind(), PgTypeKind::Array(_))
+}
+
+/// Decodes an array value into a JSON array; multidimensional arrays become nested arrays
+pub fn array_to_json(value: PgValueRef<'_>, money_scale: u32) -> JsonValue {
+    if value.is_null() {
+        return JsonValue::Null;
+    }
+
+    let type_info = value.type_info().into_owned();
+    let format = value.format();
+    let Ok(bytes) = value.as_bytes() else {
+        return JsonValue::Null;
+    };
+
+    match format {
+        PgValueFormat::Binary => {
+            let element_type = match type_info.kind() {
+                PgTypeKind::Array(element_type) => Some(element_type),
+                _ => None,
+            };
+            decode_binary_array(bytes, element_type, money_scale).unwrap_or(JsonValue::Null)
+        }
+        // Text-format results already carry Postgres' own `{a,b}` rendering
+        PgValueFormat::Text => JsonValue::String(String::from_utf8_lossy(bytes).into_owned()),
+    }
+}
+
+/// Decodes a value of a type that has no decoder of its own in row_to_json_values.
+/// Whatever the binary decoders can't read is shown as its bytes taken as text, so a
+/// value never turns into null
+pub fn value_to_json(value: PgValueRef<'_>, money_scale: u32) -> JsonValue {
+    if value.is_null() {
+        return JsonValue::Null;
+    }
+
+    let type_info = value.type_info().into_owned();
+    let format = value.format();
+    let Ok(bytes) = value.as_bytes() else {
+        return JsonValue::Null;
+    };
+
+    match format {
+        PgValueFormat::Binary => {
+            let oid = type_info.oid().map_or(0, |oid| oid.0);
+            decode_value(oid, bytes, Some(&type_info), money_scale)
+                .unwrap_or_else(|| lossy_text(bytes))
+        }
+        PgValueFormat::Text => lossy_text(bytes),
+    }
+}
+
+/// Stands in for a non-null value that couldn't be decoded, naming its type so the cell
+/// can't be mistaken for NULL. A JSON column's value could be this very object, so
+/// those columns never get it
+pub fn decode_error(type_name: &str) -> JsonValue {
+    let mut object = Map::new();
+    object.insert(
+        DECODE_ERROR_KEY.to_string(),
+        JsonValue::String(type_name.to_string()),
+    );
+    JsonValue::Object(object)
+}
+
+/// The value of a SQL NULL cell: `{"__null__": true}` with `distinguish_nulls`, for
+/// consumers that would otherwise blur it with an empty string, and null otherwise.
+/// Nulls inside arrays, records and JSON values are left as they are
+pub fn null_cell(distinguish_nulls: bool) -> JsonValue {
+    if !distinguish_nulls {
+        return JsonValue::Null;
+    }
+    let mut object = Map::new();
+    object.insert(NULL_KEY.to_string(), JsonValue::Bool(true));
+    JsonValue::Object(object)
+}
+
+/// Decodes a NUMERIC value into a decimal string, which keeps every digit
+/// (an f64 would silently round large or very precise values)
+pub fn numeric_to_json(value: PgValueRef<'_>) -> JsonValue {
+    if value.is_null() {
+        return JsonValue::Null;
+    }
+
+    let format = value.format();
+    let Ok(bytes) = value.as_bytes() else {
+        return JsonValue::Null;
+    };
+
+    let decoded = match format {
+        PgValueFormat::Binary => decode_numeric(bytes),
+        PgValueFormat::Text => std::str::from_utf8(bytes).ok().map(str::to_string),
+    };
+    decoded.map(JsonValue::String).unwrap_or(JsonValue::Null)
+}
+
+/// Parses the binary record wire format: a field count, then for each field its type
+/// OID, its length (-1 for NULL) and its bytes. Returns None if the buffer is malformed.
+pub fn decode_binary_record(
+    mut buf: &[u8],
+    fields: Option<&[(String, PgTypeInfo)]>,
+    money_scale: u32,
+) -> Option<JsonValue> {
+    let count = read_u32(&mut buf)? as usize;
+    let mut values = Vec::with_capacity(count);
+
+    for index in 0..count {
+        let oid = read_u32(&mut buf)?;
+        let len = read_u32(&mut buf)? as i32;
+        if len < 0 {
+            values.push(JsonValue::Null);
+            continue;
+        }
+
+        let len = len as usize;
+        if buf.len() < len {
+            return None;
+        }
+        let (data, rest) = buf.split_at(len);
+        buf = rest;
+
+        let field_type = fields.and_then(|f| f.get(index)).map(|(_, ty)| ty);
+        values.push(decode_value(oid, data, field_type, money_scale)?);
+    }
+
+    match fields {
+        Some(fields) if fields.len() == values.len() => Some(JsonValue::Object(
+            fields
+                .iter()
+                .map(|(name, _)| name.clone())
+                .zip(values)
+                .collect::<Map<String, JsonValue>>(),
+        )),
+        _ => Some(JsonValue::Array(values)),
+    }
+}
+
+/// Parses the binary array wire format: the number of dimensions, a has-nulls flag and the
+/// element type OID, then a (length, lower bound) pair per dimension, then every element
+/// as a length (-1 for NULL) and its bytes. Returns None if the buffer is malformed.
+pub fn decode_binary_array(
+    mut buf: &[u8],
+    element_type: Option<&PgTypeInfo>,
+    money_scale: u32,
+) -> Option<JsonValue> {
+    let ndim = read_u32(&mut buf)? as usize;
+    let _has_nulls = read_u32(&mut buf)?;
+    let element_oid = read_u32(&mut buf)?;
+
+    let mut dims = Vec::with_capacity(ndim);
+    for _ in 0..ndim {
+        dims.push(read_u32(&mut buf)? as usize);
+        let _lower_bound = read_u32(&mut buf)?;
+    }
+
+    if dims.is_empty() {
+        return Some(JsonValue::Array(vec![]));
+    }
+    decode_array_dimension(&mut buf, &dims, element_oid, element_type, money_scale)
+}
+
+fn decode_array_dimension(
+    buf: &mut &[u8],
+    dims: &[usize],
+    element_oid: u32,
+    element_type: Option<&PgTypeInfo>,
+    money_scale: u32,
+) -> Option<JsonValue> {
+    let (&len, inner) = dims.split_first()?;
+    let mut values = Vec::with_capacity(len);
+
+    for _ in 0..len {
+        if !inner.is_empty() {
+            values.push(decode_array_dimension(
+                buf,
+                inner,
+                element_oid,
+                element_type,
+                money_scale,
+            )?);
+            continue;
+        }
+
+        let element_len = read_u32(buf)? as i32;
+        if element_len < 0 {
+            values.push(JsonValue::Null);
+            continue;
+        }
+
+        let element_len = element_len as usize;
+        if buf.len() < element_len {
+            return None;
+        }
+        let (data, rest) = buf.split_at(element_len);
+        *buf = rest;
+        values.push(decode_value(element_oid, data, element_type, money_scale)?);
+    }
+
+    Some(JsonValue::Array(values))
+}
+
+/// Parses the binary range wire format: a flags byte, then each bound that isn't
+/// infinite as a length and its bytes. Renders the range the way Postgres prints it,
+/// e.g. `[1,5)`. Returns None if the buffer is malformed.
+fn decode_binary_range(
+    mut buf: &[u8],
+    element_type: &PgTypeInfo,
+    money_scale: u32,
+) -> Option<JsonValue> {
+    let (&flags, rest) = buf.split_first()?;
+    buf = rest;
+    if flags & RANGE_EMPTY != 0 {
+        return Some(JsonValue::String("empty".to_string()));
+    }
+
+    let element_oid = element_type.oid().map_or(0, |oid| oid.0);
+    let mut bound = |infinite: bool| -> Option<String> {
+        if infinite {
+            return Some(String::new());
+        }
+        let len = read_u32(&mut buf)? as usize;
+        if buf.len() < len {
+            return None;
+        }
+        let (data, rest) = buf.split_at(len);
+        buf = rest;
+        Some(
+            match decode_value(element_oid, data, Some(element_type), money_scale)? {
+                JsonValue::String(text) => quote_range_bound(&text),
+                value => value.to_string(),
+            },
+        )
+    };
+    let lower = bound(flags & RANGE_LB_INF != 0)?;
+    let upper = bound(flags & RANGE_UB_INF != 0)?;
+
+    Some(JsonValue::String(format!(
+        "{}{},{}{}",
+        if flags & RANGE_LB_INC != 0 { '[' } else { '(' },
+        lower,
+        upper,
+        if flags & RANGE_UB_INC != 0 { ']' } else { ')' },
+    )))
+}
+
+/// Quotes a range bound like range_out does when it is empty or holds characters that
+/// are part of the range syntax
+fn quote_range_bound(text: &str) -> String {
+    let needs_quotes = text.is_empty()
+        || text
+            .chars()
+            .any(|c| matches!(c, '"' | '\\' | ',' | '(' | ')' | '[' | ']') || c.is_whitespace());
+    if !needs_quotes {
+        return text.to_string();
+    }
+
+    let mut quoted = String::from('"');
+    for c in text.chars() {
+        if matches!(c, '"' | '\\') {
+            quoted.push(c);
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Decodes one binary value, recursing into nested records, arrays and ranges
+fn decode_value(
+    oid: u32,
+    data: &[u8],
+    type_info: Option<&PgTypeInfo>,
+    money_scale: u32,
+) -> Option<JsonValue> {
+    match type_info.map(|ty| ty.kind()) {
+        Some(PgTypeKind::Composite(fields)) => {
+            decode_binary_record(data, Some(fields), money_scale)
+        }
+        Some(PgTypeKind::Array(element_type)) => {
+            decode_binary_array(data, Some(element_type), money_scale)
+        }
+        Some(PgTypeKind::Range(element_type)) => {
+            decode_binary_range(data, element_type, money_scale)
+        }
+        // Domains are sent in the format of their base type
+        Some(PgTypeKind::Domain(base_type)) => decode_value(
+            base_type.oid().map_or(oid, |oid| oid.0),
+            data,
+            Some(base_type),
+            money_scale,
+        ),
+        _ if oid == RECORD_OID => decode_binary_record(data, None, money_scale),
+        _ => Some(decode_field(oid, data, money_scale)),
+    }
+}
+
+/// Decodes a single binary-format field. Types without a decoder fall back to their
+/// bytes as text, which is correct for text-like types (text, varchar, enums, ...) and
+/// at least shows something for the others.
+fn decode_field(oid: u32, data: &[u8], money_scale: u32) -> JsonValue {
+    let decoded = match oid {
+        BOOL_OID => data.first().map(|b| JsonValue::Bool(*b != 0)),
+        BYTEA_OID => Some(JsonValue::String(binary_encoding().encode(data))),
+        INT2_OID => data
+            .try_into()
+            .ok()
+            .map(|b| JsonValue::from(i16::from_be_bytes(b))),
+        INT4_OID => data
+            .try_into()
+            .ok()
+            .map(|b| JsonValue::from(i32::from_be_bytes(b))),
+        INT8_OID => data
+            .try_into()
+            .ok()
+            .map(|b| JsonValue::from(i64::from_be_bytes(b))),
+        OID_OID => data
+            .try_into()
+            .ok()
+            .map(|b| JsonValue::from(u32::from_be_bytes(b))),
+        FLOAT4_OID => data
+            .try_into()
+            .ok()
+            .and_then(|b| serde_json::Number::from_f64(f32::from_be_bytes(b) as f64))
+            .map(JsonValue::Number),
+        FLOAT8_OID => data
+            .try_into()
+            .ok()
+            .and_then(|b| serde_json::Number::from_f64(f64::from_be_bytes(b)))
+            .map(JsonValue::Number),
+        JSON_OID => serde_json::from_slice(data).ok(),
+        // jsonb is prefixed with a one-byte format version
+        JSONB_OID => data.get(1..).and_then(|d| serde_json::from_slice(d).ok()),
+        UUID_OID => uuid::Uuid::from_slice(data)
+            .ok()
+            .map(|u| JsonValue::String(u.to_string())),
+        NUMERIC_OID => decode_numeric(data).map(JsonValue::String),
+        MONEY_OID => data
+            .try_into()
+            .ok()
+            .map(|b| JsonValue::String(format_money(i64::from_be_bytes(b), money_scale))),
+        INET_OID | CIDR_OID => decode_inet(data).map(JsonValue::String),
+        MACADDR_OID | MACADDR8_OID if matches!(data.len(), 6 | 8) => Some(JsonValue::String(
+            data.iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<Vec<_>>()
+                .join(":"),
+        )),
+        DATE_OID => data.try_into().ok().and_then(|b| {
+            pg_epoch_date()
+                .checked_add_signed(Duration::days(i32::from_be_bytes(b) as i64))
+                .map(|d| JsonValue::String(d.to_string()))
+        }),
+        TIMESTAMP_OID => data.try_into().ok().and_then(|b| {
+            pg_epoch_timestamp(i64::from_be_bytes(b)).map(|ts| {
+                JsonValue::String(ts.naive_utc().format(ISO_TIMESTAMP_FORMAT).to_string())
+            })
+        }),
+        TIMESTAMPTZ_OID => data.try_into().ok().and_then(|b| {
+            pg_epoch_timestamp(i64::from_be_bytes(b)).map(|ts| JsonValue::String(ts.to_rfc3339()))
+        }),
+        TIME_OID => data
+            .try_into()
+            .ok()
+            .and_then(|b| pg_time(i64::from_be_bytes(b)))
+            .map(|time| JsonValue::String(time.to_string())),
+        // The time is followed by its UTC offset in seconds west of Greenwich
+        TIMETZ_OID => data.split_first_chunk::<8>().and_then(|(time, offset)| {
+            let time = pg_time(i64::from_be_bytes(*time))?;
+            let offset = FixedOffset::west_opt(i32::from_be_bytes(offset.try_into().ok()?))?;
+            Some(JsonValue::String(format!("{}{}", time, offset)))
+        }),
+        _ => None,
+    };
+
+    decoded.unwrap_or_else(|| lossy_text(data))
+}
+
+fn lossy_text(data: &[u8]) -> JsonValue {
+    JsonValue::String(String::from_utf8_lossy(data).into_owned())
+}
+
+/// Renders a MONEY amount, sent as a count of the currency's smallest unit, with `scale`
+/// fraction digits and without the currency symbol, as money::numeric does
+fn format_money(amount: i64, scale: u32) -> String {
+    let sign = if amount < 0 { "-" } else { "" };
+    let amount = amount.unsigned_abs();
+    match 10u64.checked_pow(scale).filter(|&unit| unit > 1) {
+        Some(unit) => format!(
+            "{}{}.{:0width$}",
+            sign,
+            amount / unit,
+            amount % unit,
+            width = scale as usize
+        ),
+        None => format!("{}{}", sign, amount),
+    }
+}
+
+/// Renders a binary INET or CIDR: family, netmask bits, a cidr flag, the address length
+/// and the address. Like Postgres, an inet holding a single host leaves out the netmask
+fn decode_inet(data: &[u8]) -> Option<String> {
+    let ([family, bits, is_cidr, len], address) = data.split_first_chunk::<4>()?;
+    let (address, host_bits) = match (family, len) {
+        (2, 4) => (IpAddr::from(<[u8; 4]>::try_from(address).ok()?), 32),
+        (3, 16) => (IpAddr::from(<[u8; 16]>::try_from(address).ok()?), 128),
+        _ => return None,
+    };
+
+    if *is_cidr == 0 && *bits == host_bits {
+        Some(address.to_string())
+    } else {
+        Some(format!("{}/{}", address, bits))
+    }
+}
+
+/// Renders a binary NUMERIC (base-10000 digit groups) as a decimal string, keeping full precision
+fn decode_numeric(data: &[u8]) -> Option<String> {
+    let header = |i: usize| data.get(i..i + 2).map(|b| u16::from_be_bytes([b[0], b[1]]));
+    let ndigits = header(0)? as usize;
+    let weight = header(2)? as i16 as i64;
+    let sign = header(4)?;
+    let dscale = header(6)? as usize;
+
+    match sign {
+        0xC000 => return Some("NaN".to_string()),
+        0xD000 => return Some("Infinity".to_string()),
+        0xF000 => return Some("-Infinity".to_string()),
+        _ => {}
+    }
+
+    let digits = (0..ndigits)
+        .map(|i| header(8 + i * 2))
+        .collect::<Option<Vec<u16>>>()?;
+    let digit = |i: i64| -> u16 {
+        if i < 0 {
+            0
+        } else {
+            digits.get(i as usize).copied().unwrap_or(0)
+        }
+    };
+
+    let mut out = String::new();
+    if sign == 0x4000 {
+        out.push('-');
+    }
+
+    if weight < 0 {
+        out.push('0');
+    } else {
+        for i in 0..=weight {
+            if i == 0 {
+                write!(out, "{}", digit(i)).ok()?;
+            } else {
+                write!(out, "{:04}", digit(i)).ok()?;
+            }
+        }
+    }
+
+    if dscale > 0 {
+        let mut fraction = String::new();
+        let mut i = weight + 1;
+        while fraction.len() < dscale {
+            write!(fraction, "{:04}", digit(i)).ok()?;
+            i += 1;
+        }
+        fraction.truncate(dscale);
+        out.push('.');
+        out.push_str(&fraction);
+    }
+
+    Some(out)
+}
+
+/// Postgres dates and timestamps count from 2000-01-01
+fn pg_epoch_date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2000, 1, 1).expect("valid date")
+}
+
+fn pg_epoch_timestamp(micros: i64) -> Option<DateTime<chrono::Utc>> {
+    pg_epoch_date()
+        .and_hms_opt(0, 0, 0)?
+        .and_utc()
+        .checked_add_signed(Duration::microseconds(micros))
+}
+
+/// Times are sent as microseconds since midnight
+fn pg_time(micros: i64) -> Option<NaiveTime> {
+    let seconds = u32::try_from(micros / 1_000_000).ok()?;
+    let nanos = u32::try_from(micros % 1_000_000).ok()? * 1_000;
+    NaiveTime::from_num_seconds_from_midnight_opt(seconds, nanos)
+}
+
+fn read_u32(buf: &mut &[u8]) -> Option<u32> {
+    let (head, rest) = buf.split_first_chunk::<4>()?;
+    *buf = rest;
+    Some(u32::from_be_bytes(*head))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_binary_encoding() {
+        let data = [0xde, 0xad, 0xbe, 0xef];
+        assert_eq!(BinaryEncoding::Hex.encode(&data), "\\xdeadbeef");
+        assert_eq!(BinaryEncoding::Base64.encode(&data), "3q2+7w==");
+        assert_eq!(
+            BinaryEncoding::from_name("base64"),
+            Some(BinaryEncoding::Base64)
+        );
+        assert_eq!(BinaryEncoding::from_name("octal"), None);
+    }
+
+    fn field(oid: u32, data: Option<&[u8]>) -> Vec<u8> {
+        let mut out = oid.to_be_bytes().to_vec();
+        match data {
+            Some(data) => {
+                out.extend((data.len() as i32).to_be_bytes());
+                out.extend(data);
+            }
+            None => out.extend((-1i32).to_be_bytes()),
+        }
+        out
+    }
+
+    #[test]
+    fn test_decode_binary_record() {
+        // Binary form of ROW(7, 'seven', NULL, ROW(true), 12.50::numeric)
+        let nested = [1u32.to_be_bytes().to_vec(), field(BOOL_OID, Some(&[1]))].concat();
+        let numeric = [
+            2u16.to_be_bytes(), // ndigits
+            0u16.to_be_bytes(), // weight
+            0u16.to_be_bytes(), // sign
+            2u16.to_be_bytes(), // dscale
+            12u16.to_be_bytes(),
+            5000u16.to_be_bytes(),
+        ]
+        .concat();
+        let record = [
+            5u32.to_be_bytes().to_vec(),
+            field(INT4_OID, Some(&7i32.to_be_bytes())),
+            field(25, Some(b"seven")),
+            field(INT4_OID, None),
+            field(RECORD_OID, Some(&nested)),
+            field(NUMERIC_OID, Some(&numeric)),
+        ]
+        .concat();
+
+        assert_eq!(
+            decode_binary_record(&record, None, DEFAULT_MONEY_SCALE),
+            Some(json!([7, "seven", null, [true], "12.50"]))
+        );
+        assert_eq!(
+            decode_binary_record(&record[..10], None, DEFAULT_MONEY_SCALE),
+            None
+        );
+    }
+
+    #[test]
+    fn test_decode_binary_array() {
+        let header = |ndim: u32, oid: u32, dims: &[u32]| -> Vec<u8> {
+            let mut out = [ndim.to_be_bytes(), 1u32.to_be_bytes(), oid.to_be_bytes()].concat();
+            dims.iter()
+                .for_each(|d| out.extend([d.to_be_bytes(), 1u32.to_be_bytes()].concat()));
+            out
+        };
+        let element = |data: Option<&[u8]>| -> Vec<u8> {
+            match data {
+                Some(data) => [&(data.len() as u32).to_be_bytes()[..], data].concat(),
+                None => (-1i32).to_be_bytes().to_vec(),
+            }
+        };
+
+        // ARRAY[1, NULL, 3]
+        let ints = [
+            header(1, INT4_OID, &[3]),
+            element(Some(&1i32.to_be_bytes())),
+            element(None),
+            element(Some(&3i32.to_be_bytes())),
+        ]
+        .concat();
+        assert_eq!(
+            decode_binary_array(&ints, None, DEFAULT_MONEY_SCALE),
+            Some(json!([1, null, 3]))
+        );
+
+        // ARRAY[['a', 'b'], ['c', 'd']]
+        let mut texts = header(2, 25, &[2, 2]);
+        for s in ["a", "b", "c", "d"] {
+            texts.extend(element(Some(s.as_bytes())));
+        }
+        assert_eq!(
+            decode_binary_array(&texts, None, DEFAULT_MONEY_SCALE),
+            Some(json!([["a", "b"], ["c", "d"]]))
+        );
+
+        assert_eq!(
+            decode_binary_array(&header(0, INT4_OID, &[]), None, DEFAULT_MONEY_SCALE),
+            Some(json!([]))
+        );
+        assert_eq!(
+            decode_binary_array(&ints[..ints.len() - 2], None, DEFAULT_MONEY_SCALE),
+            None
+        );
+    }
+
+    #[test]
+    fn test_decode_numeric() {
+        let encode = |weight: i16, sign: u16, dscale: u16, digits: &[u16]| -> Vec<u8> {
+            let mut out = (digits.len() as u16).to_be_bytes().to_vec();
+            out.extend(weight.to_be_bytes());
+            out.extend(sign.to_be_bytes());
+            out.extend(dscale.to_be_bytes());
+            digits.iter().for_each(|d| out.extend(d.to_be_bytes()));
+            out
+        };
+
+        assert_eq!(
+            decode_numeric(&encode(1, 0x4000, 0, &[1, 2345])).as_deref(),
+            Some("-12345")
+        );
+        assert_eq!(
+            decode_numeric(&encode(-2, 0, 8, &[12])).as_deref(),
+            Some("0.00000012")
+        );
+        assert_eq!(
+            decode_numeric(&encode(0, 0xC000, 0, &[])).as_deref(),
+            Some("NaN")
+        );
+        assert_eq!(
+            decode_numeric(&encode(2, 0, 9, &[1, 2345, 6789, 9876, 5432, 1000])).as_deref(),
+            Some("123456789.987654321")
+        );
+    }
+
+    #[test]
+    fn test_decode_binary_range() {
+        let bound = |value: i32| [4u32.to_be_bytes(), value.to_be_bytes()].concat();
+        let int4 = <i32 as sqlx::Type<sqlx::Postgres>>::type_info();
+
+        let closed = [&[RANGE_LB_INC][..], &bound(1), &bound(5)].concat();
+        assert_eq!(
+            decode_binary_range(&closed, &int4, DEFAULT_MONEY_SCALE),
+            Some(json!("[1,5)"))
+        );
+        let unbounded = [&[RANGE_LB_INF | RANGE_UB_INC][..], &bound(5)].concat();
+        assert_eq!(
+            decode_binary_range(&unbounded, &int4, DEFAULT_MONEY_SCALE),
+            Some(json!("(,5]"))
+        );
+        assert_eq!(
+            decode_binary_range(&[RANGE_EMPTY], &int4, DEFAULT_MONEY_SCALE),
+            Some(json!("empty"))
+        );
+        assert_eq!(
+            decode_binary_range(&closed[..6], &int4, DEFAULT_MONEY_SCALE),
+            None
+        );
+
+        assert_eq!(quote_range_bound("a b"), "\"a b\"");
+        assert_eq!(quote_range_bound("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_decode_field_fallbacks() {
+        assert_eq!(
+            decode_field(INET_OID, &[2, 32, 0, 4, 10, 0, 0, 1], DEFAULT_MONEY_SCALE),
+            json!("10.0.0.1")
+        );
+        assert_eq!(
+            decode_field(CIDR_OID, &[2, 8, 1, 4, 10, 0, 0, 0], DEFAULT_MONEY_SCALE),
+            json!("10.0.0.0/8")
+        );
+        assert_eq!(
+            decode_field(MACADDR_OID, &[8, 0, 0x2b, 1, 2, 3], DEFAULT_MONEY_SCALE),
+            json!("08:00:2b:01:02:03")
+        );
+        assert_eq!(
+            decode_field(MONEY_OID, &(-123_456i64).to_be_bytes(), DEFAULT_MONEY_SCALE),
+            json!("-1234.56")
+        );
+        assert_eq!(format_money(5, 2), "0.05");
+        assert_eq!(format_money(-123_456, 3), "-123.456");
+        assert_eq!(format_money(7, 0), "7");
+        assert_eq!(
+            decode_field(
+                TIME_OID,
+                &45_296_500_000i64.to_be_bytes(),
+                DEFAULT_MONEY_SCALE
+            ),
+            json!("12:34:56.500")
+        );
+        let timetz = [
+            &45_296_000_000i64.to_be_bytes()[..],
+            &(-7_200i32).to_be_bytes(),
+        ]
+        .concat();
+        assert_eq!(
+            decode_field(TIMETZ_OID, &timetz, DEFAULT_MONEY_SCALE),
+            json!("12:34:56+02:00")
+        );
+        // Types without a decoder never come out as null
+        assert_eq!(
+            decode_field(0, &[b'o', b'k', 0xff], DEFAULT_MONEY_SCALE),
+            json!("ok\u{fffd}")
+        );
+    }
+
+    #[test]
+    fn test_null_cell() {
+        assert_eq!(null_cell(false), JsonValue::Null);
+        assert_eq!(null_cell(true), json!({ "__null__": true }));
+    }
+
+    #[test]
+    fn test_decode_error() {
+        assert_eq!(
+            decode_error("TIMETZ"),
+            json!({ "__decode_error__": "TIMETZ" })
+        );
+    }
+}
@@ -0,0 +1,900 @@
+use serde::{Deserialize, Serialize};
+
+/// What a statement does, by its leading keyword. A statement starting with WITH is
+/// classified by the statement after its CTEs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryKind {
+    Select,
+    Insert,
+    Update,
+    Delete,
+    Merge,
+    Truncate,
+    Drop,
+    Alter,
+    Create,
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryClassification {
+    pub kind: QueryKind,
+    /// TRUNCATE, DROP, ALTER, or an UPDATE or DELETE without a WHERE clause, anywhere in
+    /// the statement (a data-modifying CTE counts too)
+    pub is_destructive: bool,
+    /// A TRUNCATE, or an UPDATE or DELETE without a WHERE clause
+    pub affects_all_rows: bool,
+}
+
+/// A statement reduced to what classification needs. Literals, quoted identifiers and
+/// comments are dropped, so keywords inside them are never seen
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// An unquoted word, uppercased
+    Word(String),
+    Open,
+    Close,
+    Other,
+}
+
+/// Splits a SQL script into its statements on top-level semicolons. Semicolons inside
+/// string literals, quoted identifiers, dollar-quoted bodies (`$$ ... $$`, `$tag$ ... $tag$`)
+/// and comments do not end a statement. Segments that hold nothing but whitespace and
+/// comments are dropped.
+pub fn split_statements(sql: &str) -> Vec<String> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut has_code = false;
+    let mut i = 0;
+
+    let mut finish = |current: &mut String, has_code: &mut bool| {
+        if *has_code {
+            statements.push(current.trim().to_string());
+        }
+        current.clear();
+        *has_code = false;
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        let end = match c {
+            ';' => {
+                finish(&mut current, &mut has_code);
+                i += 1;
+                continue;
+            }
+            '-' if next == Some('-') => {
+                let end = chars[i..]
+                    .iter()
+                    .position(|&c| c == '\n')
+                    .map_or(chars.len(), |p| i + p + 1);
+                current.extend(&chars[i..end]);
+                i = end;
+                continue;
+            }
+            '/' if next == Some('*') => {
+                let end = block_comment_end(&chars, i);
+                current.extend(&chars[i..end]);
+                i = end;
+                continue;
+            }
+            '\'' => {
+                // E'...' strings allow backslash escapes, but not a trailing e in a word
+                let escapes = i > 0
+                    && matches!(chars[i - 1], 'E' | 'e')
+                    && !is_word_char(i.checked_sub(2).and_then(|j| chars.get(j)));
+                quoted_end(&chars, i, '\'', escapes)
+            }
+            '"' => quoted_end(&chars, i, '"', false),
+            '$' => dollar_quote_end(&chars, i).unwrap_or(i + 1),
+            _ => i + 1,
+        };
+
+        if !c.is_whitespace() {
+            has_code = true;
+        }
+        current.extend(&chars[i..end]);
+        i = end;
+    }
+    finish(&mut current, &mut has_code);
+
+    statements
+}
+
+/// Replaces each named placeholder (`:name`) with what `placeholder` returns for its name.
+/// Placeholders inside literals, quoted identifiers, dollar-quoted bodies and comments are
+/// left alone, as are `::` casts and slices such as `arr[lo:hi]`, where the colon follows
+/// a word character
+pub fn replace_named_params(sql: &str, mut placeholder: impl FnMut(&str) -> String) -> String {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut out = String::with_capacity(sql.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        let end = match c {
+            ':' if next == Some(':') => i + 2,
+            ':' if next.is_some_and(|c| c.is_alphabetic() || c == '_')
+                && !is_word_char(i.checked_sub(1).and_then(|j| chars.get(j))) =>
+            {
+                let end = (i + 1..chars.len())
+                    .find(|&j| !is_word_char(chars.get(j)))
+                    .unwrap_or(chars.len());
+                let name: String = chars[i + 1..end].iter().collect();
+                out.push_str(&placeholder(&name));
+                i = end;
+                continue;
+            }
+            '-' if next == Some('-') => chars[i..]
+                .iter()
+                .position(|&c| c == '\n')
+                .map_or(chars.len(), |p| i + p + 1),
+            '/' if next == Some('*') => block_comment_end(&chars, i),
+            '\'' => {
+                let escapes = i > 0
+                    && matches!(chars[i - 1], 'E' | 'e')
+                    && !is_word_char(i.checked_sub(2).and_then(|j| chars.get(j)));
+                quoted_end(&chars, i, '\'', escapes)
+            }
+            '"' => quoted_end(&chars, i, '"', false),
+            '$' => dollar_quote_end(&chars, i).unwrap_or(i + 1),
+            _ => i + 1,
+        };
+
+        out.extend(&chars[i..end]);
+        i = end;
+    }
+
+    out
+}
+
+/// Classifies a script by its most dangerous statement: the first destructive one, or
+/// else the first statement. An empty script is a harmless Other
+pub fn classify(sql: &str) -> QueryClassification {
+    let classifications: Vec<_> = split_statements(sql)
+        .iter()
+        .map(|statement| classify_statement(&tokenize(statement)))
+        .collect();
+
+    classifications
+        .iter()
+        .find(|c| c.is_destructive)
+        .or(classifications.first())
+        .cloned()
+        .unwrap_or(QueryClassification {
+            kind: QueryKind::Other,
+            is_destructive: false,
+            affects_all_rows: false,
+        })
+}
+
+fn classify_statement(tokens: &[Token]) -> QueryClassification {
+    // EXPLAIN ANALYZE runs the statement, so it is as destructive as that one; plain
+    // EXPLAIN runs nothing
+    if let Some((analyze, explained)) = explain_target(tokens) {
+        let explained = classify_statement(explained);
+        return QueryClassification {
+            kind: QueryKind::Other,
+            is_destructive: analyze && explained.is_destructive,
+            affects_all_rows: analyze && explained.affects_all_rows,
+        };
+    }
+
+    let kind = match main_keyword(tokens) {
+        Some("SELECT" | "VALUES" | "TABLE") => QueryKind::Select,
+        Some("INSERT") => QueryKind::Insert,
+        Some("UPDATE") => QueryKind::Update,
+        Some("DELETE") => QueryKind::Delete,
+        Some("MERGE") => QueryKind::Merge,
+        Some("TRUNCATE") => QueryKind::Truncate,
+        Some("DROP") => QueryKind::Drop,
+        Some("ALTER") => QueryKind::Alter,
+        Some("CREATE") => QueryKind::Create,
+        _ => QueryKind::Other,
+    };
+
+    // UPDATE and DELETE start a statement at the beginning, after the CTE list (")") or
+    // inside a CTE ("("); elsewhere they are part of FOR UPDATE, ON DELETE, GRANT and such
+    let unfiltered_dml = tokens.iter().enumerate().any(|(i, token)| {
+        matches!(token, Token::Word(w) if w == "UPDATE" || w == "DELETE")
+            && matches!(
+                i.checked_sub(1).map(|j| &tokens[j]),
+                None | Some(Token::Open | Token::Close)
+            )
+            && !has_where(&tokens[i + 1..])
+    });
+    let affects_all_rows = unfiltered_dml || kind == QueryKind::Truncate;
+
+    QueryClassification {
+        kind,
+        is_destructive: affects_all_rows || matches!(kind, QueryKind::Drop | QueryKind::Alter),
+        affects_all_rows,
+    }
+}
+
+/// Whether `sql` could switch a read-only session to read-write: it names
+/// `default_transaction_read_only` or `transaction_read_only` (SET, set_config, ...) or
+/// asks for READ WRITE (BEGIN, START TRANSACTION, SET TRANSACTION, SET SESSION
+/// CHARACTERISTICS). Errs on the side of caution, so a comment naming them counts too
+pub fn lifts_read_only(sql: &str) -> bool {
+    split_statements(sql).iter().any(|statement| {
+        let tokens = tokenize(statement);
+        statement.to_ascii_lowercase().contains("transaction_read_only")
+            || tokens.windows(2).any(|pair| {
+                matches!(pair, [Token::Word(read), Token::Word(write)] if read == "READ" && write == "WRITE")
+            })
+    })
+}
+
+/// Whether `sql` holds an INSERT, UPDATE, DELETE or MERGE (after any CTEs), whose count of
+/// changed rows is worth reporting even when RETURNING makes it return rows too
+pub fn modifies_rows(sql: &str) -> bool {
+    split_statements(sql).iter().any(|statement| {
+        matches!(
+            classify_statement(&tokenize(statement)).kind,
+            QueryKind::Insert | QueryKind::Update | QueryKind::Delete | QueryKind::Merge
+        )
+    })
+}
+
+/// modifies_rows for each of the `result_sets` result sets a batch returned. They pair
+/// up with the statements of the batch when there are as many of each; otherwise (empty
+/// statements, or a procedure call returning several sets) the batch is taken as a whole
+pub fn result_sets_modify_rows(sql: &str, result_sets: usize) -> Vec<bool> {
+    let statements = split_statements(sql);
+    if statements.len() == result_sets {
+        statements.iter().map(|s| modifies_rows(s)).collect()
+    } else {
+        vec![modifies_rows(sql); result_sets]
+    }
+}
+
+/// How format_sql lays out SQL
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormatOptions {
+    /// Write keywords in capitals; otherwise they keep their case
+    pub uppercase: bool,
+    /// Spaces per indentation level
+    pub indent_width: u8,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            uppercase: true,
+            indent_width: 2,
+        }
+    }
+}
+
+/// Reformats SQL with each clause on a line of its own and its contents indented below.
+/// Only whitespace outside literals and keyword case change
+pub fn format_sql(sql: &str, options: FormatOptions) -> String {
+    // sqlformat doesn't know dollar quoting and would reflow function bodies, so each
+    // dollar-quoted string passes through as a stand-in literal and is put back afterwards
+    let chars: Vec<char> = sql.chars().collect();
+    let mut shielded = String::with_capacity(sql.len());
+    let mut bodies: Vec<String> = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        let end = match c {
+            '-' if next == Some('-') => chars[i..]
+                .iter()
+                .position(|&c| c == '\n')
+                .map_or(chars.len(), |p| i + p + 1),
+            '/' if next == Some('*') => block_comment_end(&chars, i),
+            '\'' => {
+                let escapes = i > 0
+                    && matches!(chars[i - 1], 'E' | 'e')
+                    && !is_word_char(i.checked_sub(2).and_then(|j| chars.get(j)));
+                quoted_end(&chars, i, '\'', escapes)
+            }
+            '"' => quoted_end(&chars, i, '"', false),
+            '$' => match dollar_quote_end(&chars, i) {
+                Some(end) => {
+                    shielded.push_str(&dollar_stand_in(bodies.len()));
+                    bodies.push(chars[i..end].iter().collect());
+                    i = end;
+                    continue;
+                }
+                None => i + 1,
+            },
+            _ => i + 1,
+        };
+
+        shielded.extend(&chars[i..end]);
+        i = end;
+    }
+
+    let formatted = sqlformat::format(
+        &shielded,
+        &sqlformat::QueryParams::None,
+        sqlformat::FormatOptions {
+            indent: sqlformat::Indent::Spaces(options.indent_width),
+            uppercase: options.uppercase,
+            lines_between_queries: 2,
+        },
+    );
+    bodies
+        .iter()
+        .enumerate()
+        .fold(formatted, |formatted, (n, body)| {
+            formatted.replacen(&dollar_stand_in(n), body, 1)
+        })
+}
+
+/// The literal format_sql puts in place of the n-th dollar-quoted string
+fn dollar_stand_in(n: usize) -> String {
+    format!("'\u{1}{}'", n)
+}
+
+/// What limit_select puts in front of the query
+const LIMITED_PREFIX: &str = "SELECT * FROM (\n";
+
+/// Wraps a lone SELECT (or TABLE or VALUES) so that it returns at most `limit` rows.
+/// Returns None when the SQL must run as written: several statements, anything but a
+/// query, a query that already has a top-level LIMIT or FETCH, SELECT INTO, and queries
+/// whose CTEs modify data, which Postgres only allows at the top level
+pub fn limit_select(sql: &str, limit: u64) -> Option<String> {
+    let (statement, tokens) = lone_query(sql)?;
+    if top_level_words(&tokens).any(|word| matches!(word, "LIMIT" | "FETCH")) {
+        return None;
+    }
+
+    // On lines of their own, so a trailing line comment can't swallow the parenthesis
+    Some(format!(
+        "{}{}\n) AS _dt_limited LIMIT {}",
+        LIMITED_PREFIX, statement, limit
+    ))
+}
+
+/// Maps the 1-based character position of an error in the query limit_select built from
+/// `sql` back to `sql`. None when it falls in the wrapping
+pub fn unlimited_position(sql: &str, position: usize) -> Option<usize> {
+    let (statement, _) = lone_query(sql)?;
+    let offset = sql[..sql.find(&statement)?].chars().count();
+    let position = position
+        .checked_sub(LIMITED_PREFIX.chars().count())
+        .filter(|&p| p > 0 && p <= statement.chars().count())?;
+    Some(offset + position)
+}
+
+/// Whether `sql` is a lone query that only reads, so running it again returns the same
+/// rows until the data changes. Row-locking queries (FOR UPDATE, FOR SHARE) don't count
+pub fn is_read_only_query(sql: &str) -> bool {
+    lone_query(sql).is_some_and(|(_, tokens)| !top_level_words(&tokens).any(|word| word == "FOR"))
+}
+
+/// The statement and its tokens when `sql` is a single SELECT (or TABLE or VALUES) that
+/// writes nothing: no SELECT INTO and no data-modifying CTE
+fn lone_query(sql: &str) -> Option<(String, Vec<Token>)> {
+    let statement = match split_statements(sql).as_slice() {
+        [statement] => statement.clone(),
+        _ => return None,
+    };
+    let tokens = tokenize(&statement);
+    if classify_statement(&tokens).kind != QueryKind::Select
+        || top_level_words(&tokens).any(|word| word == "INTO")
+    {
+        return None;
+    }
+
+    // Same rule as for unfiltered DML: a statement starts here, not FOR UPDATE
+    let modifies_data = tokens.iter().enumerate().any(|(i, token)| {
+        matches!(token, Token::Word(word) if matches!(word.as_str(), "INSERT" | "UPDATE" | "DELETE" | "MERGE"))
+            && matches!(
+                i.checked_sub(1).map(|j| &tokens[j]),
+                None | Some(Token::Open | Token::Close)
+            )
+    });
+    (!modifies_data).then_some((statement, tokens))
+}
+
+/// The keywords and names outside any parentheses
+fn top_level_words(tokens: &[Token]) -> impl Iterator<Item = &str> {
+    let mut depth = 0;
+    tokens.iter().filter_map(move |token| {
+        match token {
+            Token::Open => depth += 1,
+            Token::Close => depth -= 1,
+            Token::Word(word) if depth == 0 => return Some(word.as_str()),
+            _ => {}
+        }
+        None
+    })
+}
+
+/// The keyword saying what the statement does: the first one, or for WITH the first one
+/// after the CTE list
+fn main_keyword(tokens: &[Token]) -> Option<&str> {
+    let mut depth = 0;
+    let mut after_cte = false;
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Open => depth += 1,
+            Token::Close => {
+                depth -= 1;
+                after_cte = depth == 0;
+            }
+            Token::Word(word) if i == 0 && word != "WITH" => return Some(word),
+            Token::Word(word) if depth == 0 && after_cte && word != "AS" => return Some(word),
+            Token::Word(_) | Token::Other => after_cte = false,
+        }
+    }
+    None
+}
+
+/// The statement an EXPLAIN explains, and whether ANALYZE is on so that it is executed:
+/// `EXPLAIN ANALYZE ...` or `EXPLAIN (ANALYZE ...) ...` without `false` or `off`
+fn explain_target(tokens: &[Token]) -> Option<(bool, &[Token])> {
+    let is_analyze =
+        |token: &Token| matches!(token, Token::Word(w) if w == "ANALYZE" || w == "ANALYSE");
+    let (Token::Word(explain), rest) = tokens.split_first()? else {
+        return None;
+    };
+    if explain != "EXPLAIN" {
+        return None;
+    }
+
+    if let Some((Token::Open, options)) = rest.split_first() {
+        let close = options
+            .iter()
+            .position(|token| matches!(token, Token::Close))?;
+        let analyze = options[..close].iter().enumerate().any(|(i, token)| {
+            is_analyze(token)
+                && !matches!(options.get(i + 1), Some(Token::Word(w)) if w == "FALSE" || w == "OFF")
+        });
+        return Some((analyze, &options[close + 1..]));
+    }
+
+    // The legacy syntax: EXPLAIN [ANALYZE] [VERBOSE] statement
+    let options = rest
+        .iter()
+        .take_while(|token| is_analyze(token) || matches!(token, Token::Word(w) if w == "VERBOSE"))
+        .count();
+    Some((rest[..options].iter().any(is_analyze), &rest[options..]))
+}
+
+/// Whether a WHERE follows at the current nesting level before it ends
+fn has_where(tokens: &[Token]) -> bool {
+    let mut depth = 0;
+    for token in tokens {
+        match token {
+            Token::Open => depth += 1,
+            Token::Close if depth == 0 => return false,
+            Token::Close => depth -= 1,
+            Token::Word(word) if depth == 0 && word == "WHERE" => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+fn tokenize(sql: &str) -> Vec<Token> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        i = match c {
+            '-' if next == Some('-') => chars[i..]
+                .iter()
+                .position(|&c| c == '\n')
+                .map_or(chars.len(), |p| i + p + 1),
+            '/' if next == Some('*') => block_comment_end(&chars, i),
+            c if c.is_whitespace() => i + 1,
+            '(' => {
+                tokens.push(Token::Open);
+                i + 1
+            }
+            ')' => {
+                tokens.push(Token::Close);
+                i + 1
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let end = (i..chars.len())
+                    .find(|&j| !is_word_char(chars.get(j)))
+                    .unwrap_or(chars.len());
+                // E'...' strings
+                if matches!(c, 'E' | 'e') && end == i + 1 && next == Some('\'') {
+                    tokens.push(Token::Other);
+                    quoted_end(&chars, end, '\'', true)
+                } else {
+                    tokens.push(Token::Word(
+                        chars[i..end].iter().collect::<String>().to_uppercase(),
+                    ));
+                    end
+                }
+            }
+            '\'' => {
+                tokens.push(Token::Other);
+                quoted_end(&chars, i, '\'', false)
+            }
+            '"' => {
+                tokens.push(Token::Other);
+                quoted_end(&chars, i, '"', false)
+            }
+            '$' => {
+                tokens.push(Token::Other);
+                dollar_quote_end(&chars, i).unwrap_or(i + 1)
+            }
+            _ => {
+                tokens.push(Token::Other);
+                i + 1
+            }
+        };
+    }
+
+    tokens
+}
+
+/// The first keyword of a statement, uppercased, skipping leading whitespace, comments and
+/// opening parentheses
+pub fn leading_keyword(sql: &str) -> Option<String> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match (chars[i], chars.get(i + 1)) {
+            ('-', Some('-')) => {
+                i = chars[i..]
+                    .iter()
+                    .position(|&c| c == '\n')
+                    .map_or(chars.len(), |p| i + p + 1)
+            }
+            ('/', Some('*')) => i = block_comment_end(&chars, i),
+            (c, _) if c.is_whitespace() || c == '(' => i += 1,
+            _ => break,
+        }
+    }
+
+    let keyword: String = chars[i..]
+        .iter()
+        .take_while(|c| c.is_alphabetic())
+        .collect();
+    (!keyword.is_empty()).then(|| keyword.to_uppercase())
+}
+
+fn is_word_char(c: Option<&char>) -> bool {
+    c.is_some_and(|c| c.is_alphanumeric() || *c == '_')
+}
+
+/// Returns the index just past a quoted literal or identifier starting at `start`.
+/// A doubled quote is an escaped quote; `escapes` also honours backslash escapes (E'...')
+fn quoted_end(chars: &[char], start: usize, quote: char, escapes: bool) -> usize {
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if escapes => i += 2,
+            c if c == quote && chars.get(i + 1) == Some(&quote) => i += 2,
+            c if c == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    chars.len()
+}
+
+/// Returns the index just past a (possibly nested) block comment starting at `start`
+fn block_comment_end(chars: &[char], start: usize) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+    while i < chars.len() {
+        match (chars[i], chars.get(i + 1)) {
+            ('/', Some('*')) => {
+                depth += 1;
+                i += 2;
+            }
+            ('*', Some('/')) => {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    chars.len()
+}
+
+/// If a dollar quote opens at `start`, returns the index just past its closing tag.
+/// Returns None for anything else starting with `$`, such as the `$1` parameter syntax
+fn dollar_quote_end(chars: &[char], start: usize) -> Option<usize> {
+    let tag_len = chars[start + 1..]
+        .iter()
+        .position(|&c| c == '$')
+        .filter(|&len| {
+            let tag = &chars[start + 1..start + 1 + len];
+            tag.iter().all(|c| c.is_alphanumeric() || *c == '_')
+                && !tag.first().is_some_and(|c| c.is_ascii_digit())
+        })?;
+    let tag = &chars[start..start + tag_len + 2];
+
+    let body_start = start + tag.len();
+    let end = (body_start..=chars.len().saturating_sub(tag.len()))
+        .find(|&i| chars[i..i + tag.len()] == *tag)
+        .map_or(chars.len(), |i| i + tag.len());
+    Some(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_statements() {
+        assert_eq!(
+            split_statements("SELECT 1; SELECT 2;\n\n-- trailing comment\n"),
+            vec!["SELECT 1", "SELECT 2"]
+        );
+        assert_eq!(
+            split_statements("INSERT INTO t VALUES ('a;b', 'it''s;'); SELECT \"x;y\" FROM t"),
+            vec![
+                "INSERT INTO t VALUES ('a;b', 'it''s;')",
+                "SELECT \"x;y\" FROM t"
+            ]
+        );
+        assert_eq!(
+            split_statements(
+                "CREATE FUNCTION f() RETURNS int AS $$ BEGIN RETURN 1; END; $$ LANGUAGE plpgsql;\
+                 DO $body$ BEGIN PERFORM 1; END $body$; SELECT $1"
+            ),
+            vec![
+                "CREATE FUNCTION f() RETURNS int AS $$ BEGIN RETURN 1; END; $$ LANGUAGE plpgsql",
+                "DO $body$ BEGIN PERFORM 1; END $body$",
+                "SELECT $1"
+            ]
+        );
+        assert_eq!(
+            split_statements("SELECT E'\\';' /* a; /* nested; */ b; */; -- c;\nSELECT 2"),
+            vec![
+                "SELECT E'\\';' /* a; /* nested; */ b; */",
+                "-- c;\nSELECT 2"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_classify() {
+        let classify_as = |sql: &str| {
+            let c = classify(sql);
+            (c.kind, c.is_destructive, c.affects_all_rows)
+        };
+
+        assert_eq!(
+            classify_as("DELETE FROM t"),
+            (QueryKind::Delete, true, true)
+        );
+        assert_eq!(
+            classify_as("delete from t where id = 1"),
+            (QueryKind::Delete, false, false)
+        );
+        assert_eq!(
+            classify_as("UPDATE t SET a = (SELECT b FROM u WHERE u.id = 1)"),
+            (QueryKind::Update, true, true)
+        );
+        assert_eq!(
+            classify_as("UPDATE t SET note = 'no where here' -- WHERE\n WHERE id = 1"),
+            (QueryKind::Update, false, false)
+        );
+        assert_eq!(
+            classify_as("UPDATE t SET note = ' WHERE ' /* WHERE */"),
+            (QueryKind::Update, true, true)
+        );
+        assert_eq!(
+            classify_as("WITH gone AS (DELETE FROM t RETURNING *) SELECT count(*) FROM gone"),
+            (QueryKind::Select, true, true)
+        );
+        assert_eq!(
+            classify_as("WITH x AS (SELECT 1) UPDATE t SET a = 1 WHERE id IN (SELECT * FROM x)"),
+            (QueryKind::Update, false, false)
+        );
+        assert_eq!(
+            classify_as("SELECT * FROM t FOR UPDATE"),
+            (QueryKind::Select, false, false)
+        );
+        assert_eq!(
+            classify_as("INSERT INTO t VALUES (1) ON CONFLICT (id) DO UPDATE SET a = 1"),
+            (QueryKind::Insert, false, false)
+        );
+        assert_eq!(
+            classify_as("SELECT 'DROP TABLE t'; truncate t"),
+            (QueryKind::Truncate, true, true)
+        );
+        assert_eq!(
+            classify_as("ALTER TABLE t ADD COLUMN c int"),
+            (QueryKind::Alter, true, false)
+        );
+        assert_eq!(classify_as("DROP TABLE t"), (QueryKind::Drop, true, false));
+        assert_eq!(
+            classify_as("CREATE TABLE t (a int REFERENCES u ON DELETE CASCADE)"),
+            (QueryKind::Create, false, false)
+        );
+        assert_eq!(
+            classify_as("DO $$ BEGIN DELETE FROM t; END $$"),
+            (QueryKind::Other, false, false)
+        );
+        assert_eq!(classify_as("-- nothing"), (QueryKind::Other, false, false));
+
+        assert_eq!(
+            classify_as("EXPLAIN ANALYZE DELETE FROM t"),
+            (QueryKind::Other, true, true)
+        );
+        assert_eq!(
+            classify_as("explain (analyze, buffers) update t set a = 1"),
+            (QueryKind::Other, true, true)
+        );
+        assert_eq!(
+            classify_as("EXPLAIN ANALYZE VERBOSE DELETE FROM t WHERE id = 1"),
+            (QueryKind::Other, false, false)
+        );
+        assert_eq!(
+            classify_as("EXPLAIN DELETE FROM t"),
+            (QueryKind::Other, false, false)
+        );
+        assert_eq!(
+            classify_as("EXPLAIN (ANALYZE off) DELETE FROM t"),
+            (QueryKind::Other, false, false)
+        );
+    }
+
+    #[test]
+    fn test_limit_select() {
+        assert_eq!(
+            limit_select("SELECT * FROM t -- all of it\n;", 101).as_deref(),
+            Some("SELECT * FROM (\nSELECT * FROM t -- all of it\n) AS _dt_limited LIMIT 101")
+        );
+        assert!(limit_select("WITH x AS (SELECT 1 LIMIT 1) SELECT * FROM x", 10).is_some());
+        assert!(limit_select("SELECT * FROM t FOR UPDATE", 10).is_some());
+
+        assert_eq!(limit_select("SELECT * FROM t LIMIT 5", 10), None);
+        assert_eq!(
+            limit_select("SELECT * FROM t FETCH FIRST 5 ROWS ONLY", 10),
+            None
+        );
+        assert_eq!(limit_select("SELECT * INTO copy FROM t", 10), None);
+        assert_eq!(limit_select("SELECT 1; SELECT 2", 10), None);
+        assert_eq!(limit_select("UPDATE t SET a = 1", 10), None);
+        assert_eq!(
+            limit_select("WITH d AS (DELETE FROM t RETURNING *) SELECT * FROM d", 10),
+            None
+        );
+    }
+
+    #[test]
+    fn test_unlimited_position() {
+        let sql = "\n  SELECT * FORM t;";
+        // FORM, after the 16 characters of "SELECT * FROM (\n" and 9 of the statement
+        assert_eq!(unlimited_position(sql, 26), Some(13));
+        assert_eq!(unlimited_position(sql, 5), None);
+        assert_eq!(unlimited_position(sql, 32), None);
+    }
+
+    #[test]
+    fn test_lifts_read_only() {
+        assert!(lifts_read_only("SET default_transaction_read_only = off"));
+        assert!(lifts_read_only(
+            "SELECT 1; select set_config('transaction_read_only', 'off', false)"
+        ));
+        assert!(lifts_read_only("BEGIN READ WRITE"));
+        assert!(lifts_read_only(
+            "START TRANSACTION ISOLATION LEVEL SERIALIZABLE, READ WRITE"
+        ));
+        assert!(lifts_read_only(
+            "SET SESSION CHARACTERISTICS AS TRANSACTION READ WRITE"
+        ));
+
+        assert!(!lifts_read_only("BEGIN READ ONLY"));
+        assert!(!lifts_read_only("SELECT 'read write'"));
+        assert!(!lifts_read_only("SET search_path = app"));
+    }
+
+    #[test]
+    fn test_is_read_only_query() {
+        assert!(is_read_only_query("  SELECT count(*) FROM t;  "));
+        assert!(is_read_only_query("SELECT * FROM t LIMIT 10"));
+        assert!(is_read_only_query(
+            "SELECT * FROM (SELECT * FROM t FOR UPDATE) x"
+        ));
+
+        assert!(!is_read_only_query("SELECT * FROM t FOR UPDATE"));
+        assert!(!is_read_only_query("SELECT * INTO copy FROM t"));
+        assert!(!is_read_only_query("INSERT INTO t SELECT * FROM u"));
+        assert!(!is_read_only_query("SELECT 1; SELECT 2"));
+    }
+
+    #[test]
+    fn test_modifies_rows() {
+        assert!(modifies_rows("INSERT INTO t (a) VALUES (1) RETURNING id"));
+        assert!(modifies_rows(
+            "WITH src AS (SELECT 1 AS a) INSERT INTO t SELECT a FROM src RETURNING *"
+        ));
+        assert!(modifies_rows("SELECT 1; DELETE FROM t WHERE id = 1"));
+        assert!(!modifies_rows("SELECT * FROM t FOR UPDATE"));
+        assert!(!modifies_rows("CREATE TABLE t (a int)"));
+
+        assert_eq!(
+            result_sets_modify_rows("SELECT 1; UPDATE t SET a = 1 RETURNING a", 2),
+            [false, true]
+        );
+        assert_eq!(result_sets_modify_rows("CALL p()", 3), [false; 3]);
+    }
+
+    #[test]
+    fn test_format_sql() {
+        assert_eq!(
+            format_sql(
+                "select a, b::int from t join u on u.id=t.id where a = :user_id and b > $1 \
+                 order by b desc",
+                FormatOptions::default()
+            ),
+            "SELECT\n  a,\n  b :: int\nFROM\n  t\n  JOIN u ON u.id = t.id\nWHERE\n  \
+             a = :user_id\n  AND b > $1\nORDER BY\n  b DESC"
+        );
+        assert_eq!(
+            format_sql(
+                "SELECT a FROM t; select 1",
+                FormatOptions {
+                    uppercase: false,
+                    indent_width: 4
+                }
+            ),
+            "SELECT\n    a\nFROM\n    t;\n\nselect\n    1"
+        );
+        // Dollar-quoted bodies come back untouched
+        assert_eq!(
+            format_sql(
+                "create function f() returns int as $body$ select  1;\n$body$ language sql",
+                FormatOptions::default()
+            ),
+            "CREATE FUNCTION f() RETURNS int AS $body$ select  1;\n$body$ language SQL"
+        );
+        assert_eq!(
+            format_sql("select $$ a  b $$, 'x$$y'", FormatOptions::default()),
+            "SELECT\n  $$ a  b $$,\n  'x$$y'"
+        );
+    }
+
+    #[test]
+    fn test_leading_keyword() {
+        assert_eq!(leading_keyword("  select 1").as_deref(), Some("SELECT"));
+        assert_eq!(
+            leading_keyword("-- note\n/* a /* b */ */ ((WITH x AS (SELECT 1) TABLE x))").as_deref(),
+            Some("WITH")
+        );
+        assert_eq!(leading_keyword("-- only a comment"), None);
+    }
+
+    #[test]
+    fn test_replace_named_params() {
+        let mut names = Vec::new();
+        let sql = replace_named_params(
+            "SELECT a[lo:hi], x::int, ':skip', \":skip\" -- :skip\n\
+             FROM t WHERE id = :user_id AND (:since IS NULL OR at > :since) AND b=:_b",
+            |name| {
+                names.push(name.to_string());
+                format!("${}", names.len())
+            },
+        );
+        assert_eq!(
+            sql,
+            "SELECT a[lo:hi], x::int, ':skip', \":skip\" -- :skip\n\
+             FROM t WHERE id = $1 AND ($2 IS NULL OR at > $3) AND b=$4"
+        );
+        assert_eq!(names, vec!["user_id", "since", "since", "_b"]);
+    }
+}
@@ -0,0 +1,106 @@
+/// Characters of context kept on each side of the match in a snippet
+const SNIPPET_CONTEXT: usize = 40;
+
+/// Turns a search term into a LIKE pattern matching it anywhere, with `%`, `_` and the
+/// escape character itself taken literally. Use with `ESCAPE '\'`
+pub fn like_pattern(term: &str) -> String {
+    let mut pattern = String::with_capacity(term.len() + 2);
+    pattern.push('%');
+    for c in term.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+/// Ranks a match: hits in the name count far more than hits in the SQL, an exact or
+/// leading name match most of all. Case-insensitive for ASCII, like SQLite's LIKE
+pub fn match_score(term: &str, name: Option<&str>, sql: &str) -> u32 {
+    let term = term.to_ascii_lowercase();
+    if term.is_empty() {
+        return 0;
+    }
+
+    let name_score = match name.map(str::to_ascii_lowercase) {
+        Some(name) if name == term => 100,
+        Some(name) if name.starts_with(&term) => 50,
+        Some(name) if name.contains(&term) => 30,
+        _ => 0,
+    };
+    let sql_hits = sql.to_ascii_lowercase().matches(&term).count().min(10) as u32;
+
+    name_score + sql_hits * 5
+}
+
+/// The SQL around the first occurrence of `term`, on one line, with an ellipsis where it
+/// was cut. Falls back to the start of the SQL when the term isn't in it
+pub fn snippet(sql: &str, term: &str) -> String {
+    let text = sql.split_whitespace().collect::<Vec<_>>().join(" ");
+    let term = term.to_ascii_lowercase();
+
+    let (start, end) = match text
+        .to_ascii_lowercase()
+        .find(&term)
+        .filter(|_| !term.is_empty())
+    {
+        Some(at) => (at, at + term.len()),
+        None => (0, 0),
+    };
+
+    let from = text[..start]
+        .char_indices()
+        .rev()
+        .nth(SNIPPET_CONTEXT - 1)
+        .map_or(0, |(i, _)| i);
+    let context_after = if end == 0 {
+        SNIPPET_CONTEXT * 2
+    } else {
+        SNIPPET_CONTEXT
+    };
+    let to = text[end..]
+        .char_indices()
+        .nth(context_after)
+        .map_or(text.len(), |(i, _)| end + i);
+
+    format!(
+        "{}{}{}",
+        if from > 0 { "…" } else { "" },
+        &text[from..to],
+        if to < text.len() { "…" } else { "" }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_helpers() {
+        assert_eq!(like_pattern(r"50%_off\"), r"%50\%\_off\\%");
+
+        assert_eq!(match_score("Users", Some("users"), "SELECT 1"), 100);
+        assert!(match_score("user", Some("users by day"), "") > match_score("user", None, "users"));
+        assert_eq!(
+            match_score("from", None, "SELECT 1 FROM a, (SELECT 2 from b)"),
+            10
+        );
+        assert_eq!(match_score("missing", Some("name"), "SELECT 1"), 0);
+
+        assert_eq!(
+            snippet("SELECT *\n  FROM users", "from"),
+            "SELECT * FROM users"
+        );
+        let long = format!(
+            "SELECT {} FROM orders WHERE {}",
+            "a, ".repeat(30),
+            "b = 1 AND ".repeat(10)
+        );
+        let found = snippet(&long, "orders");
+        assert!(found.starts_with('…') && found.ends_with('…'));
+        assert!(found.contains("FROM orders WHERE"));
+        assert_eq!(snippet("SELECT ü FROM t", "nope"), "SELECT ü FROM t");
+    }
+}
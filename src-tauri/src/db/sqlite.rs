@@ -0,0 +1,495 @@
+use crate::db::batch;
+use crate::db::browse::{self, ColumnFilter, Dialect, DistinctValue, SortColumn};
+use crate::db::cache::DataVersion;
+use crate::db::postgres::{
+    ColumnInfo, ColumnMeta, PaginatedResult, QueryResult, TableFilter, TableInfo,
+};
+use crate::db::record;
+use crate::db::script;
+use futures::TryStreamExt;
+use serde_json::Value as JsonValue;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteRow};
+use sqlx::{Column, Either, Executor, Row, TypeInfo, ValueRef};
+use std::sync::Arc;
+use std::time::Instant;
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+/// SQLite has no schemas; the file itself is always attached as `main`
+const MAIN_SCHEMA: &str = "main";
+
+const SQLITE_DIALECT: Dialect = Dialect {
+    quote_ident,
+    like: |column, pattern| format!("{} LIKE {}", column, pattern),
+    json_path: None,
+    json_number: None,
+};
+
+#[derive(Error, Debug)]
+pub enum SqliteError {
+    #[error("Connection failed: {0}")]
+    ConnectionFailed(String),
+    #[error("Query execution failed: {0}")]
+    QueryFailed(#[source] sqlx::Error),
+    #[error("No active connection")]
+    NoActiveConnection,
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+}
+
+/// Builds the options for opening an existing database file
+pub fn connect_options(path: &str, read_only: bool) -> SqliteConnectOptions {
+    SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(false)
+        .read_only(read_only)
+}
+
+/// Connection pool over a local SQLite database file
+pub struct SqliteManager {
+    pool: RwLock<Option<SqlitePool>>,
+    connection_id: RwLock<Option<String>>,
+    data_version: DataVersion,
+}
+
+impl SqliteManager {
+    pub fn new() -> Self {
+        Self {
+            pool: RwLock::new(None),
+            connection_id: RwLock::new(None),
+            data_version: DataVersion::default(),
+        }
+    }
+
+    /// Opens a SQLite database file. The file must already exist
+    pub async fn connect(
+        &self,
+        connection_id: &str,
+        path: &str,
+        read_only: bool,
+    ) -> Result<(), SqliteError> {
+        // Disconnect existing pool if any
+        self.disconnect().await;
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect_with(connect_options(path, read_only))
+            .await
+            .map_err(|e| SqliteError::ConnectionFailed(e.to_string()))?;
+
+        *self.pool.write().await = Some(pool);
+        *self.connection_id.write().await = Some(connection_id.to_string());
+
+        Ok(())
+    }
+
+    /// Closes the current database file
+    pub async fn disconnect(&self) {
+        if let Some(pool) = self.pool.write().await.take() {
+            pool.close().await;
+        }
+        *self.connection_id.write().await = None;
+    }
+
+    /// Gets the current connection ID
+    pub async fn get_connection_id(&self) -> Option<String> {
+        self.connection_id.read().await.clone()
+    }
+
+    /// Changes whenever SQL run here may have changed the data; see
+    /// PostgresManager::data_version
+    pub fn data_version(&self) -> u64 {
+        self.data_version.get()
+    }
+
+    /// Executes a raw SQL query and returns results as JSON. With `distinguish_nulls`,
+    /// NULL cells come back as record::null_cell's sentinel
+    pub async fn execute_query(
+        &self,
+        sql: &str,
+        distinguish_nulls: bool,
+    ) -> Result<QueryResult, SqliteError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(SqliteError::NoActiveConnection)?;
+        let _change = self.data_version.change(sql);
+
+        // fetch_many, unlike fetch_all, also yields the rows-affected count
+        let started = Instant::now();
+        let mut stream = pool.fetch_many(sqlx::query(sql));
+        let mut rows: Vec<SqliteRow> = Vec::new();
+        let mut rows_affected = 0;
+        while let Some(step) = stream.try_next().await.map_err(SqliteError::QueryFailed)? {
+            match step {
+                Either::Left(result) => rows_affected += result.rows_affected(),
+                Either::Right(row) => rows.push(row),
+            }
+        }
+        let duration_ms = started.elapsed().as_millis() as u64;
+
+        Ok(QueryResult {
+            duration_ms,
+            ..rows_to_query_result(
+                rows,
+                rows_affected,
+                script::modifies_rows(sql),
+                distinguish_nulls,
+            )
+        })
+    }
+
+    /// Executes one or more statements and returns a QueryResult per result set, in the
+    /// order they arrived. Every result carries the duration of the whole batch, and
+    /// each keeps at most `max_rows` rows. `distinguish_nulls` works as in execute_query
+    pub async fn execute_query_multi(
+        &self,
+        sql: &str,
+        max_rows: Option<u64>,
+        distinguish_nulls: bool,
+    ) -> Result<Vec<QueryResult>, SqliteError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(SqliteError::NoActiveConnection)?;
+        let _change = self.data_version.change(sql);
+
+        let started = Instant::now();
+        let result_sets = batch::fetch_result_sets(pool, sql, max_rows)
+            .await
+            .map_err(SqliteError::QueryFailed)?;
+        let duration_ms = started.elapsed().as_millis() as u64;
+
+        let mut results =
+            batch::into_query_results(sql, result_sets, |rows, rows_affected, modifies_rows| {
+                rows_to_query_result(rows, rows_affected, modifies_rows, distinguish_nulls)
+            });
+        for result in &mut results {
+            result.duration_ms = duration_ms;
+        }
+        Ok(results)
+    }
+
+    /// Fetches the tables and views from `sqlite_master` that match `filter`, skipping
+    /// SQLite's internal tables. SQLite keeps no row estimates, so the order is always by name
+    pub async fn fetch_tables(&self, filter: &TableFilter) -> Result<Vec<TableInfo>, SqliteError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(SqliteError::NoActiveConnection)?;
+
+        if filter
+            .schema
+            .as_deref()
+            .is_some_and(|schema| schema != MAIN_SCHEMA)
+        {
+            return Ok(Vec::new());
+        }
+
+        // LIKE is case-insensitive for ASCII in SQLite; a negative LIMIT means no limit
+        let tables: Vec<TableInfo> = sqlx::query_as::<_, (String, String)>(
+            r#"
+            SELECT name, type
+            FROM sqlite_master
+            WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%'
+              AND (?1 IS NULL OR name LIKE ?1)
+            ORDER BY name
+            LIMIT ?2 OFFSET ?3
+            "#,
+        )
+        .bind(filter.name_pattern.as_deref())
+        .bind(filter.limit.unwrap_or(-1))
+        .bind(filter.offset.unwrap_or(0))
+        .fetch_all(pool)
+        .await
+        .map_err(SqliteError::QueryFailed)?
+        .into_iter()
+        .map(|(name, kind)| TableInfo {
+            schema: MAIN_SCHEMA.to_string(),
+            name,
+            // Same labels information_schema uses for the other drivers
+            table_type: match kind.as_str() {
+                "view" => "VIEW".to_string(),
+                _ => "BASE TABLE".to_string(),
+            },
+            estimated_rows: None,
+        })
+        .collect();
+
+        Ok(tables)
+    }
+
+    /// Fetches columns for a specific table
+    pub async fn fetch_columns(
+        &self,
+        schema: &str,
+        table: &str,
+    ) -> Result<Vec<ColumnInfo>, SqliteError> {
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(SqliteError::NoActiveConnection)?;
+
+        let columns: Vec<ColumnInfo> =
+            sqlx::query_as::<_, (String, String, bool, Option<String>, i64)>(
+                r#"
+                SELECT name, type, "notnull", dflt_value, pk
+                FROM pragma_table_info(?1, ?2)
+                ORDER BY cid
+                "#,
+            )
+            .bind(table)
+            .bind(schema)
+            .fetch_all(pool)
+            .await
+            .map_err(SqliteError::QueryFailed)?
+            .into_iter()
+            .map(
+                |(name, data_type, not_null, column_default, pk)| ColumnInfo {
+                    name,
+                    data_type,
+                    is_nullable: !not_null,
+                    column_default,
+                    is_primary_key: pk > 0,
+                    is_unique_key: false,
+                    comment: None,
+                    udt_schema: None,
+                    udt_name: None,
+                    is_enum: false,
+                },
+            )
+            .collect();
+
+        Ok(columns)
+    }
+
+    /// Fetches paginated table data, running the COUNT(*) only when `with_count` is set.
+    /// `filters` narrow both the rows and the count. `distinguish_nulls` works as in
+    /// execute_query
+    #[allow(clippy::too_many_arguments)]
+    pub async fn fetch_table_data(
+        &self,
+        schema: &str,
+        table: &str,
+        page: i32,
+        page_size: i32,
+        with_count: bool,
+        order_by: &[SortColumn],
+        filters: &[ColumnFilter],
+        distinguish_nulls: bool,
+    ) -> Result<PaginatedResult, SqliteError> {
+        let columns: Vec<String> = if order_by.is_empty() && filters.is_empty() {
+            Vec::new()
+        } else {
+            self.fetch_columns(schema, table)
+                .await?
+                .into_iter()
+                .map(|column| column.name)
+                .collect()
+        };
+        let (conditions, values) =
+            browse::build_filters(filters, &columns, &SQLITE_DIALECT, |_, _| "?".to_string())
+                .map_err(SqliteError::InvalidInput)?;
+        let order_items = browse::build_order_by(order_by, &columns, &SQLITE_DIALECT)
+            .map_err(SqliteError::InvalidInput)?;
+
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(SqliteError::NoActiveConnection)?;
+
+        let offset = (page - 1) * page_size;
+        let table_ref = format!("{}.{}", quote_ident(schema), quote_ident(table));
+        let where_clause = browse::where_clause(&conditions);
+
+        let total_count = if with_count {
+            let count_sql = format!("SELECT COUNT(*) FROM {}{}", table_ref, where_clause);
+            let count: i64 = values
+                .iter()
+                .fold(sqlx::query(&count_sql), browse::bind_json)
+                .fetch_one(pool)
+                .await
+                .map_err(SqliteError::QueryFailed)?
+                .get(0);
+            Some(count)
+        } else {
+            None
+        };
+
+        let data_sql = format!(
+            "SELECT * FROM {}{}{} LIMIT {} OFFSET {}",
+            table_ref,
+            where_clause,
+            browse::order_by_clause(&order_items),
+            page_size,
+            offset
+        );
+        let rows: Vec<SqliteRow> = values
+            .iter()
+            .fold(sqlx::query(&data_sql), browse::bind_json)
+            .fetch_all(pool)
+            .await
+            .map_err(SqliteError::QueryFailed)?;
+
+        Ok(PaginatedResult {
+            columns: rows.first().map(column_meta).unwrap_or_default(),
+            rows: rows
+                .iter()
+                .map(|row| row_to_json_values(row, distinguish_nulls))
+                .collect(),
+            total_count,
+            page,
+            page_size,
+            next_after: None,
+        })
+    }
+
+    /// Fetches up to `limit` distinct values of a column, most frequent first
+    pub async fn fetch_distinct_values(
+        &self,
+        schema: &str,
+        table: &str,
+        column: &str,
+        limit: i64,
+    ) -> Result<Vec<DistinctValue>, SqliteError> {
+        let column_names: Vec<String> = self
+            .fetch_columns(schema, table)
+            .await?
+            .into_iter()
+            .map(|column| column.name)
+            .collect();
+
+        let pool = self.pool.read().await;
+        let pool = pool.as_ref().ok_or(SqliteError::NoActiveConnection)?;
+
+        let table_ref = format!("{}.{}", quote_ident(schema), quote_ident(table));
+        let sql =
+            browse::distinct_values_sql(&table_ref, column, &column_names, &SQLITE_DIALECT, "?")
+                .map_err(SqliteError::InvalidInput)?;
+
+        let rows: Vec<SqliteRow> = sqlx::query(&sql)
+            .bind(limit)
+            .fetch_all(pool)
+            .await
+            .map_err(SqliteError::QueryFailed)?;
+
+        Ok(rows
+            .iter()
+            .map(|row| DistinctValue {
+                value: row_to_json_values(row, false).swap_remove(0),
+                count: row.get(1),
+            })
+            .collect())
+    }
+}
+
+impl Default for SqliteManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Quotes an identifier with double quotes, doubling any embedded quote
+fn quote_ident(ident: &str) -> String {
+    format!("\"{}\"", ident.replace('"', "\"\""))
+}
+
+/// Converts fetched rows into a QueryResult, leaving `duration_ms` for the caller.
+/// A SELECT's rows-affected count would only repeat the row count, so it is reported
+/// only for statements without rows or, with `modifies_rows`, for DML returning rows.
+/// `distinguish_nulls` is passed on to row_to_json_values
+fn rows_to_query_result(
+    rows: Vec<SqliteRow>,
+    rows_affected: u64,
+    modifies_rows: bool,
+    distinguish_nulls: bool,
+) -> QueryResult {
+    let columns = rows.first().map(column_meta).unwrap_or_default();
+    let json_rows: Vec<Vec<JsonValue>> = rows
+        .iter()
+        .map(|row| row_to_json_values(row, distinguish_nulls))
+        .collect();
+
+    QueryResult {
+        columns,
+        row_count: json_rows.len(),
+        affected_rows: (json_rows.is_empty() || modifies_rows).then_some(rows_affected),
+        rows: json_rows,
+        notices: Vec::new(),
+        duration_ms: 0,
+        truncated: false,
+        cache_status: None,
+    }
+}
+
+/// Extracts column names and declared type names from a row
+fn column_meta(row: &SqliteRow) -> Vec<ColumnMeta> {
+    row.columns()
+        .iter()
+        .map(|col| ColumnMeta {
+            name: col.name().to_string(),
+            data_type: col.type_info().name().to_string(),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Converts a SqliteRow to a vector of JSON values. SQLite columns are loosely typed, so
+/// values are decoded by the storage class they actually have, not the declared type.
+/// Only SQL NULL becomes null, or record::null_cell's sentinel with `distinguish_nulls`;
+/// a value that fails to decode is replaced with record::decode_error
+fn row_to_json_values(row: &SqliteRow, distinguish_nulls: bool) -> Vec<JsonValue> {
+    row.columns()
+        .iter()
+        .enumerate()
+        .map(|(i, col)| {
+            let Ok(value) = row.try_get_raw(i) else {
+                return JsonValue::Null;
+            };
+            if value.is_null() {
+                return record::null_cell(distinguish_nulls);
+            }
+            let storage_class = value.type_info().name().to_string();
+
+            let json = match (col.type_info().name(), storage_class.as_str()) {
+                ("BOOLEAN", "INTEGER") => row.try_get::<bool, _>(i).ok().map(JsonValue::Bool),
+                (_, "INTEGER") => row
+                    .try_get_unchecked::<i64, _>(i)
+                    .ok()
+                    .map(|v| JsonValue::Number(v.into())),
+                (_, "REAL") => row.try_get_unchecked::<f64, _>(i).ok().map(|v| {
+                    serde_json::Number::from_f64(v).map_or(JsonValue::Null, JsonValue::Number)
+                }),
+                _ => {
+                    // Default to string representation
+                    row.try_get_unchecked::<String, _>(i)
+                        .ok()
+                        .map(JsonValue::String)
+                }
+            };
+            json.unwrap_or_else(|| record::decode_error(&storage_class))
+        })
+        .collect()
+}
+
+/// Thread-safe wrapper for use with Tauri state
+pub type SqliteState = Arc<SqliteManager>;
+
+pub fn create_sqlite_state() -> SqliteState {
+    Arc::new(SqliteManager::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_null_and_decode_error_values() {
+        let pool = SqlitePoolOptions::new()
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let row = sqlx::query("SELECT NULL, X'FF', 'x'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            row_to_json_values(&row, false),
+            [
+                JsonValue::Null,
+                record::decode_error("BLOB"),
+                JsonValue::String("x".to_string()),
+            ]
+        );
+    }
+}
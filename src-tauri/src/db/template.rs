@@ -0,0 +1,243 @@
+use crate::db::postgres::PostgresError;
+use crate::db::script::replace_named_params;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+
+/// What a placeholder of a saved query holds, so the UI can render a matching input and
+/// the value can be converted before it is bound
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParameterType {
+    #[default]
+    Text,
+    Integer,
+    Number,
+    Boolean,
+    Date,
+    Timestamp,
+    Json,
+}
+
+impl ParameterType {
+    /// The cast added to the placeholder for types that are bound as text
+    fn cast(self) -> Option<&'static str> {
+        match self {
+            ParameterType::Date => Some("date"),
+            ParameterType::Timestamp => Some("timestamptz"),
+            ParameterType::Json => Some("jsonb"),
+            _ => None,
+        }
+    }
+}
+
+/// A named placeholder (`:name`) of a saved query
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryParameter {
+    pub name: String,
+    #[serde(default)]
+    pub param_type: ParameterType,
+    /// Used when no value is given for the parameter
+    #[serde(default)]
+    pub default: Option<JsonValue>,
+}
+
+/// One definition per distinct placeholder in `sql`, in order of first use. Definitions
+/// from `defined` are kept for placeholders that still exist; new ones are plain text
+/// without a default
+pub fn detect_parameters(sql: &str, defined: &[QueryParameter]) -> Vec<QueryParameter> {
+    let mut parameters: Vec<QueryParameter> = Vec::new();
+    replace_named_params(sql, |name| {
+        if !parameters.iter().any(|p| p.name == name) {
+            parameters.push(
+                defined
+                    .iter()
+                    .find(|p| p.name == name)
+                    .cloned()
+                    .unwrap_or_else(|| QueryParameter {
+                        name: name.to_string(),
+                        param_type: ParameterType::Text,
+                        default: None,
+                    }),
+            );
+        }
+        String::new()
+    });
+    parameters
+}
+
+/// Rewrites the named placeholders of `sql` into `$1`, `$2`, ... and returns the values to
+/// bind to them, taken from `values` or else the parameter's default and converted to its
+/// type. A name used several times is bound once
+pub fn bind_template(
+    sql: &str,
+    defined: &[QueryParameter],
+    values: &HashMap<String, JsonValue>,
+) -> Result<(String, Vec<JsonValue>), PostgresError> {
+    let parameters = detect_parameters(sql, defined);
+    let binds = parameters
+        .iter()
+        .map(|p| {
+            let value = values.get(&p.name).or(p.default.as_ref()).ok_or_else(|| {
+                PostgresError::InvalidInput(format!("No value given for :{}", p.name))
+            })?;
+            coerce(p, value)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let sql = replace_named_params(sql, |name| {
+        let (index, parameter) = parameters
+            .iter()
+            .enumerate()
+            .find(|(_, p)| p.name == name)
+            .expect("every placeholder was detected");
+        match parameter.param_type.cast() {
+            Some(cast) => format!("${}::{}", index + 1, cast),
+            None => format!("${}", index + 1),
+        }
+    });
+
+    Ok((sql, binds))
+}
+
+/// Converts a value to the parameter's type. Strings are parsed, since prompt forms
+/// submit text; NULL stays NULL. Text, the type of placeholders nobody has typed yet,
+/// keeps numbers and booleans as they are, so `id = :id` still compares numbers
+fn coerce(parameter: &QueryParameter, value: &JsonValue) -> Result<JsonValue, PostgresError> {
+    let invalid = || {
+        PostgresError::InvalidInput(format!(
+            ":{} expects {:?}, got {}",
+            parameter.name, parameter.param_type, value
+        ))
+    };
+    let text = value.as_str().map(str::trim);
+
+    let coerced = match (parameter.param_type, value) {
+        (_, JsonValue::Null) => JsonValue::Null,
+        (ParameterType::Text, JsonValue::String(_) | JsonValue::Number(_) | JsonValue::Bool(_)) => {
+            value.clone()
+        }
+        (ParameterType::Text, _) => JsonValue::String(value.to_string()),
+        (ParameterType::Integer, _) => value
+            .as_i64()
+            .or_else(|| text.and_then(|s| s.parse().ok()))
+            .ok_or_else(invalid)?
+            .into(),
+        (ParameterType::Number, _) => value
+            .as_f64()
+            .or_else(|| text.and_then(|s| s.parse().ok()))
+            .ok_or_else(invalid)?
+            .into(),
+        (ParameterType::Boolean, _) => value
+            .as_bool()
+            .or_else(|| match text.map(str::to_ascii_lowercase).as_deref() {
+                Some("true" | "t" | "yes" | "1") => Some(true),
+                Some("false" | "f" | "no" | "0") => Some(false),
+                _ => None,
+            })
+            .ok_or_else(invalid)?
+            .into(),
+        (ParameterType::Date | ParameterType::Timestamp, JsonValue::String(_)) => value.clone(),
+        (ParameterType::Date | ParameterType::Timestamp, _) => return Err(invalid()),
+        // Bound as text and cast to jsonb, so scalars work too; a string must hold JSON
+        (ParameterType::Json, JsonValue::String(s)) => {
+            serde_json::from_str::<JsonValue>(s).map_err(|_| invalid())?;
+            value.clone()
+        }
+        (ParameterType::Json, _) => JsonValue::String(value.to_string()),
+    };
+
+    Ok(coerced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_bind_template() {
+        let sql = "SELECT * FROM events WHERE user_id = :user_id AND at >= :since \
+                   AND (:kind IS NULL OR kind = :kind) AND meta @> :meta";
+        let defined = vec![
+            QueryParameter {
+                name: "user_id".to_string(),
+                param_type: ParameterType::Integer,
+                default: None,
+            },
+            QueryParameter {
+                name: "since".to_string(),
+                param_type: ParameterType::Date,
+                default: Some(json!("2024-01-01")),
+            },
+            QueryParameter {
+                name: "meta".to_string(),
+                param_type: ParameterType::Json,
+                default: None,
+            },
+            QueryParameter {
+                name: "gone".to_string(),
+                param_type: ParameterType::Boolean,
+                default: None,
+            },
+        ];
+
+        let detected = detect_parameters(sql, &defined);
+        let names: Vec<_> = detected.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["user_id", "since", "kind", "meta"]);
+        assert_eq!(detected[2].param_type, ParameterType::Text);
+
+        let values = HashMap::from([
+            ("user_id".to_string(), json!(" 42 ")),
+            ("kind".to_string(), JsonValue::Null),
+            ("meta".to_string(), json!({ "beta": true })),
+        ]);
+        let (bound_sql, binds) = bind_template(sql, &defined, &values).unwrap();
+        assert_eq!(
+            bound_sql,
+            "SELECT * FROM events WHERE user_id = $1 AND at >= $2::date \
+             AND ($3 IS NULL OR kind = $3) AND meta @> $4::jsonb"
+        );
+        assert_eq!(
+            binds,
+            vec![
+                json!(42),
+                json!("2024-01-01"),
+                JsonValue::Null,
+                json!(r#"{"beta":true}"#)
+            ]
+        );
+
+        let missing = HashMap::from([("user_id".to_string(), json!(1))]);
+        assert!(bind_template(sql, &defined, &missing).is_err());
+        let wrong = HashMap::from([
+            ("user_id".to_string(), json!("abc")),
+            ("kind".to_string(), json!("a")),
+            ("meta".to_string(), json!("{}")),
+        ]);
+        assert!(bind_template(sql, &defined, &wrong).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_detected_parameters() {
+        let Some(pg) = crate::db::postgres::tests::test_database().await else {
+            return;
+        };
+        let sql = "SELECT n FROM generate_series(1, 3) n \
+                   WHERE (n = :n OR :n IS NULL) AND :flag AND :label = 'x'";
+        let run = |n: JsonValue| {
+            let values = HashMap::from([
+                ("n".to_string(), n),
+                ("flag".to_string(), json!(true)),
+                ("label".to_string(), json!("x")),
+            ]);
+            let (sql, binds) = bind_template(sql, &[], &values).unwrap();
+            let pg = &pg;
+            async move { pg.query_with_params(&sql, &binds).await.unwrap().rows }
+        };
+
+        // Placeholders nobody has typed take the type of where they are used
+        assert_eq!(run(json!(2)).await, [[json!(2)]]);
+        assert_eq!(run(JsonValue::Null).await.len(), 3);
+    }
+}
@@ -2,7 +2,11 @@ mod commands;
 mod crypto;
 mod db;
 
+use db::cache::create_query_cache_state;
+use db::manager::create_database_state;
+use db::mysql::create_mysql_state;
 use db::postgres::create_postgres_state;
+use db::sqlite::create_sqlite_state;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -11,33 +15,164 @@ pub fn run() {
         eprintln!("Failed to initialize metadata database: {}", e);
     }
 
+    // Restore how BYTEA values are rendered
+    if let Some(encoding) = db::metadata::get_app_state(db::record::BINARY_ENCODING_KEY)
+        .ok()
+        .flatten()
+        .and_then(|name| db::record::BinaryEncoding::from_name(&name))
+    {
+        db::record::set_binary_encoding(encoding);
+    }
+
+    let postgres = create_postgres_state();
+    if let Ok(Some(enabled)) =
+        db::metadata::get_app_state(commands::connections::AUTO_RECONNECT_KEY)
+    {
+        postgres.set_auto_reconnect(enabled == "true");
+    }
+    let mysql = create_mysql_state();
+    let sqlite = create_sqlite_state();
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
-        .manage(create_postgres_state())
+        .manage(create_database_state(
+            postgres.clone(),
+            mysql.clone(),
+            sqlite.clone(),
+        ))
+        .manage(postgres)
+        .manage(mysql)
+        .manage(sqlite)
+        .manage(create_query_cache_state())
         .invoke_handler(tauri::generate_handler![
             // Connection commands
             commands::connections::list_connections,
+            commands::connections::list_connections_grouped,
+            commands::connections::build_connection_url,
             commands::connections::create_connection,
+            commands::connections::rename_connection,
+            commands::connections::duplicate_connection,
             commands::connections::update_connection,
             commands::connections::delete_connection,
             commands::connections::test_connection_by_id,
             commands::connections::connect_to_database,
+            commands::connections::connect_from_url,
             commands::connections::disconnect_database,
             commands::connections::get_active_connection,
+            commands::connections::switch_database,
+            commands::connections::set_search_path,
+            commands::connections::set_auto_reconnect,
+            commands::connections::get_auto_reconnect,
+            commands::connections::start_connection_monitor,
+            commands::connections::stop_connection_monitor,
             commands::connections::get_last_connection_id,
+            commands::connections::get_metadata_db_path,
+            commands::connections::rekey_all_connections,
+            commands::connections::export_connections,
+            commands::connections::import_connections,
             // Query commands
             commands::queries::execute_query,
+            commands::queries::execute_query_multi,
+            commands::queries::execute_query_params,
+            commands::queries::fetch_scalar,
+            commands::queries::clear_query_cache,
+            commands::queries::execute_script,
+            commands::queries::validate_query,
+            commands::queries::classify_query,
+            commands::queries::format_sql,
+            commands::queries::cancel_query,
+            commands::queries::cancel_all_queries,
+            commands::queries::execute_query_stream,
+            commands::queries::execute_query_arrow,
+            commands::queries::declare_cursor,
+            commands::queries::fetch_cursor,
+            commands::queries::close_cursor,
             commands::queries::fetch_tables,
+            commands::queries::fetch_schema_catalog,
             commands::queries::fetch_columns,
+            commands::queries::fetch_table_details,
+            commands::queries::fetch_enum_values,
+            commands::queries::detect_row_key,
+            commands::queries::fetch_indexes,
+            commands::queries::fetch_constraints,
+            commands::queries::fetch_foreign_keys,
             commands::queries::fetch_table_data,
+            commands::queries::preview_table,
+            commands::queries::fetch_distinct_values,
             commands::queries::save_query,
             commands::queries::list_saved_queries,
+            commands::queries::list_saved_queries_by_tag,
+            commands::queries::list_saved_query_tags,
+            commands::queries::set_saved_query_tags,
+            commands::queries::set_saved_query_parameters,
+            commands::queries::run_saved_query,
+            commands::queries::search_saved_queries,
             commands::queries::delete_saved_query,
+            commands::queries::list_query_history,
+            commands::queries::search_query_history,
+            commands::queries::clear_query_history,
+            commands::queries::start_polling,
+            commands::queries::stop_polling,
+            commands::queries::get_poll_result,
             commands::queries::save_editor_content,
             commands::queries::get_editor_content,
+            commands::queries::create_editor_tab,
+            commands::queries::list_editor_tabs,
+            commands::queries::update_editor_tab,
+            commands::queries::delete_editor_tab,
+            commands::queries::set_binary_encoding,
+            commands::queries::get_binary_encoding,
+            // DDL commands
+            commands::ddl::create_index,
+            commands::ddl::drop_index,
+            commands::ddl::generate_table_ddl,
+            commands::ddl::export_schema_ddl,
+            commands::ddl::fetch_view_definition,
+            commands::ddl::set_table_comment,
+            commands::ddl::set_column_comment,
+            commands::ddl::refresh_materialized_view,
+            commands::rows::update_row,
+            commands::rows::delete_rows,
+            commands::rows::insert_row,
+            // Transaction commands
+            commands::transactions::begin_transaction,
+            commands::transactions::commit_transaction,
+            commands::transactions::rollback_transaction,
+            commands::transactions::savepoint,
+            commands::transactions::rollback_to_savepoint,
+            commands::transactions::get_transaction_status,
+            commands::transactions::set_default_isolation_level,
+            // Checksum commands
+            commands::checksum::query_checksum,
             // Explain commands
             commands::explain::explain_query,
             commands::explain::explain_query_no_analyze,
+            commands::explain::explain_query_dot,
+            commands::explain::query_risk_check,
+            commands::explain::explain_script,
+            commands::explain::save_explain_plan,
+            commands::explain::list_explain_plans,
+            commands::explain::delete_explain_plan,
+            commands::explain::diff_explain_plans,
+            // Export commands
+            commands::export::format_as_literal,
+            commands::export::export_table_streaming,
+            commands::export::export_query_json,
+            commands::export::cancel_export,
+            commands::import::import_csv,
+            // Monitoring commands
+            commands::monitoring::fetch_replication_status,
+            commands::monitoring::connection_stats,
+            commands::monitoring::list_sessions,
+            commands::monitoring::list_locks,
+            commands::monitoring::fetch_top_queries,
+            commands::monitoring::fetch_table_sizes,
+            commands::monitoring::terminate_session,
+            commands::monitoring::listen_channel,
+            commands::monitoring::unlisten_channel,
+            commands::monitoring::list_listen_channels,
+            // Profiling commands
+            commands::profile::infer_jsonb_schema,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
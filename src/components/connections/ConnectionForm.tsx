@@ -122,11 +122,11 @@ export function ConnectionForm() {
     }
 
     try {
-      const success = await testMutation.mutateAsync(editingConnection.id);
-      if (success) {
-        toast.success("Connection successful!");
+      const result = await testMutation.mutateAsync(editingConnection.id);
+      if (!result.error_kind) {
+        toast.success(`Connection successful (${result.latency_ms} ms)`);
       } else {
-        toast.error("Connection failed");
+        toast.error(result.message ?? "Connection failed");
       }
     } catch {
       toast.error("Connection test failed");
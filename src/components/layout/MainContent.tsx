@@ -21,6 +21,8 @@ import {
 } from "@/components/ui/table";
 import { ToggleGroup, ToggleGroupItem } from "@/components/ui/toggle-group";
 import { useTableData } from "@/hooks/queries/useTables";
+import { errorMessage } from "@/lib/tauri";
+import { isDecodeError, isNullSentinel } from "@/lib/utils";
 import { useConnectionStore } from "@/stores/connectionStore";
 import { useQueryStore } from "@/stores/queryStore";
 import {
@@ -81,7 +83,7 @@ export function MainContent() {
         columns: tableResult.columns,
         rows: tableResult.rows,
         rowCount: tableResult.rows.length,
-        totalCount: tableResult.total_count,
+        totalCount: tableResult.total_count ?? tableResult.rows.length,
         isPaginated: true,
       };
     }
@@ -93,15 +95,19 @@ export function MainContent() {
     ? Math.ceil(displayData.totalCount / pageSize)
     : 1;
 
-  const formatCellValue = (value: unknown): string => {
-    if (value === null) return "NULL";
+  const formatCellValue = (value: unknown, dataType?: string): string => {
+    if (value === null || isNullSentinel(value)) return "NULL";
     if (value === undefined) return "";
+    if (isDecodeError(value, dataType))
+      return `<${value.__decode_error__}: not decoded>`;
     if (typeof value === "object") return JSON.stringify(value);
     return String(value);
   };
 
-  const getCellClassName = (value: unknown): string => {
-    if (value === null) return "text-muted-foreground italic";
+  const getCellClassName = (value: unknown, dataType?: string): string => {
+    if (value === null || isNullSentinel(value))
+      return "text-muted-foreground italic";
+    if (isDecodeError(value, dataType)) return "text-destructive italic";
     if (typeof value === "number") return "text-blue-400 font-mono";
     if (typeof value === "boolean")
       return value ? "text-green-400" : "text-red-400";
@@ -150,7 +156,7 @@ export function MainContent() {
   }
 
   // Error state - show errors from both query execution and table data fetching
-  const displayError = error || (isTableDataError && tableDataError ? String(tableDataError) : null);
+  const displayError = error || (isTableDataError && tableDataError ? errorMessage(tableDataError) : null);
   if (displayError && (resultMode === "query" || resultMode === "table")) {
     return (
       <div className="flex h-full flex-col items-center justify-center gap-4 bg-background p-8">
@@ -327,14 +333,17 @@ export function MainContent() {
             <TableBody>
               {displayData.rows.map((row, rowIndex) => (
                 <TableRow key={rowIndex}>
-                  {(row as unknown[]).map((cell, cellIndex) => (
-                    <TableCell
-                      key={cellIndex}
-                      className={`whitespace-nowrap font-mono text-xs ${getCellClassName(cell)}`}
-                    >
-                      {formatCellValue(cell)}
-                    </TableCell>
-                  ))}
+                  {(row as unknown[]).map((cell, cellIndex) => {
+                    const dataType = displayData.columns[cellIndex]?.data_type;
+                    return (
+                      <TableCell
+                        key={cellIndex}
+                        className={`whitespace-nowrap font-mono text-xs ${getCellClassName(cell, dataType)}`}
+                      >
+                        {formatCellValue(cell, dataType)}
+                      </TableCell>
+                    );
+                  })}
                 </TableRow>
               ))}
             </TableBody>
@@ -1,5 +1,6 @@
 import { useMutation, useQuery, useQueryClient } from "@tanstack/react-query";
 import {
+  errorMessage,
   executeQuery,
   explainQuery,
   saveQuery,
@@ -30,8 +31,7 @@ export function useExecuteQuery() {
         setQueryResult(result);
         return result;
       } catch (e) {
-        const error = e instanceof Error ? e.message : String(e);
-        setError(error);
+        setError(errorMessage(e));
         throw e;
       } finally {
         setIsExecuting(false);
@@ -54,8 +54,7 @@ export function useExplainQuery() {
         setExplainResult(result);
         return result;
       } catch (e) {
-        const error = e instanceof Error ? e.message : String(e);
-        setError(error);
+        setError(errorMessage(e));
         throw e;
       } finally {
         setIsExplaining(false);
@@ -1,5 +1,5 @@
 import { useQuery } from "@tanstack/react-query";
-import { fetchTables, fetchColumns, fetchTableData } from "@/lib/tauri";
+import { errorMessage, fetchTables, fetchColumns, fetchTableData } from "@/lib/tauri";
 import { useConnectionStore } from "@/stores/connectionStore";
 import { useQueryStore } from "@/stores/queryStore";
 
@@ -64,8 +64,7 @@ export function useTableData(schema: string, table: string) {
         setTableResult(result);
         return result;
       } catch (error) {
-        const errorMessage = error instanceof Error ? error.message : String(error);
-        setError(errorMessage);
+        setError(errorMessage(error));
         throw error;
       }
     },
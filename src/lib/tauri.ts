@@ -1,22 +1,43 @@
 import { invoke } from "@tauri-apps/api/core";
 import type {
+  CommandError,
   ConnectionInfo,
+  ConnectionTestResult,
+  ConnectionUrl,
+  ConnectionUrlInput,
   CreateConnectionInput,
   UpdateConnectionInput,
   TableInfo,
   ColumnInfo,
   QueryResult,
   PaginatedResult,
+  TablePreview,
   SavedQueryInfo,
   ExplainResult,
+  StatementPlan,
 } from "@/types";
 
+// Commands reject with a CommandError; anything else is shown as text
+export function errorMessage(error: unknown): string {
+  if (error instanceof Error) return error.message;
+  if (typeof error === "object" && error !== null && "message" in error) {
+    return (error as CommandError).message;
+  }
+  return String(error);
+}
+
 // ============ Connection Commands ============
 
 export async function listConnections(): Promise<ConnectionInfo[]> {
   return invoke("list_connections");
 }
 
+export async function buildConnectionUrl(
+  input: ConnectionUrlInput
+): Promise<ConnectionUrl> {
+  return invoke("build_connection_url", { input });
+}
+
 export async function createConnection(
   input: CreateConnectionInput
 ): Promise<ConnectionInfo> {
@@ -33,7 +54,23 @@ export async function deleteConnection(id: string): Promise<void> {
   return invoke("delete_connection", { id });
 }
 
-export async function testConnectionById(id: string): Promise<boolean> {
+export async function renameConnection(
+  id: string,
+  newName: string
+): Promise<ConnectionInfo> {
+  return invoke("rename_connection", { id, newName });
+}
+
+export async function duplicateConnection(
+  id: string,
+  newName: string
+): Promise<ConnectionInfo> {
+  return invoke("duplicate_connection", { id, newName });
+}
+
+export async function testConnectionById(
+  id: string
+): Promise<ConnectionTestResult> {
   return invoke("test_connection_by_id", { id });
 }
 
@@ -55,8 +92,28 @@ export async function getLastConnectionId(): Promise<string | null> {
 
 // ============ Query Commands ============
 
-export async function executeQuery(sql: string): Promise<QueryResult> {
-  return invoke("execute_query", { sql });
+// With distinguishNulls, NULL cells come back as NullSentinel instead of null
+export async function executeQuery(
+  sql: string,
+  distinguishNulls?: boolean
+): Promise<QueryResult> {
+  return invoke("execute_query", { sql, distinguishNulls });
+}
+
+export async function executeQueryMulti(
+  sql: string,
+  queryId?: string | null,
+  distinguishNulls?: boolean
+): Promise<QueryResult[]> {
+  return invoke("execute_query_multi", { sql, queryId, distinguishNulls });
+}
+
+export async function cancelAllQueries(): Promise<number> {
+  return invoke("cancel_all_queries");
+}
+
+export async function fetchScalar(sql: string): Promise<unknown> {
+  return invoke("fetch_scalar", { sql });
 }
 
 export async function fetchTables(): Promise<TableInfo[]> {
@@ -70,20 +127,37 @@ export async function fetchColumns(
   return invoke("fetch_columns", { schema, table });
 }
 
+export async function fetchEnumValues(
+  typeName: string,
+  schema?: string | null
+): Promise<string[]> {
+  return invoke("fetch_enum_values", { typeName, schema });
+}
+
 export async function fetchTableData(
   schema: string,
   table: string,
   page: number,
-  pageSize: number
+  pageSize: number,
+  distinguishNulls?: boolean
 ): Promise<PaginatedResult> {
   return invoke("fetch_table_data", {
     schema,
     table,
     page,
     pageSize, // Tauri will convert camelCase to snake_case automatically
+    distinguishNulls,
   });
 }
 
+export async function previewTable(
+  schema: string,
+  table: string,
+  limit?: number
+): Promise<TablePreview> {
+  return invoke("preview_table", { schema, table, limit });
+}
+
 // ============ Saved Queries Commands ============
 
 export async function saveQuery(
@@ -126,3 +200,7 @@ export async function explainQueryNoAnalyze(sql: string): Promise<unknown> {
   return invoke("explain_query_no_analyze", { sql });
 }
 
+export async function explainScript(sql: string): Promise<StatementPlan[]> {
+  return invoke("explain_script", { sql });
+}
+
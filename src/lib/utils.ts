@@ -1,6 +1,27 @@
 import { clsx, type ClassValue } from "clsx"
 import { twMerge } from "tailwind-merge"
+import type { DecodeError, NullSentinel } from "@/types"
 
 export function cn(...inputs: ClassValue[]) {
   return twMerge(clsx(inputs))
 }
+
+// A JSON column's value may itself look like the sentinel; the backend never uses
+// the sentinel for those columns
+export function isDecodeError(
+  value: unknown,
+  dataType?: string
+): value is DecodeError {
+  if (dataType && /^jsonb?$/i.test(dataType)) return false
+  return (
+    typeof value === "object" &&
+    value !== null &&
+    !Array.isArray(value) &&
+    Object.keys(value).length === 1 &&
+    "__decode_error__" in value
+  )
+}
+
+export function isNullSentinel(value: unknown): value is NullSentinel {
+  return typeof value === "object" && value !== null && "__null__" in value
+}
@@ -1,4 +1,15 @@
 // Connection types
+export type SslMode = 'disable' | 'prefer' | 'require' | 'verify-ca' | 'verify-full';
+
+export type Driver = 'postgres' | 'mysql' | 'sqlite';
+
+export interface PoolSettings {
+  max_connections: number;
+  acquire_timeout_secs: number;
+  idle_timeout_secs: number;
+  connect_timeout_secs: number;
+}
+
 export interface ConnectionInfo {
   id: string;
   name: string;
@@ -7,15 +18,70 @@ export interface ConnectionInfo {
   database: string;
   user: string;
   created_at: string;
+  sslmode: SslMode;
+  ca_cert_path: string | null;
+  use_keychain: boolean;
+  driver: Driver;
+  pool_settings: PoolSettings;
+  group: string;
+  color: string | null;
+  is_favorite: boolean;
+  // Writes are rejected by the server; disable editing
+  read_only: boolean;
+  // Prepared statements cached per session (Postgres); 0 disables the cache
+  statement_cache_capacity: number;
+  // Overrides the application_name sessions report (Postgres); null for "datatool - <name>"
+  application_name: string | null;
+  // search_path every session starts with, e.g. "app, public" (Postgres); null for the
+  // server's
+  default_search_path: string | null;
+}
+
+export type ConnectionErrorKind =
+  | "dns"
+  | "refused"
+  | "timeout"
+  | "tls"
+  | "authentication"
+  | "database_not_found"
+  | "other";
+
+export interface ConnectionTestResult {
+  // The server answered, even if only to turn the login down
+  reachable: boolean;
+  authenticated: boolean;
+  server_version: string | null;
+  latency_ms: number | null;
+  // null when the connection came up
+  error_kind: ConnectionErrorKind | null;
+  message: string | null;
+}
+
+export interface ConnectionGroup {
+  name: string;
+  connections: ConnectionInfo[];
 }
 
 export interface CreateConnectionInput {
   name: string;
-  host: string;
-  port: number;
+  // host, port and user are unused for sqlite, where database is the file path
+  host?: string;
+  port?: number;
   database: string;
-  user: string;
+  user?: string;
   password: string;
+  sslmode?: SslMode;
+  ca_cert_path?: string | null;
+  use_keychain?: boolean;
+  driver?: Driver;
+  pool_settings?: Partial<PoolSettings>;
+  group?: string;
+  color?: string | null;
+  is_favorite?: boolean;
+  read_only?: boolean;
+  statement_cache_capacity?: number;
+  application_name?: string | null;
+  default_search_path?: string | null;
 }
 
 export interface UpdateConnectionInput {
@@ -26,6 +92,43 @@ export interface UpdateConnectionInput {
   database: string;
   user: string;
   password?: string;
+  sslmode?: SslMode;
+  ca_cert_path?: string | null;
+  use_keychain?: boolean;
+  driver?: Driver;
+  pool_settings?: Partial<PoolSettings>;
+  group?: string;
+  color?: string | null;
+  is_favorite?: boolean;
+  read_only?: boolean;
+  statement_cache_capacity?: number;
+  application_name?: string | null;
+  default_search_path?: string | null;
+}
+
+export interface ConnectionUrlInput {
+  driver?: Driver;
+  host?: string;
+  port?: number;
+  database?: string;
+  user?: string;
+  password?: string;
+  sslmode?: SslMode;
+  ca_cert_path?: string | null;
+  read_only?: boolean;
+  application_name?: string | null;
+}
+
+export interface FieldError {
+  // "url" when only the driver's parser objects
+  field: string;
+  message: string;
+}
+
+export interface ConnectionUrl {
+  // Password masked
+  display_url: string;
+  errors: FieldError[];
 }
 
 // Table types
@@ -33,6 +136,31 @@ export interface TableInfo {
   schema: string;
   name: string;
   table_type: string;
+  // From planner statistics; null for views and never-analyzed tables
+  estimated_rows: number | null;
+}
+
+export interface ViewDefinition {
+  database: string;
+  schema: string;
+  name: string;
+  table_type: 'VIEW' | 'MATERIALIZED VIEW';
+  definition: string;
+  // null for plain views
+  is_populated: boolean | null;
+  // Last refresh made from the app; Postgres doesn't record refresh times
+  last_refreshed: string | null;
+}
+
+export type TableOrder = 'name' | 'estimated_rows';
+
+export interface TableFilter {
+  schema?: string | null;
+  // Case-insensitive LIKE pattern, e.g. '%order%'
+  name_pattern?: string | null;
+  limit?: number | null;
+  offset?: number | null;
+  order?: TableOrder;
 }
 
 export interface ColumnInfo {
@@ -41,11 +169,60 @@ export interface ColumnInfo {
   is_nullable: boolean;
   column_default: string | null;
   is_primary_key: boolean;
+  is_unique_key: boolean;
+  comment: string | null;
+  udt_schema: string | null;
+  udt_name: string | null;
+  is_enum: boolean;
+}
+
+export interface TableDetails {
+  schema: string;
+  name: string;
+  table_comment: string | null;
+  columns: ColumnInfo[];
+}
+
+export interface IndexInfo {
+  name: string;
+  columns: string[];
+  is_unique: boolean;
+  is_primary: boolean;
+  method: string;
+  definition: string;
+}
+
+export interface ForeignKeyInfo {
+  name: string;
+  source_schema: string;
+  source_table: string;
+  source_columns: string[];
+  target_schema: string;
+  target_table: string;
+  target_columns: string[];
+  on_delete: string;
+  on_update: string;
+}
+
+export interface ConstraintInfo {
+  name: string;
+  constraint_type: string;
+  columns: string[];
+  definition: string;
+  referenced_table: string | null;
+  referenced_columns: string[];
 }
 
 export interface ColumnMeta {
   name: string;
   data_type: string;
+  // Source of the column; null for computed expressions and non-Postgres drivers
+  table_oid: number | null;
+  column_number: number | null;
+  source_schema: string | null;
+  source_table: string | null;
+  source_column: string | null;
+  column_not_null: boolean | null;
 }
 
 // Query result types
@@ -54,14 +231,256 @@ export interface QueryResult {
   rows: unknown[][];
   row_count: number;
   affected_rows: number | null;
+  // Server notices raised while the query ran (RAISE NOTICE)
+  notices: string[];
+  // Client-side round trip, network latency included
+  duration_ms: number;
+  // True when rows were left out because the query hit max_rows
+  truncated: boolean;
+  // Set when the query was run with use_cache
+  cache_status: "hit" | "miss" | null;
+}
+
+// Stands in for a non-null cell whose type couldn't be decoded
+export interface DecodeError {
+  __decode_error__: string;
+}
+
+// Stands in for a NULL cell when the query ran with distinguishNulls
+export interface NullSentinel {
+  __null__: true;
+}
+
+// Server-side fields are null when their query failed
+export interface ConnectionStats {
+  pool_size: number;
+  pool_idle: number;
+  pool_max: number;
+  user_sessions: number | null;
+  server_version: string | null;
+  database_size_bytes: number | null;
+  uptime_seconds: number | null;
+}
+
+export interface SessionInfo {
+  pid: number;
+  user: string | null;
+  database: string | null;
+  application_name: string | null;
+  client_addr: string | null;
+  backend_type: string | null;
+  state: string | null;
+  wait_event_type: string | null;
+  wait_event: string | null;
+  query: string | null;
+  query_start: string | null;
+  transaction_start: string | null;
+  // PIDs holding the locks this session waits for
+  blocked_by: number[];
+}
+
+export interface LockInfo {
+  pid: number;
+  lock_type: string;
+  mode: string;
+  granted: boolean;
+  relation: string | null;
+  transaction_id: string | null;
+  blocked_by: number[];
+}
+
+export type TopQueryOrder = 'total_time' | 'mean_time' | 'calls';
+
+// A pg_stat_statements entry; query_id is null for other users' statements
+// without pg_read_all_stats
+export interface TopQuery {
+  query_id: number | null;
+  query: string | null;
+  user: string | null;
+  database: string | null;
+  calls: number;
+  total_exec_time_ms: number;
+  mean_exec_time_ms: number;
+  rows: number;
+}
+
+export interface TableSize {
+  schema: string;
+  name: string;
+  total_bytes: number;
+  table_bytes: number;
+  indexes_bytes: number;
+  toast_bytes: number;
+  // null until the table is first analyzed
+  estimated_rows: number | null;
+  // Only set when requested and pgstattuple is installed
+  bloat: TableBloat | null;
+}
+
+export interface TableBloat {
+  dead_tuple_percent: number;
+  free_percent: number;
 }
 
 export interface PaginatedResult {
   columns: ColumnMeta[];
   rows: unknown[][];
-  total_count: number;
+  // null when the count was skipped
+  total_count: number | null;
   page: number;
   page_size: number;
+  next_after: unknown[] | null;
+}
+
+export interface TablePreview {
+  columns: ColumnMeta[];
+  rows: unknown[][];
+  // Whether the rows are a sample from all over the table rather than the first ones
+  sampled: boolean;
+  // null when the table was never analyzed
+  estimated_rows: number | null;
+}
+
+export type SortDirection = 'asc' | 'desc';
+
+export interface SortColumn {
+  column: string;
+  direction?: SortDirection;
+}
+
+export type FilterOp =
+  | 'eq'
+  | 'neq'
+  | 'lt'
+  | 'lte'
+  | 'gt'
+  | 'gte'
+  | 'like'
+  | 'is_null'
+  | 'is_not_null';
+
+export interface ColumnFilter {
+  column: string;
+  op: FilterOp;
+  // Ignored by is_null and is_not_null
+  value?: unknown;
+  // Keys into a json/jsonb column; the filter then compares the text found there
+  json_path?: string[];
+}
+
+export interface DistinctValue {
+  value: unknown;
+  count: number;
+}
+
+export interface CsvImportOptions {
+  delimiter?: string;
+  header?: boolean;
+  null_string?: string | null;
+  // Target columns in file order; every column in table order when empty
+  columns?: string[];
+  // COPY, all rows or none; false inserts row by row and reports bad lines
+  atomic?: boolean;
+}
+
+export interface CsvRowError {
+  line: number;
+  message: string;
+}
+
+export interface CsvImportSummary {
+  rows_imported: number;
+  rows_failed: number;
+  errors: CsvRowError[];
+}
+
+export type QueryKind =
+  | 'select'
+  | 'insert'
+  | 'update'
+  | 'delete'
+  | 'merge'
+  | 'truncate'
+  | 'drop'
+  | 'alter'
+  | 'create'
+  | 'other';
+
+export interface QueryClassification {
+  kind: QueryKind;
+  is_destructive: boolean;
+  affects_all_rows: boolean;
+}
+
+// Options for format_sql; defaults are uppercase keywords and a two-space indent
+export interface FormatOptions {
+  uppercase?: boolean;
+  indent_width?: number;
+}
+
+export interface QueryValidation {
+  valid: boolean;
+  error: string | null;
+  error_code: string | null;
+  error_position: number | null;
+  parameter_types: string[];
+  // null on servers before PostgreSQL 16
+  result_types: string[] | null;
+}
+
+// How BYTEA values are rendered: hex is \x... as in psql
+export type BinaryEncoding = 'hex' | 'base64';
+
+// Autocomplete catalog types
+export interface CatalogColumn {
+  name: string;
+  data_type: string;
+}
+
+export interface CatalogTable {
+  name: string;
+  table_type: string;
+  columns: CatalogColumn[];
+}
+
+export interface CatalogSchema {
+  name: string;
+  tables: CatalogTable[];
+}
+
+export interface CatalogFunction {
+  schema: string;
+  name: string;
+  arguments: string;
+  return_type: string | null;
+  kind: 'function' | 'procedure' | 'aggregate' | 'window';
+}
+
+export interface SchemaCatalog {
+  schemas: CatalogSchema[];
+  functions: CatalogFunction[];
+}
+
+export interface SavedQuerySearchResult {
+  query: SavedQueryInfo;
+  snippet: string;
+  score: number;
+}
+
+export interface QueryHistorySearchResult {
+  entry: QueryHistoryEntry;
+  snippet: string;
+  score: number;
+}
+
+// Editor tab types
+export interface EditorTab {
+  id: string;
+  connection_id: string | null;
+  title: string;
+  content: string;
+  position: number;
+  updated_at: string;
 }
 
 // Saved query types
@@ -71,19 +490,138 @@ export interface SavedQueryInfo {
   name: string;
   sql: string;
   created_at: string;
+  tags: string[];
+  parameters: QueryParameter[];
+}
+
+export type ParameterType =
+  | 'text'
+  | 'integer'
+  | 'number'
+  | 'boolean'
+  | 'date'
+  | 'timestamp'
+  | 'json';
+
+export interface QueryParameter {
+  name: string;
+  param_type: ParameterType;
+  default: unknown | null;
+}
+
+export interface QueryHistoryEntry {
+  id: string;
+  connection_id: string | null;
+  sql: string;
+  executed_at: string;
+  duration_ms: number;
+  row_count: number | null;
+  success: boolean;
+  error: string | null;
 }
 
 // Explain types
+export interface PlanNode {
+  id: number;
+  parent: number | null;
+  node_type: string;
+  schema: string | null;
+  relation_name: string | null;
+  startup_cost: number | null;
+  total_cost: number | null;
+  plan_rows: number | null;
+  actual_rows: number | null;
+  actual_time: number | null;
+  loops: number | null;
+  self_time: number | null;
+  estimate_ratio: number | null;
+  bad_estimate: boolean;
+}
+
+export type PlanWarningKind = 'slow_node' | 'seq_scan_on_large_table' | 'bad_estimate';
+
+export interface PlanWarning {
+  kind: PlanWarningKind;
+  node_id: number;
+  node_type: string;
+  relation_name: string | null;
+  message: string;
+}
+
 export interface ExplainResult {
   plan: unknown;
+  nodes: PlanNode[];
+  warnings: PlanWarning[];
   planning_time: number | null;
   execution_time: number | null;
   total_cost: number | null;
 }
 
+// One statement of explain_script; plan is null when EXPLAIN doesn't apply or failed
+export interface StatementPlan {
+  statement_index: number;
+  sql_snippet: string;
+  kind: QueryKind;
+  total_cost: number | null;
+  plan: unknown | null;
+  error: string | null;
+}
+
+export interface SavedExplainPlan {
+  id: string;
+  connection_id: string | null;
+  sql: string;
+  plan: unknown;
+  captured_at: string;
+  label: string | null;
+}
+
+export interface PlanNodeDiff {
+  node_type: string;
+  relation_name: string | null;
+  before: PlanNode | null;
+  after: PlanNode | null;
+  total_cost_delta: number | null;
+  self_time_delta: number | null;
+}
+
+export interface PlanDiff {
+  planning_time_delta: number | null;
+  execution_time_delta: number | null;
+  total_cost_delta: number | null;
+  nodes: PlanNodeDiff[];
+}
+
 // UI State types
 export interface SelectedTable {
   schema: string;
   name: string;
 }
 
+
+// Error types
+export type CommandErrorKind =
+  | "connection"
+  | "auth"
+  | "syntax"
+  | "permission"
+  | "not_found"
+  | "timeout"
+  | "cancelled"
+  | "invalid_input"
+  | "database"
+  | "internal";
+
+// What every command rejects with
+export interface CommandError {
+  kind: CommandErrorKind;
+  message: string;
+  // SQLSTATE of a server error
+  code: string | null;
+  detail: string | null;
+  hint: string | null;
+  // Constraint a statement violated
+  constraint: string | null;
+  // 1-based character offset of the error in the SQL the command was given
+  position: number | null;
+}